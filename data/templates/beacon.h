@@ -0,0 +1,67 @@
+/* Functions Beacon provides to BOFs, as declared by Cobalt Strike's beacon.h
+ * (https://github.com/Cobalt-Strike/bof_template); generated by bof-new */
+
+#ifndef BEACON_H
+#define BEACON_H
+
+#include <windows.h>
+
+/* Argument parsing: values packed by bof_pack() in the order given */
+typedef struct {
+    char *original; /* the buffer as received, for freeing */
+    char *buffer;   /* next value */
+    int length;     /* bytes left */
+    int size;       /* bytes in total */
+} datap;
+
+DECLSPEC_IMPORT void BeaconDataParse(datap *parser, char *buffer, int size);
+DECLSPEC_IMPORT char *BeaconDataPtr(datap *parser, int size);
+DECLSPEC_IMPORT int BeaconDataInt(datap *parser);
+DECLSPEC_IMPORT short BeaconDataShort(datap *parser);
+DECLSPEC_IMPORT int BeaconDataLength(datap *parser);
+DECLSPEC_IMPORT char *BeaconDataExtract(datap *parser, int *size);
+
+/* Output buffers: allocate, append, send with BeaconOutput, then free */
+typedef struct {
+    char *original;
+    char *buffer;
+    int length;
+    int size;
+} formatp;
+
+DECLSPEC_IMPORT void BeaconFormatAlloc(formatp *format, int maxsz);
+DECLSPEC_IMPORT void BeaconFormatReset(formatp *format);
+DECLSPEC_IMPORT void BeaconFormatAppend(formatp *format, const char *text, int len);
+DECLSPEC_IMPORT void BeaconFormatPrintf(formatp *format, const char *fmt, ...);
+DECLSPEC_IMPORT char *BeaconFormatToString(formatp *format, int *size);
+DECLSPEC_IMPORT void BeaconFormatFree(formatp *format);
+DECLSPEC_IMPORT void BeaconFormatInt(formatp *format, int value);
+
+/* Output */
+#define CALLBACK_OUTPUT 0x0
+#define CALLBACK_OUTPUT_OEM 0x1e
+#define CALLBACK_OUTPUT_UTF8 0x20
+#define CALLBACK_ERROR 0x0d
+
+DECLSPEC_IMPORT void BeaconOutput(int type, const char *data, int len);
+DECLSPEC_IMPORT void BeaconPrintf(int type, const char *fmt, ...);
+
+/* Tokens */
+DECLSPEC_IMPORT BOOL BeaconUseToken(HANDLE token);
+DECLSPEC_IMPORT void BeaconRevertToken(void);
+DECLSPEC_IMPORT BOOL BeaconIsAdmin(void);
+
+/* Spawning and injection; clean up every process with BeaconCleanupProcess */
+DECLSPEC_IMPORT void BeaconGetSpawnTo(BOOL x86, char *buffer, int length);
+DECLSPEC_IMPORT BOOL BeaconSpawnTemporaryProcess(BOOL x86, BOOL ignoreToken, STARTUPINFO *si, PROCESS_INFORMATION *pInfo);
+DECLSPEC_IMPORT void BeaconInjectProcess(HANDLE hProc, int pid, char *payload, int p_len, int p_offset, char *arg, int a_len);
+DECLSPEC_IMPORT void BeaconInjectTemporaryProcess(PROCESS_INFORMATION *pInfo, char *payload, int p_len, int p_offset, char *arg, int a_len);
+DECLSPEC_IMPORT void BeaconCleanupProcess(PROCESS_INFORMATION *pInfo);
+
+/* Utilities */
+DECLSPEC_IMPORT BOOL toWideChar(char *src, wchar_t *dst, int max);
+
+/* Windows APIs are resolved at load time as MODULE$Function, e.g.
+ * DECLSPEC_IMPORT DWORD WINAPI KERNEL32$GetCurrentProcessId(void); */
+
+#endif
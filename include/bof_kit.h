@@ -0,0 +1,42 @@
+/* C interface to bof-kit, built with `cargo build --release --features ffi`
+ * (target/release/libbof_kit.so, or bof_kit.dll on Windows) */
+
+#ifndef BOF_KIT_H
+#define BOF_KIT_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Results, matching the exit codes of `bof-check --strict` */
+#define BOF_KIT_OK 0
+#define BOF_KIT_ERRORS 1
+#define BOF_KIT_WARNINGS 2
+#define BOF_KIT_PARSE_FAILURE 3
+/* A null pointer, an unknown target or a bug in the library */
+#define BOF_KIT_INVALID_ARGUMENT (-1)
+
+/* Analyze the len bytes at buf against Cobalt Strike's loader, storing the
+ * report as JSON in *out_json. If the buffer isn't a COFF file, stores
+ * {"error": "..."} instead. *out_json is left NULL only for
+ * BOF_KIT_INVALID_ARGUMENT, and is freed with bof_free_string(). */
+int bof_analyze(const uint8_t *buf, size_t len, char **out_json);
+
+/* Like bof_analyze(), against the loader target names ("cs", "cs-4.9",
+ * "sliver", ...); NULL means Cobalt Strike */
+int bof_analyze_for(const uint8_t *buf, size_t len, const char *target, char **out_json);
+
+/* Free a string returned by the library; does nothing for NULL */
+void bof_free_string(char *string);
+
+/* Version of the library; not to be freed */
+const char *bof_kit_version(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,147 @@
+//! API hashes for custom resolvers
+//!
+//! Loaders that resolve dynamic function resolution imports themselves
+//! often walk the PEB and export tables comparing hashes instead of names,
+//! so that the strings never appear in the loader. Function hashes are of
+//! the export name as written after the `$`; module hashes are of the
+//! lowercase DLL name (`kernel32.dll`), since `BaseDllName` casing varies
+//! between Windows versions and resolvers lowercase it before hashing.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::Bof;
+
+/// A 32 bit string hash common in shellcode and loaders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Algorithm {
+    /// Rotate right by 13 and add each byte, as Metasploit's block_api
+    Ror13,
+    /// Multiply by 33 and add each byte, from 5381
+    Djb2,
+    /// 32 bit FNV-1a
+    Fnv1a,
+    /// CRC-32 as zlib computes it
+    Crc32,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [Algorithm::Ror13, Algorithm::Djb2, Algorithm::Fnv1a, Algorithm::Crc32];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Ror13 => "ror13",
+            Algorithm::Djb2 => "djb2",
+            Algorithm::Fnv1a => "fnv1a",
+            Algorithm::Crc32 => "crc32",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn hash(self, name: &str) -> u32 {
+        let bytes = name.bytes();
+        match self {
+            Algorithm::Ror13 => bytes.fold(0, |hash, byte| hash.rotate_right(13).wrapping_add(byte as u32)),
+            Algorithm::Djb2 => bytes.fold(5381, |hash: u32, byte| hash.wrapping_mul(33).wrapping_add(byte as u32)),
+            Algorithm::Fnv1a => bytes.fold(0x811c_9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193)),
+            Algorithm::Crc32 => !bytes.fold(!0, |hash, byte| {
+                (0..8).fold(hash ^ byte as u32, |hash, _| match hash & 1 {
+                    1 => (hash >> 1) ^ 0xedb8_8320,
+                    _ => hash >> 1,
+                })
+            }),
+        }
+    }
+}
+
+/// A `MODULE$Function` import
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DfrImport {
+    /// Module as written in the import, without `.dll`
+    pub module: String,
+    pub function: String,
+}
+
+impl DfrImport {
+    /// Name the module hash is computed over
+    pub fn dll(&self) -> String {
+        format!("{}.dll", self.module.to_ascii_lowercase())
+    }
+}
+
+/// Every dynamic function resolution import, sorted by module and function
+pub fn dfr_imports(bof: &Bof) -> Vec<DfrImport> {
+    let imports: BTreeSet<DfrImport> = bof
+        .import_names()
+        .iter()
+        .filter_map(|name| {
+            let (module, function) = name.split_once('$')?;
+            // x86 stdcall decoration isn't part of the export name
+            let function = function.split('@').next().unwrap_or(function);
+            Some(DfrImport { module: module.to_string(), function: function.to_string() })
+        })
+        .collect();
+    imports.into_iter().collect()
+}
+
+/// Modules of `imports`, each once
+fn modules(imports: &[DfrImport]) -> Vec<&DfrImport> {
+    let mut seen = BTreeSet::new();
+    imports.iter().filter(|import| seen.insert(import.dll())).collect()
+}
+
+/// `name` with anything that can't be in a C or Rust identifier replaced
+fn identifier(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect()
+}
+
+/// A C header defining `HASH_<ALGORITHM>_<MODULE>` and
+/// `HASH_<ALGORITHM>_<MODULE>_<Function>` for every import
+pub fn c_header(imports: &[DfrImport], algorithms: &[Algorithm], source: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "/* API hashes of the DFR imports of {} */", source);
+    let _ = writeln!(out, "#pragma once");
+    for algorithm in algorithms {
+        let prefix = format!("HASH_{}", algorithm.name().to_ascii_uppercase());
+        let _ = writeln!(out);
+        for import in modules(imports) {
+            let dll = import.dll();
+            let name = identifier(&import.module.to_ascii_uppercase());
+            let _ = writeln!(out, "#define {}_{} 0x{:08x} /* {} */", prefix, name, algorithm.hash(&dll), dll);
+        }
+        for import in imports {
+            let name = format!("{}_{}", identifier(&import.module.to_ascii_uppercase()), identifier(&import.function));
+            let _ = writeln!(out, "#define {}_{} 0x{:08x}", prefix, name, algorithm.hash(&import.function));
+        }
+    }
+    out
+}
+
+/// Rust tables of the module and function hashes, one `u32` per algorithm
+/// in the order given
+pub fn rust_table(imports: &[DfrImport], algorithms: &[Algorithm], source: &str) -> String {
+    let hashes = |name: &str| {
+        let hashes: Vec<String> = algorithms.iter().map(|algorithm| format!("0x{:08x}", algorithm.hash(name))).collect();
+        hashes.join(", ")
+    };
+    let names: Vec<&str> = algorithms.iter().map(|algorithm| algorithm.name()).collect();
+    let count = algorithms.len();
+    let mut out = String::new();
+    let _ = writeln!(out, "// API hashes of the DFR imports of {}: {}", source, names.join(", "));
+    let _ = writeln!(out, "pub const MODULES: &[(&str, [u32; {}])] = &[", count);
+    for import in modules(imports) {
+        let dll = import.dll();
+        let _ = writeln!(out, "    ({:?}, [{}]),", dll, hashes(&dll));
+    }
+    let _ = writeln!(out, "];");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "pub const FUNCTIONS: &[(&str, &str, [u32; {}])] = &[", count);
+    for import in imports {
+        let _ = writeln!(out, "    ({:?}, {:?}, [{}]),", import.dll(), import.function, hashes(&import.function));
+    }
+    let _ = writeln!(out, "];");
+    out
+}
@@ -0,0 +1,43 @@
+//! Static archives (`.a`/`.lib`) of object files
+//!
+//! BOF collections are often shipped as one archive per architecture. Each
+//! member is an ordinary COFF object and is analyzed on its own.
+
+use goblin::archive::{Archive, MAGIC};
+use goblin::error::{Error, Result};
+
+use crate::bigobj;
+
+/// Signature of a short import object, which MSVC import libraries use
+/// in place of a full object for each imported function
+const IMPORT_OBJECT_SIGNATURE: &[u8] = &[0x00, 0x00, 0xff, 0xff];
+
+/// An object file in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+}
+
+/// Whether `buffer` starts with the `!<arch>` magic
+pub fn is_archive(buffer: &[u8]) -> bool {
+    buffer.starts_with(MAGIC)
+}
+
+/// Object file members of an archive, in archive order
+///
+/// Symbol indexes, the long name table and import library stubs are left out.
+pub fn members(buffer: &[u8]) -> Result<Vec<Member<'_>>> {
+    let archive = Archive::parse(buffer)?;
+    let mut members = Vec::with_capacity(archive.len());
+    for member in (0..archive.len()).filter_map(|index| archive.get_at(index)) {
+        let start = member.offset as usize;
+        let data = buffer
+            .get(start..start + member.size())
+            .ok_or_else(|| Error::Malformed(format!("archive member {} is out of bounds", member.extended_name())))?;
+        if !data.starts_with(IMPORT_OBJECT_SIGNATURE) || bigobj::is_bigobj(data) {
+            members.push(Member { name: member.extended_name().to_string(), data });
+        }
+    }
+    Ok(members)
+}
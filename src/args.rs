@@ -0,0 +1,367 @@
+//! Argument packing and unpacking, compatible with Aggressor's `bof_pack()`
+//!
+//! The packed buffer starts with the total length of the arguments, followed
+//! by each argument in order. Every integer is little-endian, which is what
+//! `BeaconDataInt`/`BeaconDataShort` read back on x86 and x64.
+//! https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics_aggressor-scripts/as-resources_functions.htm#bof_pack
+
+use std::error;
+use std::fmt;
+
+/// A single argument, tagged with its `bof_pack()` format character
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arg {
+    /// `b`: binary data, prefixed with its length
+    Binary(Vec<u8>),
+    /// `i`: 4-byte integer
+    Int(i32),
+    /// `s`: 2-byte short integer
+    Short(i16),
+    /// `z`: zero-terminated string, prefixed with its length
+    Str(String),
+    /// `Z`: zero-terminated UTF-16LE string, prefixed with its length
+    WStr(String),
+}
+
+impl Arg {
+    /// Parse `value` according to the `bof_pack()` format character `typ`
+    ///
+    /// Binary values are given as hex.
+    pub fn parse(typ: char, value: &str) -> Result<Self, ArgsError> {
+        let invalid = || ArgsError::InvalidValue { typ, value: value.to_string() };
+        match typ {
+            'b' => decode_hex(value).map(Self::Binary).ok_or_else(invalid),
+            // unsigned values are accepted too, e.g. 0xffffffff for -1
+            'i' => parse_int(value)
+                .and_then(|v| i32::try_from(v).ok().or_else(|| u32::try_from(v).ok().map(|v| v as i32)))
+                .map(Self::Int)
+                .ok_or_else(invalid),
+            's' => parse_int(value)
+                .and_then(|v| i16::try_from(v).ok().or_else(|| u16::try_from(v).ok().map(|v| v as i16)))
+                .map(Self::Short)
+                .ok_or_else(invalid),
+            'z' => Ok(Self::Str(value.to_string())),
+            'Z' => Ok(Self::WStr(value.to_string())),
+            _ => Err(ArgsError::UnknownType(typ)),
+        }
+    }
+
+    /// The `bof_pack()` format character for this argument
+    pub fn typ(&self) -> char {
+        match self {
+            Self::Binary(_) => 'b',
+            Self::Int(_) => 'i',
+            Self::Short(_) => 's',
+            Self::Str(_) => 'z',
+            Self::WStr(_) => 'Z',
+        }
+    }
+
+    /// Read an argument of type `typ` from the start of `data`, returning it
+    /// along with the number of bytes consumed
+    fn read(typ: char, data: &[u8]) -> Result<(Self, usize), ArgsError> {
+        let truncated = |needed| ArgsError::Truncated { typ, needed, available: data.len() };
+        match typ {
+            'i' => {
+                let bytes = data.get(..4).ok_or_else(|| truncated(4))?;
+                Ok((Self::Int(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])), 4))
+            }
+            's' => {
+                let bytes = data.get(..2).ok_or_else(|| truncated(2))?;
+                Ok((Self::Short(i16::from_le_bytes([bytes[0], bytes[1]])), 2))
+            }
+            'b' | 'z' | 'Z' => {
+                let header = data.get(..4).ok_or_else(|| truncated(4))?;
+                let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+                let value = data.get(4..4 + length).ok_or_else(|| truncated(4 + length))?;
+                let invalid = || ArgsError::InvalidValue { typ, value: hex(value) };
+                let arg = match typ {
+                    'b' => Self::Binary(value.to_vec()),
+                    'z' => {
+                        let text = value.strip_suffix(&[0]).ok_or_else(invalid)?;
+                        Self::Str(String::from_utf8(text.to_vec()).map_err(|_| invalid())?)
+                    }
+                    _ => {
+                        let units: Vec<u16> = value.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])).collect();
+                        if !value.len().is_multiple_of(2) || units.last() != Some(&0) {
+                            return Err(invalid());
+                        }
+                        Self::WStr(String::from_utf16(&units[..units.len() - 1]).map_err(|_| invalid())?)
+                    }
+                };
+                Ok((arg, 4 + length))
+            }
+            _ => Err(ArgsError::UnknownType(typ)),
+        }
+    }
+
+    fn write(&self, buffer: &mut Vec<u8>) {
+        match self {
+            Self::Binary(data) => write_sized(buffer, data),
+            Self::Int(value) => buffer.extend_from_slice(&value.to_le_bytes()),
+            Self::Short(value) => buffer.extend_from_slice(&value.to_le_bytes()),
+            Self::Str(value) => {
+                let mut data = value.as_bytes().to_vec();
+                data.push(0);
+                write_sized(buffer, &data);
+            }
+            Self::WStr(value) => {
+                let data: Vec<u8> = value.encode_utf16().chain([0]).flat_map(u16::to_le_bytes).collect();
+                write_sized(buffer, &data);
+            }
+        }
+    }
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Binary(data) => write!(f, "{}", hex(data)),
+            Self::Int(value) => write!(f, "{} (0x{:08x})", value, value),
+            Self::Short(value) => write!(f, "{} (0x{:04x})", value, value),
+            Self::Str(value) | Self::WStr(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    /// A format character other than `b`, `i`, `s`, `z` or `Z`
+    UnknownType(char),
+    /// A value that can't be converted to its format type
+    InvalidValue { typ: char, value: String },
+    /// The number of values doesn't match the format string
+    CountMismatch { expected: usize, found: usize },
+    /// The buffer ends in the middle of an argument
+    Truncated { typ: char, needed: usize, available: usize },
+    /// The length header doesn't match the size of the buffer
+    LengthMismatch { header: usize, actual: usize },
+    /// Bytes left over after every argument in the format string was read
+    TrailingData(usize),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownType(typ) => write!(f, "unknown argument type '{}'", typ),
+            Self::InvalidValue { typ, value } => write!(f, "invalid value for type '{}': {}", typ, value),
+            Self::CountMismatch { expected, found } => {
+                write!(f, "format expects {} values but {} were given", expected, found)
+            }
+            Self::Truncated { typ, needed, available } => {
+                write!(f, "argument of type '{}' needs {} bytes but only {} remain", typ, needed, available)
+            }
+            Self::LengthMismatch { header, actual } => {
+                write!(f, "length header says {} bytes but {} follow it", header, actual)
+            }
+            Self::TrailingData(count) => write!(f, "{} bytes left over after the last argument", count),
+        }
+    }
+}
+
+impl error::Error for ArgsError {}
+
+/// Pack arguments into a buffer suitable for passing to a BOF entrypoint
+pub fn pack(args: &[Arg]) -> Vec<u8> {
+    let mut body = Vec::new();
+    for arg in args {
+        arg.write(&mut body);
+    }
+    let mut buffer = Vec::with_capacity(body.len() + 4);
+    write_sized(&mut buffer, &body);
+    buffer
+}
+
+/// Parse `values` according to a `bof_pack()` format string such as `"izZ"`
+pub fn parse_format<S: AsRef<str>>(format: &str, values: &[S]) -> Result<Vec<Arg>, ArgsError> {
+    let expected = format.chars().count();
+    if expected != values.len() {
+        return Err(ArgsError::CountMismatch { expected, found: values.len() });
+    }
+    format
+        .chars()
+        .zip(values)
+        .map(|(typ, value)| Arg::parse(typ, value.as_ref()))
+        .collect()
+}
+
+/// Decode a packed buffer according to a `bof_pack()` format string
+pub fn unpack(buffer: &[u8], format: &str) -> Result<Vec<Arg>, ArgsError> {
+    let mut data = body(buffer)?;
+    let mut args = Vec::new();
+    for typ in format.chars() {
+        let (arg, consumed) = Arg::read(typ, data)?;
+        args.push(arg);
+        data = &data[consumed..];
+    }
+    match data.len() {
+        0 => Ok(args),
+        count => Err(ArgsError::TrailingData(count)),
+    }
+}
+
+/// Guess the format string of a packed buffer
+///
+/// The packed format doesn't record argument types, so this is a heuristic:
+/// of every way to split the buffer into arguments, it picks the one with
+/// the most printable strings and the fewest arguments overall. Binary
+/// values that happen to look like strings, or ints that look like length
+/// prefixes, will be misreported.
+pub fn guess_format(buffer: &[u8]) -> Result<String, ArgsError> {
+    let data = body(buffer)?;
+    // best[i] is the highest scoring way to read data[i..], if there is one
+    let mut best: Vec<Option<(i64, char, usize)>> = vec![None; data.len() + 1];
+    best[data.len()] = Some((0, '\0', 0));
+    for offset in (0..data.len()).rev() {
+        for typ in ['z', 'Z', 'b', 'i', 's'] {
+            let (score, consumed) = match candidate(typ, &data[offset..]) {
+                Some(candidate) => candidate,
+                None => continue,
+            };
+            if let Some((rest, _, _)) = best[offset + consumed] {
+                if best[offset].is_none_or(|(current, _, _)| score + rest > current) {
+                    best[offset] = Some((score + rest, typ, consumed));
+                }
+            }
+        }
+    }
+    let mut format = String::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let (_, typ, consumed) = best[offset].ok_or(ArgsError::TrailingData(data.len() - offset))?;
+        format.push(typ);
+        offset += consumed;
+    }
+    Ok(format)
+}
+
+/// Score and size of reading an argument of type `typ` from the start of
+/// `data`, if it reads as one: printable strings score highest, then binary
+/// blobs, ints and shorts. Values are checked in place rather than copied,
+/// since every offset of the buffer is tried.
+fn candidate(typ: char, data: &[u8]) -> Option<(i64, usize)> {
+    let printable = |c: char| !c.is_control() || c.is_whitespace();
+    match typ {
+        'i' => (data.len() >= 4).then_some((-1, 4)),
+        's' => (data.len() >= 2).then_some((-2, 2)),
+        'b' | 'z' | 'Z' => {
+            let header = data.get(..4)?;
+            let length = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+            let value = data.get(4..)?.get(..length)?;
+            let score = match typ {
+                'b' => 0,
+                'z' => {
+                    let text = std::str::from_utf8(value.strip_suffix(&[0])?).ok()?;
+                    text.chars().all(printable).then_some(4)?
+                }
+                _ => {
+                    let units = value.strip_suffix(&[0, 0]).filter(|units| units.len().is_multiple_of(2))?;
+                    let mut text = char::decode_utf16(units.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]])));
+                    text.all(|c| c.is_ok_and(printable)).then_some(4)?
+                }
+            };
+            Some((score, 4 + length))
+        }
+        _ => None,
+    }
+}
+
+/// Validate the length header and return the packed arguments after it
+fn body(buffer: &[u8]) -> Result<&[u8], ArgsError> {
+    let header = buffer.get(..4).ok_or(ArgsError::Truncated { typ: 'i', needed: 4, available: buffer.len() })?;
+    let header = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let body = &buffer[4..];
+    match header == body.len() {
+        true => Ok(body),
+        false => Err(ArgsError::LengthMismatch { header, actual: body.len() }),
+    }
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn write_sized(buffer: &mut Vec<u8>, data: &[u8]) {
+    buffer.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    buffer.extend_from_slice(data);
+}
+
+/// Parse a decimal or `0x`-prefixed hex integer
+fn parse_int(value: &str) -> Option<i64> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let magnitude = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -magnitude } else { magnitude })
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| value.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> Vec<Arg> {
+        vec![
+            Arg::Int(-1),
+            Arg::Short(0x1234),
+            Arg::Str("whoami".to_string()),
+            Arg::WStr("C:\\Users\\Ünïcode".to_string()),
+            Arg::Binary(vec![0, 0xff, 0x10]),
+            Arg::Str(String::new()),
+        ]
+    }
+
+    #[test]
+    fn pack_matches_bof_pack() {
+        let packed = pack(&[Arg::Int(1), Arg::Short(2), Arg::Str("ab".to_string()), Arg::WStr("c".to_string())]);
+        let expected = [
+            21, 0, 0, 0, // total
+            1, 0, 0, 0, // i
+            2, 0, // s
+            3, 0, 0, 0, b'a', b'b', 0, // z
+            4, 0, 0, 0, b'c', 0, 0, 0, // Z
+        ];
+        assert_eq!(packed, expected);
+    }
+
+    #[test]
+    fn pack_and_unpack_round_trip() {
+        let args = args();
+        let format: String = args.iter().map(Arg::typ).collect();
+        assert_eq!(format, "iszZbz");
+        let packed = pack(&args);
+        assert_eq!(unpack(&packed, &format).unwrap(), args);
+        assert_eq!(parse_format(&format, &["-1", "0x1234", "whoami", "C:\\Users\\Ünïcode", "00ff10", ""]).unwrap(), args);
+    }
+
+    #[test]
+    fn unpack_rejects_bad_buffers() {
+        let packed = pack(&args());
+        assert!(matches!(unpack(&packed, "iszZb"), Err(ArgsError::TrailingData(5))));
+        assert!(matches!(unpack(&packed[..packed.len() - 1], "iszZbz"), Err(ArgsError::LengthMismatch { .. })));
+        assert!(matches!(unpack(&pack(&[Arg::Short(1)]), "i"), Err(ArgsError::Truncated { typ: 'i', .. })));
+    }
+
+    #[test]
+    fn guess_format_prefers_strings() {
+        let packed = pack(&[Arg::Str("whoami".to_string()), Arg::WStr("C:\\Users".to_string()), Arg::Int(7)]);
+        assert_eq!(guess_format(&packed).unwrap(), "zZi");
+        // a blob too large to be anything else
+        let packed = pack(&[Arg::Binary(vec![0xff; 1 << 20]), Arg::Str("x".to_string())]);
+        assert_eq!(guess_format(&packed).unwrap(), "bz");
+    }
+}
@@ -0,0 +1,179 @@
+//! Auxiliary symbol records
+//!
+//! Symbol table entries can be followed by auxiliary records, whose format
+//! depends on the entry: a function definition's holds the function's size,
+//! a section definition's the section's length, relocation count, checksum
+//! and COMDAT selection, a `.file` entry's the source file name, and a weak
+//! external's its default. Each record takes up a symbol table slot, which
+//! relocations and tag indexes count but symbol iteration skips.
+
+use std::fmt;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use goblin::pe::symbol::{
+    IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_FILE, IMAGE_SYM_CLASS_FUNCTION, IMAGE_SYM_CLASS_STATIC,
+    IMAGE_SYM_CLASS_WEAK_EXTERNAL,
+};
+
+use crate::disasm::is_section_definition;
+use crate::weak::Search;
+use crate::{section_name, Bof, Symbol};
+
+/// The contents of a symbol's auxiliary records
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AuxRecord {
+    /// A function definition: the function's size in bytes, and the index of
+    /// its `.bf` entry
+    FunctionDefinition { size: u32, tag_index: u32 },
+    /// A `.bf` or `.ef` entry, with its source line
+    FunctionBoundary { line: u16 },
+    /// A section definition
+    SectionDefinition {
+        length: u32,
+        relocations: u16,
+        line_numbers: u16,
+        /// CRC of the section's contents, which COMDAT selection compares;
+        /// zero if the compiler didn't compute one
+        checksum: u32,
+        /// One-based number of the section an associative COMDAT goes with
+        number: u16,
+        /// COMDAT selection, zero for sections that aren't COMDATs
+        selection: u8,
+    },
+    /// The source file name of a `.file` entry
+    File(String),
+    /// A weak external's default
+    WeakExternal { tag_index: u32, search: Search },
+}
+
+impl fmt::Display for AuxRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuxRecord::FunctionDefinition { size, .. } => write!(f, "function, {} bytes", size),
+            AuxRecord::FunctionBoundary { line } => write!(f, "function boundary, line {}", line),
+            AuxRecord::SectionDefinition { length, relocations, checksum, .. } => {
+                write!(f, "section, {} bytes, {} relocations, checksum 0x{:08x}", length, relocations, checksum)
+            }
+            AuxRecord::File(name) => write!(f, "source file {}", name),
+            AuxRecord::WeakExternal { tag_index, search } => write!(f, "weak external, default #{} ({})", tag_index, search),
+        }
+    }
+}
+
+/// A symbol table entry with auxiliary records
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuxSymbol {
+    /// Symbol table index of the entry the records follow
+    pub index: usize,
+    pub symbol: String,
+    pub record: AuxRecord,
+}
+
+impl fmt::Display for AuxSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.symbol, self.record)
+    }
+}
+
+/// Parse the auxiliary records following `symbol`, at symbol table index
+/// `index`; `None` if it has none or they're in a format nothing uses
+pub(crate) fn parse(bof: &Bof, index: usize, symbol: &Symbol) -> Option<AuxRecord> {
+    if symbol.number_of_aux_symbols == 0 {
+        return None;
+    }
+    let symbols = &bof.coff.symbols;
+    let next = index + 1;
+    Some(match symbol.storage_class {
+        IMAGE_SYM_CLASS_FILE => {
+            let name = symbols.aux_file(next, symbol.number_of_aux_symbols as usize)?;
+            AuxRecord::File(name.trim_end_matches('\0').to_string())
+        }
+        IMAGE_SYM_CLASS_WEAK_EXTERNAL => {
+            let aux = symbols.aux_weak_external(next)?;
+            AuxRecord::WeakExternal { tag_index: aux.tag_index, search: Search::new(aux.characteristics) }
+        }
+        IMAGE_SYM_CLASS_FUNCTION => AuxRecord::FunctionBoundary { line: symbols.aux_begin_and_end_function(next)?.line_number },
+        _ if symbol.is_function_definition() => {
+            let aux = symbols.aux_function_definition(next)?;
+            AuxRecord::FunctionDefinition { size: aux.total_size, tag_index: aux.tag_index }
+        }
+        _ if is_section_definition(bof, symbol) && symbol.section_number > 0 => {
+            let aux = symbols.aux_section_definition(next)?;
+            AuxRecord::SectionDefinition {
+                length: aux.length,
+                relocations: aux.number_of_relocations,
+                line_numbers: aux.number_of_line_numbers,
+                checksum: aux.checksum,
+                number: aux.number,
+                selection: aux.selection,
+            }
+        }
+        _ => return None,
+    })
+}
+
+/// Every symbol table entry's auxiliary records, in symbol table order
+pub fn records(bof: &Bof) -> Vec<AuxSymbol> {
+    bof.coff
+        .symbols
+        .iter()
+        .filter_map(|(index, _, symbol)| {
+            let record = parse(bof, index, &symbol)?;
+            Some(AuxSymbol { index, symbol: bof.symbol_name(&symbol), record })
+        })
+        .collect()
+}
+
+/// A source file compiled into the object, from its `.file` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TranslationUnit {
+    pub file: String,
+    /// Functions defined between this `.file` entry and the next, in symbol
+    /// table order
+    pub functions: Vec<String>,
+}
+
+impl fmt::Display for TranslationUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.functions.is_empty() {
+            true => write!(f, "{}: no functions", self.file),
+            false => write!(f, "{}: {}", self.file, self.functions.join(", ")),
+        }
+    }
+}
+
+/// The source files named by `.file` entries, each with the functions that
+/// follow it
+///
+/// Compilers write one `.file` entry per object, so more than one means
+/// objects were merged. GCC and MSVC write it first, but LLVM's assembler
+/// writes it last, so functions before the first belong to the first.
+pub fn translation_units(bof: &Bof) -> Vec<TranslationUnit> {
+    let mut units: Vec<TranslationUnit> = Vec::new();
+    let mut before = Vec::new();
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        if let Some(AuxRecord::File(file)) = parse(bof, index, &symbol) {
+            let functions = match units.is_empty() {
+                true => std::mem::take(&mut before),
+                false => Vec::new(),
+            };
+            units.push(TranslationUnit { file, functions });
+            continue;
+        }
+        let is_function = matches!(symbol.storage_class, IMAGE_SYM_CLASS_EXTERNAL | IMAGE_SYM_CLASS_STATIC)
+            && symbol.section_number > 0
+            && !is_section_definition(bof, &symbol)
+            && bof.coff.sections.get(symbol.section_number as usize - 1).is_some_and(|section| {
+                // section symbols without a definition record are named after their section
+                section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0
+                    && section_name(section) != bof.symbol_name(&symbol)
+            });
+        if is_function {
+            units.last_mut().map_or(&mut before, |unit| &mut unit.functions).push(bof.symbol_name(&symbol));
+        }
+    }
+    units
+}
@@ -0,0 +1,152 @@
+//! Checking many BOFs at once
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use rayon::prelude::*;
+
+use crate::pe::{self, PeImage};
+use crate::sniff::{self, Foreign};
+use crate::{archive, AnalysisOptions, AnalysisReport, Bof, Hashes};
+
+/// File extensions of object files and archives picked up when scanning a
+/// directory
+pub static OBJECT_EXTENSIONS: &[&str] = &["o", "obj", "a", "lib"];
+
+/// Why a file in a batch couldn't be analyzed
+#[derive(Debug)]
+pub enum BatchError {
+    Read(io::Error),
+    Parse(String),
+    /// The file is a linked EXE or DLL
+    Image(PeImage),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "failed to read input file: {}", e),
+            Self::Parse(e) => write!(f, "failed to parse input as COFF file: {}", e),
+            Self::Image(image) => write!(f, "{}", image),
+        }
+    }
+}
+
+impl error::Error for BatchError {}
+
+/// Analyze every file in `paths` in parallel, returning the results in the
+/// same order as `paths`
+///
+/// Archives are expanded into one result per member, named
+/// `archive.a(member.o)`.
+pub fn analyze_many(
+    paths: &[PathBuf],
+    options: &AnalysisOptions,
+) -> Vec<(PathBuf, Result<AnalysisReport, BatchError>)> {
+    paths
+        .par_iter()
+        .flat_map_iter(|path| each_object(path, |_, bof| bof.analyze_with(options)))
+        .collect()
+}
+
+/// Analyze every file in `paths` in parallel like [`analyze_many`], also
+/// calling `done` with each file's results (one per member for archives) as
+/// soon as they're ready, in the order the files finish
+pub fn analyze_streaming(
+    paths: &[PathBuf],
+    options: &AnalysisOptions,
+    done: impl Fn(&[(PathBuf, Result<AnalysisReport, BatchError>)]) + Sync,
+) -> Vec<(PathBuf, Result<AnalysisReport, BatchError>)> {
+    paths
+        .par_iter()
+        .flat_map_iter(|path| {
+            let results = each_object(path, |_, bof| bof.analyze_with(options));
+            done(&results);
+            results
+        })
+        .collect()
+}
+
+/// Hash every file in `paths` in parallel without analyzing them, like
+/// [`analyze_many`]
+pub fn hash_many(paths: &[PathBuf]) -> Vec<(PathBuf, Result<Hashes, BatchError>)> {
+    map_many(paths, |_, bof| bof.hashes())
+}
+
+/// Run `f` on every object in `paths` in parallel, with the name it's
+/// reported under, like [`analyze_many`]
+pub fn map_many<T: Send>(
+    paths: &[PathBuf],
+    f: impl Fn(&Path, &Bof) -> T + Sync,
+) -> Vec<(PathBuf, Result<T, BatchError>)> {
+    paths.par_iter().flat_map_iter(|path| each_object(path, &f)).collect()
+}
+
+/// Run `f` on the object file at `path`, or on each member if it's an
+/// archive
+fn each_object<T>(path: &Path, f: impl Fn(&Path, &Bof) -> T) -> Vec<(PathBuf, Result<T, BatchError>)> {
+    let parse = |name: PathBuf, buffer: &[u8]| {
+        let result = match Bof::parse(buffer) {
+            Ok(bof) => Ok(f(&name, &bof)),
+            Err(e) => Err(match sniff::sniff(buffer) {
+                Some(Foreign::Pe) => pe::inspect(buffer).map_or_else(|_| BatchError::Parse(e.to_string()), BatchError::Image),
+                _ => BatchError::Parse(e.to_string()),
+            }),
+        };
+        (name, result)
+    };
+    let buffer = match fs::read(path) {
+        Ok(buffer) => buffer,
+        Err(e) => return vec![(path.to_path_buf(), Err(BatchError::Read(e)))],
+    };
+    if !archive::is_archive(&buffer) {
+        return vec![parse(path.to_path_buf(), &buffer)];
+    }
+    match archive::members(&buffer) {
+        Ok(members) => members
+            .into_iter()
+            .map(|member| parse(PathBuf::from(format!("{}({})", path.display(), member.name)), member.data))
+            .collect(),
+        Err(e) => vec![(path.to_path_buf(), Err(BatchError::Parse(e.to_string())))],
+    }
+}
+
+/// Expand `paths` into the object files to check
+///
+/// Files are kept as given, whatever their extension; directories are
+/// searched recursively for object files, which are returned sorted.
+pub fn collect_objects(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut objects = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut found = Vec::new();
+            walk(path, &mut found)?;
+            found.sort();
+            objects.extend(found);
+        } else {
+            objects.push(path.clone());
+        }
+    }
+    Ok(objects)
+}
+
+fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk(&path, found)?;
+        } else if is_object(&path) {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_object(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| OBJECT_EXTENSIONS.iter().any(|known| extension.eq_ignore_ascii_case(known)))
+}
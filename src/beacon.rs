@@ -0,0 +1,274 @@
+//! Beacon API types shared by the in-process mock and the emulator
+
+use std::os::raw::c_int;
+
+/// Callback output types (beacon.h)
+pub const CALLBACK_OUTPUT: c_int = 0x0;
+pub const CALLBACK_OUTPUT_OEM: c_int = 0x1e;
+pub const CALLBACK_OUTPUT_UTF8: c_int = 0x20;
+pub const CALLBACK_ERROR: c_int = 0x0d;
+
+/// Output sent back through `BeaconOutput` or `BeaconPrintf`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Callback {
+    /// Callback type, e.g. [`CALLBACK_OUTPUT`] or [`CALLBACK_ERROR`]
+    pub typ: c_int,
+    /// Serialized as text, with invalid UTF-8 replaced
+    #[cfg_attr(feature = "serde", serde(with = "text"))]
+    pub data: Vec<u8>,
+}
+
+#[cfg(feature = "serde")]
+mod text {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&String::from_utf8_lossy(data))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        String::deserialize(deserializer).map(String::into_bytes)
+    }
+}
+
+/// Widest field and largest precision a conversion is rendered with, so a
+/// format string can't ask for gigabytes of padding; `format!` itself takes
+/// no precision past `u16::MAX`
+const MAX_FIELD: usize = 0x8000;
+
+/// Where the strings a format string refers to are read from
+#[cfg_attr(not(any(feature = "emulate", all(unix, target_arch = "x86_64"))), allow(dead_code))]
+pub(crate) trait Memory {
+    /// NUL-terminated string at `address`, without the terminator
+    fn string(&self, address: u64) -> Vec<u8>;
+    /// NUL-terminated UTF-16 string at `address`, without the terminator
+    fn wide_string(&self, address: u64) -> Vec<u16>;
+}
+
+/// Render a printf-style format string, reading `%s`-style arguments from
+/// `memory`
+#[cfg_attr(not(any(feature = "emulate", all(unix, target_arch = "x86_64"))), allow(dead_code))]
+pub(crate) fn format(fmt: &[u8], args: &[u64], memory: &impl Memory) -> Vec<u8> {
+    let mut output = Vec::with_capacity(fmt.len());
+    let mut args = args.iter().copied();
+    let mut next = || args.next().unwrap_or(0);
+    let mut i = 0;
+    while i < fmt.len() {
+        if fmt[i] != b'%' {
+            output.push(fmt[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+
+        // flags
+        let (mut left, mut zero, mut plus, mut space, mut alt) = (false, false, false, false, false);
+        while let Some(&c) = fmt.get(i) {
+            match c {
+                b'-' => left = true,
+                b'0' => zero = true,
+                b'+' => plus = true,
+                b' ' => space = true,
+                b'#' => alt = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        // width
+        let mut width = 0;
+        if fmt.get(i) == Some(&b'*') {
+            width = next() as i32 as i64;
+            if width < 0 {
+                left = true;
+                width = -width;
+            }
+            i += 1;
+        } else {
+            while let Some(c @ b'0'..=b'9') = fmt.get(i) {
+                width = width.saturating_mul(10).saturating_add((c - b'0') as i64);
+                i += 1;
+            }
+        }
+        let width = (width as usize).min(MAX_FIELD);
+        // precision
+        let mut precision = None;
+        if fmt.get(i) == Some(&b'.') {
+            i += 1;
+            if fmt.get(i) == Some(&b'*') {
+                // a negative precision is taken as if it were left out
+                precision = usize::try_from(next() as i32).ok();
+                i += 1;
+            } else {
+                let mut value = 0usize;
+                while let Some(c @ b'0'..=b'9') = fmt.get(i) {
+                    value = value.saturating_mul(10).saturating_add((c - b'0') as usize);
+                    i += 1;
+                }
+                precision = Some(value);
+            }
+            precision = precision.map(|precision| precision.min(MAX_FIELD));
+        }
+        // length
+        let mut long = 0;
+        let mut short = 0;
+        let mut wide = false;
+        loop {
+            match fmt.get(i) {
+                Some(b'h') => short += 1,
+                Some(b'l') | Some(b'w') => {
+                    long += 1;
+                    wide = true;
+                }
+                Some(b'z') | Some(b'j') | Some(b't') => long = 2,
+                Some(b'I') => {
+                    if fmt[i + 1..].starts_with(b"64") {
+                        long = 2;
+                        i += 2;
+                    } else if fmt[i + 1..].starts_with(b"32") {
+                        i += 2;
+                    } else {
+                        long = 2;
+                    }
+                }
+                _ => break,
+            }
+            i += 1;
+        }
+        let conversion = match fmt.get(i) {
+            Some(c) => *c,
+            None => break,
+        };
+        i += 1;
+
+        // `long` is 32 bits on Windows, so only `ll`/`I64`/`z` are 64-bit;
+        // `h` narrows to 16 bits and `hh` to 8
+        let signed = |value: u64| match (long, short) {
+            (2.., _) => value as i64,
+            (_, 0) => value as i32 as i64,
+            (_, 1) => value as i16 as i64,
+            _ => value as i8 as i64,
+        };
+        let unsigned = |value: u64| match (long, short) {
+            (2.., _) => value,
+            (_, 0) => value as u32 as u64,
+            (_, 1) => value as u16 as u64,
+            _ => value as u8 as u64,
+        };
+        // (sign or radix prefix, digits or text, whether zero padding applies)
+        let (prefix, body, numeric): (&str, Vec<u8>, bool) = match conversion {
+            b'%' => ("", b"%".to_vec(), false),
+            b'd' | b'i' => {
+                let value = signed(next());
+                let sign = if value < 0 { "-" } else if plus { "+" } else if space { " " } else { "" };
+                (sign, digits(value.unsigned_abs().to_string(), precision), precision.is_none())
+            }
+            b'u' => ("", digits(unsigned(next()).to_string(), precision), precision.is_none()),
+            b'x' => (if alt { "0x" } else { "" }, digits(format!("{:x}", unsigned(next())), precision), precision.is_none()),
+            b'X' => (if alt { "0X" } else { "" }, digits(format!("{:X}", unsigned(next())), precision), precision.is_none()),
+            b'o' => ("", digits(format!("{:o}", unsigned(next())), precision), precision.is_none()),
+            b'p' => ("", format!("{:016X}", next()).into_bytes(), false),
+            b'c' => ("", vec![next() as u8], false),
+            b'C' => ("", String::from(char::from_u32(next() as u32 & 0xffff).unwrap_or('?')).into_bytes(), false),
+            b'f' | b'F' | b'e' | b'E' | b'g' | b'G' => {
+                let value = f64::from_bits(next());
+                let precision = precision.unwrap_or(6);
+                let text = match conversion {
+                    b'e' => format!("{:.*e}", precision, value.abs()),
+                    b'E' => format!("{:.*E}", precision, value.abs()),
+                    _ => format!("{:.*}", precision, value.abs()),
+                };
+                let sign = if value.is_sign_negative() { "-" } else if plus { "+" } else if space { " " } else { "" };
+                (sign, text.into_bytes(), true)
+            }
+            b's' if !wide => {
+                let mut text = match next() {
+                    0 => b"(null)".to_vec(),
+                    address => memory.string(address),
+                };
+                if let Some(precision) = precision {
+                    text.truncate(precision);
+                }
+                ("", text, false)
+            }
+            b's' | b'S' => {
+                let mut text = match next() {
+                    0 => "(null)".to_string(),
+                    address => String::from_utf16_lossy(&memory.wide_string(address)),
+                };
+                if let Some(precision) = precision {
+                    text = text.chars().take(precision).collect();
+                }
+                ("", text.into_bytes(), false)
+            }
+            other => ("", vec![b'%', other], false),
+        };
+
+        let padding = width.saturating_sub(prefix.len() + body.len());
+        if left {
+            output.extend_from_slice(prefix.as_bytes());
+            output.extend_from_slice(&body);
+            output.extend(std::iter::repeat_n(b' ', padding));
+        } else if zero && numeric {
+            output.extend_from_slice(prefix.as_bytes());
+            output.extend(std::iter::repeat_n(b'0', padding));
+            output.extend_from_slice(&body);
+        } else {
+            output.extend(std::iter::repeat_n(b' ', padding));
+            output.extend_from_slice(prefix.as_bytes());
+            output.extend_from_slice(&body);
+        }
+    }
+    output
+}
+
+/// Integer digits zero padded to `precision`; a zero printed with precision
+/// 0 has no digits at all
+fn digits(digits: String, precision: Option<usize>) -> Vec<u8> {
+    match precision {
+        Some(0) if digits == "0" => Vec::new(),
+        Some(precision) => format!("{:0>1$}", digits, precision).into_bytes(),
+        None => digits.into_bytes(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Strings are read from an address's position in a list
+    struct Strings(Vec<&'static str>);
+
+    impl Memory for Strings {
+        fn string(&self, address: u64) -> Vec<u8> {
+            self.0[address as usize - 1].as_bytes().to_vec()
+        }
+
+        fn wide_string(&self, address: u64) -> Vec<u16> {
+            self.0[address as usize - 1].encode_utf16().collect()
+        }
+    }
+
+    fn render(fmt: &str, args: &[u64]) -> String {
+        String::from_utf8(format(fmt.as_bytes(), args, &Strings(vec!["text"]))).unwrap()
+    }
+
+    #[test]
+    fn precision_pads_every_integer_conversion() {
+        assert_eq!(render("%.4d %.4u %.4x %.4X %.4o", &[-7i64 as u64, 7, 0xab, 0xab, 8]), "-0007 0007 00ab 00AB 0010");
+        assert_eq!(render("[%.0d] [%.0x] [%3.0u]", &[0, 0, 0]), "[] [] [   ]");
+        assert_eq!(render("%05d %-6.3s|", &[42, 1]), "00042 tex   |");
+    }
+
+    #[test]
+    fn negative_precision_is_left_out() {
+        assert_eq!(render("%.*d|%.*s|%.*f", &[-1i64 as u64, 0, -3i64 as u64, 1, -1i64 as u64, 1.5f64.to_bits()]), "0|text|1.500000");
+        assert_eq!(render("%.*s", &[2, 1]), "te");
+    }
+
+    #[test]
+    fn short_modifiers_truncate() {
+        assert_eq!(render("%hd %hu %hx %hhd %hhu %hhX", &[0x18000, 0x1ffff, 0x12345, 0x1ff, 0x1ff, 0xabc]), "-32768 65535 2345 -1 255 BC");
+        assert_eq!(render("%lld %d", &[0x1_0000_0001, 0x1_0000_0001]), "4294967297 1");
+    }
+}
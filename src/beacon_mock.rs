@@ -0,0 +1,327 @@
+//! Stand-ins for the Beacon API, for use with the [loader](crate::loader)
+//!
+//! Output is written to stdout, or collected into a buffer while running
+//! inside [`capture`]. Everything is implemented with the Windows x64 calling
+//! convention so the functions can be bound directly to a BOF's `__imp_`
+//! symbols.
+
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::io::{self, Write};
+use std::os::raw::{c_char, c_int, c_short};
+use std::ptr;
+use std::slice;
+
+use crate::beacon::{self, Memory};
+use crate::loader::{LoadError, LoadedBof};
+use crate::Bof;
+
+/// Parser state for the data API (`datap` in beacon.h)
+#[repr(C)]
+pub struct DataParser {
+    original: *mut c_char,
+    buffer: *mut c_char,
+    length: c_int,
+    size: c_int,
+}
+
+/// Output buffer state for the format API (`formatp` in beacon.h)
+#[repr(C)]
+pub struct FormatBuffer {
+    original: *mut c_char,
+    buffer: *mut c_char,
+    length: c_int,
+    size: c_int,
+}
+
+pub use crate::beacon::{Callback, CALLBACK_ERROR, CALLBACK_OUTPUT, CALLBACK_OUTPUT_OEM, CALLBACK_OUTPUT_UTF8};
+
+/// Look up the mock implementation of a Beacon API function by name
+pub fn resolve(name: &str) -> Option<usize> {
+    let address = match name {
+        "BeaconDataParse" => beacon_data_parse as *const () as usize,
+        "BeaconDataInt" => beacon_data_int as *const () as usize,
+        "BeaconDataShort" => beacon_data_short as *const () as usize,
+        "BeaconDataLength" => beacon_data_length as *const () as usize,
+        "BeaconDataExtract" => beacon_data_extract as *const () as usize,
+        "BeaconPrintf" => beacon_printf as *const () as usize,
+        "BeaconOutput" => beacon_output as *const () as usize,
+        "BeaconFormatAlloc" => beacon_format_alloc as *const () as usize,
+        "BeaconFormatReset" => beacon_format_reset as *const () as usize,
+        "BeaconFormatFree" => beacon_format_free as *const () as usize,
+        "BeaconFormatAppend" => beacon_format_append as *const () as usize,
+        "BeaconFormatPrintf" => beacon_format_printf as *const () as usize,
+        "BeaconFormatToString" => beacon_format_to_string as *const () as usize,
+        "BeaconFormatInt" => beacon_format_int as *const () as usize,
+        _ => return None,
+    };
+    Some(address)
+}
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<Callback>>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, collecting the Beacon output produced on this thread instead of
+/// printing it
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<Callback>) {
+    struct Restore(Option<Vec<Callback>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CAPTURED.with(|captured| *captured.borrow_mut() = previous);
+        }
+    }
+
+    let previous = CAPTURED.with(|captured| captured.borrow_mut().replace(Vec::new()));
+    let restore = Restore(previous);
+    let result = f();
+    let callbacks = CAPTURED.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+    drop(restore);
+    (result, callbacks)
+}
+
+/// Load `bof`, call `entrypoint` with packed `args` and return its output
+///
+/// # Safety
+///
+/// This runs arbitrary native code from the object file, see
+/// [`LoadedBof::call`].
+pub unsafe fn run(bof: &Bof, entrypoint: &str, args: &[u8]) -> Result<Vec<Callback>, LoadError> {
+    let loaded = LoadedBof::load(bof, resolve)?;
+    let (result, callbacks) = capture(|| loaded.call(entrypoint, args));
+    result.map(|_| callbacks)
+}
+
+fn emit(typ: c_int, data: &[u8]) {
+    let data = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(callbacks) => {
+            callbacks.push(Callback { typ, data: data.to_vec() });
+            None
+        }
+        None => Some(data),
+    });
+    if let Some(data) = data {
+        let _ = io::stdout().write_all(data);
+    }
+}
+
+extern "win64" fn beacon_data_parse(parser: *mut DataParser, buffer: *mut c_char, size: c_int) {
+    let parser = match unsafe { parser.as_mut() } {
+        Some(parser) => parser,
+        None => return,
+    };
+    // The first four bytes hold the total length of the packed arguments
+    parser.original = buffer;
+    if buffer.is_null() || size < 4 {
+        parser.buffer = buffer;
+        parser.length = 0;
+        parser.size = 0;
+    } else {
+        parser.buffer = unsafe { buffer.add(4) };
+        parser.length = size - 4;
+        parser.size = size - 4;
+    }
+}
+
+extern "win64" fn beacon_data_int(parser: *mut DataParser) -> c_int {
+    match take(parser, 4) {
+        Some(bytes) => c_int::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        None => 0,
+    }
+}
+
+extern "win64" fn beacon_data_short(parser: *mut DataParser) -> c_short {
+    match take(parser, 2) {
+        Some(bytes) => c_short::from_le_bytes([bytes[0], bytes[1]]),
+        None => 0,
+    }
+}
+
+extern "win64" fn beacon_data_length(parser: *mut DataParser) -> c_int {
+    unsafe { parser.as_ref() }.map_or(0, |parser| parser.length)
+}
+
+extern "win64" fn beacon_data_extract(parser: *mut DataParser, size: *mut c_int) -> *mut c_char {
+    let length = beacon_data_int(parser);
+    // an empty item is the current position, not NULL
+    let data = match usize::try_from(length).ok().and_then(|length| take_ptr(parser, length)) {
+        Some(data) => data,
+        None => {
+            if !size.is_null() {
+                unsafe { *size = 0 };
+            }
+            return ptr::null_mut();
+        }
+    };
+    if !size.is_null() {
+        unsafe { *size = length };
+    }
+    data
+}
+
+/// `BeaconPrintf(int type, char *fmt, ...)`
+///
+/// Variadic arguments are passed exactly like regular ones in the Windows x64
+/// ABI, so they are picked up as a fixed number of 64-bit slots. Slots past
+/// the ones the caller actually passed are never read by the formatter.
+#[allow(clippy::too_many_arguments)]
+extern "win64" fn beacon_printf(
+    typ: c_int,
+    fmt: *const c_char,
+    a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64, a7: u64,
+    a8: u64, a9: u64, a10: u64, a11: u64, a12: u64, a13: u64, a14: u64, a15: u64,
+) {
+    if fmt.is_null() {
+        return;
+    }
+    let fmt = unsafe { CStr::from_ptr(fmt) }.to_bytes();
+    let args = [a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11, a12, a13, a14, a15];
+    let output = unsafe { format(fmt, &args) };
+    emit(typ, &output);
+}
+
+/// `BeaconOutput(int type, char *data, int len)`
+extern "win64" fn beacon_output(typ: c_int, data: *const c_char, len: c_int) {
+    if data.is_null() || len <= 0 {
+        return;
+    }
+    let data = unsafe { slice::from_raw_parts(data as *const u8, len as usize) };
+    emit(typ, data);
+}
+
+extern "win64" fn beacon_format_alloc(format: *mut FormatBuffer, maxsz: c_int) {
+    let format = match unsafe { format.as_mut() } {
+        Some(format) => format,
+        None => return,
+    };
+    let size = maxsz.max(0);
+    let buffer = unsafe { libc::calloc(size as usize + 1, 1) } as *mut c_char;
+    format.original = buffer;
+    format.buffer = buffer;
+    format.length = 0;
+    format.size = if buffer.is_null() { 0 } else { size };
+}
+
+extern "win64" fn beacon_format_reset(format: *mut FormatBuffer) {
+    let format = match unsafe { format.as_mut() } {
+        Some(format) if !format.original.is_null() => format,
+        _ => return,
+    };
+    unsafe { ptr::write_bytes(format.original, 0, format.size as usize) };
+    format.buffer = format.original;
+    format.length = 0;
+}
+
+extern "win64" fn beacon_format_free(format: *mut FormatBuffer) {
+    let format = match unsafe { format.as_mut() } {
+        Some(format) => format,
+        None => return,
+    };
+    unsafe { libc::free(format.original as *mut libc::c_void) };
+    format.original = ptr::null_mut();
+    format.buffer = ptr::null_mut();
+    format.length = 0;
+    format.size = 0;
+}
+
+extern "win64" fn beacon_format_append(format: *mut FormatBuffer, text: *const c_char, len: c_int) {
+    if text.is_null() || len <= 0 {
+        return;
+    }
+    append(format, unsafe { slice::from_raw_parts(text as *const u8, len as usize) });
+}
+
+/// `BeaconFormatPrintf(formatp *format, char *fmt, ...)`, see [`beacon_printf`]
+#[allow(clippy::too_many_arguments)]
+extern "win64" fn beacon_format_printf(
+    format: *mut FormatBuffer,
+    fmt: *const c_char,
+    a0: u64, a1: u64, a2: u64, a3: u64, a4: u64, a5: u64, a6: u64, a7: u64,
+    a8: u64, a9: u64, a10: u64, a11: u64, a12: u64, a13: u64, a14: u64, a15: u64,
+) {
+    if fmt.is_null() {
+        return;
+    }
+    let fmt = unsafe { CStr::from_ptr(fmt) }.to_bytes();
+    let args = [a0, a1, a2, a3, a4, a5, a6, a7, a8, a9, a10, a11, a12, a13, a14, a15];
+    let output = unsafe { self::format(fmt, &args) };
+    append(format, &output);
+}
+
+extern "win64" fn beacon_format_to_string(format: *mut FormatBuffer, size: *mut c_int) -> *mut c_char {
+    let format = match unsafe { format.as_mut() } {
+        Some(format) => format,
+        None => return ptr::null_mut(),
+    };
+    if !size.is_null() {
+        unsafe { *size = format.length };
+    }
+    format.original
+}
+
+/// Appends the value in network byte order, like Beacon does
+extern "win64" fn beacon_format_int(format: *mut FormatBuffer, value: c_int) {
+    append(format, &value.to_be_bytes());
+}
+
+/// Append to a format buffer, silently dropping data that doesn't fit
+fn append(format: *mut FormatBuffer, data: &[u8]) {
+    let format = match unsafe { format.as_mut() } {
+        Some(format) if !format.buffer.is_null() => format,
+        _ => return,
+    };
+    if (format.size - format.length) < data.len() as c_int {
+        return;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(data.as_ptr(), format.buffer as *mut u8, data.len());
+        format.buffer = format.buffer.add(data.len());
+    }
+    format.length += data.len() as c_int;
+}
+
+fn take<'a>(parser: *mut DataParser, count: usize) -> Option<&'a [u8]> {
+    take_ptr(parser, count).map(|data| unsafe { slice::from_raw_parts(data as *const u8, count) })
+}
+
+fn take_ptr(parser: *mut DataParser, count: usize) -> Option<*mut c_char> {
+    let parser = unsafe { parser.as_mut() }?;
+    if parser.buffer.is_null() || (parser.length as usize) < count || parser.length < 0 {
+        return None;
+    }
+    let data = parser.buffer;
+    parser.buffer = unsafe { parser.buffer.add(count) };
+    parser.length -= count as c_int;
+    Some(data)
+}
+
+/// This process's memory, for format strings from a loaded BOF
+struct Native;
+
+impl Memory for Native {
+    fn string(&self, address: u64) -> Vec<u8> {
+        unsafe { CStr::from_ptr(address as *const c_char) }.to_bytes().to_vec()
+    }
+
+    fn wide_string(&self, address: u64) -> Vec<u16> {
+        let pointer = address as *const u16;
+        let mut length = 0;
+        unsafe {
+            while *pointer.add(length) != 0 {
+                length += 1;
+            }
+            slice::from_raw_parts(pointer, length).to_vec()
+        }
+    }
+}
+
+/// Render a printf-style format string
+///
+/// # Safety
+///
+/// `%s`-style conversions dereference the corresponding argument as a
+/// pointer, so `args` must match what `fmt` describes.
+unsafe fn format(fmt: &[u8], args: &[u64]) -> Vec<u8> {
+    beacon::format(fmt, args, &Native)
+}
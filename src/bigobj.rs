@@ -0,0 +1,138 @@
+//! Big object (`/bigobj`, `-Wa,-mbig-obj`) COFF files
+//!
+//! MSVC and GNU as switch to this format for objects with more than 65279
+//! sections, which usually means heavy template or inline use. It has a
+//! longer header with 32-bit section and symbol counts and 20-byte symbol
+//! records, so neither goblin nor any BOF loader reads it. [`to_regular`]
+//! converts objects with few enough sections back to the ordinary format.
+
+use goblin::error::{Error, Result};
+use goblin::pe::section_table::SIZEOF_SECTION_TABLE;
+use goblin::pe::symbol::{COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_FILE};
+
+/// `ANON_OBJECT_HEADER_BIGOBJ.ClassID`
+const CLASS_ID: [u8; 16] = [
+    0xc7, 0xa1, 0xba, 0xd1, 0xee, 0xba, 0xa9, 0x4b, 0xaf, 0x20, 0xfa, 0xf6, 0x6a, 0xa4, 0xdc, 0xb8,
+];
+const HEADER_SIZE: usize = 56;
+const REGULAR_HEADER_SIZE: usize = 20;
+const SYMBOL_SIZE: usize = 20;
+/// Sections above this number collide with the special section numbers
+const MAX_REGULAR_SECTIONS: u32 = 0xfeff;
+
+/// Whether `buffer` starts with a bigobj header
+///
+/// Short import objects in import libraries start with the same signature;
+/// the class ID tells them apart.
+pub fn is_bigobj(buffer: &[u8]) -> bool {
+    buffer.len() >= HEADER_SIZE
+        && buffer[0..4] == [0x00, 0x00, 0xff, 0xff]
+        && u16_at(buffer, 4) >= 2
+        && buffer[12..28] == CLASS_ID
+}
+
+/// What a bigobj file can't be read as, for parse errors
+pub(crate) fn error() -> Error {
+    Error::Malformed(
+        "bigobj COFF file, which BOF loaders can't read; build without /bigobj or -Wa,-mbig-obj, \
+         or convert it with bof-strip"
+            .to_string(),
+    )
+}
+
+/// Convert a bigobj file to an ordinary COFF object with the same sections,
+/// symbols and symbol indices
+///
+/// `.file` names lose their last characters if they fill their 20-byte aux
+/// records, since each converted record holds 18.
+pub fn to_regular(buffer: &[u8]) -> Result<Vec<u8>> {
+    if !is_bigobj(buffer) {
+        return Err(Error::Malformed("not a bigobj COFF file".to_string()));
+    }
+    let sections = u32_at(buffer, 44);
+    let symbol_table = u32_at(buffer, 48) as usize;
+    let symbols = u32_at(buffer, 52) as usize;
+    if sections > MAX_REGULAR_SECTIONS {
+        return Err(Error::Malformed(format!(
+            "{} sections don't fit in an ordinary COFF object, which holds at most {}",
+            sections, MAX_REGULAR_SECTIONS
+        )));
+    }
+    let headers_end = HEADER_SIZE + sections as usize * SIZEOF_SECTION_TABLE;
+    let symbols_end = symbol_table + symbols * SYMBOL_SIZE;
+    if headers_end > buffer.len() || (symbols > 0 && (symbol_table < headers_end || symbols_end > buffer.len())) {
+        return Err(Error::Malformed("bigobj section or symbol table is out of bounds".to_string()));
+    }
+    // everything after the header moves up, and everything after the symbol
+    // table moves up again by what the shorter symbols save
+    let header_saving = HEADER_SIZE - REGULAR_HEADER_SIZE;
+    let symbol_saving = symbols * (SYMBOL_SIZE - COFF_SYMBOL_SIZE);
+    let moved = |pointer: u32| -> Result<u32> {
+        let pointer = pointer as usize;
+        match pointer {
+            0 => Ok(0),
+            _ if pointer < headers_end => Err(Error::Malformed("section data overlaps the headers".to_string())),
+            _ if symbols == 0 || pointer < symbol_table => Ok((pointer - header_saving) as u32),
+            _ if pointer >= symbols_end => Ok((pointer - header_saving - symbol_saving) as u32),
+            _ => Err(Error::Malformed("section data overlaps the symbol table".to_string())),
+        }
+    };
+
+    let mut out = Vec::with_capacity(buffer.len());
+    out.extend_from_slice(&buffer[6..8]); // machine
+    out.extend_from_slice(&(sections as u16).to_le_bytes());
+    out.extend_from_slice(&buffer[8..12]); // timestamp
+    let symbol_table_pointer = if symbols == 0 { 0 } else { symbol_table - header_saving };
+    out.extend_from_slice(&(symbol_table_pointer as u32).to_le_bytes());
+    out.extend_from_slice(&(symbols as u32).to_le_bytes());
+    out.extend_from_slice(&[0; 4]); // no optional header, no characteristics
+    for header in buffer[HEADER_SIZE..headers_end].chunks(SIZEOF_SECTION_TABLE) {
+        let mut header = header.to_vec();
+        // PointerToRawData, PointerToRelocations, PointerToLinenumbers
+        for offset in [20, 24, 28] {
+            let pointer = moved(u32_at(&header, offset))?;
+            header[offset..offset + 4].copy_from_slice(&pointer.to_le_bytes());
+        }
+        out.extend_from_slice(&header);
+    }
+    if symbols == 0 {
+        out.extend_from_slice(&buffer[headers_end..]);
+        return Ok(out);
+    }
+    out.extend_from_slice(&buffer[headers_end..symbol_table]);
+    let mut index = 0;
+    while index < symbols {
+        let record = &buffer[symbol_table + index * SYMBOL_SIZE..][..SYMBOL_SIZE];
+        let section_number = u32_at(record, 12) as i32;
+        let section_number = i16::try_from(section_number)
+            .map_err(|_| Error::Malformed(format!("symbol {} has section number {}", index, section_number)))?;
+        let storage_class = record[18];
+        let aux = (record[19] as usize).min(symbols - index - 1);
+        out.extend_from_slice(&record[0..12]); // name, value
+        out.extend_from_slice(&section_number.to_le_bytes());
+        out.extend_from_slice(&record[16..20]); // type, storage class, aux count
+        let aux_records = &buffer[symbol_table + (index + 1) * SYMBOL_SIZE..][..aux * SYMBOL_SIZE];
+        if storage_class == IMAGE_SYM_CLASS_FILE {
+            let name: Vec<u8> = aux_records.chunks(SYMBOL_SIZE).flatten().copied().take(aux * COFF_SYMBOL_SIZE).collect();
+            out.extend_from_slice(&name);
+        } else {
+            // a section definition's high half of its COMDAT section number
+            // lands in the ordinary format's padding, and is 0 with this few
+            // sections
+            for aux in aux_records.chunks(SYMBOL_SIZE) {
+                out.extend_from_slice(&aux[..COFF_SYMBOL_SIZE]);
+            }
+        }
+        index += 1 + aux;
+    }
+    out.extend_from_slice(&buffer[symbols_end..]);
+    Ok(out)
+}
+
+fn u16_at(buffer: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([buffer[offset], buffer[offset + 1]])
+}
+
+fn u32_at(buffer: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]])
+}
@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::apihash::{self, Algorithm};
+use bof_kit::Bof;
+use clap::{ArgEnum, Parser};
+
+/// What to print the hashes as
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Format {
+    /// A header of #defines
+    C,
+    /// MODULES and FUNCTIONS constants
+    Rust,
+}
+
+/// Print hashes of a BOF's MODULE$Function imports for loaders that resolve them by hash
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    #[clap(short, long, arg_enum, default_value = "c")]
+    format: Format,
+    /// Hash to compute: ror13, djb2, fnv1a or crc32 (repeatable) [default: all of them]
+    #[clap(short, long = "algorithm", value_name = "NAME", parse(try_from_str = parse_algorithm))]
+    algorithms: Vec<Algorithm>,
+    /// Write to a file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+    let imports = apihash::dfr_imports(&bof);
+    if imports.is_empty() {
+        fail("Nothing to hash", "the object has no MODULE$Function imports");
+    }
+    let algorithms = match args.algorithms.is_empty() {
+        true => Algorithm::ALL.to_vec(),
+        false => args.algorithms,
+    };
+    let source = args.input.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let text = match args.format {
+        Format::C => apihash::c_header(&imports, &algorithms, &source),
+        Format::Rust => apihash::rust_table(&imports, &algorithms, &source),
+    };
+    match &args.output {
+        Some(path) => {
+            if let Err(e) = fs::write(path, text) {
+                fail("Failed to write output file", e);
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+fn parse_algorithm(name: &str) -> Result<Algorithm, String> {
+    Algorithm::from_name(name).ok_or_else(|| format!("unknown hash: {}", name))
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    eprintln!("[!] {}", message);
+    eprintln!(" -> Error: {}", e);
+    process::exit(1);
+}
@@ -0,0 +1,77 @@
+use std::process;
+use std::sync::Arc;
+use bof_kit::compile::{self, Compiler};
+use bof_kit::profile;
+use bof_kit::rules::RULES;
+use bof_kit::{ApiProfile, Arch};
+use clap::Parser;
+
+/// Print the compiler commands that build a BOF bof-check accepts for a target
+#[derive(Parser)]
+struct Args {
+    /// C source file of the BOF
+    source: String,
+    /// Loader the BOF is built for: cs (or cs-4.9, etc.), sliver, havoc, meterpreter or brc4
+    #[clap(long, default_value = "cs", parse(try_from_str = profile::by_name))]
+    target: Arc<dyn ApiProfile>,
+    /// Architectures to build, separated by commas
+    #[clap(long, default_value = "x64,x86", use_value_delimiter = true, parse(try_from_str = parse_arch))]
+    arch: Vec<Arch>,
+    /// Toolchain: mingw, clang or msvc
+    #[clap(long, default_value = "mingw")]
+    compiler: Compiler,
+    /// Print a Makefile building the source instead
+    #[clap(long)]
+    makefile: bool,
+    /// Explain each flag and the bof-check rule it satisfies
+    #[clap(short, long)]
+    verbose: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.makefile {
+        let name = args.source.strip_suffix(".c").unwrap_or(&args.source);
+        match compile::makefile(name, args.target.as_ref(), &args.arch, None) {
+            Ok(makefile) => print!("{}", makefile),
+            Err(e) => fail("Failed to generate a Makefile", e),
+        }
+        return;
+    }
+
+    let invocations = match compile::invocations(args.target.as_ref(), args.compiler, &args.arch, &args.source) {
+        Ok(invocations) => invocations,
+        Err(e) => fail("Failed to generate compiler commands", e),
+    };
+    for invocation in &invocations {
+        if args.compiler == Compiler::Msvc {
+            println!("# from an {} Native Tools prompt", invocation.arch);
+        }
+        println!("{}", invocation);
+    }
+    if args.verbose {
+        let mut flags = Vec::new();
+        for flag in args.arch.iter().flat_map(|&arch| args.compiler.flags(arch)) {
+            if !flags.contains(&flag) {
+                flags.push(flag);
+            }
+        }
+        for flag in flags {
+            let rule = flag.rule.and_then(|rule| RULES.iter().find(|(name, _)| *name == rule));
+            match rule {
+                Some((name, description)) => println!("# {}: {} ({}: {})", flag.flag, flag.reason, name, description),
+                None => println!("# {}: {}", flag.flag, flag.reason),
+            }
+        }
+    }
+}
+
+fn parse_arch(name: &str) -> Result<Arch, String> {
+    Arch::from_name(name).ok_or_else(|| format!("unknown architecture: {}", name))
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
@@ -1,17 +1,1242 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-use clap::Parser;
+use std::io::{self, IsTerminal};
+use std::num::ParseIntError;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use bof_kit::pair::{self, PairIssue};
+use bof_kit::profile::{BeaconApiVersion, CobaltStrike, FileProfile};
+use bof_kit::sliver::{Manifest, ManifestFile};
+use bof_kit::descriptor::Descriptor;
+use bof_kit::detection::{self, DetectionSummary};
+use bof_kit::batch::{self, BatchError};
+use bof_kit::{archive, diagnostic, duplicates};
+use bof_kit::names::DEFAULT_MAX_NAME_LENGTH;
+use bof_kit::rule_file;
+use bof_kit::stack::DEFAULT_MAX_STACK;
+use bof_kit::rules::{RuleConfig, RuleRegistry};
+use bof_kit::watch::Watcher;
+use bof_kit::{profile, AnalysisOptions, AnalysisReport, ApiProfile, ArgSpec, Arch, ComdatProblem, DuplicateSymbol, WeakProblem, EntrypointProblem, ExceptionIssue, ExportDatabase, FingerprintDatabase, Finding, InitializerIssue, TableKind, ImportKind, Provenance, Severity, DEFAULT_TASKS_MAX_SIZE};
+use clap::{ArgEnum, Parser};
+use colored::Colorize;
+
+/// Exit codes, so bof-check can be used as a CI gate
+const EXIT_OK: i32 = 0;
+const EXIT_ERRORS: i32 = 1;
+const EXIT_WARNINGS: i32 = 2;
+const EXIT_PARSE_FAILURE: i32 = 3;
+
+/// When to color the output
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum ColorChoice {
+    /// When stdout is a terminal and NO_COLOR isn't set
+    Auto,
+    Always,
+    Never,
+}
+
+/// How to print findings
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum OutputFormat {
+    /// The full report
+    Text,
+    /// Only findings, as file:line:column diagnostics for editors' problem
+    /// matchers
+    Gcc,
+    /// One JSON object per file as soon as it's checked, then a summary
+    Ndjson,
+}
+
+/// How much of each report to print
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Only findings
+    Quiet,
+    Normal,
+    /// Relocation counts by section and type
+    Verbose,
+    /// Every symbol and relocation
+    Debug,
+}
 
 #[derive(Parser)]
 struct Args {
-    /// Path to object file
-    input: PathBuf,
+    /// Object files or archives to check, or directories to search for them
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Exit with a non-zero code on warnings as well as errors
+    #[clap(long)]
+    strict: bool,
+    /// Maximum task size in bytes (tasks_max_size in the Malleable C2 profile)
+    #[clap(long, default_value_t = DEFAULT_TASKS_MAX_SIZE)]
+    max_size: usize,
+    /// Longest import name in bytes before it's flagged, for loaders that copy names into fixed buffers
+    #[clap(long, default_value_t = DEFAULT_MAX_NAME_LENGTH)]
+    max_name_length: usize,
+    /// Entrypoint to check, for objects exporting several commands [default: the target's]
+    #[clap(long, value_name = "NAME")]
+    entrypoint: Option<String>,
+    /// Largest worst-case stack usage of the entrypoint's call chains, in bytes (or hex, 0x10000)
+    #[clap(long, default_value_t = DEFAULT_MAX_STACK, parse(try_from_str = parse_bytes))]
+    max_stack: usize,
+    /// Architecture the BOF is built for: x64, x86 or arm64; objects for another one are errors
+    #[clap(long, conflicts_with = "pair", parse(try_from_str = parse_arch))]
+    arch: Option<Arch>,
+    /// Loader to validate against: cs (or cs-4.9, etc.), sliver, havoc, meterpreter or brc4
+    #[clap(long, default_value = "cs", parse(try_from_str = profile::by_name))]
+    target: Arc<dyn ApiProfile>,
+    /// Cobalt Strike release to validate against: 4.1, 4.7, 4.8, 4.9 or 4.10 (latest by default)
+    #[clap(long)]
+    cs_version: Option<BeaconApiVersion>,
+    /// Load the loader's API profile from a TOML or JSON file instead of using a built-in target
+    #[clap(long, conflicts_with = "target")]
+    profile: Option<PathBuf>,
+    /// Write a Sliver extension.json manifest for the BOF (requires --target sliver)
+    #[clap(long)]
+    manifest: Option<PathBuf>,
+    /// Write a generic JSON task descriptor (name, entry, args) for other COFF loaders
+    #[clap(long)]
+    descriptor: Option<PathBuf>,
+    /// Directory of extra export lists (MODULE.txt, one function per line) to verify imports against
+    #[clap(long)]
+    exports: Option<PathBuf>,
+    /// bof_pack() format of the BOF's arguments, for the generated manifest or descriptor
+    #[clap(long, default_value = "")]
+    args_format: String,
+    /// Argument spec (args.toml or bof.json) to check against the BOF and use for the generated
+    /// manifest or descriptor
+    #[clap(long, conflicts_with = "args-format")]
+    spec: Option<PathBuf>,
+    #[clap(skip)]
+    arg_spec: Option<Arc<ArgSpec>>,
+    /// Flag imports of heavily monitored functions, such as CreateRemoteThread, and absolute
+    /// build paths that name the build machine user
+    #[clap(long)]
+    opsec: bool,
+    /// Only print each file's hashes, skipping the checks
+    #[clap(long)]
+    hash_only: bool,
+    /// Summarize what the imports let the BOF do, with MITRE ATT&CK technique hints
+    #[clap(long)]
+    capabilities: bool,
+    /// Print a detection engineering summary for defenders instead of the report: unique strings,
+    /// API combinations, section names and entropy anomalies, as Markdown for a ticket
+    #[clap(long, conflicts_with_all = &["format", "watch"])]
+    blue: bool,
+    /// Report which widely used public BOFs (TrustedSec's kits, nanodump, ...) the object probably is
+    #[clap(long)]
+    identify: bool,
+    /// TOML file of extra fingerprints ([[bof]] entries with sha256, imports, strings and symbols)
+    #[clap(long, value_name = "FILE", requires = "identify")]
+    fingerprints: Vec<PathBuf>,
+    /// Also check the other architecture's build (foo.x64.o and foo.x86.o) and
+    /// compare the two
+    #[clap(long)]
+    pair: bool,
+    /// List the readable strings in each loaded section
+    #[clap(long)]
+    strings: bool,
+    /// Disassemble the entrypoint, showing the symbols relocated operands point at
+    #[clap(long)]
+    disasm: bool,
+    /// Write the call graph between functions and imports to a Graphviz DOT file
+    #[clap(long, value_name = "FILE")]
+    callgraph: Option<PathBuf>,
+    /// Rule configuration to use instead of the nearest .bofkit.toml
+    #[clap(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// TOML file of extra rules, such as banned imports or required strings
+    #[clap(long, value_name = "FILE")]
+    extra_rules: Vec<PathBuf>,
+    /// Only print findings
+    #[clap(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print relocation counts; twice for every symbol and relocation
+    #[clap(short, long, parse(from_occurrences))]
+    verbose: u64,
+    /// Output format: text, gcc for file:line:column diagnostics placed on
+    /// the source line naming each finding's symbol, or ndjson to stream a
+    /// JSON object per file as it's checked
+    #[clap(long, arg_enum, default_value = "text")]
+    format: OutputFormat,
+    /// C source to place --format gcc diagnostics in, instead of the one the
+    /// object names or the one next to it
+    #[clap(long, value_name = "FILE")]
+    source: Option<PathBuf>,
+    /// Color the output: auto, always or never
+    #[clap(long, arg_enum, default_value = "auto", value_name = "WHEN")]
+    color: ColorChoice,
+    /// Keep running, checking each input again when it's rebuilt and printing
+    /// the findings that appeared or went away
+    #[clap(long, conflicts_with_all = &["hash-only", "manifest", "callgraph"])]
+    watch: bool,
+    /// Shortest string to list with --strings
+    #[clap(long, default_value_t = 4, requires = "strings")]
+    min_string_len: usize,
+}
+
+impl Args {
+    fn verbosity(&self) -> Verbosity {
+        match (self.quiet || self.format != OutputFormat::Text || self.blue, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            (false, _) => Verbosity::Debug,
+        }
+    }
 }
 
 fn main() {
-    let args = Args::parse();
-    let buffer = fs::read(&args.input).unwrap();
-    println!("[*] Parsing {}", &args.input.display());
-    bof_kit::parse(&buffer);
-    println!("[*] Done!");
-}
\ No newline at end of file
+    let mut args = Args::parse();
+    // colored already turns itself off for pipes and NO_COLOR/CLICOLOR, so auto leaves it be
+    match args.color {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+    if let Some(version) = args.cs_version {
+        if !args.target.name().starts_with("cs") {
+            println!("[!] --cs-version is only supported with --target cs");
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+        args.target = Arc::new(CobaltStrike::new(version));
+    }
+    if let Some(path) = &args.profile {
+        match FileProfile::load(path) {
+            Ok(profile) => args.target = Arc::new(profile),
+            Err(e) => {
+                println!("[!] {}", e);
+                process::exit(EXIT_PARSE_FAILURE);
+            }
+        }
+    }
+    if args.manifest.is_some() && args.target.name() != "sliver" {
+        println!("[!] --manifest is only supported with --target sliver");
+        process::exit(EXIT_PARSE_FAILURE);
+    }
+    if let Some(path) = &args.spec {
+        match ArgSpec::load(path) {
+            Ok(spec) => args.arg_spec = Some(Arc::new(spec)),
+            Err(e) => {
+                println!("[!] {}", e);
+                process::exit(EXIT_PARSE_FAILURE);
+            }
+        }
+    }
+    let config = args.config.clone().or_else(|| env::current_dir().ok().and_then(|dir| RuleConfig::discover(&dir)));
+    let rules = match &config {
+        Some(path) => match RuleConfig::load(path) {
+            Ok(rules) => {
+                if args.verbosity() > Verbosity::Quiet {
+                    println!("[*] Using rules from {}", path.display());
+                }
+                rules
+            }
+            Err(e) => {
+                println!("[!] {}", e);
+                process::exit(EXIT_PARSE_FAILURE);
+            }
+        },
+        None => RuleConfig::default(),
+    };
+    let mut registry = RuleRegistry::new();
+    for path in &args.extra_rules {
+        match rule_file::load(path) {
+            Ok(rules) => rules.into_iter().for_each(|rule| registry.register(rule)),
+            Err(e) => {
+                println!("[!] Failed to load {}", path.display());
+                println!(" -> Error: {}", e);
+                process::exit(EXIT_PARSE_FAILURE);
+            }
+        }
+    }
+    if let Err(e) = rules.validate(&registry) {
+        println!("[!] {}", e);
+        process::exit(EXIT_PARSE_FAILURE);
+    }
+    let mut exports = ExportDatabase::bundled();
+    if let Some(dir) = &args.exports {
+        if let Err(e) = exports.load_dir(dir) {
+            println!("[!] Failed to read export lists");
+            println!(" -> Error: {}", e);
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+    }
+    let fingerprints = match args.identify {
+        true => {
+            let mut database = FingerprintDatabase::bundled();
+            for path in &args.fingerprints {
+                if let Err(e) = database.load(path) {
+                    println!("[!] {}", e);
+                    process::exit(EXIT_PARSE_FAILURE);
+                }
+            }
+            Some(Arc::new(database))
+        }
+        false => None,
+    };
+    let options = AnalysisOptions {
+        profile: args.target.clone(),
+        max_size: args.max_size,
+        entrypoint: args.entrypoint.clone(),
+        arch: args.arch,
+        max_name_length: args.max_name_length,
+        max_stack: args.max_stack,
+        exports: Arc::new(exports),
+        // build paths and strings are what defenders match on
+        opsec: args.opsec || args.blue,
+        strings: match (args.strings, args.blue) {
+            (true, _) => Some(args.min_string_len),
+            (false, true) => Some(detection::MIN_STRING_LEN),
+            (false, false) => None,
+        },
+        fingerprints,
+        spec: args.arg_spec.clone(),
+        disassemble: args.disasm,
+        call_graph: args.callgraph.is_some(),
+        rules: Arc::new(rules),
+        extra_rules: Arc::new(registry),
+    };
+    let inputs = match batch::collect_objects(&args.inputs) {
+        Ok(inputs) if inputs.is_empty() => {
+            println!("[!] No object files found");
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+        Ok(inputs) => inputs,
+        Err(e) => {
+            println!("[!] Failed to read input directory");
+            println!(" -> Error: {}", e);
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+    };
+    if args.hash_only {
+        print_hashes(&inputs);
+    }
+    let mut pairs = Vec::new();
+    let inputs = match args.pair {
+        true => with_siblings(inputs, &mut pairs),
+        false => inputs,
+    };
+    if args.format == OutputFormat::Ndjson && (args.pair || args.watch) {
+        println!("[!] --format ndjson doesn't support --pair or --watch");
+        process::exit(EXIT_PARSE_FAILURE);
+    }
+    // before anything is analyzed, so an ndjson stream is never cut short
+    for (flag, given) in [
+        ("--manifest", args.manifest.is_some()),
+        ("--descriptor", args.descriptor.is_some()),
+        ("--callgraph", args.callgraph.is_some()),
+    ] {
+        if given && object_count(&inputs) > 1 {
+            println!("[!] {} only supports a single input file", flag);
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+    }
+    // text reports are printed once everything is checked, so long scans show a bar meanwhile
+    let shown = args.format == OutputFormat::Text && !args.blue && args.verbosity() > Verbosity::Quiet;
+    let progress = Progress::new(inputs.len(), shown && inputs.len() > 1 && io::stderr().is_terminal());
+    let results = batch::analyze_streaming(&inputs, &options, |results| {
+        if args.format == OutputFormat::Ndjson {
+            for (input, result) in results {
+                println!("{}", file_event(&args, input, result));
+            }
+        }
+        progress.tick(results.first().map(|(input, _)| input.as_path()));
+    });
+    progress.finish();
+
+    let renderer = Renderer { verbosity: args.verbosity() };
+    let mut outcomes: Vec<Outcome> = results.iter().map(|(input, result)| check(&args, &renderer, input, result)).collect();
+    for (a, b) in &pairs {
+        let (index, a_report, b_report) = match (report(&results, a), report(&results, b)) {
+            (Some((index, a_report)), Some((_, b_report))) => (index, a_report, b_report),
+            _ => continue,
+        };
+        let issues = pair::compare(a_report, b_report);
+        if !issues.is_empty() || renderer.verbosity > Verbosity::Quiet {
+            println!("[*] Comparing {} and {}", a.display(), b.display());
+        }
+        for issue in &issues {
+            print_colored(&format!("[!] {}", issue), issue.severity());
+        }
+        if issues.is_empty() {
+            renderer.info("[+] builds match");
+        }
+        let outcome = &mut outcomes[index];
+        let code = severity_code(issues.iter().map(PairIssue::severity).max(), args.strict);
+        outcome.code = [outcome.code, code].into_iter().max_by_key(|code| rank(*code)).unwrap_or(EXIT_OK);
+        outcome.errors += issues.iter().filter(|issue| issue.severity() == Severity::Error).count();
+        outcome.warnings += issues.iter().filter(|issue| issue.severity() == Severity::Warning).count();
+    }
+    for input in &inputs {
+        let duplicates = match fs::read(input) {
+            Ok(buffer) if archive::is_archive(&buffer) => duplicates::in_archive(&buffer),
+            _ => continue,
+        };
+        let duplicates: Vec<(Severity, DuplicateSymbol)> = duplicates
+            .into_iter()
+            .filter_map(|duplicate| Some((options.rules.severity(&Finding::DuplicateSymbol(duplicate.clone()))?, duplicate)))
+            .collect();
+        let members = format!("{}(", input.display());
+        let index = match results.iter().position(|(path, _)| path.to_string_lossy().starts_with(&members)) {
+            Some(index) if !duplicates.is_empty() => index,
+            _ => continue,
+        };
+        let rule = |duplicate: &DuplicateSymbol| Finding::DuplicateSymbol(duplicate.clone()).rule().to_string();
+        if args.format == OutputFormat::Ndjson {
+            let findings: Vec<serde_json::Value> =
+                duplicates.iter().map(|(severity, duplicate)| finding_json(&rule(duplicate), *severity, duplicate)).collect();
+            println!("{}", serde_json::json!({ "event": "archive", "file": input, "findings": findings }));
+        } else if args.format == OutputFormat::Gcc {
+            // the members share the archive's name, so every duplicate is on its first line
+            let location = diagnostic::Location { file: input.clone(), line: 1, column: 1 };
+            for (severity, duplicate) in &duplicates {
+                println!("{}: {}: {} [{}]", location, severity, duplicate, rule(duplicate));
+                println!("{}: note: {}", location, duplicate_fix(duplicate));
+            }
+        } else if args.blue {
+            println!("## Duplicate symbols: {}", input.display());
+            println!();
+            for (severity, duplicate) in &duplicates {
+                println!("- {} ({}): {}", duplicate, severity, duplicate_fix(duplicate));
+            }
+            println!();
+        } else {
+            println!("[*] Comparing the members of {}", input.display());
+            for (severity, duplicate) in &duplicates {
+                print_colored(&format!("[!] {}", duplicate), *severity);
+                println!(" -> {}", duplicate_fix(duplicate));
+            }
+        }
+        let severities: Vec<Severity> = duplicates.iter().map(|(severity, _)| *severity).collect();
+        let outcome = &mut outcomes[index];
+        let code = severity_code(severities.iter().copied().max(), args.strict);
+        outcome.code = [outcome.code, code].into_iter().max_by_key(|code| rank(*code)).unwrap_or(EXIT_OK);
+        outcome.errors += severities.iter().filter(|severity| **severity == Severity::Error).count();
+        outcome.warnings += severities.iter().filter(|severity| **severity == Severity::Warning).count();
+    }
+    let totals = Totals::new(&results, &outcomes);
+    if args.format == OutputFormat::Ndjson {
+        println!(
+            "{}",
+            serde_json::json!({
+                "event": "summary",
+                "files": totals.files,
+                "passed": totals.passed,
+                "warned": totals.warned,
+                "failed": totals.failed,
+                "errors": totals.errors,
+                "warnings": totals.warnings,
+                "total_size": totals.bytes,
+                "average_size": totals.average_size(),
+            })
+        );
+    }
+    if results.len() > 1 && renderer.verbosity > Verbosity::Quiet {
+        print_summary(&results, &outcomes, &totals);
+    }
+    if args.watch {
+        watch(&inputs, &options, &results);
+    }
+    renderer.info("[*] Done!");
+    process::exit(outcomes.iter().map(|outcome| outcome.code).max_by_key(|code| rank(*code)).unwrap_or(EXIT_OK));
+}
+
+/// Check inputs again each time they're rebuilt, printing how their
+/// findings changed
+fn watch(inputs: &[PathBuf], options: &AnalysisOptions, results: &[(PathBuf, Result<AnalysisReport, BatchError>)]) -> ! {
+    let mut watcher = match Watcher::new(inputs) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            println!("[!] Failed to watch the input files");
+            println!(" -> Error: {}", e);
+            process::exit(EXIT_PARSE_FAILURE);
+        }
+    };
+    let mut last: BTreeMap<PathBuf, Vec<(Severity, String)>> =
+        results.iter().map(|(input, result)| (input.clone(), messages(result))).collect();
+    println!("[*] Watching {} files for rebuilds", inputs.len());
+    loop {
+        let changed = match watcher.wait() {
+            Ok(changed) => changed,
+            Err(e) => {
+                println!("[!] Failed to watch the input files");
+                println!(" -> Error: {}", e);
+                process::exit(EXIT_PARSE_FAILURE);
+            }
+        };
+        for (input, result) in batch::analyze_many(&changed, options) {
+            let current = messages(&result);
+            let before = last.insert(input.clone(), current.clone()).unwrap_or_default();
+            let count = |severity| current.iter().filter(|(found, _)| *found == severity).count();
+            println!(
+                "[*] {} rebuilt: {} errors, {} warnings",
+                input.display(),
+                count(Severity::Error),
+                count(Severity::Warning)
+            );
+            let mut unchanged = true;
+            for (severity, message) in current.iter().filter(|message| !before.contains(message)) {
+                print_colored(&format!("[!] new: {}", message), *severity);
+                unchanged = false;
+            }
+            for (_, message) in before.iter().filter(|message| !current.contains(message)) {
+                println!("{}", format!("[+] cleared: {}", message).green());
+                unchanged = false;
+            }
+            if unchanged {
+                println!(" -> findings unchanged");
+            }
+        }
+    }
+}
+
+/// Each finding of a result with its configured severity
+fn messages(result: &Result<AnalysisReport, BatchError>) -> Vec<(Severity, String)> {
+    match result {
+        Ok(report) => report
+            .findings()
+            .iter()
+            .filter_map(|finding| Some((report.severity(finding)?, finding.to_string())))
+            .collect(),
+        Err(e) => vec![(Severity::Error, e.to_string())],
+    }
+}
+
+/// Print the hashes of every input, then exit
+fn print_hashes(inputs: &[PathBuf]) -> ! {
+    let mut code = EXIT_OK;
+    for (input, result) in batch::hash_many(inputs) {
+        println!("[*] {}", input.display());
+        match result {
+            Ok(hashes) => hashes.to_string().lines().for_each(|line| println!("[+] {}", line)),
+            Err(e) => {
+                println!("[!] {}", e);
+                code = EXIT_PARSE_FAILURE;
+            }
+        }
+    }
+    process::exit(code);
+}
+
+/// Index and report of `path` among the results, if it was analyzed
+fn report<'a>(
+    results: &'a [(PathBuf, Result<AnalysisReport, BatchError>)],
+    path: &Path,
+) -> Option<(usize, &'a AnalysisReport)> {
+    let index = results.iter().position(|(input, _)| input == path)?;
+    Some((index, results[index].1.as_ref().ok()?))
+}
+
+/// Add the other architecture's build of each input, remembering which
+/// inputs go together
+fn with_siblings(inputs: Vec<PathBuf>, pairs: &mut Vec<(PathBuf, PathBuf)>) -> Vec<PathBuf> {
+    let mut expanded: Vec<PathBuf> = Vec::new();
+    for input in &inputs {
+        if !expanded.contains(input) {
+            expanded.push(input.clone());
+        }
+        let sibling = match pair::sibling(input) {
+            Some(sibling) if sibling.exists() => sibling,
+            _ => {
+                println!("{} {}", "[!] no other architecture build found for".bold().yellow(), input.display());
+                continue;
+            }
+        };
+        if pairs.iter().any(|(a, b)| a == &sibling && b == input) {
+            continue;
+        }
+        pairs.push((input.clone(), sibling.clone()));
+        if !expanded.contains(&sibling) && !inputs.contains(&sibling) {
+            expanded.push(sibling);
+        }
+    }
+    expanded
+}
+
+/// What checking one file came to
+struct Outcome {
+    code: i32,
+    errors: usize,
+    warnings: usize,
+}
+
+fn check(args: &Args, renderer: &Renderer, input: &Path, result: &Result<AnalysisReport, BatchError>) -> Outcome {
+    if args.format == OutputFormat::Gcc {
+        return diagnose(args, input, result);
+    }
+    if args.blue {
+        return summarize_detection(args, input, result);
+    }
+    // streamed by file_event as each file finished
+    if args.format == OutputFormat::Ndjson {
+        return match result {
+            Ok(report) => outcome(args, report),
+            Err(_) => Outcome { code: EXIT_PARSE_FAILURE, errors: 0, warnings: 0 },
+        };
+    }
+    let findings = match result {
+        Ok(report) => report.findings(),
+        Err(_) => Vec::new(),
+    };
+    // quiet runs only name the files something was found in
+    if result.is_err() || !findings.is_empty() || renderer.verbosity > Verbosity::Quiet {
+        println!("[*] Parsing {}", input.display());
+    }
+    let report = match result {
+        Ok(report) => report,
+        Err(BatchError::Image(image)) => {
+            println!("[!] {}", image);
+            println!(" -> {}", image.hint());
+            for export in &image.exports {
+                renderer.info(format_args!("[+] export {}", export));
+            }
+            for import in &image.imports {
+                renderer.print(Verbosity::Verbose, format_args!("[*] import {}", import));
+            }
+            return Outcome { code: EXIT_PARSE_FAILURE, errors: 0, warnings: 0 };
+        }
+        Err(e) => {
+            println!("[!] {}", e);
+            return Outcome { code: EXIT_PARSE_FAILURE, errors: 0, warnings: 0 };
+        }
+    };
+    renderer.report(report);
+    if args.capabilities && report.capabilities.is_empty() {
+        renderer.info("[*] No recognized capabilities");
+    } else if args.capabilities {
+        renderer.info("[*] Capabilities");
+        for capability in &report.capabilities {
+            renderer.info(format_args!("[+] {}", capability));
+        }
+        for technique in &report.techniques {
+            renderer.info(format_args!("[+] ATT&CK {}", technique));
+        }
+    }
+    if args.identify && report.identifications.is_empty() {
+        renderer.info("[*] No known public BOF matched");
+    } else if args.identify {
+        for identification in &report.identifications {
+            renderer.info(format_args!("[+] {}", identification));
+            if !identification.url.is_empty() {
+                renderer.print(Verbosity::Verbose, format_args!(" -> {}", identification.url));
+            }
+            for matched in &identification.matched {
+                renderer.print(Verbosity::Verbose, format_args!(" -> {}", matched));
+            }
+        }
+    }
+    for string in &report.strings {
+        renderer.info(format_args!("[+] string {}", string));
+    }
+    if let (true, Some(entrypoint)) = (args.disasm, &report.entrypoint) {
+        renderer.info(format_args!("[*] Disassembly of {}", entrypoint));
+        for instruction in &report.disassembly {
+            renderer.info(format_args!("    {}", instruction));
+        }
+    }
+    if let Some(path) = &args.manifest {
+        write_manifest(args, renderer, input, path, report);
+    }
+    if let Some(path) = &args.descriptor {
+        write_descriptor(args, renderer, input, path, report);
+    }
+    if let (Some(path), Some(graph)) = (&args.callgraph, &report.call_graph) {
+        match fs::write(path, graph.to_dot()) {
+            Ok(()) => renderer.info(format_args!("[+] wrote call graph: {}", path.display())),
+            Err(e) => println!("{} {}", "[!] failed to write call graph:".bold().red(), e),
+        }
+    }
+    outcome(args, report)
+}
+
+fn outcome(args: &Args, report: &AnalysisReport) -> Outcome {
+    let findings = report.findings();
+    Outcome {
+        code: severity_code(report.max_severity(), args.strict),
+        errors: findings.iter().filter(|finding| report.severity(finding) == Some(Severity::Error)).count(),
+        warnings: findings.iter().filter(|finding| report.severity(finding) == Some(Severity::Warning)).count(),
+    }
+}
+
+/// Print findings as GCC-style diagnostics in the source the object was
+/// built from
+fn diagnose(args: &Args, input: &Path, result: &Result<AnalysisReport, BatchError>) -> Outcome {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            println!("{}:1:1: error: {}", input.display(), e);
+            return Outcome { code: EXIT_PARSE_FAILURE, errors: 0, warnings: 0 };
+        }
+    };
+    let source = args.source.clone().or_else(|| diagnostic::source_file(input, report));
+    for diagnostic in diagnostic::diagnostics(input, source.as_deref(), report) {
+        println!("{}", diagnostic);
+    }
+    outcome(args, report)
+}
+
+/// Number of objects in `inputs`, counting each member of an archive; a
+/// file that can't be read counts as one
+fn object_count(inputs: &[PathBuf]) -> usize {
+    match inputs {
+        [input] => match fs::read(input) {
+            Ok(buffer) if archive::is_archive(&buffer) => archive::members(&buffer).map_or(1, |members| members.len()),
+            _ => 1,
+        },
+        _ => inputs.len(),
+    }
+}
+
+/// An `--format ndjson` line for one checked file
+fn file_event(args: &Args, input: &Path, result: &Result<AnalysisReport, BatchError>) -> serde_json::Value {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => return serde_json::json!({ "event": "file", "file": input, "result": "error", "error": e.to_string() }),
+    };
+    let outcome = outcome(args, report);
+    let findings: Vec<serde_json::Value> = report
+        .findings()
+        .iter()
+        .filter_map(|finding| Some(finding_json(finding.rule(), report.severity(finding)?, finding)))
+        .collect();
+    serde_json::json!({
+        "event": "file",
+        "file": input,
+        "result": if outcome.code == EXIT_OK { "pass" } else { "fail" },
+        "errors": outcome.errors,
+        "warnings": outcome.warnings,
+        "arch": report.arch.map(|arch| arch.name()),
+        "sha256": report.hashes.sha256,
+        "estimated_size": report.estimated_size,
+        "findings": findings,
+    })
+}
+
+fn finding_json(rule: &str, severity: Severity, message: &dyn fmt::Display) -> serde_json::Value {
+    serde_json::json!({ "rule": rule, "severity": severity.to_string(), "message": message.to_string() })
+}
+
+fn summarize_detection(args: &Args, input: &Path, result: &Result<AnalysisReport, BatchError>) -> Outcome {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            println!("[!] {}: {}", input.display(), e);
+            return Outcome { code: EXIT_PARSE_FAILURE, errors: 0, warnings: 0 };
+        }
+    };
+    println!("{}", DetectionSummary::new(report).to_markdown(&input.display().to_string()));
+    outcome(args, report)
+}
+
+/// A byte count, in decimal or with 0x in hex
+fn parse_bytes(value: &str) -> Result<usize, ParseIntError> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+}
+
+fn parse_arch(name: &str) -> Result<Arch, String> {
+    Arch::from_name(name).ok_or_else(|| format!("unknown architecture: {}", name))
+}
+
+/// How bad an exit code is, for picking the worst one of a batch
+fn rank(code: i32) -> u8 {
+    match code {
+        EXIT_PARSE_FAILURE => 3,
+        EXIT_ERRORS => 2,
+        EXIT_WARNINGS => 1,
+        _ => 0,
+    }
+}
+
+/// Progress of a batch, drawn on stderr while the files are checked
+struct Progress {
+    total: usize,
+    done: AtomicUsize,
+    shown: bool,
+}
+
+impl Progress {
+    const WIDTH: usize = 30;
+
+    fn new(total: usize, shown: bool) -> Self {
+        Self { total, done: AtomicUsize::new(0), shown }
+    }
+
+    /// Count a finished file and redraw the bar
+    fn tick(&self, input: Option<&Path>) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if !self.shown {
+            return;
+        }
+        let filled = done * Self::WIDTH / self.total.max(1);
+        let name = input.map(|input| input.display().to_string()).unwrap_or_default();
+        // keep the end of long paths, which is the part that tells files apart
+        let name = match name.char_indices().rev().nth(39) {
+            Some((start, _)) => format!("...{}", &name[start..]),
+            None => name,
+        };
+        eprint!("\x1b[2K\r[*] Checking [{}{}] {}/{} {}", "#".repeat(filled), ".".repeat(Self::WIDTH - filled), done, self.total, name);
+    }
+
+    /// Clear the bar, before the reports are printed
+    fn finish(&self) {
+        if self.shown {
+            eprint!("\x1b[2K\r");
+        }
+    }
+}
+
+/// What a batch came to, for the closing summary
+struct Totals {
+    files: usize,
+    passed: usize,
+    /// Passed, but with warnings
+    warned: usize,
+    failed: usize,
+    errors: usize,
+    warnings: usize,
+    /// Bytes of the objects that parsed
+    bytes: usize,
+    parsed: usize,
+}
+
+impl Totals {
+    fn new(results: &[(PathBuf, Result<AnalysisReport, BatchError>)], outcomes: &[Outcome]) -> Self {
+        let reports: Vec<&AnalysisReport> = results.iter().filter_map(|(_, result)| result.as_ref().ok()).collect();
+        let passed = outcomes.iter().filter(|outcome| outcome.code == EXIT_OK);
+        Self {
+            files: outcomes.len(),
+            passed: passed.clone().filter(|outcome| outcome.warnings == 0).count(),
+            warned: passed.clone().filter(|outcome| outcome.warnings > 0).count(),
+            failed: outcomes.len() - passed.count(),
+            errors: outcomes.iter().map(|outcome| outcome.errors).sum(),
+            warnings: outcomes.iter().map(|outcome| outcome.warnings).sum(),
+            bytes: reports.iter().map(|report| report.compressed_size.raw).sum(),
+            parsed: reports.len(),
+        }
+    }
+
+    fn average_size(&self) -> usize {
+        self.bytes / self.parsed.max(1)
+    }
+}
+
+fn print_summary(results: &[(PathBuf, Result<AnalysisReport, BatchError>)], outcomes: &[Outcome], totals: &Totals) {
+    println!("[*] Summary");
+    println!("    {:<6} {:>6} {:>8}  FILE", "RESULT", "ERRORS", "WARNINGS");
+    for ((input, _), outcome) in results.iter().zip(outcomes) {
+        let result = match outcome.code {
+            EXIT_OK if outcome.warnings > 0 => "warn".yellow(),
+            EXIT_OK => "pass".green(),
+            EXIT_PARSE_FAILURE => "error".bold().red(),
+            _ => "FAIL".bold().red(),
+        };
+        println!("    {:<6} {:>6} {:>8}  {}", result, outcome.errors, outcome.warnings, input.display());
+    }
+    println!(
+        "[*] {} files scanned: {} passed, {} warned, {} failed ({} errors, {} warnings)",
+        totals.files, totals.passed, totals.warned, totals.failed, totals.errors, totals.warnings
+    );
+    println!("[*] {} bytes in total, {} bytes on average", totals.bytes, totals.average_size());
+}
+
+fn write_manifest(args: &Args, renderer: &Renderer, input: &Path, path: &PathBuf, report: &AnalysisReport) {
+    let arch = match report.arch {
+        Some(arch) => arch,
+        None => return,
+    };
+    let name = command_name(input);
+    let file = ManifestFile {
+        arch,
+        path: input.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default(),
+    };
+    let manifest = match &args.arg_spec {
+        Some(spec) => Manifest::from_spec(name, &report.expected_entrypoint, file, spec),
+        None => Manifest::new(name, &report.expected_entrypoint, file, &args.args_format),
+    };
+    match fs::write(path, manifest.to_json()) {
+        Ok(()) => renderer.info(format_args!("[+] wrote extension manifest: {}", path.display())),
+        Err(e) => println!("{} {}", "[!] failed to write extension manifest:".bold().red(), e),
+    }
+}
+
+fn write_descriptor(args: &Args, renderer: &Renderer, input: &Path, path: &PathBuf, report: &AnalysisReport) {
+    let name = command_name(input);
+    let descriptor = match &args.arg_spec {
+        Some(spec) => Descriptor::from_spec(name, &report.expected_entrypoint, spec),
+        None => Descriptor::new(name, &report.expected_entrypoint, &args.args_format),
+    };
+    match fs::write(path, descriptor.to_json()) {
+        Ok(()) => renderer.info(format_args!("[+] wrote task descriptor: {}", path.display())),
+        Err(e) => println!("{} {}", "[!] failed to write task descriptor:".bold().red(), e),
+    }
+}
+
+/// Command name for `input`, the file name without the conventional
+/// .x64/.x86 suffix
+fn command_name(input: &Path) -> &str {
+    let name = input.file_stem().and_then(|stem| stem.to_str()).unwrap_or("bof");
+    name.rsplit_once('.').map_or(name, |(name, _)| name)
+}
+
+/// How to get rid of the extra copies of `duplicate`
+fn duplicate_fix(duplicate: &DuplicateSymbol) -> &'static str {
+    match duplicate.is_comdat() {
+        true => "link the objects with bof-merge, which keeps one copy of each COMDAT",
+        false => "make all but one definition static, or leave out the object with the extra copy",
+    }
+}
+
+fn severity_code(severity: Option<Severity>, strict: bool) -> i32 {
+    match severity {
+        Some(Severity::Error) => EXIT_ERRORS,
+        Some(Severity::Warning) if strict => EXIT_WARNINGS,
+        _ => EXIT_OK,
+    }
+}
+
+fn print_colored(line: &str, severity: Severity) {
+    match severity {
+        Severity::Error => println!("{}", line.bold().red()),
+        Severity::Warning => println!("{}", line.bold().yellow()),
+    }
+}
+
+/// Prints reports, leaving out what wasn't asked for
+struct Renderer {
+    verbosity: Verbosity,
+}
+
+impl Renderer {
+    /// Print `line` if at least `level` of output was asked for
+    fn print(&self, level: Verbosity, line: impl fmt::Display) {
+        if self.verbosity >= level {
+            println!("{}", line);
+        }
+    }
+
+    fn info(&self, line: impl fmt::Display) {
+        self.print(Verbosity::Normal, line);
+    }
+
+    /// Print a finding in the color of its configured severity, whatever the
+    /// verbosity, and its fix-it with -v; false if its rule is off
+    fn finding(&self, report: &AnalysisReport, finding: &Finding) -> bool {
+        match report.severity(finding) {
+            Some(severity) => {
+                print_colored(&format!("[!] {}", finding), severity);
+                if let Some(fix) = report.fix(finding).filter(|_| self.verbosity >= Verbosity::Verbose) {
+                    println!(" -> fix: {}", fix);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn provenance(&self, provenance: &Provenance) {
+        match provenance.toolchain {
+            Some(_) => self.info(format_args!("[+] toolchain: {}", provenance)),
+            None => self.print(Verbosity::Verbose, "[*] toolchain: unknown"),
+        }
+        for evidence in &provenance.evidence {
+            self.print(Verbosity::Verbose, format_args!(" -> {}", evidence));
+        }
+        if let Some(timestamp) = provenance.timestamp_utc() {
+            self.info(format_args!("[+] timestamp: {}", timestamp));
+            self.info(" -> bof-strip --scrub-timestamp zeroes it");
+        }
+        for directive in &provenance.directives {
+            self.print(Verbosity::Verbose, format_args!("[+] linker directive: {}", directive));
+        }
+    }
+
+    fn sources(&self, report: &AnalysisReport) {
+        for unit in &report.translation_units {
+            self.info(format_args!("[+] source file: {}", unit.file));
+            if !unit.functions.is_empty() {
+                self.print(Verbosity::Verbose, format_args!(" -> functions: {}", unit.functions.join(", ")));
+            }
+        }
+        if report.translation_units.len() > 1 {
+            self.info(format_args!(" -> {} translation units, so objects were merged into this one", report.translation_units.len()));
+        }
+    }
+
+    fn report(&self, report: &AnalysisReport) {
+        // the same architecture findings the counts are made from
+        let arch_findings: Vec<Finding> = report
+            .findings()
+            .into_iter()
+            .filter(|finding| {
+                matches!(finding, Finding::UnsupportedMachine(_) | Finding::ArchMismatch(..) | Finding::UnsupportedArch(..))
+            })
+            .collect();
+        if let (Some(arch), true) = (report.arch, arch_findings.is_empty()) {
+            self.info(format_args!("[+] machine arch: {}", arch));
+        }
+        for finding in &arch_findings {
+            self.finding(report, finding);
+            if let Finding::ArchMismatch(expected, _) = finding {
+                let compiler = match expected {
+                    Arch::X64 => "x86_64-w64-mingw32-gcc, or cl from an x64 Native Tools prompt",
+                    Arch::X86 => "i686-w64-mingw32-gcc, or cl from an x86 Native Tools prompt",
+                    Arch::Arm64 => "aarch64-w64-mingw32-clang, or cl from an ARM64 Native Tools prompt",
+                };
+                println!(" -> rebuild it with the {} compiler ({})", expected, compiler);
+            }
+        }
+        for line in report.hashes.to_string().lines() {
+            self.print(Verbosity::Verbose, format_args!("[+] {}", line));
+        }
+        self.provenance(&report.provenance);
+        self.sources(report);
+        match &report.entrypoint {
+            Some(name) => {
+                self.info(format_args!("[+] entrypoint: {}()", name));
+                let others: Vec<String> =
+                    report.entrypoints.iter().filter(|other| *other != name).map(|other| format!("{}()", other)).collect();
+                if !others.is_empty() {
+                    self.info(format_args!("[+] other entrypoints: {}", others.join(", ")));
+                }
+            }
+            None if !report.entrypoint_issues.is_empty() => {}
+            None => {
+                let missing = Finding::MissingEntrypoint(report.entrypoint_symbol());
+                if self.finding(report, &missing) && !report.entrypoints.is_empty() {
+                    // --entrypoint takes the C name
+                    let names: Vec<&str> = report
+                        .entrypoints
+                        .iter()
+                        .map(|name| match report.arch {
+                            Some(Arch::X86) => name.strip_prefix('_').unwrap_or(name),
+                            _ => name,
+                        })
+                        .collect();
+                    println!(" -> the object defines {}; check one with --entrypoint", names.join(", "));
+                }
+            }
+        }
+        for issue in &report.entrypoint_issues {
+            if self.finding(report, &Finding::Entrypoint(issue.clone())) {
+                match issue.problem {
+                    EntrypointProblem::Static => println!(" -> remove static from its definition"),
+                    EntrypointProblem::Stdcall => println!(" -> remove WINAPI or __stdcall from its definition"),
+                    EntrypointProblem::Undecorated { .. } => println!(" -> build without -fno-leading-underscore"),
+                    _ => {}
+                }
+            }
+        }
+        for import in &report.imports {
+            let name = &import.name;
+            match &import.kind {
+                _ if report.is_misdecorated(name) => {}
+                ImportKind::BeaconApi => self.info(format_args!("[+] {} export: {}", report.target, name)),
+                ImportKind::Win32Builtin => self.info(format_args!("[+] {} win32 builtin: {}", report.target, name)),
+                ImportKind::UnsupportedApi => {
+                    self.finding(report, &Finding::UnsupportedApi(import.clone(), report.target.clone()));
+                }
+                ImportKind::Dynamic { module, function } => {
+                    self.info(format_args!("[+] dynamic function resolution: {}${}", module, function))
+                }
+                ImportKind::UnrecognizedModule { .. } if report.is_misspelled(import) => {}
+                ImportKind::UnrecognizedModule { .. } => {
+                    self.finding(report, &Finding::UnrecognizedModule(import.clone()));
+                }
+                ImportKind::Unknown => {
+                    self.finding(report, &Finding::UnknownImport(import.clone()));
+                }
+            }
+            if !import.referenced && self.finding(report, &Finding::UnreferencedImport(import.clone())) {
+                println!(" -> bof-strip removes unreferenced imports");
+            }
+        }
+        for issue in &report.dfr_issues {
+            if self.finding(report, &Finding::Dfr(issue.clone())) {
+                if let Some(fix) = issue.fix() {
+                    println!(" -> {}", fix);
+                }
+            }
+        }
+        for issue in &report.decoration_issues {
+            if self.finding(report, &Finding::Decoration(issue.clone())) {
+                println!(" -> {}", issue.fix());
+            }
+        }
+        for issue in &report.name_issues {
+            self.finding(report, &Finding::SymbolName(issue.clone()));
+        }
+        for issue in &report.opsec_issues {
+            self.finding(report, &Finding::Opsec(issue.clone()));
+        }
+        for leak in &report.path_leaks {
+            if self.finding(report, &Finding::BuildPath(leak.clone())) {
+                println!(" -> bof-strip --scrub-paths cuts build paths to file names");
+            }
+        }
+        if let Some(format) = &report.inferred_format {
+            self.info(format_args!("[+] inferred argument format: {}", format));
+        }
+        for issue in &report.spec_issues {
+            self.finding(report, &Finding::Spec(issue.clone()));
+        }
+        for issue in &report.format_issues {
+            self.finding(report, &Finding::Format(issue.clone()));
+        }
+        for issue in &report.injection_issues {
+            if self.finding(report, &Finding::Injection(issue.clone())) {
+                println!(" -> call BeaconCleanupProcess with the PROCESS_INFORMATION once the injection is done");
+            }
+        }
+        for issue in &report.exception_issues {
+            if self.finding(report, &Finding::Exceptions(issue.clone())) {
+                match issue {
+                    ExceptionIssue::UnwindInfo { removable: true, .. } => {
+                        println!(" -> bof-strip --unwind drops them; compile with -fno-asynchronous-unwind-tables")
+                    }
+                    ExceptionIssue::UnwindInfo { removable: false, .. } => {
+                        println!(" -> compile with -fno-asynchronous-unwind-tables")
+                    }
+                    ExceptionIssue::Handler { .. } => {
+                        println!(" -> replace __try/__except and try/catch with error checks")
+                    }
+                }
+            }
+        }
+        for issue in &report.tls_issues {
+            if self.finding(report, &Finding::ThreadLocal(issue.clone())) {
+                println!(" -> keep the state in locals or a structure passed to the functions that need it");
+            }
+        }
+        for issue in &report.initializer_issues {
+            if self.finding(report, &Finding::Initializer(issue.clone())) {
+                match issue {
+                    InitializerIssue::Table { kind: TableKind::Constructors, .. } => {
+                        println!(" -> initialize globals explicitly at the start of the entrypoint")
+                    }
+                    InitializerIssue::Table { kind: TableKind::Destructors, .. } | InitializerIssue::AtExit { .. } => {
+                        println!(" -> clean up explicitly before the entrypoint returns")
+                    }
+                }
+            }
+        }
+        for external in report.reported_externals() {
+            if self.finding(report, &Finding::UnresolvedExternal(external.clone())) {
+                println!(" -> {}", external.fix());
+            }
+        }
+        for symbol in &report.symbols {
+            self.print(Verbosity::Debug, format_args!("[*] symbol {}", symbol));
+        }
+        for aux in &report.aux_records {
+            self.print(Verbosity::Debug, format_args!("[*] aux record {}", aux));
+        }
+        for section in &report.sections {
+            let mut line = format!("[+] section {}: {} bytes, entropy {:.2}", section.name, section.size, section.entropy());
+            if self.verbosity >= Verbosity::Verbose {
+                let count = report.relocations.iter().filter(|relocation| relocation.section == section.name).count();
+                line.push_str(&format!(", {} relocations", count));
+            }
+            match section.is_high_entropy() {
+                true => self.info(line.bold().yellow()),
+                false => self.info(line),
+            }
+        }
+        for issue in &report.section_issues {
+            self.finding(report, &Finding::Section(issue.clone()));
+        }
+        for duplicate in &report.duplicate_symbols {
+            if self.finding(report, &Finding::DuplicateSymbol(duplicate.clone())) {
+                println!(" -> {}", duplicate_fix(duplicate));
+            }
+        }
+        for comdat in &report.comdats {
+            self.print(Verbosity::Verbose, format_args!("[*] COMDAT {}", comdat));
+        }
+        for issue in &report.comdat_issues {
+            if self.finding(report, &Finding::Comdat(issue.clone())) {
+                match issue.problem {
+                    ComdatProblem::Unreferenced { .. } => println!(" -> remove it, or make it static so the compiler can drop it"),
+                    ComdatProblem::BadAssociation { .. } | ComdatProblem::UnknownSelection(_) => {
+                        println!(" -> rebuild the object; whatever wrote it didn't follow the PE specification")
+                    }
+                }
+            }
+        }
+        for issue in &report.checksum_issues {
+            if self.finding(report, &Finding::Checksum(issue.clone())) {
+                println!(" -> bof-strip --update-checksums recomputes them");
+            }
+        }
+        for weak in &report.weak_externals {
+            self.print(Verbosity::Verbose, format_args!("[*] weak external {}", weak));
+        }
+        for issue in &report.weak_issues {
+            if self.finding(report, &Finding::WeakExternal(issue.clone())) {
+                match issue.problem {
+                    WeakProblem::BadDefault => println!(" -> rebuild the object; whatever wrote it didn't follow the PE specification"),
+                    WeakProblem::UndefinedDefault => println!(" -> define {} or its default, or drop the weak declaration", issue.weak.symbol),
+                    WeakProblem::Referenced => println!(" -> define {} without the weak attribute, or call {} directly", issue.weak.symbol, issue.weak.resolution()),
+                }
+            }
+        }
+        self.info(format_args!("[+] relocations: {}", report.relocations.len()));
+        let mut by_type: BTreeMap<&str, usize> = BTreeMap::new();
+        for relocation in &report.relocations {
+            *by_type.entry(relocation.type_name).or_default() += 1;
+        }
+        for (type_name, count) in by_type {
+            self.print(Verbosity::Verbose, format_args!(" -> {} {}", count, type_name));
+        }
+        for relocation in &report.relocations {
+            let target = relocation.target_section.as_ref().map_or(String::new(), |section| format!(" in {}", section));
+            self.print(
+                Verbosity::Debug,
+                format_args!(
+                    "[*] relocation {}+{:#x}: {} -> {}{}",
+                    relocation.section, relocation.offset, relocation.type_name, relocation.symbol, target
+                ),
+            );
+        }
+        for relocation in report.relocations.iter().filter(|relocation| !relocation.supported) {
+            self.finding(report, &Finding::UnsupportedRelocation(relocation.clone()));
+        }
+        for finding in &report.custom_findings {
+            self.finding(report, &Finding::Custom(finding.clone()));
+        }
+        let oversized = Finding::Oversized { size: report.estimated_size, limit: report.max_size };
+        if report.estimated_size <= report.max_size || !self.finding(report, &oversized) {
+            self.info(format_args!("[+] estimated loaded size: {} bytes", report.estimated_size));
+        }
+        let size = &report.compressed_size;
+        self.info(format_args!("[+] object size: {} bytes, {} deflated, {} with LZNT1", size.raw, size.deflate, size.lznt1));
+        if let Some(stack) = &report.stack {
+            let usage = Finding::StackUsage { size: stack.size, limit: report.max_stack, chain: stack.chain.clone() };
+            if stack.size <= report.max_stack || !self.finding(report, &usage) {
+                let chain = stack.chain.join(" -> ");
+                self.info(format_args!("[+] estimated stack usage: {} bytes ({})", stack.size, chain));
+            } else {
+                println!(" -> move large buffers to the heap, or allocate them once and pass them down");
+            }
+            if stack.recursive {
+                self.info(" -> a recursive call can use more");
+            }
+            for function in &stack.chain {
+                let frame = stack.frames.get(function).copied().unwrap_or_default();
+                self.print(Verbosity::Verbose, format_args!("[*] frame {}: {} bytes", function, frame));
+            }
+        }
+        if !report.dead_code.functions.is_empty() && self.finding(report, &Finding::DeadCode(report.dead_code.clone())) {
+            for function in &report.dead_code.functions {
+                self.print(Verbosity::Verbose, format_args!("[*] unreachable {}: {} bytes in {}", function.name, function.size, function.section));
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::PathBuf;
+use std::process;
+use bof_kit::batch;
+use bof_kit::cluster::{self, Sample, DEFAULT_THRESHOLD};
+use clap::Parser;
+
+/// Group similar BOFs in a collection, to find variants and rebuilds of the same tool
+#[derive(Parser)]
+struct Args {
+    /// Object files or archives to compare, or directories to search for them
+    #[clap(required = true)]
+    inputs: Vec<PathBuf>,
+    /// Lowest similarity, from 0 to 1, at which two objects are grouped
+    #[clap(long, default_value_t = DEFAULT_THRESHOLD)]
+    threshold: f64,
+}
+
+fn main() {
+    let args = Args::parse();
+    let inputs = match batch::collect_objects(&args.inputs) {
+        Ok(inputs) if inputs.is_empty() => {
+            println!("[!] No object files found");
+            process::exit(1);
+        }
+        Ok(inputs) => inputs,
+        Err(e) => {
+            println!("[!] Failed to read input directory");
+            println!(" -> Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let mut samples = Vec::new();
+    for (input, result) in batch::map_many(&inputs, |name, bof| Sample::new(name.display().to_string(), bof)) {
+        match result {
+            Ok(sample) => samples.push(sample),
+            Err(e) => println!("[!] {}: {}", input.display(), e),
+        }
+    }
+
+    let clusters = cluster::cluster(&samples, args.threshold);
+    for (number, members) in clusters.iter().enumerate() {
+        let first = &samples[members[0]];
+        println!("[*] Cluster {}: {} objects", number + 1, members.len());
+        println!("[+] {}", first.name);
+        for sample in members[1..].iter().map(|index| &samples[*index]) {
+            println!(
+                "[+] {} ({} of {}, similarity {:.2})",
+                sample.name,
+                first.relation(sample),
+                first.name,
+                first.similarity(sample)
+            );
+        }
+    }
+    let clustered: usize = clusters.iter().map(Vec::len).sum();
+    println!(
+        "[*] {} objects: {} in {} clusters, {} unlike any other",
+        samples.len(),
+        clustered,
+        clusters.len(),
+        samples.len() - clustered
+    );
+}
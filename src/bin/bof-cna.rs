@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::args::ArgsError;
+use bof_kit::cna::Script;
+use bof_kit::{pair, ArgSpec, Bof, BEACON_ENTRYPOINT};
+use clap::Parser;
+
+/// Generate an Aggressor script that runs a BOF from a Cobalt Strike alias
+#[derive(Parser)]
+struct Args {
+    /// Path to object file; an x64/x86 tag in the name (foo.x64.o) is swapped for the beacon's architecture
+    input: PathBuf,
+    /// Alias name (defaults to the file name without the architecture tag)
+    #[clap(short, long)]
+    name: Option<String>,
+    /// bof_pack() format of the BOF's arguments: b (file), i (int), s (short), z (string), Z (wide string)
+    #[clap(short, long, default_value = "")]
+    format: String,
+    /// Name of each argument, in format order, for the usage line
+    #[clap(long = "arg", value_name = "NAME")]
+    arg_names: Vec<String>,
+    /// Help text shown by the help command
+    #[clap(short, long)]
+    description: Option<String>,
+    /// Argument spec (args.toml or bof.json) with the names, types and defaults of the arguments
+    #[clap(long, conflicts_with_all = &["format", "arg-names", "description"])]
+    spec: Option<PathBuf>,
+    /// Function beacon_inline_execute calls
+    #[clap(long, default_value = BEACON_ENTRYPOINT)]
+    entrypoint: String,
+    /// Write the script to a file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+    let spec = args.spec.as_ref().map(|path| match ArgSpec::load(path) {
+        Ok(spec) => spec,
+        Err(e) => fail("Failed to load argument spec", e),
+    });
+    if let Some(typ) = args.format.chars().find(|typ| !"bisZz".contains(*typ)) {
+        fail("Invalid argument format", ArgsError::UnknownType(typ));
+    }
+    let expected = args.format.chars().count();
+    if !args.arg_names.is_empty() && args.arg_names.len() != expected {
+        fail("Wrong number of argument names", ArgsError::CountMismatch { expected, found: args.arg_names.len() });
+    }
+
+    let file = args.input.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    let name = args.name.clone().unwrap_or_else(|| command_name(&file));
+    let mut script = match &spec {
+        Some(spec) => Script::from_spec(&name, &args.entrypoint, &file, bof.arch(), spec),
+        None => Script::new(&name, &args.entrypoint, &file, bof.arch(), &args.format),
+    };
+    if let Some(name) = &args.name {
+        script.command = name.clone();
+    }
+    for (argument, name) in script.arguments.iter_mut().zip(&args.arg_names) {
+        argument.name = name.clone();
+    }
+    if let Some(description) = &args.description {
+        script.description = description.clone();
+    }
+    if !bof.has_symbol(&script.entrypoint) {
+        eprintln!("[!] entrypoint not found: {}", script.entrypoint);
+    }
+
+    let cna = script.to_cna();
+    match &args.output {
+        Some(path) => match fs::write(path, cna) {
+            Ok(()) => eprintln!("[+] wrote aggressor script: {}", path.display()),
+            Err(e) => fail("Failed to write aggressor script", e),
+        },
+        None => print!("{}", cna),
+    }
+}
+
+/// File name up to the architecture tag, or up to the extension
+fn command_name(file: &str) -> String {
+    let end = match pair::arch_tag(file) {
+        Some(tag) => tag.start.saturating_sub(1),
+        None => file.find('.').unwrap_or(file.len()),
+    };
+    file[..end].to_string()
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    eprintln!("[!] {}", message);
+    eprintln!(" -> Error: {}", e);
+    process::exit(1);
+}
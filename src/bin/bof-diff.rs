@@ -0,0 +1,79 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::{diff, Bof};
+use clap::Parser;
+
+/// How many differing ranges to list per code section
+const MAX_RANGES: usize = 16;
+
+/// Compare two builds of a BOF
+#[derive(Parser)]
+struct Args {
+    /// Path to the old object file
+    old: PathBuf,
+    /// Path to the new object file
+    new: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+    let old_buffer = read(&args.old);
+    let new_buffer = read(&args.new);
+    let old = parse(&old_buffer);
+    let new = parse(&new_buffer);
+    println!("[*] Comparing {} -> {}", args.old.display(), args.new.display());
+
+    let diff = diff::diff(&old, &new);
+    for import in &diff.imports_added {
+        println!("[+] import added: {}", import);
+    }
+    for import in &diff.imports_removed {
+        println!("[-] import removed: {}", import);
+    }
+    for section in &diff.sections {
+        match (section.old, section.new) {
+            (Some(old), Some(new)) => {
+                println!("[~] section {}: {} -> {} bytes ({:+})", section.name, old, new, section.delta())
+            }
+            (None, Some(new)) => println!("[+] section added: {} ({} bytes)", section.name, new),
+            (Some(old), None) => println!("[-] section removed: {} ({} bytes)", section.name, old),
+            (None, None) => {}
+        }
+    }
+    for symbol in &diff.symbols_added {
+        println!("[+] symbol added: {}", symbol);
+    }
+    for symbol in &diff.symbols_removed {
+        println!("[-] symbol removed: {}", symbol);
+    }
+    for code in &diff.code {
+        println!("[~] {}: {} bytes differ in {} ranges", code.section, code.changed_bytes(), code.ranges.len());
+        for range in code.ranges.iter().take(MAX_RANGES) {
+            println!(" -> 0x{:x}..0x{:x}", range.start, range.end);
+        }
+        if code.ranges.len() > MAX_RANGES {
+            println!(" -> ... and {} more", code.ranges.len() - MAX_RANGES);
+        }
+    }
+    if diff.is_empty() {
+        println!("[+] no differences");
+    }
+    println!("[*] Done!");
+}
+
+fn read(path: &PathBuf) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|e| {
+        println!("[!] Failed to read {}", path.display());
+        println!(" -> Error: {}", e);
+        process::exit(1);
+    })
+}
+
+fn parse(buffer: &[u8]) -> Bof<'_> {
+    Bof::parse(buffer).unwrap_or_else(|e| {
+        println!("[!] Failed to parse input as COFF file");
+        println!(" -> Error: {}", e);
+        process::exit(1);
+    })
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Arc;
+use bof_kit::{dump, profile, ApiProfile, Bof};
+use clap::Parser;
+
+/// Dump the headers, symbols, strings and relocations of a BOF
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// Print the file and section headers
+    #[clap(long)]
+    headers: bool,
+    /// Print a hex dump of a section, e.g. .text
+    #[clap(long, value_name = "NAME")]
+    section: Vec<String>,
+    /// Print the symbol table
+    #[clap(long)]
+    symbols: bool,
+    /// Print the string table
+    #[clap(long)]
+    strings: bool,
+    /// Print the relocation entries
+    #[clap(long)]
+    relocations: bool,
+    /// Print the bytes each function and variable takes up, largest first
+    #[clap(long)]
+    bloat: bool,
+    /// Loader to classify symbols against: cs (or cs-4.9, etc.), sliver, havoc, meterpreter or brc4
+    #[clap(long, default_value = "cs", parse(try_from_str = profile::by_name))]
+    target: Arc<dyn ApiProfile>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+
+    // everything but the hex dumps and size breakdown unless something was
+    // picked
+    let all = !(args.headers || args.symbols || args.strings || args.relocations || args.bloat || !args.section.is_empty());
+    if all || args.headers {
+        println!("[*] File header");
+        print!("{}", dump::file_header(&bof));
+        println!("[*] Section headers");
+        print!("{}", dump::section_headers(&bof));
+    }
+    for name in &args.section {
+        match dump::section_hex(&bof, name) {
+            Some(hex) => {
+                println!("[*] Contents of {}", name);
+                print!("{}", hex);
+            }
+            None => fail("Failed to dump section", format!("no section named {}", name)),
+        }
+    }
+    if all || args.symbols {
+        println!("[*] Symbol table");
+        print!("{}", dump::symbol_table(&bof, args.target.as_ref()));
+    }
+    if all || args.strings {
+        println!("[*] String table");
+        print!("{}", dump::string_table(&bof));
+    }
+    if all || args.relocations {
+        println!("[*] Relocations");
+        print!("{}", dump::relocation_table(&bof));
+    }
+    if args.bloat {
+        println!("[*] Size by symbol");
+        print!("{}", dump::bloat(&bof));
+    }
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
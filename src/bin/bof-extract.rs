@@ -0,0 +1,106 @@
+use std::fs;
+use std::num::ParseIntError;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::Bof;
+use clap::Parser;
+
+/// Carve embedded blobs, such as shellcode or configuration, out of a BOF's
+/// sections into files
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// Section to carve from; with neither --symbol nor --offset, every symbol in it is written
+    #[clap(long, default_value = ".data")]
+    section: String,
+    /// Symbol to write, from its value to the next symbol in its section (repeatable)
+    #[clap(long, value_name = "NAME", conflicts_with = "offset")]
+    symbol: Vec<String>,
+    /// Offset into the section to start at, in decimal or with 0x in hex
+    #[clap(long, parse(try_from_str = parse_number))]
+    offset: Option<usize>,
+    /// Bytes to write from --offset [default: up to the end of the section]
+    #[clap(long, requires = "offset", parse(try_from_str = parse_number))]
+    length: Option<usize>,
+    /// Directory to write the blobs to, one NAME.bin per symbol
+    #[clap(short, long, default_value = ".")]
+    output: PathBuf,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+
+    let mut blobs = Vec::new();
+    for name in &args.symbol {
+        match bof.symbol_data(name) {
+            Some((_, data)) => blobs.push((name.clone(), data)),
+            None => fail(&format!("Failed to find {}", name), "no symbol by that name is defined in the object"),
+        }
+    }
+    if args.symbol.is_empty() {
+        let data = match bof.section_data(&args.section) {
+            Some(data) => data,
+            None => fail(&format!("Failed to find {}", args.section), "no section by that name"),
+        };
+        match args.offset {
+            Some(offset) => {
+                let end = args.length.map_or(data.len(), |length| offset.saturating_add(length));
+                match data.get(offset..end) {
+                    Some(blob) => blobs.push((format!("{}+0x{:x}", args.section, offset), blob)),
+                    None => fail("Failed to carve blob", format!("{} is only {} bytes", args.section, data.len())),
+                }
+            }
+            None => blobs.extend(bof.section_symbols(&args.section)),
+        }
+    }
+    if blobs.is_empty() {
+        fail(&format!("Nothing to extract from {}", args.section), "the section defines no symbols; pass --offset");
+    }
+
+    if let Err(e) = fs::create_dir_all(&args.output) {
+        fail("Failed to create output directory", e);
+    }
+    for (name, data) in blobs {
+        let path = args.output.join(file_name(&name));
+        if let Err(e) = fs::write(&path, data) {
+            fail("Failed to write output file", e);
+        }
+        println!("[+] {}: {} bytes -> {}", name, data.len(), path.display());
+    }
+}
+
+/// A file name for the blob `name`, without the characters symbol names can
+/// have and paths can't
+fn file_name(name: &str) -> PathBuf {
+    let name: String = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+') {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    PathBuf::from(format!("{}.bin", name.trim_start_matches('.')))
+}
+
+/// A number, in decimal or with 0x in hex
+fn parse_number(value: &str) -> Result<usize, ParseIntError> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
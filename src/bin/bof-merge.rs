@@ -0,0 +1,62 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::merge;
+use bof_kit::writer::Object;
+use clap::Parser;
+
+/// Link several object files into a single BOF
+#[derive(Parser)]
+struct Args {
+    /// Object files to merge, in link order
+    #[clap(required = true, min_values = 2)]
+    inputs: Vec<PathBuf>,
+    /// Where to write the merged object
+    #[clap(short, long)]
+    output: PathBuf,
+    /// Recompute the checksums in COMDAT section definition records
+    #[clap(long)]
+    update_checksums: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut objects = Vec::with_capacity(args.inputs.len());
+    for input in &args.inputs {
+        let buffer = match fs::read(input) {
+            Ok(buffer) => buffer,
+            Err(e) => fail(&format!("Failed to read {}", input.display()), e),
+        };
+        match Object::parse(&buffer) {
+            Ok(object) => objects.push(object),
+            Err(e) => fail(&format!("Failed to parse {}", input.display()), e),
+        }
+    }
+    let mut merged = match merge::merge(&objects) {
+        Ok(merged) => merged,
+        Err(e) => fail("Failed to merge objects", e),
+    };
+    if args.update_checksums {
+        println!("[+] updated {} section checksums", merged.update_checksums());
+    }
+    let bytes = match merged.to_bytes() {
+        Ok(bytes) => bytes,
+        Err(e) => fail("Failed to write object", e),
+    };
+    if let Err(e) = fs::write(&args.output, &bytes) {
+        fail("Failed to write output file", e);
+    }
+    println!(
+        "[+] merged {} objects into {} sections and {} symbols: {}",
+        objects.len(),
+        merged.sections.len(),
+        merged.symbols.len(),
+        args.output.display()
+    );
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
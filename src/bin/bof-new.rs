@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::args::ArgsError;
+use bof_kit::scaffold;
+use bof_kit::spec::ArgumentSpec;
+use bof_kit::ArgSpec;
+use clap::Parser;
+
+/// Create a BOF project: beacon.h, a go() that parses the arguments, a Makefile, args.toml and an
+/// Aggressor script
+#[derive(Parser)]
+struct Args {
+    /// Name of the BOF, its source file and its alias
+    name: String,
+    /// Directory to create the project in (defaults to the name)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// bof_pack() format of the BOF's arguments: b (file), i (int), s (short), z (string), Z (wide string)
+    #[clap(short, long, default_value = "")]
+    format: String,
+    /// Name of each argument, in format order
+    #[clap(long = "arg", value_name = "NAME")]
+    arg_names: Vec<String>,
+    /// Help text shown by the help command
+    #[clap(short, long, default_value = "")]
+    description: String,
+    /// Argument spec (args.toml or bof.json) to take the arguments from
+    #[clap(long, conflicts_with_all = &["format", "arg-names", "description"])]
+    spec: Option<PathBuf>,
+    /// Write into a directory that already has files in it, replacing the generated ones
+    #[clap(long)]
+    force: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.name.is_empty() || !args.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        fail("Invalid BOF name", "use letters, digits, underscores and dashes");
+    }
+    let spec = match &args.spec {
+        Some(path) => match ArgSpec::load(path) {
+            Ok(spec) => spec,
+            Err(e) => fail("Failed to load argument spec", e),
+        },
+        None => spec_from_format(&args),
+    };
+
+    let dir = args.output.clone().unwrap_or_else(|| PathBuf::from(&args.name));
+    let occupied = fs::read_dir(&dir).map(|mut entries| entries.next().is_some()).unwrap_or(false);
+    if occupied && !args.force {
+        fail("Output directory isn't empty", format!("{} (pass --force to write into it anyway)", dir.display()));
+    }
+    if let Err(e) = fs::create_dir_all(&dir) {
+        fail("Failed to create project directory", e);
+    }
+    for file in scaffold::project(&args.name, &spec) {
+        let path = dir.join(&file.path);
+        if let Err(e) = fs::write(&path, &file.contents) {
+            fail(&format!("Failed to write {}", path.display()), e);
+        }
+        println!("[+] wrote {}", path.display());
+    }
+    println!("[*] Build it with make -C {}", dir.display());
+}
+
+/// A spec with one argument per character of --format
+fn spec_from_format(args: &Args) -> ArgSpec {
+    if let Some(typ) = args.format.chars().find(|typ| !"bisZz".contains(*typ)) {
+        fail("Invalid argument format", ArgsError::UnknownType(typ));
+    }
+    let expected = args.format.chars().count();
+    if !args.arg_names.is_empty() && args.arg_names.len() != expected {
+        fail("Wrong number of argument names", ArgsError::CountMismatch { expected, found: args.arg_names.len() });
+    }
+    let arguments = args
+        .format
+        .chars()
+        .enumerate()
+        .map(|(index, typ)| ArgumentSpec {
+            name: args.arg_names.get(index).cloned().unwrap_or_else(|| format!("arg{}", index)),
+            typ,
+            description: String::new(),
+            default: None,
+        })
+        .collect();
+    ArgSpec { description: args.description.clone(), arguments, ..ArgSpec::default() }
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
@@ -0,0 +1,100 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process;
+use bof_kit::args::{self, Arg, ArgsError};
+use bof_kit::meterpreter;
+use bof_kit::{ArgSpec, BEACON_ENTRYPOINT};
+use clap::Parser;
+
+/// Pack arguments the same way as Aggressor's bof_pack()
+#[derive(Parser)]
+#[clap(allow_negative_numbers = true)]
+struct Args {
+    /// Format string: b (binary), i (int), s (short), z (string), Z (wide string)
+    #[clap(required_unless_present = "spec")]
+    format: Option<String>,
+    /// One value per format character; binary values are hex, or @path to read a file
+    values: Vec<String>,
+    /// Write the packed arguments to a file instead of stdout
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Print the packed arguments as hex
+    #[clap(long)]
+    hex: bool,
+    /// Instead of packing, print the Meterpreter execute_bof command that runs BOF with these arguments
+    #[clap(long, value_name = "BOF")]
+    execute_bof: Option<String>,
+    /// Take the format from an argument spec (args.toml or bof.json); every value is then an argument,
+    /// and trailing arguments with defaults can be left out
+    #[clap(long)]
+    spec: Option<PathBuf>,
+    /// Entrypoint for the execute_bof command
+    #[clap(long, default_value = BEACON_ENTRYPOINT, requires = "execute-bof")]
+    entrypoint: String,
+}
+
+fn main() {
+    let args = Args::parse();
+    let (format, values) = match &args.spec {
+        Some(path) => {
+            let spec = match ArgSpec::load(path) {
+                Ok(spec) => spec,
+                Err(e) => {
+                    eprintln!("[!] {}", e);
+                    process::exit(1);
+                }
+            };
+            // with a spec there's no format, so the first positional is a value
+            let given: Vec<String> = args.format.iter().chain(&args.values).cloned().collect();
+            match spec.values(&given) {
+                Ok(values) => (spec.format(), values),
+                Err(e) => {
+                    eprintln!("[!] {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        None => (args.format.clone().unwrap_or_default(), args.values.clone()),
+    };
+    let packed = match pack(&format, &values) {
+        Ok(packed) => packed,
+        Err(e) => {
+            eprintln!("[!] {}", e);
+            process::exit(1);
+        }
+    };
+    if let Some(bof) = &args.execute_bof {
+        println!("{}", meterpreter::execute_bof_command(bof, &args.entrypoint, &format, &values));
+        return;
+    }
+    let packed = match args.hex {
+        true => packed.iter().map(|byte| format!("{:02x}", byte)).collect::<String>().into_bytes(),
+        false => packed,
+    };
+    let result = match &args.output {
+        Some(path) => fs::write(path, &packed),
+        None => io::stdout().write_all(&packed),
+    };
+    if let Err(e) = result {
+        eprintln!("[!] Failed to write packed arguments: {}", e);
+        process::exit(1);
+    }
+}
+
+fn pack(format: &str, values: &[String]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let expected = format.chars().count();
+    if expected != values.len() {
+        return Err(ArgsError::CountMismatch { expected, found: values.len() }.into());
+    }
+    let mut parsed = Vec::with_capacity(values.len());
+    for (typ, value) in format.chars().zip(values) {
+        // binary values can also be read from a file
+        let arg = match (typ, value.strip_prefix('@')) {
+            ('b', Some(path)) => Arg::Binary(fs::read(path)?),
+            _ => Arg::parse(typ, value)?,
+        };
+        parsed.push(arg);
+    }
+    Ok(args::pack(&parsed))
+}
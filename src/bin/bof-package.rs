@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use bof_kit::bundle::{self, Layout, PackageManifest};
+use bof_kit::package::{self, Aes256Cbc, Transform, Xor};
+use bof_kit::Bof;
+use clap::{ArgEnum, Parser};
+
+/// A transform --transform applies, each with a freshly generated key
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Kind {
+    /// XOR with a repeating key of --xor-key-length bytes
+    Xor,
+    /// AES-256-CBC with PKCS#7 padding
+    Aes,
+}
+
+/// Encrypt a BOF for a custom stager, writing the encrypted file and a JSON manifest with the
+/// keys to decrypt it and the SHA-256 to report once it's decrypted; or, given a bof.toml, bundle
+/// its objects with an Aggressor script and Sliver extension.json into a .tar.gz for operators
+#[derive(Parser)]
+struct Args {
+    /// Path to object file, or to a bof.toml package manifest
+    input: PathBuf,
+    /// Transform to apply to an object, in the order given (repeatable)
+    #[clap(short, long = "transform", arg_enum, value_name = "TRANSFORM")]
+    transforms: Vec<Kind>,
+    /// Bytes of generated XOR key
+    #[clap(long, default_value_t = 16)]
+    xor_key_length: usize,
+    /// Where to write the encrypted object [default: INPUT.enc], or the bundle [default:
+    /// NAME-VERSION.tar.gz next to the manifest]
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Bundle a bof.toml as a Sliver armory package: only the objects and an armory
+    /// extension.json, in NAME.tar.gz
+    #[clap(long)]
+    armory: bool,
+    /// Where to write the decryption manifest [default: OUTPUT.json]
+    #[clap(long, requires = "transforms")]
+    manifest: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+    if args.input.extension().is_some_and(|extension| extension == "toml") {
+        if !args.transforms.is_empty() {
+            fail("Can't encrypt a bundle", "--transform applies to objects; bundles ship them as built");
+        }
+        let layout = match args.armory {
+            true => Layout::Armory,
+            false => Layout::Kit,
+        };
+        return bundle(&args.input, args.output.as_deref(), layout);
+    }
+    if args.armory {
+        fail("Can't make an armory package of an object", "--armory takes a bof.toml");
+    }
+    if args.transforms.is_empty() {
+        fail("Nothing to apply", "give at least one --transform");
+    }
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    if let Err(e) = Bof::parse(&buffer) {
+        fail("Failed to parse input as COFF file", e);
+    }
+
+    let mut transforms: Vec<Box<dyn Transform>> = Vec::new();
+    for kind in &args.transforms {
+        let transform: Result<Box<dyn Transform>, _> = match kind {
+            Kind::Xor => Xor::generate(args.xor_key_length).map(|xor| Box::new(xor) as _),
+            Kind::Aes => Aes256Cbc::generate().map(|aes| Box::new(aes) as _),
+        };
+        match transform {
+            Ok(transform) => transforms.push(transform),
+            Err(e) => fail("Failed to generate a key", e),
+        }
+    }
+    let file = args.input.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let (data, manifest) = package::package(&file, &buffer, &transforms);
+
+    let output = args.output.unwrap_or_else(|| append(&args.input, ".enc"));
+    let manifest_path = args.manifest.unwrap_or_else(|| append(&output, ".json"));
+    if let Err(e) = fs::write(&output, &data) {
+        fail("Failed to write output file", e);
+    }
+    if let Err(e) = fs::write(&manifest_path, manifest.to_json()) {
+        fail("Failed to write manifest", e);
+    }
+    println!("[+] {}", manifest);
+    println!("[+] wrote {} and {}", output.display(), manifest_path.display());
+}
+
+fn bundle(path: &Path, output: Option<&Path>, layout: Layout) {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => fail("Failed to read package manifest", e),
+    };
+    let manifest = match PackageManifest::from_toml(&text) {
+        Ok(manifest) => manifest,
+        Err(e) => fail("Failed to parse package manifest", e),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let bundle = match bundle::bundle(&manifest, &text, dir, layout) {
+        Ok(bundle) => bundle,
+        Err(e) => fail("Failed to bundle package", e),
+    };
+    for (object, issue) in &bundle.spec_issues {
+        println!("[!] {}: {}", object, issue);
+    }
+    // armory clients fetch NAME.tar.gz
+    let name = match layout {
+        Layout::Kit => format!("{}-{}.tar.gz", manifest.name, manifest.version),
+        Layout::Armory => format!("{}.tar.gz", manifest.name),
+    };
+    let output = output.map_or_else(|| dir.join(name), Path::to_path_buf);
+    let archive = bundle.to_tar_gz();
+    if let Err(e) = fs::write(&output, &archive) {
+        fail("Failed to write output file", e);
+    }
+    for (name, data) in &bundle.files {
+        println!("[+] {} ({} bytes)", name, data.len());
+    }
+    println!("[+] wrote {} {} to {} ({} bytes)", manifest.name, manifest.version, output.display(), archive.len());
+}
+
+/// `path` with `suffix` added to its file name
+fn append(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
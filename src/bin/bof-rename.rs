@@ -0,0 +1,46 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::Bof;
+use clap::Parser;
+
+/// Rename a symbol, such as the entrypoint, without recompiling the BOF
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// Current symbol name
+    old: String,
+    /// New symbol name
+    new: String,
+    /// Where to write the renamed object (defaults to overwriting the input)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+    let renamed = match bof.rename_symbol(&args.old, &args.new) {
+        Ok(renamed) => renamed,
+        Err(e) => fail("Failed to rename symbol", e),
+    };
+    let output = args.output.as_ref().unwrap_or(&args.input);
+    if let Err(e) = fs::write(output, &renamed) {
+        fail("Failed to write output file", e);
+    }
+    println!("[+] renamed {} -> {}: {}", args.old, args.new, output.display());
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
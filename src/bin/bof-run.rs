@@ -0,0 +1,149 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::{Bof, BEACON_ENTRYPOINT};
+use clap::Parser;
+#[cfg(feature = "emulate")]
+use clap::ArgEnum;
+
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// File containing packed arguments to pass to the entrypoint
+    #[clap(long)]
+    args: Option<PathBuf>,
+    /// Name of the function to call
+    #[clap(long, default_value = BEACON_ENTRYPOINT)]
+    entrypoint: String,
+    /// Run the BOF in an emulated CPU and log the APIs it calls, instead
+    /// of running it natively; works for x86 and x64 on any host
+    #[cfg(feature = "emulate")]
+    #[clap(long)]
+    emulate: bool,
+    /// How to print the calls made under emulation; json prints the calls,
+    /// indicators, output and stop reason as one object on stdout
+    #[cfg(feature = "emulate")]
+    #[clap(long, arg_enum, value_name = "FORMAT", requires = "emulate")]
+    trace: Option<Trace>,
+}
+
+#[cfg(feature = "emulate")]
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Trace {
+    Text,
+    Json,
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn main() {
+    use bof_kit::{beacon_mock, loader::LoadedBof};
+
+    let args = Args::parse();
+    let buffer = read(&args.input);
+    let packed = args.args.as_ref().map(read).unwrap_or_default();
+    let bof = parse(&buffer);
+    #[cfg(feature = "emulate")]
+    if args.emulate {
+        emulate(&args, &bof, packed);
+    }
+    let loaded = match LoadedBof::load(&bof, beacon_mock::resolve) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("[!] Failed to load {}: {}", args.input.display(), e);
+            process::exit(1);
+        }
+    };
+    eprintln!("[*] Calling {}() with {} bytes of arguments", &args.entrypoint, packed.len());
+    if let Err(e) = unsafe { loaded.call(&args.entrypoint, &packed) } {
+        eprintln!("[!] {}", e);
+        list_entrypoints(loaded.entrypoints());
+        process::exit(1);
+    }
+    eprintln!("[*] Done!");
+}
+
+#[cfg(not(all(unix, target_arch = "x86_64")))]
+fn main() {
+    let args = Args::parse();
+    #[cfg(feature = "emulate")]
+    if args.emulate {
+        let buffer = read(&args.input);
+        let packed = args.args.as_ref().map(read).unwrap_or_default();
+        emulate(&args, &parse(&buffer), packed);
+    }
+    let _ = args;
+    eprintln!("[!] bof-run is only supported on x86_64 unix hosts");
+    process::exit(1);
+}
+
+/// Point at the functions `--entrypoint` can name instead
+#[cfg(any(feature = "emulate", all(unix, target_arch = "x86_64")))]
+fn list_entrypoints(entrypoints: &[String]) {
+    match entrypoints.is_empty() {
+        true => eprintln!(" -> the object defines no functions to call"),
+        false => eprintln!(" -> entrypoints: {}", entrypoints.join(", ")),
+    }
+}
+
+/// Run the BOF under emulation, then exit
+#[cfg(feature = "emulate")]
+fn emulate(args: &Args, bof: &Bof, packed: Vec<u8>) -> ! {
+    use bof_kit::emulate::{self, EmulateOptions, Stop};
+    use std::io::{self, Write};
+
+    let options = EmulateOptions { entrypoint: args.entrypoint.clone(), args: packed, ..EmulateOptions::default() };
+    eprintln!("[*] Emulating {}() with {} bytes of arguments", &args.entrypoint, options.args.len());
+    let emulation = match emulate::emulate(bof, &options) {
+        Ok(emulation) => emulation,
+        Err(e) => {
+            eprintln!("[!] Failed to emulate {}: {}", args.input.display(), e);
+            if matches!(e, emulate::EmulateError::MissingEntrypoint(_)) {
+                list_entrypoints(&bof.entrypoints());
+            }
+            process::exit(1);
+        }
+    };
+    if args.trace == Some(Trace::Json) {
+        println!("{}", serde_json::to_string_pretty(&emulation).expect("traces serialize to JSON"));
+    } else {
+        for call in &emulation.calls {
+            eprintln!(" -> {}", call);
+        }
+        for indicator in &emulation.indicators {
+            eprintln!("[*] Touched {}", indicator);
+        }
+        for callback in &emulation.output {
+            let _ = io::stdout().write_all(&callback.data);
+        }
+    }
+    match emulation.stop {
+        Stop::Returned => eprintln!("[*] Done!"),
+        stop => {
+            eprintln!("[!] Stopped: {}", stop);
+            process::exit(1);
+        }
+    }
+    process::exit(0)
+}
+
+fn parse(buffer: &[u8]) -> Bof<'_> {
+    match Bof::parse(buffer) {
+        Ok(bof) => bof,
+        Err(e) => {
+            eprintln!("[!] Failed to parse input as COFF file");
+            eprintln!(" -> Error: {:?}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn read(path: &PathBuf) -> Vec<u8> {
+    match fs::read(path) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            eprintln!("[!] Failed to read {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::strip::{self, PathScrub, StripOptions, TimestampScrub};
+use bof_kit::writer::Object;
+use bof_kit::{bigobj, Bof};
+use clap::{ArgEnum, Parser};
+
+/// What --scrub-timestamp sets the timestamp to
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Timestamp {
+    Zero,
+    /// A random time between 2015 and now
+    Random,
+}
+
+/// What --scrub-paths leaves of a build path
+#[derive(Clone, Copy, PartialEq, Eq, ArgEnum)]
+enum Paths {
+    FileName,
+    Blank,
+}
+
+/// Remove debug information, linker directives and unused symbols from a BOF, converting
+/// bigobj files to ordinary objects
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// Where to write the stripped object (defaults to overwriting the input)
+    #[clap(short, long)]
+    output: Option<PathBuf>,
+    /// Also remove unwind information (.pdata/.xdata), which BOF loaders never
+    /// register; fails if code or data refers to it
+    #[clap(long)]
+    unwind: bool,
+    /// Also cut out the static functions no entrypoint reaches, moving the code after them up
+    #[clap(long)]
+    dead_code: bool,
+    /// Keep unreferenced symbols
+    #[clap(long)]
+    keep_symbols: bool,
+    /// Remove unreferenced imports even with --keep-symbols
+    #[clap(long, requires = "keep-symbols")]
+    dead_imports: bool,
+    /// Zero (or with =random, randomize) the header timestamp; also implies --scrub-paths
+    #[clap(long, arg_enum, value_name = "HOW", min_values = 0, require_equals = true, default_missing_value = "zero")]
+    scrub_timestamp: Option<Timestamp>,
+    /// Cut absolute build paths in .file symbols and strings down to file names (or with
+    /// =blank, remove them)
+    #[clap(long, arg_enum, value_name = "HOW", min_values = 0, require_equals = true, default_missing_value = "file-name")]
+    scrub_paths: Option<Paths>,
+    /// Recompute the checksums in COMDAT section definition records, which cutting out code or
+    /// scrubbing paths leaves stale
+    #[clap(long)]
+    update_checksums: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let input_size = buffer.len();
+    let buffer = match bigobj::is_bigobj(&buffer) {
+        true => match bigobj::to_regular(&buffer) {
+            Ok(converted) => {
+                println!("[+] converted bigobj file to an ordinary COFF object");
+                converted
+            }
+            Err(e) => fail("Failed to convert bigobj file", e),
+        },
+        false => buffer,
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+    let mut object = match Object::from_bof(&bof) {
+        Ok(object) => object,
+        Err(e) => fail("Failed to read object", e),
+    };
+    let options = StripOptions {
+        unwind: args.unwind,
+        keep_symbols: args.keep_symbols,
+        dead_imports: args.dead_imports,
+        scrub_timestamp: args.scrub_timestamp.map(|scrub| match scrub {
+            Timestamp::Zero => TimestampScrub::Zero,
+            Timestamp::Random => TimestampScrub::Random,
+        }),
+        scrub_paths: match (args.scrub_paths, args.scrub_timestamp) {
+            (Some(Paths::FileName), _) | (None, Some(_)) => Some(PathScrub::FileName),
+            (Some(Paths::Blank), _) => Some(PathScrub::Blank),
+            (None, None) => None,
+        },
+    };
+    let functions: Vec<String> = match args.dead_code {
+        true => bof.dead_code().functions.into_iter().map(|function| function.name).collect(),
+        false => Vec::new(),
+    };
+    let summary = match strip::strip_functions(&mut object, &options, &functions) {
+        Ok(summary) => summary,
+        Err(e) => fail("Failed to strip object", e),
+    };
+    let checksums = match args.update_checksums {
+        true => object.update_checksums(),
+        false => 0,
+    };
+    let stripped = match object.to_bytes() {
+        Ok(stripped) => stripped,
+        Err(e) => fail("Failed to write object", e),
+    };
+    for (name, size) in &summary.sections {
+        println!("[+] removed section {} ({} bytes)", name, size);
+    }
+    for (name, size) in &summary.functions {
+        println!("[+] removed unreachable function {} ({} bytes)", name, size);
+    }
+    for name in &summary.imports {
+        println!("[+] removed unreferenced import {}", name);
+    }
+    println!("[+] removed {} symbol table entries", summary.symbols);
+    if let Some((old, new)) = summary.timestamp {
+        println!("[+] timestamp: {:#010x} -> {:#010x}", old, new);
+    }
+    for path in &summary.paths {
+        println!("[+] scrubbed build path {}", path);
+    }
+    if args.update_checksums {
+        println!("[+] updated {} section checksums", checksums);
+    }
+    let output = args.output.as_ref().unwrap_or(&args.input);
+    if let Err(e) = fs::write(output, &stripped) {
+        fail("Failed to write output file", e);
+    }
+    println!("[+] {} -> {} bytes: {}", input_size, stripped.len(), output.display());
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    println!("[!] {}", message);
+    println!(" -> Error: {}", e);
+    process::exit(1);
+}
@@ -0,0 +1,197 @@
+// only main() is compiled where the harness isn't available
+#![cfg_attr(not(all(unix, target_arch = "x86_64")), allow(dead_code, unused_imports))]
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use bof_kit::args::{self, Arg};
+use bof_kit::ArgSpec;
+use clap::Parser;
+
+/// Run a BOF against the mock Beacon API and compare its output to a stored snapshot
+#[derive(Parser)]
+#[clap(allow_negative_numbers = true)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// One value per argument of the spec; binary values are hex, or @path to read a file
+    values: Vec<String>,
+    /// Argument spec (args.toml or bof.json) the values are packed by; trailing arguments with
+    /// defaults can be left out
+    #[clap(long)]
+    spec: Option<PathBuf>,
+    /// Snapshot to compare against [default: the input with a .snap extension]
+    #[clap(long)]
+    snapshot: Option<PathBuf>,
+    /// Write the output to the snapshot instead of comparing it
+    #[clap(long)]
+    update: bool,
+    /// Make an import return a value instead of being called, e.g. KERNEL32$GetCurrentProcessId=1234;
+    /// every import outside the Beacon API needs one
+    #[clap(long = "stub", value_name = "NAME=VALUE")]
+    stubs: Vec<String>,
+    /// Name of the function to call [default: the spec's entrypoint, or go]
+    #[clap(long)]
+    entrypoint: Option<String>,
+}
+
+#[cfg(all(unix, target_arch = "x86_64"))]
+fn main() {
+    use bof_kit::harness::Harness;
+    use bof_kit::Bof;
+
+    let args = Args::parse();
+    let spec = args.spec.as_ref().map(|path| ArgSpec::load(path).unwrap_or_else(|e| fail(e)));
+    let mut harness = Harness::new();
+    harness.args = match &spec {
+        Some(spec) => pack(spec, &args.values).unwrap_or_else(|e| fail(e)),
+        None if args.values.is_empty() => Vec::new(),
+        None => fail("values need an argument spec (--spec)"),
+    };
+    if let Some(entrypoint) = args.entrypoint.clone().or_else(|| spec.and_then(|spec| spec.entrypoint)) {
+        harness.entrypoint = entrypoint;
+    }
+    for stub in &args.stubs {
+        match parse_stub(stub) {
+            Some((name, value)) => harness.stub(name, value),
+            None => fail(format!("invalid stub, expected NAME=VALUE: {}", stub)),
+        };
+    }
+
+    let buffer = fs::read(&args.input).unwrap_or_else(|e| fail(format!("Failed to read {}: {}", args.input.display(), e)));
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => {
+            eprintln!("[!] Failed to parse input as COFF file");
+            eprintln!(" -> Error: {:?}", e);
+            process::exit(1);
+        }
+    };
+    let run = unsafe { harness.run(&bof) }.unwrap_or_else(|e| fail(format!("Failed to run {}: {}", args.input.display(), e)));
+    let output = run.text();
+
+    let snapshot = args.snapshot.clone().unwrap_or_else(|| args.input.with_extension("snap"));
+    match check_snapshot(&snapshot, &output, args.update).unwrap_or_else(|e| fail(e)) {
+        Snapshot::Updated => eprintln!("[*] Updated {}", snapshot.display()),
+        Snapshot::Matches => eprintln!("[*] {}: output matches {}", args.input.display(), snapshot.display()),
+        Snapshot::Differs(expected) => {
+            eprintln!("[!] {}: output differs from {}", args.input.display(), snapshot.display());
+            print_diff(&snapshot, &expected, &output);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(all(unix, target_arch = "x86_64")))]
+fn main() {
+    let _ = Args::parse();
+    eprintln!("[!] bof-test is only supported on x86_64 unix hosts");
+    process::exit(1);
+}
+
+/// What comparing the output to the snapshot found
+#[derive(Debug, PartialEq, Eq)]
+enum Snapshot {
+    Updated,
+    Matches,
+    /// The snapshot's contents
+    Differs(String),
+}
+
+/// Write `output` to `snapshot` if `update`, otherwise compare them
+fn check_snapshot(snapshot: &Path, output: &str, update: bool) -> Result<Snapshot, String> {
+    if update {
+        fs::write(snapshot, output).map_err(|e| format!("Failed to write {}: {}", snapshot.display(), e))?;
+        return Ok(Snapshot::Updated);
+    }
+    let expected = match fs::read(snapshot) {
+        Ok(expected) => String::from_utf8_lossy(&expected).to_string(),
+        Err(e) => return Err(format!("Failed to read {}: {} (run with --update to create it)", snapshot.display(), e)),
+    };
+    match expected == output {
+        true => Ok(Snapshot::Matches),
+        false => Ok(Snapshot::Differs(expected)),
+    }
+}
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("[!] {}", message);
+    process::exit(1);
+}
+
+fn pack(spec: &ArgSpec, values: &[String]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let values = spec.values(values)?;
+    let mut parsed = Vec::with_capacity(values.len());
+    for (typ, value) in spec.format().chars().zip(&values) {
+        // binary values can also be read from a file
+        let arg = match (typ, value.strip_prefix('@')) {
+            ('b', Some(path)) => Arg::Binary(fs::read(path)?),
+            _ => Arg::parse(typ, value)?,
+        };
+        parsed.push(arg);
+    }
+    Ok(args::pack(&parsed))
+}
+
+/// `NAME=VALUE`, with the value in decimal or 0x-prefixed hex
+fn parse_stub(stub: &str) -> Option<(&str, u64)> {
+    let (name, value) = stub.split_once('=')?;
+    let value = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => value.parse::<i64>().ok()? as u64,
+    };
+    Some((name, value))
+}
+
+/// Print the lines that differ, as `-` for the snapshot and `+` for the output
+fn print_diff(snapshot: &Path, expected: &str, output: &str) {
+    use itertools::{EitherOrBoth, Itertools};
+
+    println!("--- {}", snapshot.display());
+    println!("+++ output");
+    for (number, lines) in expected.lines().zip_longest(output.lines()).enumerate() {
+        match lines {
+            EitherOrBoth::Both(expected, output) if expected == output => continue,
+            EitherOrBoth::Both(expected, output) => {
+                println!("@@ line {} @@", number + 1);
+                println!("-{}", expected);
+                println!("+{}", output);
+            }
+            EitherOrBoth::Left(expected) => println!("-{}", expected),
+            EitherOrBoth::Right(output) => println!("+{}", output),
+        }
+    }
+    if expected.lines().eq(output.lines()) {
+        println!("(only the line endings differ)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_writes_the_snapshot_compared_against() {
+        let dir = std::env::temp_dir().join(format!("bof-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let snapshot = dir.join("whoami.snap");
+        let _ = fs::remove_file(&snapshot);
+
+        assert!(check_snapshot(&snapshot, "pid: 1234\n", false).unwrap_err().contains("--update"));
+        assert_eq!(check_snapshot(&snapshot, "pid: 1234\n", true), Ok(Snapshot::Updated));
+        assert_eq!(fs::read_to_string(&snapshot).unwrap(), "pid: 1234\n");
+        assert_eq!(check_snapshot(&snapshot, "pid: 1234\n", false), Ok(Snapshot::Matches));
+        assert_eq!(check_snapshot(&snapshot, "pid: 4321\n", false), Ok(Snapshot::Differs("pid: 1234\n".to_string())));
+        assert_eq!(check_snapshot(&snapshot, "pid: 4321\n", true), Ok(Snapshot::Updated));
+        assert_eq!(check_snapshot(&snapshot, "pid: 4321\n", false), Ok(Snapshot::Matches));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn stubs_take_decimal_negative_and_hex_values() {
+        assert_eq!(parse_stub("KERNEL32$GetCurrentProcessId=1234"), Some(("KERNEL32$GetCurrentProcessId", 1234)));
+        assert_eq!(parse_stub("KERNEL32$GetLastError=0x5"), Some(("KERNEL32$GetLastError", 5)));
+        assert_eq!(parse_stub("KERNEL32$CreateFileA=-1"), Some(("KERNEL32$CreateFileA", u64::MAX)));
+        assert_eq!(parse_stub("KERNEL32$Sleep"), None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process;
+use bof_kit::args;
+use clap::Parser;
+
+/// Decode arguments packed with bof_pack()
+#[derive(Parser)]
+struct Args {
+    /// File containing packed arguments, or - for stdin
+    input: PathBuf,
+    /// Format string to decode with; guessed when omitted
+    #[clap(short, long)]
+    format: Option<String>,
+    /// Treat the input as hex, as printed by bof-pack --hex
+    #[clap(long)]
+    hex: bool,
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut buffer = match read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => {
+            eprintln!("[!] Failed to read {}: {}", args.input.display(), e);
+            process::exit(1);
+        }
+    };
+    if args.hex {
+        let text = String::from_utf8_lossy(&buffer).trim().to_string();
+        buffer = match args::Arg::parse('b', &text) {
+            Ok(args::Arg::Binary(data)) => data,
+            _ => {
+                eprintln!("[!] Input is not valid hex");
+                process::exit(1);
+            }
+        };
+    }
+    let format = match &args.format {
+        Some(format) => format.clone(),
+        None => match args::guess_format(&buffer) {
+            Ok(format) => {
+                println!("[*] Guessed format: {}", format);
+                format
+            }
+            Err(e) => {
+                eprintln!("[!] Unable to guess format: {}", e);
+                process::exit(1);
+            }
+        },
+    };
+    match args::unpack(&buffer, &format) {
+        Ok(unpacked) => {
+            println!("[*] {} bytes, {} arguments", buffer.len(), unpacked.len());
+            for (index, arg) in unpacked.iter().enumerate() {
+                println!("[{}] {}: {}", index, arg.typ(), arg);
+            }
+        }
+        Err(e) => {
+            eprintln!("[!] Failed to unpack arguments: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn read(path: &PathBuf) -> io::Result<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut buffer = Vec::new();
+        io::stdin().read_to_end(&mut buffer)?;
+        Ok(buffer)
+    } else {
+        fs::read(path)
+    }
+}
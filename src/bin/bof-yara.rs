@@ -0,0 +1,37 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use bof_kit::{yara, Bof};
+use clap::Parser;
+
+/// Print a YARA rule skeleton for detecting a BOF
+#[derive(Parser)]
+struct Args {
+    /// Path to object file
+    input: PathBuf,
+    /// Rule name (defaults to the file name)
+    #[clap(short, long)]
+    name: Option<String>,
+}
+
+fn main() {
+    let args = Args::parse();
+    let buffer = match fs::read(&args.input) {
+        Ok(buffer) => buffer,
+        Err(e) => fail("Failed to read input file", e),
+    };
+    let bof = match Bof::parse(&buffer) {
+        Ok(bof) => bof,
+        Err(e) => fail("Failed to parse input as COFF file", e),
+    };
+    let name = args.name.unwrap_or_else(|| {
+        args.input.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+    });
+    print!("{}", yara::rule(&bof, &name));
+}
+
+fn fail(message: &str, e: impl std::fmt::Display) -> ! {
+    eprintln!("[!] {}", message);
+    eprintln!(" -> Error: {}", e);
+    process::exit(1);
+}
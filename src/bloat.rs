@@ -0,0 +1,72 @@
+//! Size accounting
+//!
+//! Which functions and variables a BOF's bytes belong to, like cargo-bloat
+//! for a single object. COFF symbols have no size, so each symbol owns the
+//! bytes up to the next one in its section, and whatever comes before a
+//! section's first symbol (usually string literals and jump tables reached
+//! through the section symbol) is counted against the section itself.
+
+use goblin::pe::symbol::IMAGE_SYM_CLASS_EXTERNAL;
+
+use crate::disasm::is_section_definition;
+use crate::{is_loaded, section_name, Bof};
+
+/// Bytes attributed to one function or variable
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SizeEntry {
+    /// Function or variable, or `None` for bytes before the section's first
+    /// symbol
+    pub symbol: Option<String>,
+    pub section: String,
+    /// Bytes of the section, uninitialized data included
+    pub size: usize,
+    /// Relocation entries patching those bytes
+    pub relocations: usize,
+}
+
+/// Bytes of every loaded section attributed to the symbols in it, largest
+/// first
+pub fn attribute(bof: &Bof) -> Vec<SizeEntry> {
+    let mut entries = Vec::new();
+    for (index, section) in bof.coff.sections.iter().enumerate() {
+        if !is_loaded(section) {
+            continue;
+        }
+        let number = index as i16 + 1;
+        let size = section.size_of_raw_data.max(section.virtual_size);
+        // (offset, external, name), external symbols first at each offset so
+        // they win over labels and aliases
+        let mut symbols: Vec<(u32, bool, String)> = bof
+            .coff
+            .symbols
+            .iter()
+            .map(|(_, _, symbol)| symbol)
+            .filter(|symbol| symbol.section_number == number && !is_section_definition(bof, symbol))
+            .map(|symbol| (symbol.value.min(size), symbol.storage_class != IMAGE_SYM_CLASS_EXTERNAL, bof.symbol_name(&symbol)))
+            .collect();
+        symbols.sort();
+        symbols.dedup_by_key(|(offset, _, _)| *offset);
+
+        let offsets: Vec<u32> = section.relocations(bof.buffer).into_iter().flatten().map(|entry| entry.virtual_address).collect();
+        let first = symbols.first().map_or(size, |(offset, _, _)| *offset);
+        let mut ranges = vec![(None, 0, first)];
+        for (position, (start, _, name)) in symbols.iter().enumerate() {
+            let end = symbols.get(position + 1).map_or(size, |(next, _, _)| *next);
+            ranges.push((Some(name.clone()), *start, end));
+        }
+        for (symbol, start, end) in ranges {
+            if start == end && symbol.is_none() {
+                continue;
+            }
+            entries.push(SizeEntry {
+                symbol,
+                section: section_name(section),
+                size: (end - start) as usize,
+                relocations: offsets.iter().filter(|offset| (start..end).contains(*offset)).count(),
+            });
+        }
+    }
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.symbol.cmp(&b.symbol)));
+    entries
+}
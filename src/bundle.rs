@@ -0,0 +1,254 @@
+//! Distributable BOF packages
+//!
+//! A `bof.toml` manifest names a BOF, its builds per architecture, its
+//! arguments and what an operator needs to know before running it:
+//!
+//! ```toml
+//! name = "whoami"
+//! version = "1.0.0"
+//! author = "red team"
+//! repository = "https://git.example.com/bofs/whoami"
+//! description = "Print the current user"
+//! privileges = ["SeDebugPrivilege"]
+//!
+//! [objects]
+//! x64 = "build/whoami.x64.o"
+//! x86 = "build/whoami.x86.o"
+//!
+//! [[arguments]]
+//! name = "pid"
+//! type = "int"
+//! default = "0"
+//! ```
+//!
+//! Arguments are written as in an [argument spec](crate::spec). A bundle is
+//! a `.tar.gz` of the manifest, the objects, an Aggressor script, a Sliver
+//! `extension.json` and a generic [task descriptor](crate::descriptor), all
+//! generated from the manifest so they can't drift apart, or in the [`Layout::Armory`] layout, the objects and an armory
+//! `extension.json` only, ready to publish in a Sliver armory. Each object's
+//! `BeaconData*` calls are checked against the arguments, since a manifest
+//! that no longer matches the code is what hand-written ones get wrong.
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::checksum::crc32;
+use crate::cna::Script;
+use crate::descriptor::Descriptor;
+use crate::sliver::{self, ManifestFile};
+use crate::spec::{ArgSpec, ArgumentSpec, SpecIssue};
+use crate::{AnalysisOptions, Arch, Bof, BEACON_ENTRYPOINT};
+
+/// Contents of a `bof.toml` package manifest
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PackageManifest {
+    /// Command name
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub author: String,
+    /// Where the source lives, for the armory
+    #[serde(default)]
+    pub repository: String,
+    /// Help text
+    #[serde(default)]
+    pub description: String,
+    /// Entrypoint, if not `go`
+    pub entrypoint: Option<String>,
+    /// Privileges or integrity the BOF needs, shown with the help text
+    #[serde(default)]
+    pub privileges: Vec<String>,
+    /// Object file per architecture name, relative to the manifest
+    pub objects: BTreeMap<String, String>,
+    #[serde(default)]
+    pub arguments: Vec<ArgumentSpec>,
+}
+
+impl PackageManifest {
+    pub fn from_toml(text: &str) -> Result<Self, BundleError> {
+        let manifest: Self = toml::from_str(text).map_err(|e| BundleError::Parse(e.to_string()))?;
+        if manifest.objects.is_empty() {
+            return Err(BundleError::Parse("no objects".to_string()));
+        }
+        manifest.spec()?;
+        Ok(manifest)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, BundleError> {
+        let text = fs::read_to_string(path).map_err(|e| BundleError::Io(path.to_path_buf(), e))?;
+        Self::from_toml(&text)
+    }
+
+    pub fn entrypoint(&self) -> &str {
+        self.entrypoint.as_deref().unwrap_or(BEACON_ENTRYPOINT)
+    }
+
+    /// Help text with the required privileges appended
+    pub fn help(&self) -> String {
+        match self.privileges.is_empty() {
+            true => self.description.clone(),
+            false if self.description.is_empty() => format!("Requires {}", self.privileges.join(", ")),
+            false => format!("{} (requires {})", self.description, self.privileges.join(", ")),
+        }
+    }
+
+    /// The manifest's arguments as an argument spec
+    pub fn spec(&self) -> Result<ArgSpec, BundleError> {
+        ArgSpec {
+            name: Some(self.name.clone()),
+            description: self.help(),
+            entrypoint: Some(self.entrypoint().to_string()),
+            arguments: self.arguments.clone(),
+        }
+        .validate()
+        .map_err(|e| BundleError::Parse(e.to_string()))
+    }
+}
+
+/// Why a package couldn't be bundled
+#[derive(Debug)]
+pub enum BundleError {
+    Io(PathBuf, io::Error),
+    /// The manifest isn't valid TOML or has unexpected contents
+    Parse(String),
+    /// An object doesn't parse, isn't for the architecture it's listed
+    /// under, or lacks the entrypoint
+    Object(String, String),
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "failed to read {}: {}", path.display(), e),
+            Self::Parse(message) => write!(f, "invalid package manifest: {}", message),
+            Self::Object(object, message) => write!(f, "{}: {}", object, message),
+        }
+    }
+}
+
+impl error::Error for BundleError {}
+
+/// What goes in a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// The manifest, objects, Aggressor script, a Sliver `extension.json` and
+    /// a `descriptor.json` for other loaders
+    Kit,
+    /// The objects and an `extension.json` in the format Sliver 1.6 armories
+    /// serve
+    Armory,
+}
+
+/// The files of a package, by path in the archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bundle {
+    pub files: Vec<(String, Vec<u8>)>,
+    /// Disagreements between the manifest's arguments and the data API
+    /// calls of each object, by the object's path
+    pub spec_issues: Vec<(String, SpecIssue)>,
+}
+
+impl Bundle {
+    /// The files as a gzipped tar archive, with every timestamp zero so the
+    /// same inputs give the same archive
+    pub fn to_tar_gz(&self) -> Vec<u8> {
+        gzip(&tar(&self.files))
+    }
+}
+
+/// Check the objects `manifest` lists, relative to `dir`, and bundle them
+/// with the manifest text and the scripts generated from it
+pub fn bundle(manifest: &PackageManifest, text: &str, dir: &Path, layout: Layout) -> Result<Bundle, BundleError> {
+    let spec = manifest.spec()?;
+    let options = AnalysisOptions { spec: Some(Arc::new(spec.clone())), entrypoint: manifest.entrypoint.clone(), ..Default::default() };
+    let mut spec_issues = Vec::new();
+    let tagged = manifest.objects.len() > 1;
+    let mut objects = Vec::new();
+    for (arch_name, path) in &manifest.objects {
+        let arch = Arch::from_name(arch_name).ok_or_else(|| BundleError::Parse(format!("unknown architecture {}", arch_name)))?;
+        let full = dir.join(path);
+        let data = fs::read(&full).map_err(|e| BundleError::Io(full.clone(), e))?;
+        let bof = Bof::parse(&data).map_err(|e| BundleError::Object(path.clone(), e.to_string()))?;
+        match bof.arch() {
+            Some(found) if found == arch => {}
+            found => {
+                let found = found.map_or("an unknown machine", |found| found.name());
+                return Err(BundleError::Object(path.clone(), format!("listed as {} but built for {}", arch, found)));
+            }
+        }
+        if !bof.entrypoints().iter().any(|name| name == manifest.entrypoint() || *name == arch.decorate(manifest.entrypoint())) {
+            return Err(BundleError::Object(path.clone(), format!("entrypoint {} not found", manifest.entrypoint())));
+        }
+        spec_issues.extend(bof.analyze_with(&options).spec_issues.into_iter().map(|issue| (path.clone(), issue)));
+        // the Aggressor script picks the build by the architecture tag in its name
+        let name = match tagged {
+            true => format!("{}.{}.o", manifest.name, arch.name()),
+            false => format!("{}.o", manifest.name),
+        };
+        objects.push((arch, name, data));
+    }
+
+    let (first_arch, first_name, _) = &objects[0];
+    let arch = (!tagged).then_some(*first_arch);
+    let script = Script::from_spec(&manifest.name, manifest.entrypoint(), first_name, arch, &spec);
+    let file = |(arch, name, _): &(Arch, String, Vec<u8>)| ManifestFile { arch: *arch, path: name.clone() };
+    let mut extension = sliver::Manifest::from_spec(&manifest.name, manifest.entrypoint(), file(&objects[0]), &spec);
+    extension.version = manifest.version.clone();
+    extension.author = manifest.author.clone();
+    extension.repo_url = manifest.repository.clone();
+    extension.files = objects.iter().map(file).collect();
+
+    let mut files = match layout {
+        Layout::Kit => vec![
+            ("bof.toml".to_string(), text.as_bytes().to_vec()),
+            (format!("{}.cna", manifest.name), script.to_cna().into_bytes()),
+            ("extension.json".to_string(), extension.to_json().into_bytes()),
+            ("descriptor.json".to_string(), Descriptor::from_spec(&manifest.name, manifest.entrypoint(), &spec).to_json().into_bytes()),
+        ],
+        Layout::Armory => vec![("extension.json".to_string(), extension.to_armory_json().into_bytes())],
+    };
+    files.extend(objects.into_iter().map(|(_, name, data)| (name, data)));
+    Ok(Bundle { files, spec_issues })
+}
+
+/// A ustar archive of `files`
+fn tar(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for (name, data) in files {
+        let mut header = [0u8; 512];
+        let name = name.as_bytes();
+        header[..name.len().min(100)].copy_from_slice(&name[..name.len().min(100)]);
+        header[100..108].copy_from_slice(b"0000644\0");
+        header[108..116].copy_from_slice(b"0000000\0");
+        header[116..124].copy_from_slice(b"0000000\0");
+        header[124..136].copy_from_slice(format!("{:011o}\0", data.len()).as_bytes());
+        header[136..148].copy_from_slice(b"00000000000\0");
+        header[148..156].copy_from_slice(b"        ");
+        header[156] = b'0';
+        header[257..265].copy_from_slice(b"ustar\x0000");
+        let sum: u32 = header.iter().map(|byte| *byte as u32).sum();
+        header[148..156].copy_from_slice(format!("{:06o}\0 ", sum).as_bytes());
+        out.extend_from_slice(&header);
+        out.extend_from_slice(data);
+        out.resize(out.len().div_ceil(512) * 512, 0);
+    }
+    out.resize(out.len() + 1024, 0);
+    out
+}
+
+/// `data` in a gzip member
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 9));
+    out.extend_from_slice(&(!crc32(!0, data)).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
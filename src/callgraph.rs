@@ -0,0 +1,202 @@
+//! Call graphs between the functions of a BOF and its imports
+//!
+//! A function runs from its symbol to the next symbol in its section. It
+//! calls whatever its relocations point at: an import, another function, or
+//! a section at the offset of a function in it. Near branches and
+//! RIP-relative operands the assembler already resolved within a section
+//! count too, since they have no relocation.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use iced_x86::{Decoder, DecoderOptions, OpKind};
+
+use crate::disasm::{extent, is_section_definition};
+use crate::{opsec, relocations, Arch, Bof, Severity};
+
+/// Which functions call which functions and imports
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CallGraph {
+    /// Functions defined in the object, in symbol table order
+    pub functions: Vec<String>,
+    /// Imports, without the import prefix
+    pub imports: BTreeSet<String>,
+    /// Caller and callee names
+    pub calls: BTreeSet<(String, String)>,
+    /// Callees of each function in the order its code calls them, repeats
+    /// included
+    pub sequences: BTreeMap<String, Vec<String>>,
+}
+
+impl CallGraph {
+    /// Functions and imports `name` calls directly
+    pub fn callees<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.calls.iter().filter(move |(caller, _)| caller == name).map(|(_, callee)| callee.as_str())
+    }
+
+    /// Functions that call `name` directly
+    pub fn callers<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.calls.iter().filter(move |(_, callee)| callee == name).map(|(caller, _)| caller.as_str())
+    }
+
+    /// Imports `name` calls directly or through other functions
+    pub fn reachable_imports(&self, name: &str) -> BTreeSet<String> {
+        self.reachable(name).into_iter().filter(|callee| self.imports.contains(callee)).collect()
+    }
+
+    /// Functions and imports `name` calls directly or through other functions
+    pub fn reachable(&self, name: &str) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut pending = vec![name.to_string()];
+        while let Some(function) = pending.pop() {
+            for callee in self.callees(&function) {
+                if seen.insert(callee.to_string()) {
+                    pending.push(callee.to_string());
+                }
+            }
+        }
+        seen
+    }
+
+    /// Graphviz source, with heavily monitored imports and the functions
+    /// that reach them highlighted
+    pub fn to_dot(&self) -> String {
+        let color = |severity: Option<Severity>| match severity {
+            Some(Severity::Error) => ", color=red, fontcolor=red",
+            Some(Severity::Warning) => ", color=orange, fontcolor=orange",
+            None => "",
+        };
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph callgraph {{");
+        let _ = writeln!(out, "    node [fontname=monospace];");
+        for function in &self.functions {
+            let severity = self.reachable_imports(function).iter().filter_map(|import| import_severity(import)).max();
+            let _ = writeln!(out, "    {} [shape=ellipse{}];", quote(function), color(severity));
+        }
+        for import in &self.imports {
+            let _ = writeln!(out, "    {} [shape=box{}];", quote(import), color(import_severity(import)));
+        }
+        for (caller, callee) in &self.calls {
+            let _ = writeln!(out, "    {} -> {};", quote(caller), quote(callee));
+        }
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+/// Severity of the OPSEC finding for an import, if it's heavily monitored
+fn import_severity(import: &str) -> Option<Severity> {
+    let function = import.split_once('$').map_or(import, |(_, function)| function);
+    opsec::lookup(function).map(|(severity, _)| severity)
+}
+
+/// Build the call graph of every function in the code sections
+pub fn build(bof: &Bof) -> CallGraph {
+    let (arch, prefix) = match (bof.arch(), bof.import_prefix()) {
+        (Some(arch), Some(prefix)) => (arch, prefix),
+        _ => return CallGraph::default(),
+    };
+    let is_code = |number: i16| {
+        number > 0
+            && bof.coff.sections.get(number as usize - 1).is_some_and(|section| {
+                section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0
+            })
+    };
+
+    // section number -> (start, end, name) of each function in it
+    let mut layout: BTreeMap<i16, Vec<(u32, u32, String)>> = BTreeMap::new();
+    let mut graph = CallGraph::default();
+    // caller, offset and callee of every call
+    let mut sites: Vec<(String, u32, String)> = Vec::new();
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if !is_code(symbol.section_number) || is_section_definition(bof, &symbol) {
+            continue;
+        }
+        let section = &bof.coff.sections[symbol.section_number as usize - 1];
+        let (start, end) = extent(bof, &symbol, bof.section_contents(section).len() as u32);
+        let name = bof.symbol_name(&symbol);
+        layout.entry(symbol.section_number).or_default().push((start, end, name.clone()));
+        graph.functions.push(name);
+    }
+    let containing = |number: i16, offset: u32| {
+        layout.get(&number)?.iter().find(|(start, end, _)| (*start..*end).contains(&offset)).map(|(_, _, name)| name.clone())
+    };
+
+    for (number, functions) in &layout {
+        let section = &bof.coff.sections[*number as usize - 1];
+        let data = bof.section_contents(section);
+        let mut relocated = BTreeSet::new();
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            relocated.insert(entry.virtual_address);
+            let caller = match containing(*number, entry.virtual_address) {
+                Some(caller) => caller,
+                None => continue,
+            };
+            let target = match bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                Some((_, target)) => target,
+                None => continue,
+            };
+            let name = bof.symbol_name(&target);
+            let callee = if let Some(import) = name.strip_prefix(prefix) {
+                graph.imports.insert(import.to_string());
+                Some(import.to_string())
+            } else if is_section_definition(bof, &target) {
+                let addend = relocations::addend(arch, entry.typ, data, entry.virtual_address as usize).unwrap_or(0);
+                containing(target.section_number, addend.max(0) as u32)
+            } else if is_code(target.section_number) {
+                Some(name)
+            } else {
+                None
+            };
+            if let Some(callee) = callee.filter(|callee| callee != &caller) {
+                sites.push((caller.clone(), entry.virtual_address, callee.clone()));
+                graph.calls.insert((caller, callee));
+            }
+        }
+
+        // calls, jumps and address references out of a function that need no
+        // relocation
+        let bitness = match arch {
+            Arch::X86 => 32,
+            Arch::X64 => 64,
+            Arch::Arm64 => continue,
+        };
+        for (start, end, caller) in functions {
+            let code = &data[*start as usize..*end as usize];
+            let mut decoder = Decoder::with_ip(bitness, code, *start as u64, DecoderOptions::NONE);
+            while decoder.can_decode() {
+                let instruction = decoder.decode();
+                let ip = instruction.ip() as u32;
+                let branches = instruction.op_count() > 0
+                    && matches!(instruction.op0_kind(), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64);
+                if !(branches || instruction.is_ip_rel_memory_operand())
+                    || (ip..ip + instruction.len() as u32).any(|offset| relocated.contains(&offset))
+                {
+                    continue;
+                }
+                let target = match branches {
+                    true => instruction.near_branch_target() as u32,
+                    false => instruction.ip_rel_memory_address() as u32,
+                };
+                if (*start..*end).contains(&target) {
+                    continue;
+                }
+                if let Some(callee) = containing(*number, target) {
+                    sites.push((caller.clone(), ip, callee.clone()));
+                    graph.calls.insert((caller.clone(), callee));
+                }
+            }
+        }
+    }
+    sites.sort();
+    for (caller, _, callee) in sites {
+        graph.sequences.entry(caller).or_default().push(callee);
+    }
+    graph
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
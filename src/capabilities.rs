@@ -0,0 +1,317 @@
+//! What a BOF can do, judging by its imports
+//!
+//! Recognized Win32 functions are grouped into broad capabilities, each with
+//! the MITRE ATT&CK techniques it usually serves, so a reviewer gets the gist
+//! of an unfamiliar BOF before reading its code. Each function is also
+//! tagged with the technique it's most often used for, which reports carry
+//! as [`TechniqueUse`]s for threat-intel tooling. An import says what the BOF
+//! could do, not what it does: a BOF that opens a process token may only be
+//! reading its own user name.
+
+use std::fmt;
+
+use crate::{Import, ImportKind};
+
+/// A kind of thing a BOF can do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Capability {
+    /// Allocating, writing or running code in other processes
+    ProcessInjection,
+    /// Starting processes or services
+    Execution,
+    /// Opening, duplicating or impersonating access tokens
+    TokenManipulation,
+    /// Reading credentials or dumping processes that hold them
+    CredentialAccess,
+    /// Sockets, HTTP and DNS
+    Network,
+    Registry,
+    Filesystem,
+    /// Turning off AMSI or ETW
+    DefenseEvasion,
+}
+
+impl Capability {
+    /// ATT&CK techniques the capability usually serves, as ID and name
+    pub fn attack_hints(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::ProcessInjection => &[("T1055", "Process Injection")],
+            Self::Execution => &[("T1106", "Native API"), ("T1059", "Command and Scripting Interpreter")],
+            Self::TokenManipulation => &[("T1134", "Access Token Manipulation")],
+            Self::CredentialAccess => &[("T1003", "OS Credential Dumping"), ("T1555", "Credentials from Password Stores")],
+            Self::Network => &[("T1071", "Application Layer Protocol"), ("T1105", "Ingress Tool Transfer")],
+            Self::Registry => &[("T1012", "Query Registry"), ("T1112", "Modify Registry")],
+            Self::Filesystem => &[("T1083", "File and Directory Discovery"), ("T1005", "Data from Local System")],
+            Self::DefenseEvasion => &[("T1562", "Impair Defenses")],
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ProcessInjection => "process injection",
+            Self::Execution => "execution",
+            Self::TokenManipulation => "token manipulation",
+            Self::CredentialAccess => "credential access",
+            Self::Network => "network",
+            Self::Registry => "registry",
+            Self::Filesystem => "filesystem",
+            Self::DefenseEvasion => "defense evasion",
+        })
+    }
+}
+
+/// Functions by name without an `A`/`W` suffix, with the capability they
+/// give a BOF and the ATT&CK technique they're most often used for
+static APIS: &[(&str, Capability, &str)] = &[
+    ("OpenProcess", Capability::ProcessInjection, "T1055"),
+    ("VirtualAllocEx", Capability::ProcessInjection, "T1055"),
+    ("VirtualProtectEx", Capability::ProcessInjection, "T1055"),
+    ("WriteProcessMemory", Capability::ProcessInjection, "T1055"),
+    ("ReadProcessMemory", Capability::ProcessInjection, "T1055"),
+    ("CreateRemoteThread", Capability::ProcessInjection, "T1055"),
+    ("CreateRemoteThreadEx", Capability::ProcessInjection, "T1055"),
+    ("NtCreateThreadEx", Capability::ProcessInjection, "T1055"),
+    ("RtlCreateUserThread", Capability::ProcessInjection, "T1055"),
+    ("NtAllocateVirtualMemory", Capability::ProcessInjection, "T1055"),
+    ("NtWriteVirtualMemory", Capability::ProcessInjection, "T1055"),
+    ("NtProtectVirtualMemory", Capability::ProcessInjection, "T1055"),
+    ("NtCreateSection", Capability::ProcessInjection, "T1055"),
+    ("NtMapViewOfSection", Capability::ProcessInjection, "T1055"),
+    ("QueueUserAPC", Capability::ProcessInjection, "T1055.004"),
+    ("NtQueueApcThread", Capability::ProcessInjection, "T1055.004"),
+    ("GetThreadContext", Capability::ProcessInjection, "T1055.003"),
+    ("SetThreadContext", Capability::ProcessInjection, "T1055.003"),
+    ("SetWindowsHookEx", Capability::ProcessInjection, "T1055"),
+    ("CreateProcess", Capability::Execution, "T1106"),
+    ("CreateProcessAsUser", Capability::Execution, "T1134.002"),
+    ("CreateProcessWithLogon", Capability::Execution, "T1134.002"),
+    ("CreateProcessWithToken", Capability::Execution, "T1134.002"),
+    ("ShellExecute", Capability::Execution, "T1106"),
+    ("ShellExecuteEx", Capability::Execution, "T1106"),
+    ("WinExec", Capability::Execution, "T1106"),
+    ("system", Capability::Execution, "T1059.003"),
+    ("_popen", Capability::Execution, "T1059.003"),
+    ("OpenSCManager", Capability::Execution, "T1569.002"),
+    ("CreateService", Capability::Execution, "T1569.002"),
+    ("StartService", Capability::Execution, "T1569.002"),
+    ("OpenProcessToken", Capability::TokenManipulation, "T1134"),
+    ("OpenThreadToken", Capability::TokenManipulation, "T1134"),
+    ("DuplicateToken", Capability::TokenManipulation, "T1134.001"),
+    ("DuplicateTokenEx", Capability::TokenManipulation, "T1134.001"),
+    ("ImpersonateLoggedOnUser", Capability::TokenManipulation, "T1134.001"),
+    ("ImpersonateNamedPipeClient", Capability::TokenManipulation, "T1134.001"),
+    ("SetThreadToken", Capability::TokenManipulation, "T1134.001"),
+    ("RevertToSelf", Capability::TokenManipulation, "T1134.001"),
+    ("AdjustTokenPrivileges", Capability::TokenManipulation, "T1134"),
+    ("LookupPrivilegeValue", Capability::TokenManipulation, "T1134"),
+    ("GetTokenInformation", Capability::TokenManipulation, "T1134"),
+    ("LogonUser", Capability::TokenManipulation, "T1134.003"),
+    ("NtOpenProcessToken", Capability::TokenManipulation, "T1134"),
+    ("NtDuplicateToken", Capability::TokenManipulation, "T1134.001"),
+    ("MiniDumpWriteDump", Capability::CredentialAccess, "T1003.001"),
+    ("LsaOpenPolicy", Capability::CredentialAccess, "T1003.004"),
+    ("LsaRetrievePrivateData", Capability::CredentialAccess, "T1003.004"),
+    ("LsaEnumerateLogonSessions", Capability::CredentialAccess, "T1033"),
+    ("LsaGetLogonSessionData", Capability::CredentialAccess, "T1033"),
+    ("LsaConnectUntrusted", Capability::CredentialAccess, "T1558"),
+    ("LsaCallAuthenticationPackage", Capability::CredentialAccess, "T1558"),
+    ("CredEnumerate", Capability::CredentialAccess, "T1555.004"),
+    ("CredRead", Capability::CredentialAccess, "T1555.004"),
+    ("CryptUnprotectData", Capability::CredentialAccess, "T1555"),
+    ("SamConnect", Capability::CredentialAccess, "T1003.002"),
+    ("SamOpenUser", Capability::CredentialAccess, "T1003.002"),
+    ("AcquireCredentialsHandle", Capability::CredentialAccess, "T1558"),
+    ("InitializeSecurityContext", Capability::CredentialAccess, "T1558"),
+    ("WSAStartup", Capability::Network, "T1095"),
+    ("WSASocket", Capability::Network, "T1095"),
+    ("socket", Capability::Network, "T1095"),
+    ("connect", Capability::Network, "T1095"),
+    ("bind", Capability::Network, "T1095"),
+    ("listen", Capability::Network, "T1095"),
+    ("accept", Capability::Network, "T1095"),
+    ("send", Capability::Network, "T1095"),
+    ("recv", Capability::Network, "T1095"),
+    ("getaddrinfo", Capability::Network, "T1095"),
+    ("gethostbyname", Capability::Network, "T1095"),
+    ("DnsQuery_", Capability::Network, "T1071.004"),
+    ("InternetOpen", Capability::Network, "T1071.001"),
+    ("InternetConnect", Capability::Network, "T1071.001"),
+    ("InternetOpenUrl", Capability::Network, "T1071.001"),
+    ("InternetReadFile", Capability::Network, "T1105"),
+    ("HttpOpenRequest", Capability::Network, "T1071.001"),
+    ("HttpSendRequest", Capability::Network, "T1071.001"),
+    ("WinHttpOpen", Capability::Network, "T1071.001"),
+    ("WinHttpConnect", Capability::Network, "T1071.001"),
+    ("WinHttpOpenRequest", Capability::Network, "T1071.001"),
+    ("WinHttpSendRequest", Capability::Network, "T1071.001"),
+    ("URLDownloadToFile", Capability::Network, "T1105"),
+    ("RegOpenKey", Capability::Registry, "T1012"),
+    ("RegOpenKeyEx", Capability::Registry, "T1012"),
+    ("RegCreateKeyEx", Capability::Registry, "T1112"),
+    ("RegQueryValueEx", Capability::Registry, "T1012"),
+    ("RegGetValue", Capability::Registry, "T1012"),
+    ("RegSetValueEx", Capability::Registry, "T1112"),
+    ("RegDeleteKey", Capability::Registry, "T1112"),
+    ("RegDeleteValue", Capability::Registry, "T1112"),
+    ("RegEnumKeyEx", Capability::Registry, "T1012"),
+    ("RegEnumValue", Capability::Registry, "T1012"),
+    ("RegConnectRegistry", Capability::Registry, "T1012"),
+    ("NtOpenKey", Capability::Registry, "T1012"),
+    ("NtQueryValueKey", Capability::Registry, "T1012"),
+    ("NtSetValueKey", Capability::Registry, "T1112"),
+    ("AmsiInitialize", Capability::DefenseEvasion, "T1562.001"),
+    ("AmsiScanBuffer", Capability::DefenseEvasion, "T1562.001"),
+    ("EtwEventWrite", Capability::DefenseEvasion, "T1562.006"),
+    ("EtwEventWriteFull", Capability::DefenseEvasion, "T1562.006"),
+    ("NtTraceEvent", Capability::DefenseEvasion, "T1562.006"),
+    ("CreateFile", Capability::Filesystem, "T1005"),
+    ("ReadFile", Capability::Filesystem, "T1005"),
+    ("WriteFile", Capability::Filesystem, "T1074.001"),
+    ("DeleteFile", Capability::Filesystem, "T1070.004"),
+    ("CopyFile", Capability::Filesystem, "T1074.001"),
+    ("MoveFile", Capability::Filesystem, "T1074.001"),
+    ("MoveFileEx", Capability::Filesystem, "T1074.001"),
+    ("FindFirstFile", Capability::Filesystem, "T1083"),
+    ("FindNextFile", Capability::Filesystem, "T1083"),
+    ("CreateDirectory", Capability::Filesystem, "T1074.001"),
+    ("RemoveDirectory", Capability::Filesystem, "T1070.004"),
+    ("GetFileAttributes", Capability::Filesystem, "T1083"),
+    ("SetFileAttributes", Capability::Filesystem, "T1564.001"),
+    ("NtCreateFile", Capability::Filesystem, "T1005"),
+];
+
+/// Names of the ATT&CK techniques in [`APIS`]
+/// https://attack.mitre.org/techniques/enterprise/
+static TECHNIQUES: &[(&str, &str)] = &[
+    ("T1003.001", "OS Credential Dumping: LSASS Memory"),
+    ("T1003.002", "OS Credential Dumping: Security Account Manager"),
+    ("T1003.004", "OS Credential Dumping: LSA Secrets"),
+    ("T1005", "Data from Local System"),
+    ("T1012", "Query Registry"),
+    ("T1033", "System Owner/User Discovery"),
+    ("T1055", "Process Injection"),
+    ("T1055.003", "Process Injection: Thread Execution Hijacking"),
+    ("T1055.004", "Process Injection: Asynchronous Procedure Call"),
+    ("T1059.003", "Command and Scripting Interpreter: Windows Command Shell"),
+    ("T1070.004", "Indicator Removal: File Deletion"),
+    ("T1071.001", "Application Layer Protocol: Web Protocols"),
+    ("T1071.004", "Application Layer Protocol: DNS"),
+    ("T1074.001", "Data Staged: Local Data Staging"),
+    ("T1083", "File and Directory Discovery"),
+    ("T1095", "Non-Application Layer Protocol"),
+    ("T1105", "Ingress Tool Transfer"),
+    ("T1106", "Native API"),
+    ("T1112", "Modify Registry"),
+    ("T1134", "Access Token Manipulation"),
+    ("T1134.001", "Access Token Manipulation: Token Impersonation/Theft"),
+    ("T1134.002", "Access Token Manipulation: Create Process with Token"),
+    ("T1134.003", "Access Token Manipulation: Make and Impersonate Token"),
+    ("T1555", "Credentials from Password Stores"),
+    ("T1555.004", "Credentials from Password Stores: Windows Credential Manager"),
+    ("T1558", "Steal or Forge Kerberos Tickets"),
+    ("T1562.001", "Impair Defenses: Disable or Modify Tools"),
+    ("T1562.006", "Impair Defenses: Indicator Blocking"),
+    ("T1564.001", "Hide Artifacts: Hidden Files and Directories"),
+    ("T1569.002", "System Services: Service Execution"),
+];
+
+/// A capability and the imports that give it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CapabilityUse {
+    pub capability: Capability,
+    /// Import names, without the import prefix
+    pub imports: Vec<String>,
+}
+
+impl fmt::Display for CapabilityUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hints: Vec<String> =
+            self.capability.attack_hints().iter().map(|(id, name)| format!("{} {}", id, name)).collect();
+        write!(f, "{}: {} (ATT&CK: {})", self.capability, self.imports.join(", "), hints.join(", "))
+    }
+}
+
+/// An ATT&CK technique and the imports that suggest it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TechniqueUse {
+    /// Technique ID, e.g. `T1055` or `T1134.001`
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_id"))]
+    pub id: crate::TableStr,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_name"))]
+    pub name: crate::TableStr,
+    /// Import names, without the import prefix
+    pub imports: Vec<String>,
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_id<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    crate::deserialize_known(deserializer, TECHNIQUES.iter().map(|(id, _)| *id))
+}
+
+#[cfg(feature = "serde")]
+fn deserialize_name<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    crate::deserialize_known(deserializer, TECHNIQUES.iter().map(|(_, name)| *name))
+}
+
+impl fmt::Display for TechniqueUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.id, self.name, self.imports.join(", "))
+    }
+}
+
+/// The capabilities the imports give, in the order of [`Capability`]
+pub fn summarize(imports: &[Import]) -> Vec<CapabilityUse> {
+    let mut uses: Vec<CapabilityUse> = Vec::new();
+    for (import, (capability, _)) in recognized(imports) {
+        match uses.iter_mut().find(|used| used.capability == capability) {
+            Some(used) => used.imports.push(import.name.clone()),
+            None => uses.push(CapabilityUse { capability, imports: vec![import.name.clone()] }),
+        }
+    }
+    uses.sort_by_key(|used| used.capability);
+    uses
+}
+
+/// The ATT&CK techniques the imports suggest, by ID
+pub fn techniques(imports: &[Import]) -> Vec<TechniqueUse> {
+    let mut uses: Vec<TechniqueUse> = Vec::new();
+    for (import, (_, id)) in recognized(imports) {
+        match uses.iter_mut().find(|used| used.id == id) {
+            Some(used) => used.imports.push(import.name.clone()),
+            None => uses.push(TechniqueUse { id, name: technique_name(id), imports: vec![import.name.clone()] }),
+        }
+    }
+    uses.sort_by_key(|used| used.id);
+    uses
+}
+
+fn recognized(imports: &[Import]) -> impl Iterator<Item = (&Import, (Capability, &'static str))> {
+    imports.iter().filter_map(|import| {
+        let function = match &import.kind {
+            ImportKind::Dynamic { function, .. } | ImportKind::UnrecognizedModule { function, .. } => function,
+            _ => &import.name,
+        };
+        Some((import, lookup(function)?))
+    })
+}
+
+/// Capability calling `function` gives and the ATT&CK technique ID it's
+/// most often used for; the name can have an `A`/`W` suffix and `@N`
+/// decoration
+pub fn lookup(function: &str) -> Option<(Capability, &'static str)> {
+    let function = function.split_once('@').map_or(function, |(function, _)| function);
+    let (_, capability, technique) =
+        APIS.iter().find(|(name, _, _)| function == *name || function.strip_suffix(['A', 'W']) == Some(*name))?;
+    Some((*capability, technique))
+}
+
+/// Name of a technique from [`APIS`]
+pub fn technique_name(id: &str) -> &'static str {
+    TECHNIQUES.iter().find(|(known, _)| *known == id).map_or("", |(_, name)| name)
+}
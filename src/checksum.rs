@@ -0,0 +1,84 @@
+//! COMDAT section checksums
+//!
+//! A section definition's auxiliary record carries a checksum of the
+//! section's contents, which the linker compares to decide whether COMDAT
+//! copies under `exact match` selection are identical. MSVC and LLVM compute
+//! it as a CRC-32 with no initial or final inversion; GCC leaves it zero.
+//! Rewriting a section's contents leaves the stored checksum stale, and
+//! linkers checking it reject the object or treat identical copies as
+//! different.
+
+use std::fmt;
+
+use goblin::pe::section_table::IMAGE_SCN_LNK_COMDAT;
+
+use crate::disasm::is_section_definition;
+use crate::{section_name, Bof, Severity};
+
+/// The checksum a section definition record holds for `data`
+pub fn compute(data: &[u8]) -> u32 {
+    crc32(0, data)
+}
+
+/// Continue the CRC-32 register `crc` over `data`, without inverting it
+/// before or after
+pub(crate) fn crc32(crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, byte| {
+        (0..8).fold(crc ^ *byte as u32, |crc, _| match crc & 1 {
+            1 => (crc >> 1) ^ 0xedb8_8320,
+            _ => crc >> 1,
+        })
+    })
+}
+
+/// A COMDAT section whose stored checksum doesn't match its contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChecksumIssue {
+    pub section: String,
+    /// One-based section number
+    pub number: i16,
+    pub stored: u32,
+    pub computed: u32,
+}
+
+impl ChecksumIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for ChecksumIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "COMDAT checksum doesn't match the section's contents: {} #{} (stored 0x{:08x}, computed 0x{:08x})",
+            self.section, self.number, self.stored, self.computed
+        )
+    }
+}
+
+/// Find COMDAT sections with a checksum other than the one their contents
+/// hash to; zero checksums are left alone, since GCC never computes them
+pub fn lint(bof: &Bof) -> Vec<ChecksumIssue> {
+    let mut issues = Vec::new();
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.section_number <= 0 || !is_section_definition(bof, &symbol) {
+            continue;
+        }
+        let section = match bof.coff.sections.get(symbol.section_number as usize - 1) {
+            Some(section) if section.characteristics & IMAGE_SCN_LNK_COMDAT != 0 => section,
+            _ => continue,
+        };
+        let stored = match bof.coff.symbols.aux_section_definition(index + 1) {
+            Some(aux) if aux.checksum != 0 => aux.checksum,
+            _ => continue,
+        };
+        // uninitialized sections have no contents to read, and hash to zero
+        let computed = compute(bof.section_contents(section));
+        if stored != computed {
+            issues.push(ChecksumIssue { section: section_name(section), number: symbol.section_number, stored, computed });
+        }
+    }
+    issues
+}
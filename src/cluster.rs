@@ -0,0 +1,142 @@
+//! Grouping similar BOFs, to spot variants and rebuilds of the same tool in
+//! a large collection
+//!
+//! Two objects are compared by the overlap of their import sets and how
+//! close their loaded sizes are, and with the `fuzzy-hash` feature by the
+//! TLSH distance of the files. Objects scoring at least the threshold with
+//! any member of a cluster join it.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::hash::normalize_import;
+use crate::{Bof, Hashes};
+
+/// Lowest similarity at which two objects are considered the same tool
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+
+/// TLSH distance at which files are considered unrelated
+#[cfg(feature = "fuzzy-hash")]
+const TLSH_MAX_DISTANCE: i32 = 300;
+
+/// What's compared of one object
+pub struct Sample {
+    pub name: String,
+    pub hashes: Hashes,
+    /// Imports, normalized like the imphash
+    pub imports: BTreeSet<String>,
+    /// See [`Bof::estimated_loaded_size`]
+    pub size: usize,
+    #[cfg(feature = "fuzzy-hash")]
+    tlsh: Option<tlsh2::TlshDefault>,
+}
+
+impl Sample {
+    pub fn new(name: impl Into<String>, bof: &Bof) -> Self {
+        Self {
+            name: name.into(),
+            hashes: bof.hashes(),
+            imports: bof.import_names().iter().map(|name| normalize_import(name)).collect(),
+            size: bof.estimated_loaded_size(),
+            #[cfg(feature = "fuzzy-hash")]
+            tlsh: tlsh2::TlshDefaultBuilder::build_from(bof.buffer),
+        }
+    }
+
+    /// Similarity to `other`, from 0 (unrelated) to 1 (identical)
+    ///
+    /// The average of the ratio of the sizes, the imports' Jaccard index
+    /// unless neither imports anything, and the TLSH similarity when both
+    /// files have one.
+    pub fn similarity(&self, other: &Sample) -> f64 {
+        if self.hashes.sha256 == other.hashes.sha256 {
+            return 1.0;
+        }
+        let size = match self.size.max(other.size) {
+            0 => 1.0,
+            largest => self.size.min(other.size) as f64 / largest as f64,
+        };
+        let mut scores = vec![size];
+        let union = self.imports.union(&other.imports).count();
+        if union > 0 {
+            scores.push(self.imports.intersection(&other.imports).count() as f64 / union as f64);
+        }
+        #[cfg(feature = "fuzzy-hash")]
+        if let (Some(a), Some(b)) = (&self.tlsh, &other.tlsh) {
+            let distance = a.diff(b, true).min(TLSH_MAX_DISTANCE);
+            scores.push(1.0 - distance as f64 / TLSH_MAX_DISTANCE as f64);
+        }
+        scores.iter().sum::<f64>() / scores.len() as f64
+    }
+
+    /// How `other` relates to this object, given they're in the same cluster
+    pub fn relation(&self, other: &Sample) -> Relation {
+        if self.hashes.sha256 == other.hashes.sha256 {
+            Relation::Duplicate
+        } else if self.hashes.imphash == other.hashes.imphash {
+            Relation::Rebuild
+        } else {
+            Relation::Variant
+        }
+    }
+}
+
+/// How a member of a cluster relates to the first one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    /// Byte for byte the same file
+    Duplicate,
+    /// Same imports, different code or data
+    Rebuild,
+    /// Different imports
+    Variant,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Duplicate => "duplicate",
+            Self::Rebuild => "rebuild",
+            Self::Variant => "variant",
+        })
+    }
+}
+
+/// Group `samples` whose similarity is at least `threshold`, transitively
+///
+/// Returns the indices of each group of two or more samples, in the order
+/// the samples were given.
+pub fn cluster(samples: &[Sample], threshold: f64) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..samples.len()).collect();
+    for a in 0..samples.len() {
+        for b in a + 1..samples.len() {
+            if samples[a].similarity(&samples[b]) >= threshold {
+                let (root_a, root_b) = (root(&mut parents, a), root(&mut parents, b));
+                parents[root_a.max(root_b)] = root_a.min(root_b);
+            }
+        }
+    }
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut roots: Vec<usize> = Vec::new();
+    for index in 0..samples.len() {
+        let root = root(&mut parents, index);
+        match roots.iter().position(|known| *known == root) {
+            Some(position) => clusters[position].push(index),
+            None => {
+                roots.push(root);
+                clusters.push(vec![index]);
+            }
+        }
+    }
+    clusters.retain(|members| members.len() > 1);
+    clusters
+}
+
+/// Representative of the set `index` is in
+fn root(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
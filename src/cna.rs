@@ -0,0 +1,190 @@
+//! Aggressor script generation
+//!
+//! Cobalt Strike runs BOFs from an Aggressor (`.cna`) script: an `alias`
+//! that packs the command's arguments with `bof_pack()` and hands the object
+//! to `beacon_inline_execute()`. This writes that boilerplate for a BOF.
+//! https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics/beacon-object-files_how-to-develop.htm
+
+use std::fmt::Write;
+
+use crate::{pair, ArgSpec, Arch};
+
+/// An argument the alias accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptArgument {
+    pub name: String,
+    /// `bof_pack()` format character of the argument; binary arguments are
+    /// read from the file the operator names
+    pub typ: char,
+    /// Value packed when the argument isn't given, which makes it optional
+    pub default: Option<String>,
+}
+
+/// An alias that runs one BOF
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Script {
+    pub command: String,
+    pub description: String,
+    pub entrypoint: String,
+    /// Object file name relative to the script; an `x64`/`x86` tag in it is
+    /// replaced with the beacon's architecture
+    pub file: String,
+    /// Architecture of an object without an architecture tag, which the
+    /// alias refuses to run on other beacons
+    pub arch: Option<Arch>,
+    pub arguments: Vec<ScriptArgument>,
+}
+
+impl Script {
+    /// Alias for a single object, with one argument per character of a
+    /// `bof_pack()` format string
+    pub fn new(command: &str, entrypoint: &str, file: &str, arch: Option<Arch>, format: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            description: String::new(),
+            entrypoint: entrypoint.to_string(),
+            file: file.to_string(),
+            arch,
+            arguments: format
+                .chars()
+                .enumerate()
+                .map(|(index, typ)| ScriptArgument { name: format!("arg{}", index), typ, default: None })
+                .collect(),
+        }
+    }
+
+    /// Alias for a single object with the arguments, help and entrypoint of
+    /// a spec, falling back to `command` and `entrypoint`
+    pub fn from_spec(command: &str, entrypoint: &str, file: &str, arch: Option<Arch>, spec: &ArgSpec) -> Self {
+        let command = spec.name.as_deref().unwrap_or(command);
+        let mut script = Self::new(command, spec.entrypoint.as_deref().unwrap_or(entrypoint), file, arch, "");
+        script.description = spec.description.clone();
+        script.arguments = spec
+            .arguments
+            .iter()
+            .map(|argument| ScriptArgument {
+                name: argument.name.clone(),
+                typ: argument.typ,
+                default: argument.default.clone(),
+            })
+            .collect();
+        script
+    }
+
+    /// Usage line shown by `help` and on a wrong number of arguments
+    pub fn usage(&self) -> String {
+        let mut usage = self.command.clone();
+        for argument in &self.arguments {
+            let _ = match (argument.typ, &argument.default) {
+                ('b', _) => write!(usage, " <{} file>", argument.name),
+                (_, Some(_)) => write!(usage, " [{}]", argument.name),
+                _ => write!(usage, " <{}>", argument.name),
+            };
+        }
+        usage
+    }
+
+    pub fn to_cna(&self) -> String {
+        let command = identifier(&self.command);
+        let usage = quote(&format!("Usage: {}", self.usage()));
+        let description = match self.description.is_empty() {
+            true => format!("Run the {} BOF", self.command),
+            false => self.description.clone(),
+        };
+        let format: String = self.arguments.iter().map(|argument| argument.typ).collect();
+
+        let mut out = String::new();
+        let _ = writeln!(out, "# {} BOF, generated by bof-kit", self.command);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "beacon_command_register(");
+        let _ = writeln!(out, "    {},", quote(&command));
+        let _ = writeln!(out, "    {},", quote(&description));
+        let _ = writeln!(out, "    {});", usage);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "alias {} {{", command);
+        let mut locals = vec!["$barch".to_string(), "$handle".to_string(), "$data".to_string(), "$args".to_string()];
+        locals.extend(self.arguments.iter().enumerate().filter(|(_, argument)| argument.typ == 'b').map(|(index, _)| format!("$file{}", index)));
+        let _ = writeln!(out, "    local('{}');", locals.join(" "));
+        let _ = writeln!(out);
+        let required = self.arguments.iter().take_while(|argument| argument.default.is_none()).count();
+        let _ = match required == self.arguments.len() {
+            true => writeln!(out, "    if (size(@_) != {}) {{", required + 1),
+            false => writeln!(out, "    if (size(@_) < {} || size(@_) > {}) {{", required + 1, self.arguments.len() + 1),
+        };
+        let _ = writeln!(out, "        berror($1, {});", usage);
+        let _ = writeln!(out, "        return;");
+        let _ = writeln!(out, "    }}");
+        let _ = writeln!(out);
+        let _ = writeln!(out, "    $barch = barch($1);");
+        let file = match pair::arch_tag(&self.file) {
+            Some(tag) => format!("{} $+ $barch $+ {}", quote_inner(&self.file[..tag.start]), quote_inner(&self.file[tag.end..])),
+            None => {
+                if let Some(arch) = self.arch {
+                    let _ = writeln!(out, "    if ($barch ne {}) {{", quote(arch.name()));
+                    let _ = writeln!(out, "        berror($1, {});", quote(&format!("{} only has an {} build", self.command, arch.name())));
+                    let _ = writeln!(out, "        return;");
+                    let _ = writeln!(out, "    }}");
+                }
+                quote_inner(&self.file)
+            }
+        };
+        let _ = writeln!(out, "    $handle = openf(script_resource(\"{}\"));", file);
+        let _ = writeln!(out, "    $data = readb($handle, -1);");
+        let _ = writeln!(out, "    closef($handle);");
+        let _ = writeln!(out);
+
+        // binary arguments are read from the file they name
+        let mut values = Vec::new();
+        for (index, argument) in self.arguments.iter().enumerate() {
+            let value = match &argument.default {
+                Some(default) => format!("iff(size(@_) > {}, ${}, {})", index + 2, index + 2, quote(default)),
+                None => format!("${}", index + 2),
+            };
+            if argument.typ != 'b' {
+                values.push(value);
+                continue;
+            }
+            let variable = format!("$file{}", index);
+            let _ = writeln!(out, "    $handle = openf({});", value);
+            let _ = writeln!(out, "    {} = readb($handle, -1);", variable);
+            let _ = writeln!(out, "    closef($handle);");
+            values.push(variable);
+        }
+        let _ = match values.is_empty() {
+            true => writeln!(out, "    $args = bof_pack($1, \"\");"),
+            false => writeln!(out, "    $args = bof_pack($1, {}, {});", quote(&format), values.join(", ")),
+        };
+        let _ = writeln!(out, "    btask($1, {});", quote(&format!("Running {}", self.command)));
+        let _ = writeln!(out, "    beacon_inline_execute($1, $data, {}, $args);", quote(&self.entrypoint));
+        let _ = writeln!(out, "}}");
+        out
+    }
+}
+
+/// An alias name: letters, digits, underscores and dashes
+fn identifier(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' }).collect()
+}
+
+/// A double-quoted Sleep string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", quote_inner(value))
+}
+
+/// Escape a value for a double-quoted Sleep string, where `$` would
+/// otherwise interpolate a variable
+fn quote_inner(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted
+}
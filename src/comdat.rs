@@ -0,0 +1,299 @@
+//! COMDAT sections
+//!
+//! MSVC puts every inline function, template instance and `selectany`
+//! global in a COMDAT section of its own, and clang and GCC do the same for
+//! C++ and with `-ffunction-sections`. The section's selection rule tells
+//! the linker which copy to keep when several objects define the same
+//! COMDAT symbol, and associative sections (`.pdata`, `.xdata`, `.debug$S`)
+//! go with the COMDAT they describe, so the linker keeps or drops them
+//! together. A linker also drops COMDATs nothing refers to. BOF loaders load
+//! every section, so a COMDAT nothing calls still ships with the BOF, and
+//! duplicates (see [`duplicates`](crate::duplicates)) are all loaded with
+//! references split between them.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use goblin::pe::section_table::IMAGE_SCN_LNK_COMDAT;
+use goblin::pe::symbol::{
+    IMAGE_COMDAT_SELECT_ANY, IMAGE_COMDAT_SELECT_ASSOCIATIVE, IMAGE_COMDAT_SELECT_EXACT_MATCH,
+    IMAGE_COMDAT_SELECT_LARGEST, IMAGE_COMDAT_SELECT_NODUPLICATES, IMAGE_COMDAT_SELECT_SAME_SIZE,
+};
+
+use crate::disasm::is_section_definition;
+use crate::{is_loaded, section_name, Bof, Severity};
+
+/// How a linker picks among COMDAT sections defining the same symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Selection {
+    /// A second definition is an error
+    NoDuplicates,
+    /// Any copy will do
+    Any,
+    /// Any copy, as long as they're all the same size
+    SameSize,
+    /// Any copy, as long as they're all identical
+    ExactMatch,
+    /// Kept or dropped with the section numbered `number`
+    Associative { number: i16 },
+    /// The largest copy
+    Largest,
+    /// A value the PE specification doesn't define
+    Unknown(u8),
+}
+
+impl Selection {
+    fn new(selection: u8, number: u16) -> Self {
+        match selection {
+            IMAGE_COMDAT_SELECT_NODUPLICATES => Selection::NoDuplicates,
+            IMAGE_COMDAT_SELECT_ANY => Selection::Any,
+            IMAGE_COMDAT_SELECT_SAME_SIZE => Selection::SameSize,
+            IMAGE_COMDAT_SELECT_EXACT_MATCH => Selection::ExactMatch,
+            IMAGE_COMDAT_SELECT_ASSOCIATIVE => Selection::Associative { number: number as i16 },
+            IMAGE_COMDAT_SELECT_LARGEST => Selection::Largest,
+            other => Selection::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selection::NoDuplicates => f.write_str("no duplicates"),
+            Selection::Any => f.write_str("any"),
+            Selection::SameSize => f.write_str("same size"),
+            Selection::ExactMatch => f.write_str("exact match"),
+            Selection::Associative { number } => write!(f, "associative with section #{}", number),
+            Selection::Largest => f.write_str("largest"),
+            Selection::Unknown(value) => write!(f, "unknown selection {}", value),
+        }
+    }
+}
+
+/// A COMDAT section
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Comdat {
+    pub section: String,
+    /// One-based section number
+    pub number: i16,
+    pub selection: Selection,
+    /// The COMDAT symbol copies are matched by; associative sections have
+    /// none
+    pub symbol: Option<String>,
+    /// Size in bytes, including uninitialized data
+    pub size: usize,
+}
+
+impl fmt::Display for Comdat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} #{}", self.section, self.number)?;
+        if let Some(symbol) = &self.symbol {
+            write!(f, " ({})", symbol)?;
+        }
+        write!(f, ": {}", self.selection)
+    }
+}
+
+/// What a loader would get wrong about a COMDAT section
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ComdatProblem {
+    /// Nothing refers to it, so a linker would drop it and its associative
+    /// sections, `size` bytes in all, but loaders load them
+    Unreferenced { size: usize },
+    /// Associative with a section that doesn't exist, isn't a COMDAT or is
+    /// associative itself
+    BadAssociation { number: i16 },
+    /// A selection value linkers reject
+    UnknownSelection(u8),
+}
+
+/// A problem with a COMDAT section
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ComdatIssue {
+    pub comdat: Comdat,
+    pub problem: ComdatProblem,
+}
+
+impl ComdatIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for ComdatIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let comdat = &self.comdat;
+        let name = comdat.symbol.as_deref().unwrap_or(&comdat.section);
+        match &self.problem {
+            ComdatProblem::Unreferenced { size } => {
+                write!(f, "COMDAT nothing refers to is loaded anyway: {} ({} bytes in {} #{})", name, size, comdat.section, comdat.number)
+            }
+            ComdatProblem::BadAssociation { number } => {
+                write!(f, "COMDAT associated with section #{}, which isn't a COMDAT it can follow: {} #{}", number, comdat.section, comdat.number)
+            }
+            ComdatProblem::UnknownSelection(value) => {
+                write!(f, "COMDAT with unknown selection {}: {} #{}", value, comdat.section, comdat.number)
+            }
+        }
+    }
+}
+
+/// Every COMDAT section, in section table order
+pub fn sections(bof: &Bof) -> Vec<Comdat> {
+    let mut comdats = Vec::new();
+    let symbols: Vec<_> = bof.coff.symbols.iter().collect();
+    for (position, (index, _, symbol)) in symbols.iter().enumerate() {
+        let section = match symbol.section_number {
+            number if number > 0 && is_section_definition(bof, symbol) => match bof.coff.sections.get(number as usize - 1) {
+                Some(section) if section.characteristics & IMAGE_SCN_LNK_COMDAT != 0 => section,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let aux = match bof.coff.symbols.aux_section_definition(index + 1) {
+            Some(aux) => aux,
+            None => continue,
+        };
+        let selection = Selection::new(aux.selection, aux.number);
+        // the COMDAT symbol is the first symbol in the section after its definition
+        let symbol_name = match selection {
+            Selection::Associative { .. } => None,
+            _ => symbols[position + 1..]
+                .iter()
+                .find(|(_, _, other)| other.section_number == symbol.section_number && !is_section_definition(bof, other))
+                .map(|(_, _, other)| bof.symbol_name(other)),
+        };
+        comdats.push(Comdat {
+            section: section_name(section),
+            number: symbol.section_number,
+            selection,
+            symbol: symbol_name,
+            size: section.size_of_raw_data.max(section.virtual_size) as usize,
+        });
+    }
+    comdats
+}
+
+/// Find COMDATs nothing but their own associative sections refers to,
+/// other than the one defining `entrypoint`, and malformed COMDAT records
+pub fn lint(bof: &Bof, comdats: &[Comdat], entrypoint: Option<&str>) -> Vec<ComdatIssue> {
+    let associative_of = |parent: i16| {
+        comdats.iter().filter(move |comdat| comdat.selection == Selection::Associative { number: parent })
+    };
+    // sections something outside them and their associative sections refers to
+    let mut referenced = BTreeSet::new();
+    for (index, section) in bof.coff.sections.iter().enumerate() {
+        if !is_loaded(section) {
+            continue;
+        }
+        let number = index as i16 + 1;
+        let follows = comdats.iter().find(|comdat| comdat.number == number).and_then(|comdat| match comdat.selection {
+            Selection::Associative { number } => Some(number),
+            _ => None,
+        });
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            if let Some((_, target)) = bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                if target.section_number != number && Some(target.section_number) != follows {
+                    referenced.insert(target.section_number);
+                }
+            }
+        }
+    }
+    if let Some(entrypoint) = entrypoint {
+        for (_, _, symbol) in bof.coff.symbols.iter() {
+            if symbol.section_number > 0 && bof.symbol_name(&symbol) == entrypoint {
+                referenced.insert(symbol.section_number);
+            }
+        }
+    }
+
+    let mut issues = Vec::new();
+    for comdat in comdats {
+        let problem = match comdat.selection {
+            Selection::Unknown(value) => ComdatProblem::UnknownSelection(value),
+            Selection::Associative { number } => {
+                let parent = comdats.iter().find(|parent| parent.number == number);
+                match parent {
+                    Some(parent) if number != comdat.number && !matches!(parent.selection, Selection::Associative { .. }) => continue,
+                    _ => ComdatProblem::BadAssociation { number },
+                }
+            }
+            _ if !referenced.contains(&comdat.number) => {
+                let loaded = |number: i16| bof.coff.sections.get(number as usize - 1).is_some_and(is_loaded);
+                let size = comdat.size
+                    + associative_of(comdat.number).filter(|child| loaded(child.number)).map(|child| child.size).sum::<usize>();
+                ComdatProblem::Unreferenced { size }
+            }
+            _ => continue,
+        };
+        issues.push(ComdatIssue { comdat: comdat.clone(), problem });
+    }
+    issues
+}
+
+/// Why the sections of an object can't be picked the way a linker would
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ComdatConflict {
+    pub symbol: String,
+    pub selection: Selection,
+}
+
+impl fmt::Display for ComdatConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.selection {
+            Selection::NoDuplicates => write!(f, "COMDAT {} is defined more than once", self.symbol),
+            Selection::SameSize => write!(f, "copies of COMDAT {} differ in size", self.symbol),
+            _ => write!(f, "copies of COMDAT {} differ", self.symbol),
+        }
+    }
+}
+
+/// Section indexes a linker would discard from `bof` on its own: all copies
+/// but the one the selection rule picks of each COMDAT symbol, along with
+/// their associative sections
+pub fn discarded(bof: &Bof, comdats: &[Comdat]) -> Result<BTreeSet<usize>, ComdatConflict> {
+    let contents = |comdat: &Comdat| {
+        bof.coff.sections.get(comdat.number as usize - 1).map(|section| bof.section_contents(section)).unwrap_or_default()
+    };
+    let mut dropped = BTreeSet::new();
+    // (symbol, kept copy) for each COMDAT symbol seen so far
+    let mut kept: Vec<(&str, &Comdat)> = Vec::new();
+    for comdat in comdats {
+        let symbol = match &comdat.symbol {
+            Some(symbol) => symbol.as_str(),
+            None => continue,
+        };
+        let position = match kept.iter().position(|(name, _)| *name == symbol) {
+            Some(position) => position,
+            None => {
+                kept.push((symbol, comdat));
+                continue;
+            }
+        };
+        let first = kept[position].1;
+        let conflict = ComdatConflict { symbol: symbol.to_string(), selection: comdat.selection };
+        let loser = match comdat.selection {
+            Selection::NoDuplicates => return Err(conflict),
+            Selection::SameSize if comdat.size != first.size => return Err(conflict),
+            Selection::ExactMatch if contents(comdat) != contents(first) => return Err(conflict),
+            Selection::Largest if comdat.size > first.size => {
+                kept[position].1 = comdat;
+                first
+            }
+            _ => comdat,
+        };
+        dropped.insert(loser.number as usize - 1);
+    }
+    for comdat in comdats {
+        if let Selection::Associative { number } = comdat.selection {
+            if number > 0 && dropped.contains(&(number as usize - 1)) {
+                dropped.insert(comdat.number as usize - 1);
+            }
+        }
+    }
+    Ok(dropped)
+}
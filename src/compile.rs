@@ -0,0 +1,257 @@
+//! Compiler invocations that build a loadable BOF
+//!
+//! Every flag names the rule whose findings it keeps away, so a BOF built
+//! with these commands passes `bof-check` for its target: no unwind tables,
+//! stack probes, stack cookies or debug information, and an object rather
+//! than a linked image with a CRT.
+
+use std::error;
+use std::fmt;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use crate::{ApiProfile, Arch};
+
+/// Toolchain a BOF is compiled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compiler {
+    /// MinGW-w64 GCC cross compilers
+    Mingw,
+    /// clang targeting MinGW-w64
+    Clang,
+    /// cl.exe from a Visual Studio Native Tools prompt
+    Msvc,
+}
+
+impl Compiler {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Mingw => "mingw",
+            Self::Clang => "clang",
+            Self::Msvc => "msvc",
+        }
+    }
+
+    /// Compiler driver for `arch`
+    pub fn program(&self, arch: Arch) -> &'static str {
+        match (self, arch) {
+            (Self::Mingw, Arch::X64) => "x86_64-w64-mingw32-gcc",
+            (Self::Mingw, Arch::X86) => "i686-w64-mingw32-gcc",
+            (Self::Mingw, Arch::Arm64) => "aarch64-w64-mingw32-gcc",
+            (Self::Clang, _) => "clang",
+            (Self::Msvc, _) => "cl.exe",
+        }
+    }
+
+    /// Flags every build for `arch` gets
+    pub fn flags(&self, arch: Arch) -> Vec<Flag> {
+        let (common, intel) = match self {
+            Self::Mingw | Self::Clang => (GCC_FLAGS, GCC_INTEL_FLAGS),
+            Self::Msvc => (MSVC_FLAGS, &[][..]),
+        };
+        match arch {
+            Arch::X86 | Arch::X64 => common.iter().chain(intel).copied().collect(),
+            Arch::Arm64 => common.to_vec(),
+        }
+    }
+
+    /// Flags selecting `arch`, which cl.exe takes from the prompt instead
+    fn arch_flags(&self, arch: Arch) -> Vec<String> {
+        match self {
+            Self::Clang => vec![format!("--target={}-w64-mingw32", clang_arch(arch))],
+            Self::Mingw | Self::Msvc => Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Compiler {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "mingw" | "gcc" => Ok(Self::Mingw),
+            "clang" => Ok(Self::Clang),
+            "msvc" | "cl" | "cl.exe" => Ok(Self::Msvc),
+            _ => Err(format!("unknown compiler: {} (expected mingw, clang or msvc)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Compiler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A compiler flag and why a BOF needs it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Flag {
+    pub flag: &'static str,
+    /// Rule in [`crate::rules::RULES`] whose findings the flag prevents, if
+    /// bof-check has one
+    pub rule: Option<&'static str>,
+    pub reason: &'static str,
+}
+
+static GCC_FLAGS: &[Flag] = &[
+    Flag { flag: "-c", rule: None, reason: "compile to an object: no CRT startup code or libraries are linked in" },
+    Flag { flag: "-Os", rule: Some("size"), reason: "optimize for size to keep the BOF within a single task" },
+    Flag { flag: "-fno-asynchronous-unwind-tables", rule: Some("exceptions"), reason: "no .pdata/.xdata unwind data the loader never registers" },
+    Flag { flag: "-fno-stack-protector", rule: Some("unresolved-external"), reason: "no __stack_chk_fail and __stack_chk_guard references" },
+    Flag { flag: "-fno-ident", rule: None, reason: "no .rdata$zzz string naming the compiler build" },
+    Flag { flag: "-fno-lto", rule: None, reason: "machine code rather than LTO bitcode, which loaders can't run" },
+    Flag { flag: "-g0", rule: Some("sections"), reason: "no .debug sections" },
+];
+
+/// GCC flags that only exist for x86 and x64
+static GCC_INTEL_FLAGS: &[Flag] = &[
+    Flag { flag: "-masm=intel", rule: None, reason: "Intel syntax for inline assembly, as most BOF snippets are written" },
+    Flag { flag: "-mno-stack-arg-probe", rule: Some("unresolved-external"), reason: "no ___chkstk_ms calls for large stack frames" },
+];
+
+static MSVC_FLAGS: &[Flag] = &[
+    Flag { flag: "/nologo", rule: None, reason: "no version banner" },
+    Flag { flag: "/c", rule: None, reason: "compile to an object: no CRT startup code or libraries are linked in" },
+    Flag { flag: "/O1", rule: Some("size"), reason: "optimize for size to keep the BOF within a single task" },
+    Flag { flag: "/GS-", rule: Some("unresolved-external"), reason: "no __security_check_cookie and __security_cookie references" },
+    Flag { flag: "/Gs9999999", rule: Some("unresolved-external"), reason: "no __chkstk calls for large stack frames" },
+    Flag { flag: "/Zl", rule: None, reason: "no /DEFAULTLIB directives for a CRT the loader doesn't link" },
+];
+
+fn clang_arch(arch: Arch) -> &'static str {
+    match arch {
+        Arch::X86 => "i686",
+        Arch::X64 => "x86_64",
+        Arch::Arm64 => "aarch64",
+    }
+}
+
+/// Why no invocation could be generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompileError {
+    /// The target's loader can't run objects of this architecture
+    UnsupportedArch(Arch, String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedArch(arch, target) => write!(f, "{} objects can't be loaded by {}", arch, target),
+        }
+    }
+}
+
+impl error::Error for CompileError {}
+
+/// A command line that compiles one architecture's object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    pub arch: Arch,
+    pub program: &'static str,
+    pub args: Vec<String>,
+    /// Object file the command writes
+    pub output: String,
+}
+
+impl fmt::Display for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.program)?;
+        for arg in &self.args {
+            match arg.contains(|c: char| c.is_whitespace() || "'\"$&;|<>()".contains(c)) {
+                true => write!(f, " '{}'", arg.replace('\'', "'\\''"))?,
+                false => write!(f, " {}", arg)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Object file name for `source` built for `arch`, e.g. `whoami.x64.o`
+pub fn output_name(source: &str, arch: Arch) -> String {
+    let stem = source.rsplit_once('.').map_or(source, |(stem, _)| stem);
+    format!("{}.{}.o", stem, arch)
+}
+
+/// Commands compiling `source` once for each of `archs`
+pub fn invocations(
+    profile: &dyn ApiProfile,
+    compiler: Compiler,
+    archs: &[Arch],
+    source: &str,
+) -> Result<Vec<Invocation>, CompileError> {
+    archs
+        .iter()
+        .map(|&arch| {
+            check_arch(profile, arch)?;
+            let output = output_name(source, arch);
+            let mut args = compiler.arch_flags(arch);
+            args.extend(compiler.flags(arch).iter().map(|flag| flag.flag.to_string()));
+            match compiler {
+                Compiler::Msvc => args.extend([format!("/Fo{}", output), source.to_string()]),
+                Compiler::Mingw | Compiler::Clang => args.extend([source.to_string(), "-o".to_string(), output.clone()]),
+            }
+            Ok(Invocation { arch, program: compiler.program(arch), args, output })
+        })
+        .collect()
+}
+
+fn check_arch(profile: &dyn ApiProfile, arch: Arch) -> Result<(), CompileError> {
+    match profile.supports_arch(arch) {
+        true => Ok(()),
+        false => Err(CompileError::UnsupportedArch(arch, profile.name().to_string())),
+    }
+}
+
+/// A Makefile building `name.c` for each of `archs` with MinGW-w64 by
+/// default and cl.exe through `msvc-ARCH` targets, with a `check` target
+/// running bof-check against the target and the argument `spec`, if any
+pub fn makefile(name: &str, profile: &dyn ApiProfile, archs: &[Arch], spec: Option<&str>) -> Result<String, CompileError> {
+    for &arch in archs {
+        check_arch(profile, arch)?;
+    }
+    let flags = |list: &[Flag]| list.iter().map(|flag| flag.flag).collect::<Vec<_>>().join(" ");
+    let names: Vec<_> = archs.iter().map(|arch| arch.name()).collect();
+    let objects: Vec<_> = archs.iter().map(|arch| format!("$(NAME).{}.o", arch)).collect();
+    let variable = |arch: Arch| format!("MINGW_{}", arch.name().to_ascii_uppercase());
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# {} BOF", name);
+    let _ = writeln!(out, "#");
+    let _ = writeln!(out, "# make             {} builds with MinGW-w64", names.join(" and "));
+    if let Some(first) = names.first() {
+        let _ = writeln!(out, "# make msvc-{0:<6} from an {0} Native Tools prompt", first);
+    }
+    let _ = writeln!(out, "# make check       check the builds with bof-check");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "NAME := {}", name);
+    for &arch in archs {
+        let _ = writeln!(out, "{} := {}", variable(arch), Compiler::Mingw.program(arch));
+    }
+    let _ = writeln!(out, "CFLAGS := {} -Wall", flags(GCC_FLAGS));
+    let _ = writeln!(out, "INTEL_FLAGS := {}", flags(GCC_INTEL_FLAGS));
+    let _ = writeln!(out, "MSVC_FLAGS := {}", flags(MSVC_FLAGS));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "all: {}", objects.join(" "));
+    for &arch in archs {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "$(NAME).{}.o: $(NAME).c $(wildcard *.h)", arch);
+        let intel = if arch == Arch::Arm64 { "" } else { " $(INTEL_FLAGS)" };
+        let _ = writeln!(out, "\t$({}) $(CFLAGS){} $(NAME).c -o $@", variable(arch), intel);
+    }
+    for &arch in archs {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "msvc-{}:", arch);
+        let _ = writeln!(out, "\tcl.exe $(MSVC_FLAGS) /Fo$(NAME).{}.o $(NAME).c", arch);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "check: all");
+    let spec = spec.map_or(String::new(), |spec| format!(" --spec {}", spec));
+    let _ = writeln!(out, "\tbof-check --target {}{} {}", profile.name(), spec, objects.join(" "));
+    let _ = writeln!(out);
+    let _ = writeln!(out, "clean:");
+    let _ = writeln!(out, "\trm -f {}", objects.join(" "));
+    let _ = writeln!(out);
+    let msvc: Vec<_> = archs.iter().map(|arch| format!("msvc-{}", arch)).collect();
+    let _ = writeln!(out, ".PHONY: all {} check clean", msvc.join(" "));
+    Ok(out)
+}
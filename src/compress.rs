@@ -0,0 +1,209 @@
+//! Compressed sizes
+//!
+//! Beacon's task size limit applies to what it receives, and C2 profiles and
+//! loaders commonly compress objects before sending them: with deflate, or
+//! with LZNT1, which `RtlDecompressBuffer` undoes on the target without
+//! shipping a decompressor. An object full of zero padding and repeated
+//! strings costs far less on the wire than its file size suggests.
+
+use std::collections::HashMap;
+
+/// Bytes of an LZNT1 chunk before compression
+const CHUNK_SIZE: usize = 4096;
+
+/// Shortest back-reference LZNT1 can encode
+const MIN_MATCH: usize = 3;
+
+/// Candidates to try for each back-reference
+const MAX_CHAIN: usize = 64;
+
+/// Size of the object as read and compressed each way
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompressedSize {
+    /// Bytes of the object file
+    pub raw: usize,
+    /// Bytes of raw deflate, at zlib's default level
+    pub deflate: usize,
+    /// Bytes of LZNT1, as `RtlCompressBuffer` lays it out
+    pub lznt1: usize,
+}
+
+impl CompressedSize {
+    pub fn new(data: &[u8]) -> Self {
+        CompressedSize { raw: data.len(), deflate: deflate(data).len(), lznt1: lznt1(data).len() }
+    }
+}
+
+/// `data` compressed with raw deflate
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec(data, 6)
+}
+
+/// `data` compressed with LZNT1, in chunks `RtlDecompressBuffer` accepts
+pub fn lznt1(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(data.len() / 2);
+    for chunk in data.chunks(CHUNK_SIZE) {
+        let compressed = lznt1_chunk(chunk);
+        // a chunk that doesn't shrink is stored as is
+        let (flag, body) = match compressed.len() < chunk.len() {
+            true => (0x8000, compressed.as_slice()),
+            false => (0, chunk),
+        };
+        let header = flag | 0x3000 | (body.len() + 2 - 3) as u16;
+        output.extend_from_slice(&header.to_le_bytes());
+        output.extend_from_slice(body);
+    }
+    output
+}
+
+/// Bits of a back-reference token holding the length at `position` in a
+/// chunk; the rest hold the offset, which can reach further as the
+/// position grows
+fn length_bits(position: usize) -> u32 {
+    let mut bits = 12;
+    let mut reach = position - 1;
+    while reach >= 0x10 {
+        reach >>= 1;
+        bits -= 1;
+    }
+    bits
+}
+
+/// The body of an LZNT1 chunk: flag bytes, each followed by up to eight
+/// literals and back-references
+fn lznt1_chunk(chunk: &[u8]) -> Vec<u8> {
+    let key = |position: usize| -> Option<[u8; MIN_MATCH]> { chunk.get(position..position + MIN_MATCH)?.try_into().ok() };
+    let mut head: HashMap<[u8; MIN_MATCH], usize> = HashMap::new();
+    let mut previous = vec![usize::MAX; chunk.len()];
+    let insert = |position: usize, head: &mut HashMap<_, _>, previous: &mut Vec<usize>| {
+        if let Some(last) = key(position).and_then(|key| head.insert(key, position)) {
+            previous[position] = last;
+        }
+    };
+
+    let mut output = Vec::new();
+    let mut flags_at = 0;
+    let mut tokens = 0;
+    let mut position = 0;
+    while position < chunk.len() {
+        if tokens % 8 == 0 {
+            flags_at = output.len();
+            output.push(0);
+        }
+        let (offset, length) = match position {
+            0 => (0, 0),
+            _ => longest_match(chunk, position, &previous, key(position).and_then(|key| head.get(&key).copied())),
+        };
+        if length >= MIN_MATCH {
+            let bits = length_bits(position);
+            let token = ((offset - 1) << bits) | (length - MIN_MATCH);
+            output.extend_from_slice(&(token as u16).to_le_bytes());
+            output[flags_at] |= 1 << (tokens % 8);
+            for covered in position..position + length {
+                insert(covered, &mut head, &mut previous);
+            }
+            position += length;
+        } else {
+            output.push(chunk[position]);
+            insert(position, &mut head, &mut previous);
+            position += 1;
+        }
+        tokens += 1;
+    }
+    output
+}
+
+/// (offset, length) of the longest earlier copy of the bytes at `position`
+/// a token there can encode, following the chain of positions starting
+/// with the same bytes from `candidate`
+fn longest_match(chunk: &[u8], position: usize, previous: &[usize], mut candidate: Option<usize>) -> (usize, usize) {
+    let bits = length_bits(position);
+    let max_offset = 1 << (16 - bits);
+    let max_length = ((1 << bits) - 1 + MIN_MATCH).min(chunk.len() - position);
+    let mut best = (0, 0);
+    for _ in 0..MAX_CHAIN {
+        let start = match candidate {
+            Some(start) if position - start <= max_offset => start,
+            _ => break,
+        };
+        // copies may overlap the bytes they produce
+        let length = (0..max_length).take_while(|&index| chunk[start + index] == chunk[position + index]).count();
+        if length > best.1 {
+            best = (position - start, length);
+            if length == max_length {
+                break;
+            }
+        }
+        candidate = Some(previous[start]).filter(|&before| before != usize::MAX);
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// LZNT1 as `RtlDecompressBuffer` reads it, written from [MS-XCA] 2.5
+    fn decompress(mut data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        while data.len() >= 2 {
+            let header = u16::from_le_bytes([data[0], data[1]]);
+            let (body, rest) = data[2..].split_at((header & 0x0fff) as usize + 3 - 2);
+            data = rest;
+            if header & 0x8000 == 0 {
+                output.extend_from_slice(body);
+                continue;
+            }
+            let start = output.len();
+            let mut body = body.iter().copied();
+            while let Some(flags) = body.next() {
+                for bit in 0..8 {
+                    if flags & (1 << bit) == 0 {
+                        match body.next() {
+                            Some(byte) => output.push(byte),
+                            None => break,
+                        }
+                        continue;
+                    }
+                    let token = match (body.next(), body.next()) {
+                        (Some(low), Some(high)) => u16::from_le_bytes([low, high]) as usize,
+                        _ => break,
+                    };
+                    let bits = length_bits(output.len() - start);
+                    let offset = (token >> bits) + 1;
+                    let length = (token & ((1 << bits) - 1)) + MIN_MATCH;
+                    for _ in 0..length {
+                        output.push(output[output.len() - offset]);
+                    }
+                }
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn lznt1_known_answers() {
+        // too short to shrink, so stored
+        assert_eq!(lznt1(b"abc"), [0x02, 0x30, b'a', b'b', b'c']);
+        // a literal, then one back-reference at offset 1 for the other nine
+        assert_eq!(lznt1(b"aaaaaaaaaa"), [0x03, 0xb0, 0x02, b'a', 0x06, 0x00]);
+        assert!(lznt1(b"").is_empty());
+    }
+
+    #[test]
+    fn lznt1_round_trip() {
+        let mut data = Vec::new();
+        for index in 0..3000u32 {
+            data.extend_from_slice(format!("BeaconPrintf {} ", index % 97).as_bytes());
+            data.extend_from_slice(&index.wrapping_mul(0x9e37_79b9).to_le_bytes());
+        }
+        data.extend(std::iter::repeat_n(0, 10000));
+        let compressed = lznt1(&data);
+        assert!(compressed.len() < data.len() / 2);
+        assert_eq!(decompress(&compressed), data);
+        // and data that barely compresses
+        let random: Vec<u8> = (0..9000u32).map(|index| (index.wrapping_mul(0x9e37_79b9) >> 13) as u8).collect();
+        assert_eq!(decompress(&lznt1(&random)), random);
+    }
+}
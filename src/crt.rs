@@ -0,0 +1,161 @@
+//! Undefined external symbols that aren't imports
+//!
+//! BOF loaders only resolve `__imp_` symbols, so any other undefined symbol a
+//! relocation points at fails to load. These are almost always calls the
+//! compiler emitted on its own (`memset`, stack probes, security cookies) or
+//! C library functions used without dynamic function resolution.
+
+use std::fmt;
+
+use crate::{exceptions, initializers, tls, Arch};
+
+/// Why the compiler referenced an unresolved external, which decides the fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExternalKind {
+    /// `memset`/`memcpy` emitted for struct initialization or copies
+    MemoryIntrinsic,
+    /// `__chkstk` emitted for stack frames larger than a page
+    StackProbe,
+    /// `/GS` or `-fstack-protector` cookie checks
+    StackProtector,
+    /// `_fltused`, referenced by any code using floating point
+    FloatSupport,
+    /// 64-bit division and multiplication helpers on x86
+    ArithmeticHelper,
+    /// SEH or C++ exception handlers and helpers, see [`crate::exceptions`]
+    ExceptionHandler,
+    /// The TLS index or emulated TLS runtime, see [`crate::tls`]
+    ThreadLocal,
+    /// `atexit` and its relatives, see [`crate::initializers`]
+    ExitHandler,
+    /// A C library function called without dynamic function resolution
+    CLibrary,
+    /// Anything else
+    Other,
+}
+
+static MEMORY_INTRINSICS: &[&str] = &["memset", "memcpy", "memmove", "memcmp"];
+
+static STACK_PROBES: &[&str] = &["__chkstk", "___chkstk_ms", "__chkstk_ms", "_alloca_probe", "__alloca", "_alloca"];
+
+static STACK_PROTECTOR: &[&str] = &[
+    "__security_check_cookie",
+    "__security_cookie",
+    "__GSHandlerCheck",
+    "__stack_chk_fail",
+    "__stack_chk_guard",
+];
+
+static ARITHMETIC_HELPERS: &[&str] = &[
+    "_alldiv", "_allmul", "_allrem", "_allshl", "_allshr", "_aulldiv", "_aullrem", "_aullshr", "__divdi3",
+    "__udivdi3", "__moddi3", "__umoddi3", "__muldi3", "__ashldi3", "__ashrdi3", "__lshrdi3",
+];
+
+static C_LIBRARY: &[&str] = &[
+    "strlen", "strcpy", "strncpy", "strcat", "strncat", "strcmp", "strncmp", "strchr", "strrchr", "strstr",
+    "_stricmp", "wcslen", "wcscpy", "wcscat", "wcscmp", "_wcsicmp", "wcschr", "printf", "sprintf", "snprintf",
+    "_snprintf", "vsnprintf", "swprintf", "_snwprintf", "sscanf", "malloc", "calloc", "realloc", "free", "atoi",
+    "strtol", "strtoul", "mbstowcs", "wcstombs", "toupper", "tolower", "time", "rand", "srand",
+];
+
+/// Whether `name`, with or without the x86 leading underscore, is a stack
+/// probe
+pub(crate) fn is_stack_probe(name: &str) -> bool {
+    STACK_PROBES.contains(&name) || name.strip_prefix('_').is_some_and(|name| STACK_PROBES.contains(&name))
+}
+
+/// Whether `function` is a C library function usually imported as
+/// `MSVCRT$function`
+pub(crate) fn is_c_library(function: &str) -> bool {
+    C_LIBRARY.contains(&function) || MEMORY_INTRINSICS.contains(&function)
+}
+
+/// An undefined external symbol referenced by a relocation
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct External {
+    /// Symbol name as it appears in the symbol table
+    pub name: String,
+    /// Name without the x86 leading underscore
+    pub function: String,
+    pub kind: ExternalKind,
+}
+
+impl External {
+    pub fn new(name: &str, arch: Arch) -> Self {
+        let function = match arch {
+            Arch::X86 => name.strip_prefix('_').unwrap_or(name),
+            _ => name,
+        };
+        Self { name: name.to_string(), function: function.to_string(), kind: classify(name, function) }
+    }
+
+    /// How to get rid of the reference
+    pub fn fix(&self) -> String {
+        let function = &self.function;
+        match self.kind {
+            ExternalKind::MemoryIntrinsic => format!(
+                "the compiler emits {} for large initializers and copies; \
+                 import it as MSVCRT${} or provide your own implementation",
+                function, function
+            ),
+            ExternalKind::StackProbe => "a function has more than a page of locals; \
+                 move large buffers to the heap or disable stack probes (-mno-stack-arg-probe, /Gs999999)"
+                .to_string(),
+            ExternalKind::StackProtector => {
+                "stack cookie checks can't be resolved; compile with -fno-stack-protector or /GS-".to_string()
+            }
+            ExternalKind::FloatSupport => "floating point code references _fltused; define `int _fltused = 1;`".to_string(),
+            ExternalKind::ArithmeticHelper => {
+                format!("64-bit arithmetic on x86 calls the {} runtime helper; avoid 64-bit division or implement it", self.name)
+            }
+            ExternalKind::ExceptionHandler => "BOF loaders don't support exceptions; \
+                 replace __try/__except and try/catch with error checks"
+                .to_string(),
+            ExternalKind::ThreadLocal => "BOF loaders don't set up thread local storage; \
+                 keep the state in locals or a structure passed to the functions that need it"
+                .to_string(),
+            ExternalKind::ExitHandler => "exit handlers would run after Beacon frees the BOF; \
+                 clean up before the entrypoint returns instead"
+                .to_string(),
+            ExternalKind::CLibrary => format!("declare it as a dynamic function resolution import: MSVCRT${}", function),
+            ExternalKind::Other => {
+                "define it in the BOF, or declare it as a dynamic function resolution import (MODULE$Function)".to_string()
+            }
+        }
+    }
+}
+
+impl fmt::Display for External {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unresolved external symbol: {}", self.name)
+    }
+}
+
+/// `name` is the symbol as written, `function` without x86 decoration; the
+/// compiler helpers are listed by whichever of the two they're known by
+fn classify(name: &str, function: &str) -> ExternalKind {
+    let listed = |list: &[&str]| list.contains(&name) || list.contains(&function);
+    if MEMORY_INTRINSICS.contains(&function) {
+        ExternalKind::MemoryIntrinsic
+    } else if listed(STACK_PROBES) {
+        ExternalKind::StackProbe
+    } else if listed(STACK_PROTECTOR) {
+        ExternalKind::StackProtector
+    } else if listed(&["_fltused"]) {
+        ExternalKind::FloatSupport
+    } else if listed(ARITHMETIC_HELPERS) {
+        ExternalKind::ArithmeticHelper
+    } else if exceptions::handler_runtime(name, function).is_some() {
+        ExternalKind::ExceptionHandler
+    } else if tls::is_runtime(name, function) {
+        ExternalKind::ThreadLocal
+    } else if initializers::is_exit_handler(name, function) {
+        ExternalKind::ExitHandler
+    } else if C_LIBRARY.contains(&function) {
+        ExternalKind::CLibrary
+    } else {
+        ExternalKind::Other
+    }
+}
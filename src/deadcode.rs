@@ -0,0 +1,125 @@
+//! Functions nothing calls
+//!
+//! Template BOFs and code pasted between projects carry helpers the
+//! entrypoints never reach, and every byte of them is sent with each task.
+//! A function is live if an entrypoint reaches it through the call graph, or
+//! if a data section points at it, since a function pointer table can call
+//! it in ways the graph can't see. Unwind tables point at every function and
+//! don't count.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use goblin::pe::symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_LABEL};
+
+use crate::callgraph::CallGraph;
+use crate::disasm::{extent, function_at, is_section_definition};
+use crate::{is_loaded, relocations, section_name, Bof, Severity};
+
+/// A function no entrypoint reaches
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeadFunction {
+    pub name: String,
+    pub section: String,
+    /// Bytes from the function to the next symbol in its section
+    pub size: usize,
+}
+
+/// Unreachable functions, reported together
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeadCode {
+    pub functions: Vec<DeadFunction>,
+}
+
+impl DeadCode {
+    /// Combined size of the functions
+    pub fn size(&self) -> usize {
+        self.functions.iter().map(|function| function.size).sum()
+    }
+
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for DeadCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.functions.iter().map(|function| function.name.as_str()).collect();
+        write!(f, "functions no entrypoint calls: {} ({} bytes)", names.join(", "), self.size())
+    }
+}
+
+fn is_unwind(section: &str) -> bool {
+    section.starts_with(".pdata") || section.starts_with(".xdata")
+}
+
+/// Find the static functions that neither the entrypoints nor data reach
+pub fn find(bof: &Bof, graph: &CallGraph) -> DeadCode {
+    let arch = match bof.arch() {
+        Some(arch) => arch,
+        None => return DeadCode::default(),
+    };
+    let is_code = |characteristics: u32| characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0;
+
+    let mut roots = bof.entrypoints();
+    for section in &bof.coff.sections {
+        if is_code(section.characteristics) || !is_loaded(section) || is_unwind(&section_name(section)) {
+            continue;
+        }
+        let data = bof.section_contents(section);
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            let target = match bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                Some((_, target)) => target,
+                None => continue,
+            };
+            let pointee = match is_section_definition(bof, &target) {
+                true => {
+                    let addend = relocations::addend(arch, entry.typ, data, entry.virtual_address as usize).unwrap_or(0);
+                    function_at(bof, target.section_number, addend.max(0) as u32)
+                }
+                false => Some(bof.symbol_name(&target)),
+            };
+            roots.extend(pointee);
+        }
+    }
+    let mut live: BTreeSet<String> = roots.iter().flat_map(|root| graph.reachable(root)).collect();
+    live.extend(roots);
+
+    let symbols: Vec<_> = bof
+        .coff
+        .symbols
+        .iter()
+        .map(|(_, _, symbol)| symbol)
+        .filter(|symbol| symbol.section_number > 0 && !is_section_definition(bof, symbol))
+        .collect();
+    // an alias of a live function shares its code
+    let live_at: BTreeSet<(i16, u32)> = symbols
+        .iter()
+        .filter(|symbol| live.contains(&bof.symbol_name(symbol)))
+        .map(|symbol| (symbol.section_number, symbol.value))
+        .collect();
+    let mut seen = BTreeSet::new();
+    let mut dead = DeadCode::default();
+    for symbol in &symbols {
+        if matches!(symbol.storage_class, IMAGE_SYM_CLASS_EXTERNAL | IMAGE_SYM_CLASS_LABEL)
+            || live_at.contains(&(symbol.section_number, symbol.value))
+            || !seen.insert((symbol.section_number, symbol.value))
+        {
+            continue;
+        }
+        let section = match bof.coff.sections.get(symbol.section_number as usize - 1) {
+            Some(section) if is_code(section.characteristics) => section,
+            _ => continue,
+        };
+        let (start, end) = extent(bof, symbol, bof.section_contents(section).len() as u32);
+        dead.functions.push(DeadFunction {
+            name: bof.symbol_name(symbol),
+            section: section_name(section),
+            size: (end - start) as usize,
+        });
+    }
+    dead
+}
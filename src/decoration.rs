@@ -0,0 +1,102 @@
+//! Import prefixes decorated for the wrong architecture
+//!
+//! x86 C symbols carry a leading underscore, so an import is `__imp__Name`
+//! there and `__imp_Name` everywhere else. Objects built with the other
+//! convention, usually by a MinGW cross compiler with the wrong flags or a
+//! header written for one architecture, have imports no loader can resolve.
+
+use std::fmt;
+
+use goblin::pe::symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_UNDEFINED};
+
+use crate::{ApiProfile, Arch, Bof, Severity};
+
+/// How an import's prefix is wrong
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DecorationProblem {
+    /// An x86-style `__imp__` import in an x64 or ARM64 object
+    LeadingUnderscore,
+    /// An `__imp_` import without the x86 leading underscore
+    MissingUnderscore,
+}
+
+/// An import decorated for the wrong architecture
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecorationIssue {
+    /// Symbol name as it appears in the symbol table
+    pub symbol: String,
+    pub problem: DecorationProblem,
+    /// The symbol decorated for the object's architecture
+    pub suggestion: String,
+}
+
+impl DecorationIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// What usually causes it
+    pub fn fix(&self) -> &'static str {
+        match self.problem {
+            DecorationProblem::LeadingUnderscore => {
+                "x64 symbols have no leading underscore; this usually comes from building with -fleading-underscore, \
+                 or from a header that spells out the x86 __imp__ prefix"
+            }
+            DecorationProblem::MissingUnderscore => {
+                "x86 symbols need a leading underscore; this usually comes from building with -fno-leading-underscore, \
+                 or from a header that spells out the x64 __imp_ prefix"
+            }
+        }
+    }
+}
+
+impl fmt::Display for DecorationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arch = match self.problem {
+            DecorationProblem::LeadingUnderscore => "x86-style import in an x64 object",
+            DecorationProblem::MissingUnderscore => "x64-style import in an x86 object",
+        };
+        write!(f, "{}: {} (did you mean {}?)", arch, self.symbol, self.suggestion)
+    }
+}
+
+/// Find undefined symbols that look like imports decorated for the other
+/// architecture
+///
+/// An extra underscore in an x64 object is only reported when the rest of
+/// the name is something the loader would resolve, since C library
+/// functions such as `_snprintf` start with one of their own.
+pub fn lint(bof: &Bof, profile: &dyn ApiProfile) -> Vec<DecorationIssue> {
+    let arch = match bof.arch() {
+        Some(arch) => arch,
+        None => return Vec::new(),
+    };
+    let resolvable = |name: &str| name.contains('$') || profile.is_export(name) || profile.is_builtin(name);
+    let mut issues = Vec::new();
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.storage_class != IMAGE_SYM_CLASS_EXTERNAL || symbol.section_number != IMAGE_SYM_UNDEFINED {
+            continue;
+        }
+        let name = bof.symbol_name(&symbol);
+        let issue = match (arch, name.strip_prefix("__imp_")) {
+            (Arch::X86, Some(rest)) if !rest.starts_with('_') => DecorationIssue {
+                symbol: name.clone(),
+                problem: DecorationProblem::MissingUnderscore,
+                suggestion: format!("__imp__{}", rest),
+            },
+            (Arch::X64 | Arch::Arm64, Some(rest)) => match rest.strip_prefix('_') {
+                Some(function) if resolvable(function) => DecorationIssue {
+                    symbol: name.clone(),
+                    problem: DecorationProblem::LeadingUnderscore,
+                    suggestion: format!("__imp_{}", function),
+                },
+                _ => continue,
+            },
+            _ => continue,
+        };
+        issues.push(issue);
+    }
+    issues
+}
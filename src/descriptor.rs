@@ -0,0 +1,108 @@
+//! Generic JSON task descriptors
+//!
+//! COFF loaders outside Cobalt Strike and Sliver mostly describe a BOF to
+//! their operators and task builders with a small JSON document: the
+//! command name, the entrypoint and the arguments to pack, in order. This
+//! writes that from the same argument spec the Aggressor script and Sliver
+//! manifest come from:
+//!
+//! ```json
+//! {
+//!   "name": "whoami",
+//!   "entry": "go",
+//!   "format": "zi",
+//!   "args": [
+//!     { "type": "string", "name": "target" },
+//!     { "type": "int", "name": "pid", "default": "0" }
+//!   ]
+//! }
+//! ```
+
+use serde::Serialize;
+
+use crate::ArgSpec;
+
+/// An argument to pack
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DescriptorArgument {
+    /// `binary`, `int`, `short`, `string` or `wstring`
+    #[serde(rename = "type")]
+    pub typ: &'static str,
+    pub name: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Value packed when the argument isn't given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// A task descriptor for one BOF
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Descriptor {
+    pub name: String,
+    pub entry: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// `bof_pack()` format string of the arguments
+    pub format: String,
+    pub args: Vec<DescriptorArgument>,
+}
+
+impl Descriptor {
+    /// Descriptor with one argument per character of a `bof_pack()` format
+    /// string
+    pub fn new(name: &str, entrypoint: &str, format: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            entry: entrypoint.to_string(),
+            description: String::new(),
+            format: format.to_string(),
+            args: format
+                .chars()
+                .enumerate()
+                .map(|(index, typ)| DescriptorArgument {
+                    typ: type_name(typ),
+                    name: format!("arg{}", index),
+                    description: String::new(),
+                    default: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Descriptor with the arguments, help and entrypoint of a spec, falling
+    /// back to `name` and `entrypoint`
+    pub fn from_spec(name: &str, entrypoint: &str, spec: &ArgSpec) -> Self {
+        let mut descriptor =
+            Self::new(spec.name.as_deref().unwrap_or(name), spec.entrypoint.as_deref().unwrap_or(entrypoint), &spec.format());
+        descriptor.description = spec.description.clone();
+        descriptor.args = spec
+            .arguments
+            .iter()
+            .map(|argument| DescriptorArgument {
+                typ: type_name(argument.typ),
+                name: argument.name.clone(),
+                description: argument.description.clone(),
+                default: argument.default.clone(),
+            })
+            .collect();
+        descriptor
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut json = serde_json::to_string_pretty(self).unwrap_or_default();
+        json.push('\n');
+        json
+    }
+}
+
+/// Spelled-out name of a `bof_pack()` format character
+pub fn type_name(typ: char) -> &'static str {
+    match typ {
+        'i' => "int",
+        's' => "short",
+        'z' => "string",
+        'Z' => "wstring",
+        _ => "binary",
+    }
+}
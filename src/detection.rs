@@ -0,0 +1,257 @@
+//! Detection engineering summaries, for defenders writing signatures
+//!
+//! Where the OPSEC lint tells an operator what gives a BOF away, this
+//! collects the same traces for the other side: the strings worth matching
+//! on, the API combinations behind each capability, section names and data
+//! that's likely encrypted or compressed, each rated by how well a signature
+//! built on it would hold up. [`DetectionSummary::to_markdown`] formats it
+//! for pasting into a ticket.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use crate::sections::HIGH_ENTROPY;
+use crate::{AnalysisReport, CapabilityUse, Encoding, Identification, TechniqueUse};
+
+/// Shortest string listed
+pub const MIN_STRING_LEN: usize = 6;
+/// At most this many strings are listed, longest first
+pub const MAX_STRINGS: usize = 20;
+
+/// Section names every compiler emits, before any `$` grouping suffix
+const STANDARD_SECTIONS: &[&str] = &[
+    ".text", ".data", ".rdata", ".rodata", ".bss", ".pdata", ".xdata", ".drectve", ".CRT", ".tls", ".idata", ".gfids",
+    ".giats", ".gljmp", ".00cfg", ".eh_frame", ".comment", ".llvm_addrsig", ".ctors", ".dtors", ".voltbl", ".chks64",
+    ".sxdata", ".edata",
+];
+
+/// How well a signature on an artifact would hold up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Strength {
+    /// Common elsewhere, or easily changed; only useful combined with others
+    Weak,
+    /// Narrows things down, but other tools share it
+    Moderate,
+    /// Rarely seen outside this BOF or its family
+    Strong,
+}
+
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Strength::Weak => "weak",
+            Strength::Moderate => "moderate",
+            Strength::Strong => "strong",
+        })
+    }
+}
+
+/// A string worth matching on
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringArtifact {
+    pub value: String,
+    pub section: String,
+    /// `ascii` or `wide`, as in a YARA string modifier
+    pub encoding: String,
+    pub strength: Strength,
+}
+
+/// A section, and whether its name or contents stand out
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionArtifact {
+    pub name: String,
+    pub size: usize,
+    /// Bits per byte
+    pub entropy: f64,
+    /// Whether compilers don't emit a section by this name on their own
+    pub unusual_name: bool,
+    /// Whether it's data random enough to be encrypted or compressed
+    pub high_entropy: bool,
+}
+
+/// What a defender can write signatures for in one object
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DetectionSummary {
+    pub sha256: String,
+    pub imphash: String,
+    pub arch: Option<String>,
+    pub toolchain: String,
+    pub identifications: Vec<Identification>,
+    pub strings: Vec<StringArtifact>,
+    /// Capabilities given by more than one import, which is what behavioral
+    /// rules key on
+    pub api_combinations: Vec<CapabilityUse>,
+    pub techniques: Vec<TechniqueUse>,
+    pub sections: Vec<SectionArtifact>,
+    /// Absolute build paths, if the OPSEC lint ran
+    pub build_paths: Vec<String>,
+}
+
+impl DetectionSummary {
+    /// Summarize a report; strings are listed only if it collected them
+    pub fn new(report: &AnalysisReport) -> Self {
+        // runs of printable bytes in encrypted data aren't strings anyone wrote
+        let random: BTreeSet<&str> =
+            report.sections.iter().filter(|section| section.is_high_entropy()).map(|section| section.name.as_str()).collect();
+        let mut seen = BTreeSet::new();
+        let mut strings: Vec<StringArtifact> = report
+            .strings
+            .iter()
+            .filter(|string| !random.contains(string.section.as_str()))
+            .filter(|string| string.value.trim().chars().count() >= MIN_STRING_LEN)
+            .filter(|string| seen.insert(string.value.clone()))
+            .map(|string| StringArtifact {
+                value: string.value.clone(),
+                section: string.section.clone(),
+                encoding: match string.encoding {
+                    Encoding::Ascii => "ascii",
+                    Encoding::Utf16Le => "wide",
+                }
+                .to_string(),
+                strength: string_strength(&string.value),
+            })
+            .collect();
+        strings.sort_by(|a, b| b.strength.cmp(&a.strength).then(b.value.len().cmp(&a.value.len())));
+        strings.truncate(MAX_STRINGS);
+
+        let sections = report
+            .sections
+            .iter()
+            .map(|section| SectionArtifact {
+                name: section.name.clone(),
+                size: section.size,
+                entropy: section.entropy(),
+                unusual_name: !is_standard_section(&section.name),
+                high_entropy: section.is_high_entropy(),
+            })
+            .collect();
+
+        Self {
+            sha256: report.hashes.sha256.clone(),
+            imphash: report.hashes.imphash.clone(),
+            arch: report.arch.map(|arch| arch.name().to_string()),
+            toolchain: report.provenance.to_string(),
+            identifications: report.identifications.clone(),
+            strings,
+            api_combinations: report.capabilities.iter().filter(|used| used.imports.len() > 1).cloned().collect(),
+            techniques: report.techniques.clone(),
+            sections,
+            build_paths: report.path_leaks.iter().map(|leak| leak.path.clone()).collect(),
+        }
+    }
+
+    /// Sections with data random enough to be encrypted or compressed
+    pub fn entropy_anomalies(&self) -> impl Iterator<Item = &SectionArtifact> {
+        self.sections.iter().filter(|section| section.high_entropy)
+    }
+
+    /// How well the best artifact would hold up as a signature, or `None`
+    /// if there's nothing besides the hash to match
+    pub fn confidence(&self) -> Option<Strength> {
+        let strings = self.strings.iter().map(|string| string.strength);
+        let sections = self.sections.iter().filter(|section| section.unusual_name).map(|_| Strength::Strong);
+        let paths = self.build_paths.iter().map(|_| Strength::Strong);
+        let apis = self.api_combinations.iter().map(|_| Strength::Moderate);
+        let entropy = self.entropy_anomalies().map(|_| Strength::Weak);
+        strings.chain(sections).chain(paths).chain(apis).chain(entropy).max()
+    }
+
+    /// Markdown for a detection engineering ticket, headed with `title`
+    pub fn to_markdown(&self, title: &str) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "## Detection summary: {}", title);
+        let _ = writeln!(out);
+        let _ = writeln!(out, "| | |");
+        let _ = writeln!(out, "|---|---|");
+        let _ = writeln!(out, "| SHA-256 | `{}` |", self.sha256);
+        let _ = writeln!(out, "| imphash | `{}` |", self.imphash);
+        let _ = writeln!(out, "| Architecture | {} |", self.arch.as_deref().unwrap_or("unknown"));
+        let _ = writeln!(out, "| Toolchain | {} |", self.toolchain);
+        let confidence = self.confidence().map_or_else(|| "hash only".to_string(), |strength| strength.to_string());
+        let _ = writeln!(out, "| Signature confidence | {} |", confidence);
+
+        let section = |out: &mut String, heading: &str| {
+            let _ = writeln!(out);
+            let _ = writeln!(out, "### {}", heading);
+            let _ = writeln!(out);
+        };
+        if !self.identifications.is_empty() {
+            section(&mut out, "Identification");
+            for identification in &self.identifications {
+                let _ = writeln!(out, "- {}", identification);
+            }
+        }
+        if !self.strings.is_empty() {
+            section(&mut out, "Unique strings");
+            let _ = writeln!(out, "| Strength | Section | Encoding | String |");
+            let _ = writeln!(out, "|---|---|---|---|");
+            for string in &self.strings {
+                let value = string.value.replace('|', "\\|").replace('`', "'");
+                let _ = writeln!(out, "| {} | {} | {} | `{}` |", string.strength, string.section, string.encoding, value);
+            }
+        }
+        if !self.api_combinations.is_empty() {
+            section(&mut out, "API combinations");
+            for used in &self.api_combinations {
+                let _ = writeln!(out, "- {}: {}", used.capability, used.imports.join(" + "));
+            }
+            for technique in &self.techniques {
+                let _ = writeln!(out, "- ATT&CK {}", technique);
+            }
+        }
+        if !self.sections.is_empty() {
+            section(&mut out, "Sections");
+            let _ = writeln!(out, "| Section | Size | Entropy | Note |");
+            let _ = writeln!(out, "|---|---|---|---|");
+            for artifact in &self.sections {
+                let note = match (artifact.unusual_name, artifact.high_entropy) {
+                    (true, true) => "unusual name, high entropy",
+                    (true, false) => "unusual name",
+                    (false, true) => "high entropy",
+                    (false, false) => "",
+                };
+                let _ = writeln!(out, "| `{}` | {} | {:.2} | {} |", artifact.name, artifact.size, artifact.entropy, note);
+            }
+        }
+        let anomalies: Vec<&SectionArtifact> = self.entropy_anomalies().collect();
+        if !anomalies.is_empty() {
+            section(&mut out, "Entropy anomalies");
+            for artifact in anomalies {
+                let _ = writeln!(
+                    out,
+                    "- `{}`: {:.2} bits per byte over {} bytes (above {}), likely an encrypted or compressed payload",
+                    artifact.name, artifact.entropy, artifact.size, HIGH_ENTROPY
+                );
+            }
+        }
+        if !self.build_paths.is_empty() {
+            section(&mut out, "Build paths");
+            for path in &self.build_paths {
+                let _ = writeln!(out, "- `{}`", path);
+            }
+        }
+        out
+    }
+}
+
+/// Longer strings and ones with format specifiers or paths are less likely
+/// to turn up in unrelated binaries
+fn string_strength(value: &str) -> Strength {
+    let len = value.trim().chars().count();
+    if len >= 16 || (len >= 8 && (value.contains('%') || value.contains('\\'))) {
+        Strength::Strong
+    } else if len >= 8 {
+        Strength::Moderate
+    } else {
+        Strength::Weak
+    }
+}
+
+fn is_standard_section(name: &str) -> bool {
+    let base = name.split('$').next().unwrap_or(name);
+    STANDARD_SECTIONS.contains(&base) || base.starts_with(".debug")
+}
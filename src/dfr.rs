@@ -0,0 +1,181 @@
+//! Dynamic function resolution (`MODULE$Function`) linting
+//!
+//! Beacon resolves a DFR import by loading `MODULE` and looking up
+//! `Function` in it. Mistakes in the symbol name only show up at runtime, as
+//! a failed lookup or, for x86 calling convention mismatches, a corrupted
+//! stack.
+//! https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics/beacon-object-files_dynamic-func-resolution.htm
+
+use std::fmt;
+
+use crate::exports::ExportDatabase;
+use crate::{ApiProfile, Arch, Severity, WIN32_MODULES};
+
+/// Libraries whose exports use the cdecl calling convention
+static CDECL_MODULES: &[&str] = &["MSVCRT"];
+
+/// What's wrong with a DFR import
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DfrProblem {
+    /// More than one `$`, or an empty module or function name
+    Malformed,
+    /// The module isn't recognized, but is close to one that is
+    MisspelledModule,
+    /// The module is recognized, but isn't written in upper case
+    ModuleCase,
+    /// An x86 import of a stdcall library without an `@N` decoration,
+    /// meaning it was declared without `WINAPI`
+    MissingDecoration,
+    /// An `@N` decoration on an architecture that doesn't use stdcall
+    UnexpectedDecoration,
+    /// The module doesn't export the function, but exports one close to it
+    MisspelledFunction,
+    /// The function isn't in the export database for the module
+    UnknownFunction,
+}
+
+/// A problem with a DFR import, and the symbol name to use instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DfrIssue {
+    /// Import name, without the import prefix
+    pub import: String,
+    pub problem: DfrProblem,
+    /// Corrected import name, if one can be worked out
+    pub suggestion: Option<String>,
+}
+
+impl DfrIssue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            DfrProblem::Malformed | DfrProblem::MisspelledModule | DfrProblem::MisspelledFunction => Severity::Error,
+            DfrProblem::ModuleCase
+            | DfrProblem::MissingDecoration
+            | DfrProblem::UnexpectedDecoration
+            | DfrProblem::UnknownFunction => Severity::Warning,
+        }
+    }
+}
+
+impl DfrIssue {
+    /// What usually causes a decoration problem
+    pub fn fix(&self) -> Option<&'static str> {
+        match self.problem {
+            DfrProblem::MissingDecoration => Some(
+                "MinGW and MSVC only add @N to __stdcall functions, and undecorated declarations default to cdecl; \
+                 declare the function WINAPI rather than relying on -mrtd or /Gz, which would also change Beacon API calls",
+            ),
+            DfrProblem::UnexpectedDecoration => {
+                Some("x64 has one calling convention, so the @N was most likely copied from an x86 build or header")
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for DfrIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problem {
+            DfrProblem::Malformed => write!(f, "malformed dynamic function resolution import: {}", self.import)?,
+            DfrProblem::MisspelledModule => write!(f, "misspelled win32 library: {}", self.import)?,
+            DfrProblem::ModuleCase => write!(f, "win32 library should be upper case: {}", self.import)?,
+            DfrProblem::MissingDecoration => write!(
+                f,
+                "x86 import without stdcall decoration, declare it WINAPI: {}",
+                self.import
+            )?,
+            DfrProblem::UnexpectedDecoration => write!(f, "stdcall decoration on a non-x86 import: {}", self.import)?,
+            DfrProblem::MisspelledFunction => write!(f, "misspelled win32 function: {}", self.import)?,
+            DfrProblem::UnknownFunction => write!(f, "function not found in export list: {}", self.import)?,
+        }
+        match &self.suggestion {
+            Some(suggestion) => write!(f, " (did you mean {}?)", suggestion),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Check a DFR import name (without the import prefix) for mistakes
+///
+/// Names without a `$` aren't DFR imports and are never reported.
+pub fn lint(name: &str, arch: Arch, profile: &dyn ApiProfile, exports: &ExportDatabase) -> Vec<DfrIssue> {
+    let issue = |problem, suggestion| DfrIssue { import: name.to_string(), problem, suggestion };
+    let parts: Vec<&str> = name.split('$').collect();
+    let (module, function) = match parts[..] {
+        [_] => return Vec::new(),
+        [module, function] if !module.is_empty() && !function.is_empty() => (module, function),
+        _ => return vec![issue(DfrProblem::Malformed, None)],
+    };
+    let (function, decoration) = match function.split_once('@') {
+        Some((function, decoration)) => (function, Some(decoration)),
+        None => (function, None),
+    };
+    let mut issues = Vec::new();
+
+    let upper = module.to_ascii_uppercase();
+    let module = if profile.is_known_module(module) {
+        module.to_string()
+    } else if profile.is_known_module(&upper) {
+        issues.push(issue(DfrProblem::ModuleCase, Some(format!("{}${}", upper, function_part(name)))));
+        upper
+    } else {
+        if let Some(closest) = closest_module(&upper, profile) {
+            issues.push(issue(DfrProblem::MisspelledModule, Some(format!("{}${}", closest, function_part(name)))));
+        }
+        return issues;
+    };
+
+    match (arch, decoration) {
+        (Arch::X86, None) if !CDECL_MODULES.contains(&module.as_str()) => {
+            issues.push(issue(DfrProblem::MissingDecoration, None))
+        }
+        (Arch::X64 | Arch::Arm64, Some(_)) => {
+            issues.push(issue(DfrProblem::UnexpectedDecoration, Some(format!("{}${}", module, function))))
+        }
+        _ => {}
+    }
+
+    if exports.contains(&module, function) == Some(false) {
+        let decoration = decoration.map(|decoration| format!("@{}", decoration)).unwrap_or_default();
+        match exports.closest(&module, function) {
+            Some(closest) => issues.push(issue(
+                DfrProblem::MisspelledFunction,
+                Some(format!("{}${}{}", module, closest, decoration)),
+            )),
+            None => issues.push(issue(DfrProblem::UnknownFunction, None)),
+        }
+    }
+    issues
+}
+
+/// Everything after the first `$`
+fn function_part(name: &str) -> &str {
+    name.split_once('$').map_or("", |(_, function)| function)
+}
+
+/// The known module nearest to `module`, if any is within a couple of edits
+fn closest_module(module: &str, profile: &dyn ApiProfile) -> Option<&'static str> {
+    WIN32_MODULES
+        .iter()
+        .filter(|known| profile.is_known_module(known))
+        .map(|known| (edit_distance(module, known), *known))
+        .filter(|(distance, _)| *distance <= 2)
+        .min()
+        .map(|(_, known)| known)
+}
+
+/// Levenshtein distance between two ASCII strings
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.as_bytes();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, x) in a.bytes().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != *y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -0,0 +1,175 @@
+//! Findings as compiler-style diagnostics
+//!
+//! Editors already know how to show `file:line:column: warning: message`
+//! lines from GCC. An object has no line information without debug info,
+//! so each finding is placed at the first line of the source file that
+//! mentions the symbol it's about, such as the misspelled import or the
+//! function that leaks a Format buffer. Findings about the object as a
+//! whole, and symbols the source doesn't mention, go on line 1.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{AnalysisReport, ExceptionIssue, Finding, InitializerIssue, FixIt, Severity, SpecIssue, TlsSymbol};
+
+/// A place in the source or the object
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: PathBuf,
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column, in characters
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+/// A finding at a location, as GCC prints warnings and errors
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub location: Location,
+    pub severity: Severity,
+    pub message: String,
+    pub rule: String,
+    pub fix: Option<FixIt>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}: {} [{}]", self.location, self.severity, self.message, self.rule)?;
+        match &self.fix {
+            Some(fix) => write!(f, "\n{}: note: fix: {}", self.location, fix),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The symbol `finding` is about, spelled as in the object
+pub fn symbol(report: &AnalysisReport, finding: &Finding) -> Option<String> {
+    Some(match finding {
+        Finding::MissingEntrypoint(expected) => match report.fix(finding) {
+            Some(FixIt::RenameSymbol { from, .. }) => from,
+            _ => expected.clone(),
+        },
+        Finding::UnsupportedApi(import, _)
+        | Finding::UnrecognizedModule(import)
+        | Finding::UnknownImport(import)
+        | Finding::UnreferencedImport(import) => import.name.clone(),
+        Finding::Dfr(issue) => issue.import.clone(),
+        Finding::Decoration(issue) => issue.symbol.clone(),
+        Finding::Entrypoint(issue) => issue.symbol.clone(),
+        Finding::DuplicateSymbol(duplicate) => duplicate.symbol.clone(),
+        Finding::Comdat(issue) => issue.comdat.symbol.clone()?,
+        Finding::Checksum(issue) => issue.section.clone(),
+        Finding::WeakExternal(issue) => issue.weak.symbol.clone(),
+        Finding::DeadCode(dead) => dead.functions.first()?.name.clone(),
+        Finding::SymbolName(issue) => issue.symbol.clone(),
+        Finding::Opsec(issue) => issue.import.clone(),
+        Finding::Spec(SpecIssue::Unread { function, .. } | SpecIssue::Unspecified { function }) => {
+            function.to_string()
+        }
+        Finding::Format(issue) => issue.function.clone(),
+        Finding::Injection(issue) => issue.function.clone(),
+        Finding::UnresolvedExternal(external) => external.function.clone(),
+        Finding::Exceptions(ExceptionIssue::Handler { symbol, .. }) => symbol.clone(),
+        Finding::Initializer(InitializerIssue::AtExit { symbol, .. }) => symbol.clone(),
+        Finding::ThreadLocal(issue) => match (issue.kind, issue.functions.first()) {
+            (TlsSymbol::Index | TlsSymbol::Emulated, Some(function)) => function.clone(),
+            _ => issue.symbol.clone(),
+        },
+        _ => return None,
+    })
+}
+
+/// Ways `symbol` could be spelled in C: as is, without the import prefix,
+/// without stdcall decoration, and without the x86 leading underscore
+fn spellings(symbol: &str) -> Vec<&str> {
+    let mut spellings = vec![symbol];
+    let mut name = symbol;
+    for prefix in ["__imp__", "__imp_"] {
+        if let Some(rest) = name.strip_prefix(prefix) {
+            name = rest;
+            break;
+        }
+    }
+    name = name.split('@').next().unwrap_or(name);
+    spellings.push(name);
+    spellings.push(name.trim_start_matches('_'));
+    spellings.dedup();
+    spellings
+}
+
+/// First line and column where `identifier` appears as a whole word in
+/// `source`
+pub fn find_identifier(source: &str, identifier: &str) -> Option<(usize, usize)> {
+    if identifier.is_empty() {
+        return None;
+    }
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$';
+    for (index, line) in source.lines().enumerate() {
+        for (start, _) in line.match_indices(identifier) {
+            // pointer declarations spell the import prefix out
+            let prefix = &line[..start];
+            let prefix = prefix.strip_suffix("__imp__").or_else(|| prefix.strip_suffix("__imp_")).unwrap_or(prefix);
+            let before = prefix.chars().next_back();
+            let after = line[start + identifier.len()..].chars().next();
+            if !before.is_some_and(is_word) && !after.is_some_and(is_word) {
+                return Some((index + 1, line[..start].chars().count() + 1));
+            }
+        }
+    }
+    None
+}
+
+/// The source file `object` was compiled from: the `.file` name recorded in
+/// the object, looked up next to it, or its name with `.c` in place of
+/// `.o` and the architecture
+pub fn source_file(object: &Path, report: &AnalysisReport) -> Option<PathBuf> {
+    let dir = object.parent().unwrap_or(Path::new(""));
+    let mut candidates = Vec::new();
+    if let Some(file) = &report.provenance.source_file {
+        let file = Path::new(file);
+        candidates.push(file.to_path_buf());
+        if let Some(name) = file.file_name() {
+            candidates.push(dir.join(name));
+        }
+    }
+    if let Some(stem) = object.file_stem().and_then(|stem| stem.to_str()) {
+        let stem = stem.strip_suffix(".x64").or_else(|| stem.strip_suffix(".x86")).unwrap_or(stem);
+        candidates.push(dir.join(format!("{}.c", stem)));
+    }
+    candidates.into_iter().find(|candidate| candidate.is_file())
+}
+
+/// The report's findings placed in `source`, or at the top of `object`
+/// without one
+pub fn diagnostics(object: &Path, source: Option<&Path>, report: &AnalysisReport) -> Vec<Diagnostic> {
+    let text = source.and_then(|source| fs::read_to_string(source).ok());
+    report
+        .findings()
+        .iter()
+        .filter_map(|finding| {
+            let severity = report.severity(finding)?;
+            let position = text.as_deref().zip(symbol(report, finding)).and_then(|(text, symbol)| {
+                spellings(&symbol).into_iter().find_map(|spelling| find_identifier(text, spelling))
+            });
+            let file = match (source, text.is_some()) {
+                (Some(source), true) => source.to_path_buf(),
+                _ => object.to_path_buf(),
+            };
+            let (line, column) = position.unwrap_or((1, 1));
+            Some(Diagnostic {
+                location: Location { file, line, column },
+                severity,
+                message: finding.to_string(),
+                rule: finding.rule().to_string(),
+                fix: report.fix(finding),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,157 @@
+//! Comparing two builds of a BOF
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+
+use goblin::pe::section_table::{SectionTable, IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use goblin::pe::symbol::IMAGE_SYM_CLASS_FILE;
+
+use crate::{disasm, section_name, Bof};
+
+/// Differing bytes closer together than this are reported as one range
+const MERGE_GAP: usize = 8;
+
+/// Size of a section in each build; `None` if it's missing from that build
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionDelta {
+    pub name: String,
+    pub old: Option<usize>,
+    pub new: Option<usize>,
+}
+
+impl SectionDelta {
+    /// Change in size in bytes, counting a missing section as empty
+    pub fn delta(&self) -> i64 {
+        self.new.unwrap_or(0) as i64 - self.old.unwrap_or(0) as i64
+    }
+}
+
+/// Byte ranges that differ in a code section present in both builds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeDiff {
+    pub section: String,
+    /// Offsets compared position by position, so an insertion shows up as
+    /// everything after it changing
+    pub ranges: Vec<Range<usize>>,
+    pub old_size: usize,
+    pub new_size: usize,
+}
+
+impl CodeDiff {
+    /// Number of bytes in the differing ranges, counting a size change as
+    /// changed bytes
+    pub fn changed_bytes(&self) -> usize {
+        self.ranges.iter().map(|range| range.len()).sum()
+    }
+}
+
+/// What changed between two builds
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BofDiff {
+    pub imports_added: Vec<String>,
+    pub imports_removed: Vec<String>,
+    /// Sections whose size changed, or that only one build has
+    pub sections: Vec<SectionDelta>,
+    pub symbols_added: Vec<String>,
+    pub symbols_removed: Vec<String>,
+    /// Code sections whose contents changed
+    pub code: Vec<CodeDiff>,
+}
+
+impl BofDiff {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Compare `old` against `new`
+pub fn diff(old: &Bof, new: &Bof) -> BofDiff {
+    let (old_imports, new_imports) = (imports(old), imports(new));
+    let (old_symbols, new_symbols) = (symbols(old), symbols(new));
+
+    let (old_sections, new_sections) = (section_sizes(old), section_sizes(new));
+    let names: BTreeSet<&String> = old_sections.iter().chain(&new_sections).map(|(name, _)| name).collect();
+    let find = |sections: &[(String, usize)], name: &str| {
+        sections.iter().find(|(other, _)| other == name).map(|(_, size)| *size)
+    };
+    let sections = names
+        .into_iter()
+        .map(|name| SectionDelta {
+            name: name.clone(),
+            old: find(&old_sections, name),
+            new: find(&new_sections, name),
+        })
+        .filter(|delta| delta.old != delta.new)
+        .collect();
+
+    let mut code = Vec::new();
+    for section in old.coff.sections.iter().filter(|section| is_code(section.characteristics)) {
+        let name = section_name(section);
+        let other = match new.coff.sections.iter().find(|other| section_name(other) == name) {
+            Some(other) => other,
+            None => continue,
+        };
+        let (before, after) = (old.section_contents(section), new.section_contents(other));
+        let ranges = differing_ranges(before, after);
+        if !ranges.is_empty() {
+            code.push(CodeDiff { section: name, ranges, old_size: before.len(), new_size: after.len() });
+        }
+    }
+
+    BofDiff {
+        imports_added: new_imports.difference(&old_imports).cloned().collect(),
+        imports_removed: old_imports.difference(&new_imports).cloned().collect(),
+        sections,
+        symbols_added: new_symbols.difference(&old_symbols).cloned().collect(),
+        symbols_removed: old_symbols.difference(&new_symbols).cloned().collect(),
+        code,
+    }
+}
+
+fn imports(bof: &Bof) -> BTreeSet<String> {
+    bof.imports()
+        .map(|symbol| bof.symbol_name(&symbol))
+        .map(|name| match bof.import_prefix().and_then(|prefix| name.strip_prefix(prefix)) {
+            Some(stripped) => stripped.to_string(),
+            None => name,
+        })
+        .collect()
+}
+
+/// Names of defined and referenced symbols, leaving out section and file
+/// records, which change with the layout rather than the code
+fn symbols(bof: &Bof) -> BTreeSet<String> {
+    bof.coff.symbols.iter()
+        .map(|(_, _, symbol)| symbol)
+        .filter(|symbol| symbol.storage_class != IMAGE_SYM_CLASS_FILE)
+        .filter(|symbol| !disasm::is_section_definition(bof, symbol))
+        .map(|symbol| bof.symbol_name(&symbol))
+        .filter(|name| bof.import_prefix().is_none_or(|prefix| !name.starts_with(prefix)))
+        .collect()
+}
+
+fn section_sizes(bof: &Bof) -> Vec<(String, usize)> {
+    bof.coff.sections.iter()
+        .map(|section: &SectionTable| (section_name(section), section.size_of_raw_data.max(section.virtual_size) as usize))
+        .collect()
+}
+
+fn is_code(characteristics: u32) -> bool {
+    characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0
+}
+
+/// Ranges of positions where `old` and `new` differ, including the tail of
+/// the longer one
+fn differing_ranges(old: &[u8], new: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for offset in 0..old.len().max(new.len()) {
+        if old.get(offset) == new.get(offset) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(last) if offset - last.end < MERGE_GAP => last.end = offset + 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+    ranges
+}
@@ -0,0 +1,225 @@
+//! Disassembly of a single function
+//!
+//! Operands the loader patches are shown as the symbol the relocation points
+//! at (`call qword ptr [__imp_BeaconPrintf]`), since the bytes in the object
+//! are only placeholders. Functions run from their symbol to the next symbol
+//! in the same section.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use goblin::pe::symbol::{Symbol, IMAGE_SYM_CLASS_STATIC, IMAGE_SYM_DTYPE_FUNCTION};
+use iced_x86::{Decoder, DecoderOptions, Formatter, Instruction as Decoded, IntelFormatter, OpKind, SymbolResolver, SymbolResult};
+
+use crate::{relocations, section_name, Arch, Bof};
+
+/// A decoded instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Instruction {
+    /// Offset from the start of the section
+    pub offset: u32,
+    pub bytes: Vec<u8>,
+    /// Intel syntax, with relocated operands replaced by symbol names
+    pub text: String,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes: Vec<String> = self.bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+        write!(f, "{:6x}:  {:<30} {}", self.offset, bytes.join(" "), self.text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisasmError {
+    /// No symbol has this name
+    UnknownSymbol(String),
+    /// The symbol isn't defined in a section of this object
+    Undefined(String),
+    /// Only x86 and x64 code can be disassembled
+    UnsupportedArch,
+}
+
+impl fmt::Display for DisasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownSymbol(name) => write!(f, "no symbol named {}", name),
+            Self::Undefined(name) => write!(f, "{} isn't defined in this object", name),
+            Self::UnsupportedArch => write!(f, "only x86 and x64 objects can be disassembled"),
+        }
+    }
+}
+
+impl error::Error for DisasmError {}
+
+/// Which part of an instruction a relocation patches
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Field {
+    Displacement,
+    /// Immediates and branch targets
+    Immediate,
+}
+
+/// Names relocated operands after their symbol, and branch targets in the
+/// same section after the function they land on
+struct Symbols {
+    relocated: HashMap<(u64, Field), String>,
+    functions: HashMap<u64, String>,
+}
+
+impl SymbolResolver for Symbols {
+    fn symbol(
+        &mut self,
+        instruction: &Decoded,
+        _operand: u32,
+        instruction_operand: Option<u32>,
+        address: u64,
+        _address_size: u32,
+    ) -> Option<SymbolResult<'_>> {
+        let kind = instruction.op_kind(instruction_operand?);
+        let field = match kind {
+            OpKind::Memory => Field::Displacement,
+            _ => Field::Immediate,
+        };
+        let name = match self.relocated.get(&(instruction.ip(), field)) {
+            Some(name) => name,
+            None if matches!(kind, OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64) => {
+                self.functions.get(&address)?
+            }
+            None => return None,
+        };
+        Some(SymbolResult::with_str(address, name))
+    }
+}
+
+/// Disassemble the function `name`
+pub fn disassemble(bof: &Bof, name: &str) -> Result<Vec<Instruction>, DisasmError> {
+    let (arch, bitness) = match bof.arch() {
+        Some(Arch::X64) => (Arch::X64, 64),
+        Some(Arch::X86) => (Arch::X86, 32),
+        _ => return Err(DisasmError::UnsupportedArch),
+    };
+    let symbol = bof
+        .coff
+        .symbols
+        .iter()
+        .map(|(_, _, symbol)| symbol)
+        .find(|symbol| bof.symbol_name(symbol) == name)
+        .ok_or_else(|| DisasmError::UnknownSymbol(name.to_string()))?;
+    let section = match symbol.section_number {
+        number if number > 0 => bof.coff.sections.get(number as usize - 1),
+        _ => None,
+    }
+    .ok_or_else(|| DisasmError::Undefined(name.to_string()))?;
+    let data = bof.section_contents(section);
+    let (start, end) = extent(bof, &symbol, data.len() as u32);
+    let mut functions = HashMap::new();
+    for (_, _, other) in bof.coff.symbols.iter().filter(|(_, _, other)| other.section_number == symbol.section_number) {
+        if !is_section_definition(bof, &other) {
+            functions.insert(other.value as u64, bof.symbol_name(&other));
+        }
+    }
+
+    let mut relocated = Vec::new();
+    if let Ok(entries) = section.relocations(bof.buffer) {
+        for entry in entries.filter(|entry| (start..end).contains(&entry.virtual_address)) {
+            let target = match bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                Some((_, target)) => bof.symbol_name(&target),
+                None => continue,
+            };
+            let target = match relocations::addend(arch, entry.typ, data, entry.virtual_address as usize) {
+                Some(addend) if addend > 0 => format!("{}+{:#x}", target, addend),
+                _ => target,
+            };
+            relocated.push((entry.virtual_address as u64, target));
+        }
+    }
+
+    let code = &data[start as usize..end as usize];
+    let mut decoder = Decoder::with_ip(bitness, code, start as u64, DecoderOptions::NONE);
+    let mut instructions = Vec::new();
+    let mut fields = HashMap::new();
+    let mut decoded = Vec::new();
+    while decoder.can_decode() {
+        let instruction = decoder.decode();
+        let offsets = decoder.get_constant_offsets(&instruction);
+        let ip = instruction.ip();
+        for (offset, target) in relocated.iter().filter(|(offset, _)| (ip..ip + instruction.len() as u64).contains(offset)) {
+            let field = match (offset - ip) as usize {
+                position if offsets.has_displacement() && position == offsets.displacement_offset() => Field::Displacement,
+                position if offsets.has_immediate() && position == offsets.immediate_offset() => Field::Immediate,
+                _ => continue,
+            };
+            fields.insert((ip, field), target.clone());
+        }
+        decoded.push(instruction);
+    }
+
+    let mut formatter = IntelFormatter::with_options(Some(Box::new(Symbols { relocated: fields, functions })), None);
+    for instruction in &decoded {
+        let mut text = String::new();
+        formatter.format(instruction, &mut text);
+        let position = (instruction.ip() - start as u64) as usize;
+        instructions.push(Instruction {
+            offset: instruction.ip() as u32,
+            bytes: code[position..position + instruction.len()].to_vec(),
+            text,
+        });
+    }
+    Ok(instructions)
+}
+
+/// Start and end offset of the function at `symbol`, which runs until the
+/// next symbol in its section or the end of the section's `size` bytes
+pub(crate) fn extent(bof: &Bof, symbol: &Symbol, size: u32) -> (u32, u32) {
+    let end = bof
+        .coff
+        .symbols
+        .iter()
+        .map(|(_, _, other)| other)
+        .filter(|other| other.section_number == symbol.section_number && !is_section_definition(bof, other))
+        .map(|other| other.value)
+        .filter(|value| *value > symbol.value)
+        .fold(size, u32::min);
+    (symbol.value.min(end), end)
+}
+
+/// Name of the function in section `number` whose code covers `offset`
+pub(crate) fn function_at(bof: &Bof, number: i16, offset: u32) -> Option<String> {
+    let section = bof.coff.sections.get((number as usize).checked_sub(1)?)?;
+    let size = bof.section_contents(section).len() as u32;
+    bof.coff
+        .symbols
+        .iter()
+        .map(|(_, _, symbol)| symbol)
+        .filter(|symbol| symbol.section_number == number && !is_section_definition(bof, symbol))
+        .find(|symbol| {
+            let (start, end) = extent(bof, symbol, size);
+            (start..end).contains(&offset)
+        })
+        .map(|symbol| bof.symbol_name(&symbol))
+}
+
+/// Section definition records, which sit at the start of their section
+/// without marking a function
+pub(crate) fn is_section_definition(bof: &Bof, symbol: &Symbol) -> bool {
+    symbol.section_number > 0
+        && defines_section(symbol.storage_class, symbol.value, symbol.typ, symbol.number_of_aux_symbols as usize, || {
+            bof.coff.sections
+                .get(symbol.section_number as usize - 1)
+                .is_some_and(|section| section_name(section) == bof.symbol_name(symbol))
+        })
+}
+
+/// Whether a symbol with these fields is its section's definition record:
+/// static, at offset 0, with one auxiliary record and named after the
+/// section, which `same_name` checks last
+///
+/// GCC writes a static function at the start of its own section the same
+/// way, with a function definition record instead, so function types are
+/// never section definitions.
+pub(crate) fn defines_section(storage_class: u8, value: u32, typ: u16, aux: usize, same_name: impl FnOnce() -> bool) -> bool {
+    storage_class == IMAGE_SYM_CLASS_STATIC && value == 0 && aux == 1 && typ >> 4 != IMAGE_SYM_DTYPE_FUNCTION && same_name()
+}
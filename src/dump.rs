@@ -0,0 +1,242 @@
+//! Readable dumps of the COFF structures in a BOF
+//!
+//! Roughly what `objdump -x` shows, except that symbols are classified the
+//! way the loader will treat them, relocation types are checked against
+//! what the loader supports, and hex dumps point out the bytes the loader
+//! patches.
+
+use std::fmt::Write;
+
+use goblin::pe::section_table::{
+    SectionTable, IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_CNT_UNINITIALIZED_DATA,
+    IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ, IMAGE_SCN_MEM_WRITE,
+};
+use goblin::pe::symbol::*;
+
+use crate::{is_loaded, merge, relocations, section_name, symbols, ApiProfile, Bof};
+
+/// Bytes per line of a hex dump
+const HEX_WIDTH: usize = 16;
+
+/// The COFF file header
+pub fn file_header(bof: &Bof) -> String {
+    let header = &bof.coff.header;
+    let mut out = String::new();
+    let arch = bof.arch().map_or("unsupported", |arch| arch.name());
+    let _ = writeln!(out, "machine:          0x{:04x} ({})", header.machine, arch);
+    let _ = writeln!(out, "sections:         {}", header.number_of_sections);
+    let _ = writeln!(out, "timestamp:        0x{:08x}", header.time_date_stamp);
+    let _ = writeln!(out, "symbol table:     0x{:x} ({} entries)", header.pointer_to_symbol_table, header.number_of_symbol_table);
+    let _ = writeln!(out, "optional header:  {} bytes", header.size_of_optional_header);
+    let _ = writeln!(out, "characteristics:  0x{:04x}", header.characteristics);
+    out
+}
+
+/// One line per section header, with decoded flags
+pub fn section_headers(bof: &Bof) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>3}  {:<16} {:>8} {:>8} {:>8} {:>6}  FLAGS", "#", "NAME", "SIZE", "RAW", "RELOCS", "ALIGN");
+    for (index, section) in bof.coff.sections.iter().enumerate() {
+        let _ = writeln!(
+            out,
+            "{:>3}  {:<16} {:>8x} {:>8x} {:>8} {:>6}  0x{:08x} {}",
+            index + 1,
+            section_name(section),
+            section.size_of_raw_data.max(section.virtual_size),
+            section.pointer_to_raw_data,
+            section.number_of_relocations,
+            merge::alignment(section.characteristics),
+            section.characteristics,
+            flags(section),
+        );
+    }
+    out
+}
+
+/// Hex dump of a section's raw data, noting the relocations that patch
+/// each line; `None` if there's no section called `name`
+pub fn section_hex(bof: &Bof, name: &str) -> Option<String> {
+    let section = bof.section_table(name)?;
+    let data = bof.section_contents(section);
+    let patched: Vec<(u32, String)> = match section.relocations(bof.buffer) {
+        Ok(entries) => entries.map(|entry| (entry.virtual_address, target_name(bof, entry.symbol_table_index))).collect(),
+        Err(_) => Vec::new(),
+    };
+    let mut out = String::new();
+    if data.is_empty() {
+        let _ = writeln!(out, "no raw data ({} bytes uninitialized)", section.virtual_size.max(section.size_of_raw_data));
+        return Some(out);
+    }
+    for (line, chunk) in data.chunks(HEX_WIDTH).enumerate() {
+        let start = (line * HEX_WIDTH) as u32;
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = chunk.iter().map(|byte| if byte.is_ascii_graphic() || *byte == b' ' { *byte as char } else { '.' }).collect();
+        let notes: Vec<String> = patched
+            .iter()
+            .filter(|(offset, _)| (start..start + chunk.len() as u32).contains(offset))
+            .map(|(offset, target)| format!("+{:x} {}", offset - start, target))
+            .collect();
+        let _ = match notes.is_empty() {
+            true => writeln!(out, "{:08x}  {:<47}  {}", start, hex.join(" "), ascii),
+            false => writeln!(out, "{:08x}  {:<47}  {:<16}  ; {}", start, hex.join(" "), ascii, notes.join(", ")),
+        };
+    }
+    Some(out)
+}
+
+/// Every symbol table entry, classified against `profile`
+pub fn symbol_table(bof: &Bof, profile: &dyn ApiProfile) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>4}  {:>8}  {:<12} {:<10} {:>3}  {:<28} NAME", "#", "VALUE", "SECTION", "CLASS", "AUX", "KIND");
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        let section = match symbol.section_number {
+            IMAGE_SYM_UNDEFINED => "UNDEF".to_string(),
+            IMAGE_SYM_ABSOLUTE => "ABS".to_string(),
+            IMAGE_SYM_DEBUG => "DEBUG".to_string(),
+            number => bof.coff.sections.get(number as usize - 1).map_or_else(|| format!("#{}", number), section_name),
+        };
+        let kind = symbols::classify(bof, &symbol, profile).map_or("", |classified| classified.kind());
+        let _ = writeln!(
+            out,
+            "{:>4}  {:>8x}  {:<12} {:<10} {:>3}  {:<28} {}",
+            index,
+            symbol.value,
+            section,
+            storage_class(symbol.storage_class),
+            symbol.number_of_aux_symbols,
+            kind,
+            bof.symbol_name(&symbol),
+        );
+    }
+    out
+}
+
+/// Bytes of the loaded sections by function and variable, largest first,
+/// with each one's share of the total
+pub fn bloat(bof: &Bof) -> String {
+    let entries = bof.bloat();
+    let total: usize = entries.iter().map(|entry| entry.size).sum();
+    let mut out = String::new();
+    let _ = writeln!(out, "{:>8} {:>6} {:>7}  {:<12} NAME", "SIZE", "SHARE", "RELOCS", "SECTION");
+    for entry in &entries {
+        let share = match total {
+            0 => 0.0,
+            total => entry.size as f64 * 100.0 / total as f64,
+        };
+        let name = entry.symbol.clone().unwrap_or_else(|| format!("[{} unnamed]", entry.section));
+        let _ = writeln!(out, "{:>8} {:>5.1}% {:>7}  {:<12} {}", entry.size, share, entry.relocations, entry.section, name);
+    }
+    let relocations: usize = entries.iter().map(|entry| entry.relocations).sum();
+    let _ = writeln!(out, "{:>8} {:>5.1}% {:>7}  {:<12} total", total, 100.0, relocations, "");
+    out
+}
+
+/// Every string in the string table, by offset
+pub fn string_table(bof: &Bof) -> String {
+    let header = &bof.coff.header;
+    let start = header.pointer_to_symbol_table as usize + header.number_of_symbol_table as usize * COFF_SYMBOL_SIZE;
+    let table = bof.buffer.get(start..).unwrap_or_default();
+    let size = match table.get(..4) {
+        Some(size) => u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize,
+        None => 0,
+    };
+    let mut out = String::new();
+    let _ = writeln!(out, "{} bytes", size);
+    // offsets count from the start of the table, including its size field
+    let mut offset = 4;
+    for string in table.get(4..size.min(table.len())).unwrap_or_default().split(|byte| *byte == 0) {
+        if !string.is_empty() {
+            let _ = writeln!(out, "{:>8x}  {}", offset, String::from_utf8_lossy(string));
+        }
+        offset += string.len() + 1;
+    }
+    out
+}
+
+/// The relocation entries of every section, including ones the loader
+/// doesn't map
+pub fn relocation_table(bof: &Bof) -> String {
+    let mut out = String::new();
+    let arch = match bof.arch() {
+        Some(arch) => arch,
+        None => return out,
+    };
+    for section in bof.coff.sections.iter().filter(|section| section.number_of_relocations > 0) {
+        let data = bof.section_contents(section);
+        let loaded = match is_loaded(section) {
+            true => "",
+            false => " (not loaded)",
+        };
+        let _ = writeln!(out, "{}{}:", section_name(section), loaded);
+        let _ = writeln!(out, "    {:>8}  {:<32} {:>4}  {:>10}  SYMBOL", "OFFSET", "TYPE", "#", "ADDEND");
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            let addend = relocations::addend(arch, entry.typ, data, entry.virtual_address as usize)
+                .map_or("-".to_string(), |addend| format!("{:#x}", addend));
+            let supported = match relocations::is_supported(arch, entry.typ) {
+                true => "",
+                false => "  [unsupported by the loader]",
+            };
+            let _ = writeln!(
+                out,
+                "    {:>8x}  {:<32} {:>4}  {:>10}  {}{}",
+                entry.virtual_address,
+                relocations::type_name(arch, entry.typ),
+                entry.symbol_table_index,
+                addend,
+                target_name(bof, entry.symbol_table_index),
+                supported,
+            );
+        }
+    }
+    out
+}
+
+fn target_name(bof: &Bof, index: u32) -> String {
+    match bof.coff.symbols.get(index as usize) {
+        Some((_, symbol)) => bof.symbol_name(&symbol),
+        None => format!("<bad symbol #{}>", index),
+    }
+}
+
+/// Contents and permissions of a section, e.g. `code r-x`
+fn flags(section: &SectionTable) -> String {
+    let characteristics = section.characteristics;
+    let contents = if characteristics & IMAGE_SCN_CNT_CODE != 0 {
+        "code"
+    } else if characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0 {
+        "bss"
+    } else if characteristics & IMAGE_SCN_CNT_INITIALIZED_DATA != 0 {
+        "data"
+    } else {
+        "-"
+    };
+    let permission = |flag: u32, c: char| if characteristics & flag != 0 { c } else { '-' };
+    let mut flags = format!(
+        "{} {}{}{}",
+        contents,
+        permission(IMAGE_SCN_MEM_READ, 'r'),
+        permission(IMAGE_SCN_MEM_WRITE, 'w'),
+        permission(IMAGE_SCN_MEM_EXECUTE, 'x')
+    );
+    if !is_loaded(section) {
+        flags.push_str(" not loaded");
+    }
+    flags
+}
+
+fn storage_class(class: u8) -> String {
+    let name = match class {
+        IMAGE_SYM_CLASS_END_OF_FUNCTION => "END_FUNC",
+        IMAGE_SYM_CLASS_NULL => "NULL",
+        IMAGE_SYM_CLASS_AUTOMATIC => "AUTOMATIC",
+        IMAGE_SYM_CLASS_EXTERNAL => "EXTERNAL",
+        IMAGE_SYM_CLASS_STATIC => "STATIC",
+        IMAGE_SYM_CLASS_LABEL => "LABEL",
+        IMAGE_SYM_CLASS_FUNCTION => "FUNCTION",
+        IMAGE_SYM_CLASS_FILE => "FILE",
+        IMAGE_SYM_CLASS_SECTION => "SECTION",
+        IMAGE_SYM_CLASS_WEAK_EXTERNAL => "WEAK",
+        other => return format!("0x{:02x}", other),
+    };
+    name.to_string()
+}
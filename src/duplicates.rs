@@ -0,0 +1,152 @@
+//! External symbols defined more than once
+//!
+//! A linker refuses two definitions of an external symbol unless both are
+//! COMDATs, and then keeps only one copy. BOF loaders do neither: every
+//! section is loaded, each relocation binds to whichever copy its symbol
+//! entry names, and the loader's own lookups by name, such as for the
+//! entrypoint, find the first. Objects concatenated by hand, partially
+//! linked with `ld -r`, or pulled from an archive together can carry both
+//! copies, and code ends up calling two versions of a function or writing
+//! two copies of a global.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use goblin::pe::section_table::IMAGE_SCN_LNK_COMDAT;
+use goblin::pe::symbol::IMAGE_SYM_CLASS_EXTERNAL;
+
+use crate::disasm::is_section_definition;
+use crate::{archive, section_name, Bof, Severity};
+
+/// One of the sections defining a duplicated symbol
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Definition {
+    /// Archive member the section is in, when comparing members
+    pub object: Option<String>,
+    pub section: String,
+    /// One-based section number, which tells apart sections of the same name
+    pub number: i16,
+    /// Whether the section is a COMDAT, which a linker would have picked one
+    /// copy of
+    pub comdat: bool,
+}
+
+impl fmt::Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(object) = &self.object {
+            write!(f, "{} ", object)?;
+        }
+        write!(f, "{} #{}", self.section, self.number)
+    }
+}
+
+/// An external symbol with more than one definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DuplicateSymbol {
+    pub symbol: String,
+    /// Sections defining it, in symbol table order
+    pub definitions: Vec<Definition>,
+}
+
+impl DuplicateSymbol {
+    /// Whether every copy is a COMDAT, so the copies are meant to be
+    /// interchangeable and loading all of them only wastes space
+    pub fn is_comdat(&self) -> bool {
+        self.definitions.iter().all(|definition| definition.comdat)
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self.is_comdat() {
+            true => Severity::Warning,
+            false => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for DuplicateSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let definitions: Vec<String> = self.definitions.iter().map(Definition::to_string).collect();
+        let kind = match self.is_comdat() {
+            true => "COMDAT symbol",
+            false => "symbol",
+        };
+        write!(f, "{} defined {} times, loaders keep every copy: {} ({})", kind, definitions.len(), self.symbol, definitions.join(", "))
+    }
+}
+
+/// External definitions in `bof`, by name
+fn definitions(bof: &Bof, object: Option<&str>) -> Vec<(String, Definition)> {
+    bof.coff
+        .symbols
+        .iter()
+        .map(|(_, _, symbol)| symbol)
+        .filter(|symbol| {
+            symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL && symbol.section_number > 0 && !is_section_definition(bof, symbol)
+        })
+        .filter_map(|symbol| {
+            let section = bof.coff.sections.get(symbol.section_number as usize - 1)?;
+            let definition = Definition {
+                object: object.map(str::to_string),
+                section: section_name(section),
+                number: symbol.section_number,
+                comdat: section.characteristics & IMAGE_SCN_LNK_COMDAT != 0,
+            };
+            Some((bof.symbol_name(&symbol), definition))
+        })
+        .collect()
+}
+
+/// The names with more than one of `definitions`
+fn duplicated(definitions: impl IntoIterator<Item = (String, Definition)>) -> Vec<DuplicateSymbol> {
+    let mut order = Vec::new();
+    let mut by_name: BTreeMap<String, Vec<Definition>> = BTreeMap::new();
+    for (name, definition) in definitions {
+        let entry = by_name.entry(name.clone()).or_default();
+        if entry.is_empty() {
+            order.push(name);
+        }
+        entry.push(definition);
+    }
+    order
+        .into_iter()
+        .filter_map(|symbol| {
+            let definitions = by_name.remove(&symbol)?;
+            (definitions.len() > 1).then_some(DuplicateSymbol { symbol, definitions })
+        })
+        .collect()
+}
+
+/// Find external symbols `bof` defines more than once
+pub fn lint(bof: &Bof) -> Vec<DuplicateSymbol> {
+    duplicated(definitions(bof, None))
+}
+
+/// Find external symbols more than one of `members`, the objects of an
+/// archive, defines; symbols defined twice within one member are left to
+/// [`lint`]
+pub fn across(members: &[(&str, &Bof)]) -> Vec<DuplicateSymbol> {
+    let mut all = Vec::new();
+    for (name, bof) in members {
+        let mut seen = Vec::new();
+        for (symbol, definition) in definitions(bof, Some(name)) {
+            if !seen.contains(&symbol) {
+                seen.push(symbol.clone());
+                all.push((symbol, definition));
+            }
+        }
+    }
+    duplicated(all)
+}
+
+/// Find external symbols more than one member of the archive in `buffer`
+/// defines, like [`across`]; members that don't parse are skipped, since
+/// checking them on their own reports why
+pub fn in_archive(buffer: &[u8]) -> Vec<DuplicateSymbol> {
+    let members = archive::members(buffer).unwrap_or_default();
+    let parsed: Vec<(&str, Bof)> =
+        members.iter().filter_map(|member| Some((member.name.as_str(), Bof::parse(member.data).ok()?))).collect();
+    let borrowed: Vec<(&str, &Bof)> = parsed.iter().map(|(name, bof)| (*name, bof)).collect();
+    across(&borrowed)
+}
@@ -0,0 +1,1104 @@
+//! Running a BOF under emulation instead of natively
+//!
+//! The object is mapped into an emulated x86 or x64 CPU (Unicorn) with its
+//! relocations applied, and every import is bound to a stub that records the
+//! call and its arguments before returning. The Beacon data, output and
+//! format APIs, the loader's builtins (`LoadLibraryA`, `GetProcAddress`,
+//! ...) and the common memory and string functions are emulated well enough
+//! for a BOF to get past them; any other import returns 0.
+//!
+//! Nothing the BOF does reaches the host, so this shows what an untrusted
+//! BOF would call without running it. Code that reads the TEB or PEB, or
+//! makes system calls, stops the emulation.
+//!
+//! Besides the calls themselves, the files, registry keys and modules named
+//! in the arguments of the common file, registry and loader APIs are
+//! collected as [`Indicator`]s. Registry keys opened by the BOF get handles,
+//! so later calls report full paths, but no values are ever found.
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use goblin::pe::relocation::{
+    IMAGE_REL_AMD64_ABSOLUTE, IMAGE_REL_AMD64_ADDR32, IMAGE_REL_AMD64_ADDR32NB, IMAGE_REL_AMD64_ADDR64,
+    IMAGE_REL_AMD64_REL32, IMAGE_REL_AMD64_REL32_5, IMAGE_REL_I386_ABSOLUTE, IMAGE_REL_I386_DIR32,
+    IMAGE_REL_I386_DIR32NB, IMAGE_REL_I386_REL32,
+};
+use goblin::pe::section_table::IMAGE_SCN_CNT_UNINITIALIZED_DATA;
+use goblin::pe::symbol::{IMAGE_SYM_ABSOLUTE, IMAGE_SYM_UNDEFINED};
+use unicorn_engine::unicorn_const::{Arch as UcArch, HookType, MemType, Mode, Permission};
+use serde::{Deserialize, Serialize};
+use unicorn_engine::{RegisterX86, Unicorn};
+
+use crate::beacon::{self, Callback, Memory};
+use crate::{is_loaded, relocations, section_name, Arch, Bof, BEACON_ENTRYPOINT};
+
+// Layout of the emulated address space, all below 4GB so x86 code and
+// 32-bit relocations can reach everything
+const PAGE: u64 = 0x1000;
+const IMAGE_BASE: u64 = 0x0040_0000;
+/// Import stubs; the first one is the return address of the entrypoint
+const STUB_BASE: u64 = 0x0800_0000;
+const STUB_SIZE: u64 = 0x10;
+const STUB_COUNT: u64 = 0x1000;
+const HEAP_BASE: u64 = 0x1000_0000;
+const HEAP_SIZE: u64 = 0x0100_0000;
+const STACK_BASE: u64 = 0x2000_0000;
+const STACK_SIZE: u64 = 0x0010_0000;
+/// Handles returned by LoadLibrary and GetModuleHandle, which aren't mapped
+const MODULE_BASE: u64 = 0x6000_0000;
+const MODULE_SIZE: u64 = 0x0001_0000;
+/// Handles of the registry keys the BOF opens, which aren't mapped either
+const KEY_BASE: u64 = 0x7000_0000;
+/// Longest string read out of emulated memory
+const MAX_STRING: usize = 0x10000;
+/// Most bytes an emulated function copies, fills or reads in one call; no
+/// mapped region is larger, so anything more is a fault
+const MAX_TRANSFER: u64 = HEAP_SIZE;
+/// Bytes moved through host memory at a time
+const CHUNK: u64 = PAGE;
+
+/// How to run a BOF under emulation
+#[derive(Debug, Clone)]
+pub struct EmulateOptions {
+    /// Function to call
+    pub entrypoint: String,
+    /// Packed arguments to pass to it
+    pub args: Vec<u8>,
+    /// Instructions to run before giving up; 0 for no limit
+    pub instruction_limit: usize,
+    /// Time to run before giving up
+    pub timeout: Duration,
+}
+
+impl Default for EmulateOptions {
+    fn default() -> Self {
+        Self {
+            entrypoint: BEACON_ENTRYPOINT.to_string(),
+            args: Vec::new(),
+            instruction_limit: 10_000_000,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// A call the BOF made to an import
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiCall {
+    /// Import name without the prefix, e.g. `KERNEL32$OpenProcess`
+    pub name: String,
+    /// Raw values of the arguments; variadic ones aren't included
+    pub args: Vec<u64>,
+    /// Value the emulated function returned
+    #[serde(rename = "return")]
+    pub returned: u64,
+}
+
+impl fmt::Display for ApiCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let args: Vec<String> = self.args.iter().map(|arg| format!("{:#x}", arg)).collect();
+        write!(f, "{}({}) = {:#x}", self.name, args.join(", "), self.returned)
+    }
+}
+
+/// What an [`Indicator`] names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndicatorKind {
+    /// A file or directory path
+    File,
+    /// A registry key, or a value as `key\name`
+    Registry,
+    /// A module loaded or looked up by name
+    Module,
+}
+
+impl fmt::Display for IndicatorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::File => "file",
+            Self::Registry => "registry",
+            Self::Module => "module",
+        })
+    }
+}
+
+/// Something the BOF touched, as named in an API's arguments
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Indicator {
+    pub kind: IndicatorKind,
+    pub value: String,
+    /// The first call that named it
+    pub api: String,
+}
+
+impl fmt::Display for Indicator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({})", self.kind, self.value, self.api)
+    }
+}
+
+/// Kind of memory access that faulted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Execute => "execute",
+        })
+    }
+}
+
+/// Why the emulation ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Stop {
+    /// The entrypoint returned
+    Returned,
+    /// An instruction at `pc` accessed memory that isn't mapped, or that it
+    /// may not access
+    Fault { pc: u64, address: u64, access: Access },
+    /// A software interrupt, such as `int3` or `__fastfail`'s `int 0x29`
+    Interrupt { pc: u64, number: u32 },
+    /// The instruction limit or timeout was reached
+    Limit,
+    /// The emulator gave up, e.g. on an invalid instruction
+    Error { pc: u64, message: String },
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Returned => write!(f, "returned"),
+            Self::Fault { pc, address, access } => write!(f, "{} fault at {:#x} (pc {:#x})", access, address, pc),
+            Self::Interrupt { pc, number } => write!(f, "interrupt {:#x} (pc {:#x})", number, pc),
+            Self::Limit => write!(f, "instruction limit or timeout reached"),
+            Self::Error { pc, message } => write!(f, "{} (pc {:#x})", message, pc),
+        }
+    }
+}
+
+/// What happened during an emulated run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Emulation {
+    /// Every import called, in order
+    pub calls: Vec<ApiCall>,
+    /// Files, registry keys and modules the calls named, each once
+    pub indicators: Vec<Indicator>,
+    /// Output sent through `BeaconOutput` and `BeaconPrintf`
+    pub output: Vec<Callback>,
+    pub stop: Stop,
+}
+
+#[derive(Debug)]
+pub enum EmulateError {
+    /// Only x86 and x64 objects can be emulated
+    UnsupportedArch,
+    /// The requested entrypoint isn't a function defined by the object
+    MissingEntrypoint(String),
+    /// A section or relocation table points outside of the input buffer
+    Malformed(String),
+    /// A relocation type the emulator doesn't implement
+    UnsupportedRelocation { section: String, typ: u16 },
+    /// More imports than there are stubs for
+    TooManyImports,
+    /// Setting up the emulator failed
+    Engine(String),
+}
+
+impl fmt::Display for EmulateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedArch => write!(f, "only x86 and x64 objects can be emulated"),
+            Self::MissingEntrypoint(name) => write!(f, "entrypoint not found: {}", name),
+            Self::Malformed(reason) => write!(f, "malformed object: {}", reason),
+            Self::UnsupportedRelocation { section, typ } => {
+                write!(f, "unsupported relocation type 0x{:04x} in section {}", typ, section)
+            }
+            Self::TooManyImports => write!(f, "too many imports to emulate"),
+            Self::Engine(message) => write!(f, "emulator error: {}", message),
+        }
+    }
+}
+
+impl error::Error for EmulateError {}
+
+/// Emulator state the hooks work on
+struct State {
+    arch: Arch,
+    /// Names of the stubs after the return address stub
+    stubs: Vec<String>,
+    calls: Vec<ApiCall>,
+    indicators: Vec<Indicator>,
+    output: Vec<Callback>,
+    /// Next free heap address, and the size of every allocation
+    heap: u64,
+    allocations: BTreeMap<u64, u64>,
+    /// Modules loaded through LoadLibrary or GetModuleHandle, by handle
+    modules: Vec<String>,
+    /// Paths of the registry keys opened, by handle
+    keys: Vec<String>,
+    stop: Option<Stop>,
+}
+
+type Emulator<'a> = Unicorn<'a, State>;
+
+/// Call `options.entrypoint` of `bof` under emulation
+pub fn emulate(bof: &Bof, options: &EmulateOptions) -> Result<Emulation, EmulateError> {
+    let arch = match bof.arch() {
+        Some(arch @ (Arch::X86 | Arch::X64)) => arch,
+        _ => return Err(EmulateError::UnsupportedArch),
+    };
+    let (image, stubs, entry) = map_image(bof, arch, &options.entrypoint)?;
+    let engine = |e| EmulateError::Engine(format!("{:?}", e));
+    let mode = match arch {
+        Arch::X86 => Mode::MODE_32,
+        _ => Mode::MODE_64,
+    };
+    let state = State {
+        arch,
+        stubs,
+        calls: Vec::new(),
+        indicators: Vec::new(),
+        output: Vec::new(),
+        heap: HEAP_BASE,
+        allocations: BTreeMap::new(),
+        modules: Vec::new(),
+        keys: Vec::new(),
+        stop: None,
+    };
+    let mut uc = Unicorn::new_with_data(UcArch::X86, mode, state).map_err(engine)?;
+    uc.mem_map(IMAGE_BASE, image.len(), Permission::ALL).map_err(engine)?;
+    uc.mem_write(IMAGE_BASE, &image).map_err(engine)?;
+    // every stub is a bare `ret`, though the hook returns before it runs
+    uc.mem_map(STUB_BASE, (STUB_COUNT * STUB_SIZE) as usize, Permission::READ | Permission::EXEC).map_err(engine)?;
+    uc.mem_write(STUB_BASE, &vec![0xc3; (STUB_COUNT * STUB_SIZE) as usize]).map_err(engine)?;
+    uc.mem_map(HEAP_BASE, HEAP_SIZE as usize, Permission::READ | Permission::WRITE).map_err(engine)?;
+    uc.mem_map(STACK_BASE, STACK_SIZE as usize, Permission::READ | Permission::WRITE).map_err(engine)?;
+
+    let args = match options.args.is_empty() {
+        true => 0,
+        false => {
+            let address = allocate(&mut uc, options.args.len() as u64);
+            uc.mem_write(address, &options.args).map_err(engine)?;
+            address
+        }
+    };
+    let top = STACK_BASE + STACK_SIZE - PAGE;
+    match arch {
+        Arch::X86 => {
+            // go(args, len) with cdecl: arguments, then the return address
+            let sp = top - 12;
+            write_pointer(&mut uc, sp, STUB_BASE);
+            write_pointer(&mut uc, sp + 4, args);
+            write_pointer(&mut uc, sp + 8, options.args.len() as u64);
+            uc.reg_write(RegisterX86::ESP, sp).map_err(engine)?;
+        }
+        _ => {
+            // the return address, with home space for four arguments above it
+            let sp = top - 0x28;
+            write_pointer(&mut uc, sp, STUB_BASE);
+            uc.reg_write(RegisterX86::RSP, sp).map_err(engine)?;
+            uc.reg_write(RegisterX86::RCX, args).map_err(engine)?;
+            uc.reg_write(RegisterX86::RDX, options.args.len() as u64).map_err(engine)?;
+        }
+    }
+
+    uc.add_code_hook(STUB_BASE + STUB_SIZE, STUB_BASE + STUB_COUNT * STUB_SIZE - 1, |uc, address, _| {
+        let index = ((address - STUB_BASE) / STUB_SIZE) as usize - 1;
+        match uc.get_data().stubs.get(index).cloned() {
+            Some(name) => call(uc, &name),
+            None => {
+                let stop = Stop::Fault { pc: address, address, access: Access::Execute };
+                halt(uc, stop);
+            }
+        }
+    })
+    .map_err(engine)?;
+    uc.add_mem_hook(HookType::MEM_UNMAPPED | HookType::MEM_PROT, 1, 0, |uc, typ, address, _, _| {
+        let access = match typ {
+            MemType::WRITE_UNMAPPED | MemType::WRITE_PROT => Access::Write,
+            MemType::FETCH_UNMAPPED | MemType::FETCH_PROT => Access::Execute,
+            _ => Access::Read,
+        };
+        let pc = pc(uc);
+        uc.get_data_mut().stop.get_or_insert(Stop::Fault { pc, address, access });
+        false
+    })
+    .map_err(engine)?;
+    uc.add_intr_hook(|uc, number| {
+        let pc = pc(uc);
+        halt(uc, Stop::Interrupt { pc, number });
+    })
+    .map_err(engine)?;
+
+    let started = Instant::now();
+    let timeout = options.timeout.as_micros() as u64;
+    let result = uc.emu_start(entry, STUB_BASE, timeout, options.instruction_limit);
+    let pc = pc(&uc);
+    let stop = match (uc.get_data_mut().stop.take(), result) {
+        (Some(stop), _) => stop,
+        (None, Ok(())) if pc == STUB_BASE => Stop::Returned,
+        (None, Ok(())) => Stop::Limit,
+        (None, Err(e)) => Stop::Error { pc, message: format!("{:?}", e) },
+    };
+    // the timeout wins over whatever was interrupted
+    let stop = match started.elapsed() >= options.timeout && stop != Stop::Returned {
+        true => Stop::Limit,
+        false => stop,
+    };
+    let state = uc.get_data_mut();
+    Ok(Emulation {
+        calls: std::mem::take(&mut state.calls),
+        indicators: std::mem::take(&mut state.indicators),
+        output: std::mem::take(&mut state.output),
+        stop,
+    })
+}
+
+/// Lay out and relocate the loaded sections, an import pointer for every
+/// `__imp_` symbol and storage for common symbols, returning the image, the
+/// stub names and the entrypoint's address
+fn map_image(bof: &Bof, arch: Arch, entrypoint: &str) -> Result<(Vec<u8>, Vec<String>, u64), EmulateError> {
+    let pointer = pointer_size(arch);
+    let mut offsets = Vec::with_capacity(bof.coff.sections.len());
+    let mut size = 0;
+    for section in &bof.coff.sections {
+        match is_loaded(section) {
+            true => {
+                offsets.push(Some(size));
+                size += align(section.size_of_raw_data.max(section.virtual_size) as u64, PAGE);
+            }
+            false => offsets.push(None),
+        }
+    }
+    let mut slots = size;
+    size += bof.coff.symbols.iter().filter(|(_, _, symbol)| symbol.section_number == IMAGE_SYM_UNDEFINED).count() as u64
+        * pointer;
+    let mut commons = align(size, 16);
+    size = commons;
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.section_number == IMAGE_SYM_UNDEFINED && symbol.value != 0 {
+            size = align(size + symbol.value as u64, 16);
+        }
+    }
+    // the image has to fit below the stubs
+    let size = align(size.max(1), PAGE);
+    if size > STUB_BASE - IMAGE_BASE {
+        return Err(EmulateError::Malformed(format!("sections and common symbols need {:#x} bytes", size)));
+    }
+    let mut image = vec![0; size as usize];
+    for (section, offset) in bof.coff.sections.iter().zip(&offsets) {
+        if let (Some(offset), false) = (offset, section.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0) {
+            let data = bof.section_contents(section);
+            image[*offset as usize..*offset as usize + data.len()].copy_from_slice(data);
+        }
+    }
+
+    // Resolve every symbol: imports to a pointer to their stub, other
+    // undefined externals to the stub itself
+    let prefix = arch.import_prefix();
+    let mut stubs = Vec::new();
+    let mut stub = |name: &str| {
+        stubs.push(name.to_string());
+        STUB_BASE + stubs.len() as u64 * STUB_SIZE
+    };
+    let mut addresses = BTreeMap::new();
+    let mut entry = None;
+    let entrypoints = bof.entrypoints();
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        let name = bof.symbol_name(&symbol);
+        let address = match symbol.section_number {
+            IMAGE_SYM_UNDEFINED if symbol.value != 0 => {
+                let address = IMAGE_BASE + commons;
+                commons = align(commons + symbol.value as u64, 16);
+                address
+            }
+            IMAGE_SYM_UNDEFINED => match name.strip_prefix(prefix).or_else(|| name.strip_prefix("__imp_")) {
+                Some(import) => {
+                    let target = stub(import);
+                    let slot = slots;
+                    slots += pointer;
+                    image[slot as usize..(slot + pointer) as usize].copy_from_slice(&target.to_le_bytes()[..pointer as usize]);
+                    IMAGE_BASE + slot
+                }
+                None => stub(&name),
+            },
+            IMAGE_SYM_ABSOLUTE => symbol.value as u64,
+            number if number > 0 => match offsets.get(number as usize - 1) {
+                Some(Some(offset)) => IMAGE_BASE + offset + symbol.value as u64,
+                _ => continue,
+            },
+            _ => continue,
+        };
+        // cdecl decorates names with an underscore on x86, though not every
+        // toolchain does
+        if name == entrypoint && entrypoints.contains(&name) {
+            entry = Some(address);
+        } else if arch == Arch::X86 && name == format!("_{}", entrypoint) && entrypoints.contains(&name) {
+            entry = entry.or(Some(address));
+        }
+        addresses.insert(index, address);
+    }
+    if stubs.len() as u64 >= STUB_COUNT {
+        return Err(EmulateError::TooManyImports);
+    }
+    let entry = entry.ok_or_else(|| EmulateError::MissingEntrypoint(entrypoint.to_string()))?;
+
+    for (section, offset) in bof.coff.sections.iter().zip(&offsets) {
+        let offset = match offset {
+            Some(offset) => *offset,
+            None => continue,
+        };
+        let name = section_name(section);
+        let entries = section
+            .relocations(bof.buffer)
+            .map_err(|e| EmulateError::Malformed(format!("relocations of {}: {}", name, e)))?;
+        for entry in entries {
+            let typ = entry.typ;
+            if matches!((arch, typ), (Arch::X64, IMAGE_REL_AMD64_ABSOLUTE) | (Arch::X86, IMAGE_REL_I386_ABSOLUTE)) {
+                continue;
+            }
+            let target = addresses
+                .get(&(entry.symbol_table_index as usize))
+                .copied()
+                .ok_or_else(|| EmulateError::Malformed(format!("relocation against a missing symbol in {}", name)))?
+                as i64;
+            let place = offset as usize + entry.virtual_address as usize;
+            let data = &image[offset as usize..(offset + section.size_of_raw_data as u64) as usize];
+            let addend = relocations::addend(arch, typ, data, entry.virtual_address as usize)
+                .ok_or_else(|| EmulateError::Malformed(format!("relocation outside of section {}", name)))?;
+            let address = (IMAGE_BASE as usize + place) as i64;
+            let value = match (arch, typ) {
+                (Arch::X64, IMAGE_REL_AMD64_ADDR64) => {
+                    image[place..place + 8].copy_from_slice(&target.wrapping_add(addend).to_le_bytes());
+                    continue;
+                }
+                (Arch::X64, IMAGE_REL_AMD64_ADDR32) | (Arch::X86, IMAGE_REL_I386_DIR32) => target + addend,
+                (Arch::X64, IMAGE_REL_AMD64_ADDR32NB) | (Arch::X86, IMAGE_REL_I386_DIR32NB) => {
+                    target - IMAGE_BASE as i64 + addend
+                }
+                (Arch::X64, IMAGE_REL_AMD64_REL32..=IMAGE_REL_AMD64_REL32_5) => {
+                    target + addend - (address + 4 + (typ - IMAGE_REL_AMD64_REL32) as i64)
+                }
+                (Arch::X86, IMAGE_REL_I386_REL32) => target + addend - (address + 4),
+                _ => return Err(EmulateError::UnsupportedRelocation { section: name, typ }),
+            };
+            image[place..place + 4].copy_from_slice(&(value as u32).to_le_bytes());
+        }
+    }
+    Ok((image, stubs, entry))
+}
+
+/// An emulated function: how many arguments it takes, whether it's
+/// stdcall on x86, and what it does
+struct Api {
+    arity: usize,
+    stdcall: bool,
+    variadic: bool,
+    run: fn(&mut Emulator, &[u64]) -> u64,
+}
+
+const fn cdecl(arity: usize, run: fn(&mut Emulator, &[u64]) -> u64) -> Api {
+    Api { arity, stdcall: false, variadic: false, run }
+}
+
+const fn winapi(arity: usize, run: fn(&mut Emulator, &[u64]) -> u64) -> Api {
+    Api { arity, stdcall: true, variadic: false, run }
+}
+
+const fn printf(arity: usize, run: fn(&mut Emulator, &[u64]) -> u64) -> Api {
+    Api { arity, stdcall: false, variadic: true, run }
+}
+
+/// Emulated implementation of `function`, without its module or decoration
+fn api(function: &str) -> Option<Api> {
+    Some(match function {
+        "BeaconDataParse" => cdecl(3, beacon_data_parse),
+        "BeaconDataInt" => cdecl(1, |uc, args| take(uc, args[0], 4).map_or(0, |data| read_int(&data) as u64)),
+        "BeaconDataShort" => cdecl(1, |uc, args| take(uc, args[0], 2).map_or(0, |data| read_int(&data) as u64)),
+        "BeaconDataLength" => cdecl(1, |uc, args| read_i32(uc, args[0] + 2 * pointer_size(uc.get_data().arch)) as u64),
+        "BeaconDataExtract" => cdecl(2, beacon_data_extract),
+        "BeaconPrintf" => printf(2, |uc, args| {
+            let output = format(uc, args[1], &args[2..]);
+            emit(uc, args[0], output)
+        }),
+        "BeaconOutput" => cdecl(3, |uc, args| match read(uc, args[1], args[2] as u32 as u64) {
+            Some(data) => emit(uc, args[0], data),
+            None => 0,
+        }),
+        "BeaconFormatAlloc" => cdecl(2, beacon_format_alloc),
+        "BeaconFormatReset" => cdecl(1, |uc, args| {
+            let (original, _, _, size) = read_buffer(uc, args[0]);
+            if !fill(uc, original, 0, size as u64) {
+                return 0;
+            }
+            write_buffer(uc, args[0], (original, original, 0, size));
+            0
+        }),
+        "BeaconFormatFree" => cdecl(1, |uc, args| {
+            write_buffer(uc, args[0], (0, 0, 0, 0));
+            0
+        }),
+        "BeaconFormatAppend" => cdecl(3, |uc, args| match read(uc, args[1], args[2] as u32 as u64) {
+            Some(data) => append(uc, args[0], &data),
+            None => 0,
+        }),
+        "BeaconFormatPrintf" => printf(2, |uc, args| {
+            let output = format(uc, args[1], &args[2..]);
+            append(uc, args[0], &output)
+        }),
+        "BeaconFormatToString" => cdecl(2, |uc, args| {
+            let (original, _, length, _) = read_buffer(uc, args[0]);
+            if args[1] != 0 {
+                let _ = uc.mem_write(args[1], &length.to_le_bytes());
+            }
+            original
+        }),
+        "BeaconFormatInt" => cdecl(2, |uc, args| append(uc, args[0], &(args[1] as u32).to_be_bytes())),
+        "LoadLibraryA" | "GetModuleHandleA" => winapi(1, |uc, args| module(uc, read_string(uc, args[0]))),
+        "LoadLibraryW" | "GetModuleHandleW" => winapi(1, |uc, args| module(uc, read_wide_string(uc, args[0]))),
+        "FreeLibrary" => winapi(1, |_, _| 1),
+        "GetProcAddress" => winapi(2, get_proc_address),
+        "GetLastError" => winapi(0, |_, _| 0),
+        "GetProcessHeap" => winapi(0, |_, _| HEAP_BASE),
+        "HeapAlloc" => winapi(3, |uc, args| allocate(uc, args[2])),
+        "HeapReAlloc" => winapi(4, |uc, args| reallocate(uc, args[2], args[3])),
+        "HeapFree" | "VirtualFree" => winapi(3, |_, _| 1),
+        "VirtualAlloc" => winapi(4, |uc, args| allocate(uc, align(args[1], PAGE))),
+        "LocalAlloc" | "GlobalAlloc" => winapi(2, |uc, args| allocate(uc, args[1])),
+        "LocalFree" | "GlobalFree" => winapi(1, |_, _| 0),
+        "CreateFileA" => winapi(7, |uc, args| file(uc, &args[..1], false)),
+        "CreateFileW" => winapi(7, |uc, args| file(uc, &args[..1], true)),
+        "DeleteFileA" | "GetFileAttributesA" | "RemoveDirectoryA" => winapi(1, |uc, args| file(uc, args, false)),
+        "DeleteFileW" | "GetFileAttributesW" | "RemoveDirectoryW" => winapi(1, |uc, args| file(uc, args, true)),
+        "CreateDirectoryA" | "SetFileAttributesA" => winapi(2, |uc, args| file(uc, &args[..1], false)),
+        "CreateDirectoryW" | "SetFileAttributesW" => winapi(2, |uc, args| file(uc, &args[..1], true)),
+        "MoveFileA" => winapi(2, |uc, args| file(uc, args, false)),
+        "MoveFileW" => winapi(2, |uc, args| file(uc, args, true)),
+        "CopyFileA" | "MoveFileExA" => winapi(3, |uc, args| file(uc, &args[..2], false)),
+        "CopyFileW" | "MoveFileExW" => winapi(3, |uc, args| file(uc, &args[..2], true)),
+        // nothing is found, which ends the search
+        "FindFirstFileA" => winapi(2, |uc, args| file(uc, &args[..1], false) | invalid_handle(uc)),
+        "FindFirstFileW" => winapi(2, |uc, args| file(uc, &args[..1], true) | invalid_handle(uc)),
+        "RegOpenKeyA" => winapi(3, |uc, args| open_key(uc, args[0], args[1], args[2], false)),
+        "RegOpenKeyW" => winapi(3, |uc, args| open_key(uc, args[0], args[1], args[2], true)),
+        "RegOpenKeyExA" => winapi(5, |uc, args| open_key(uc, args[0], args[1], args[4], false)),
+        "RegOpenKeyExW" => winapi(5, |uc, args| open_key(uc, args[0], args[1], args[4], true)),
+        "RegCreateKeyExA" => winapi(9, |uc, args| open_key(uc, args[0], args[1], args[7], false)),
+        "RegCreateKeyExW" => winapi(9, |uc, args| open_key(uc, args[0], args[1], args[7], true)),
+        "RegDeleteKeyA" => winapi(2, |uc, args| key(uc, args[0], args[1], false, ERROR_SUCCESS)),
+        "RegDeleteKeyW" => winapi(2, |uc, args| key(uc, args[0], args[1], true, ERROR_SUCCESS)),
+        "RegQueryValueExA" => winapi(6, |uc, args| value(uc, args[0], 0, args[1], false, ERROR_FILE_NOT_FOUND)),
+        "RegQueryValueExW" => winapi(6, |uc, args| value(uc, args[0], 0, args[1], true, ERROR_FILE_NOT_FOUND)),
+        "RegGetValueA" => winapi(7, |uc, args| value(uc, args[0], args[1], args[2], false, ERROR_FILE_NOT_FOUND)),
+        "RegGetValueW" => winapi(7, |uc, args| value(uc, args[0], args[1], args[2], true, ERROR_FILE_NOT_FOUND)),
+        "RegSetValueExA" => winapi(6, |uc, args| value(uc, args[0], 0, args[1], false, ERROR_SUCCESS)),
+        "RegSetValueExW" => winapi(6, |uc, args| value(uc, args[0], 0, args[1], true, ERROR_SUCCESS)),
+        "RegDeleteValueA" => winapi(2, |uc, args| value(uc, args[0], 0, args[1], false, ERROR_SUCCESS)),
+        "RegDeleteValueW" => winapi(2, |uc, args| value(uc, args[0], 0, args[1], true, ERROR_SUCCESS)),
+        "RegEnumKeyExA" | "RegEnumKeyExW" | "RegEnumValueA" | "RegEnumValueW" => {
+            winapi(8, |uc, args| key(uc, args[0], 0, false, ERROR_NO_MORE_ITEMS))
+        }
+        "RegCloseKey" => winapi(1, |_, _| ERROR_SUCCESS),
+        "malloc" => cdecl(1, |uc, args| allocate(uc, args[0])),
+        "calloc" => cdecl(2, |uc, args| allocate(uc, args[0].saturating_mul(args[1]))),
+        "realloc" => cdecl(2, |uc, args| reallocate(uc, args[0], args[1])),
+        "free" => cdecl(1, |_, _| 0),
+        "memset" => cdecl(3, |uc, args| {
+            fill(uc, args[0], args[1] as u8, args[2]);
+            args[0]
+        }),
+        "memcpy" | "memmove" => cdecl(3, |uc, args| {
+            copy(uc, args[0], args[1], args[2]);
+            args[0]
+        }),
+        "strlen" => cdecl(1, |uc, args| read_string(uc, args[0]).len() as u64),
+        "wcslen" => cdecl(1, |uc, args| read_wide_string(uc, args[0]).encode_utf16().count() as u64),
+        _ => return None,
+    })
+}
+
+/// Record a call to the stub `name`, run its emulation if there is one, and
+/// return to the caller
+fn call(uc: &mut Emulator, name: &str) {
+    let arch = uc.get_data().arch;
+    let pointer = pointer_size(arch);
+    let function = name.split_once('$').map_or(name, |(_, function)| function);
+    let (function, decoration) = match function.split_once('@') {
+        Some((function, bytes)) => (function, bytes.parse::<u64>().ok()),
+        None => (function, None),
+    };
+    let api = api(function);
+    let arity = match (&api, decoration) {
+        (Some(api), _) => api.arity,
+        (None, Some(bytes)) => (bytes / pointer) as usize,
+        (None, None) => 4,
+    };
+    let variadic = api.as_ref().is_some_and(|api| api.variadic);
+    let args = read_args(uc, arity + if variadic { 16 } else { 0 });
+    uc.get_data_mut().calls.push(ApiCall { name: name.to_string(), args: args[..arity].to_vec(), returned: 0 });
+    let result = api.as_ref().map_or(0, |api| (api.run)(uc, &args));
+    if let Some(call) = uc.get_data_mut().calls.last_mut() {
+        call.returned = result;
+    }
+
+    // return, popping the arguments of stdcall functions
+    let (sp, ip, ax) = match arch {
+        Arch::X86 => (RegisterX86::ESP, RegisterX86::EIP, RegisterX86::EAX),
+        _ => (RegisterX86::RSP, RegisterX86::RIP, RegisterX86::RAX),
+    };
+    let popped = match (arch, decoration, &api) {
+        (Arch::X86, Some(bytes), _) => bytes,
+        (Arch::X86, None, Some(api)) if api.stdcall => api.arity as u64 * 4,
+        _ => 0,
+    };
+    let stack = uc.reg_read(sp).unwrap_or_default();
+    let return_address = read_pointer(uc, stack);
+    let _ = uc.reg_write(ax, result);
+    let _ = uc.reg_write(sp, stack + pointer + popped);
+    let _ = uc.reg_write(ip, return_address);
+}
+
+/// The first `count` arguments of the call being made
+fn read_args(uc: &Emulator, count: usize) -> Vec<u64> {
+    match uc.get_data().arch {
+        Arch::X86 => {
+            let stack = uc.reg_read(RegisterX86::ESP).unwrap_or_default();
+            (0..count as u64).map(|index| read_pointer(uc, stack + 4 + index * 4)).collect()
+        }
+        _ => {
+            let stack = uc.reg_read(RegisterX86::RSP).unwrap_or_default();
+            let registers = [RegisterX86::RCX, RegisterX86::RDX, RegisterX86::R8, RegisterX86::R9];
+            (0..count)
+                .map(|index| match registers.get(index) {
+                    Some(register) => uc.reg_read(*register).unwrap_or_default(),
+                    // past the return address and the home space
+                    None => read_pointer(uc, stack + 8 + index as u64 * 8),
+                })
+                .collect()
+        }
+    }
+}
+
+/// `pc_read` always reads RIP, which x86 mode doesn't have
+fn pc(uc: &Emulator) -> u64 {
+    let register = match uc.get_data().arch {
+        Arch::X86 => RegisterX86::EIP,
+        _ => RegisterX86::RIP,
+    };
+    uc.reg_read(register).unwrap_or_default()
+}
+
+fn halt(uc: &mut Emulator, stop: Stop) {
+    uc.get_data_mut().stop.get_or_insert(stop);
+    let _ = uc.emu_stop();
+}
+
+fn emit(uc: &mut Emulator, typ: u64, data: Vec<u8>) -> u64 {
+    uc.get_data_mut().output.push(Callback { typ: typ as i32, data });
+    0
+}
+
+/// Fresh zeroed memory from the emulated heap, or 0 if it's exhausted
+fn allocate(uc: &mut Emulator, size: u64) -> u64 {
+    let state = uc.get_data_mut();
+    let address = state.heap;
+    if size > HEAP_BASE + HEAP_SIZE - address {
+        return 0;
+    }
+    state.heap = align(address + size.max(1), 16);
+    state.allocations.insert(address, size);
+    address
+}
+
+fn reallocate(uc: &mut Emulator, old: u64, size: u64) -> u64 {
+    let copied = uc.get_data().allocations.get(&old).copied().unwrap_or(0).min(size);
+    let new = allocate(uc, size);
+    if new != 0 && copied > 0 {
+        copy(uc, new, old, copied);
+    }
+    new
+}
+
+/// Stop the emulation with a fault at `address`, for a call passed memory
+/// that isn't mapped or a size larger than any mapping
+fn fault(uc: &mut Emulator, address: u64, access: Access) {
+    let pc = pc(uc);
+    halt(uc, Stop::Fault { pc, address, access });
+}
+
+/// `count` bytes at `address`, or `None` after faulting
+fn read(uc: &mut Emulator, address: u64, count: u64) -> Option<Vec<u8>> {
+    if count > MAX_TRANSFER {
+        fault(uc, address.wrapping_add(MAX_TRANSFER), Access::Read);
+        return None;
+    }
+    let mut data = Vec::new();
+    let mut offset = 0;
+    while offset < count {
+        let chunk = CHUNK.min(count - offset);
+        let at = address.wrapping_add(offset);
+        match uc.mem_read_as_vec(at, chunk as usize) {
+            Ok(bytes) => data.extend(bytes),
+            Err(_) => {
+                fault(uc, at, Access::Read);
+                return None;
+            }
+        }
+        offset += chunk;
+    }
+    Some(data)
+}
+
+/// Set `count` bytes at `address` to `byte`, returning whether it all could
+/// be written
+fn fill(uc: &mut Emulator, address: u64, byte: u8, count: u64) -> bool {
+    if count > MAX_TRANSFER {
+        fault(uc, address.wrapping_add(MAX_TRANSFER), Access::Write);
+        return false;
+    }
+    let bytes = vec![byte; CHUNK.min(count) as usize];
+    let mut offset = 0;
+    while offset < count {
+        let chunk = CHUNK.min(count - offset);
+        let at = address.wrapping_add(offset);
+        if uc.mem_write(at, &bytes[..chunk as usize]).is_err() {
+            fault(uc, at, Access::Write);
+            return false;
+        }
+        offset += chunk;
+    }
+    true
+}
+
+/// Copy `count` bytes from `source` to `destination`, back to front when
+/// they overlap that way, like `memmove`
+fn copy(uc: &mut Emulator, destination: u64, source: u64, count: u64) -> bool {
+    if count > MAX_TRANSFER {
+        fault(uc, source.wrapping_add(MAX_TRANSFER), Access::Read);
+        return false;
+    }
+    let backwards = destination > source && destination < source.saturating_add(count);
+    let chunks = count.div_ceil(CHUNK);
+    for index in 0..chunks {
+        let index = if backwards { chunks - 1 - index } else { index };
+        let offset = index * CHUNK;
+        let chunk = CHUNK.min(count - offset);
+        let data = match uc.mem_read_as_vec(source.wrapping_add(offset), chunk as usize) {
+            Ok(data) => data,
+            Err(_) => {
+                fault(uc, source.wrapping_add(offset), Access::Read);
+                return false;
+            }
+        };
+        if uc.mem_write(destination.wrapping_add(offset), &data).is_err() {
+            fault(uc, destination.wrapping_add(offset), Access::Write);
+            return false;
+        }
+    }
+    true
+}
+
+const ERROR_SUCCESS: u64 = 0;
+const ERROR_FILE_NOT_FOUND: u64 = 2;
+const ERROR_NO_MORE_ITEMS: u64 = 259;
+
+/// Root keys, as the low 32 bits of their handles (winreg.h)
+static ROOT_KEYS: &[(u64, &str)] = &[
+    (0x8000_0000, "HKEY_CLASSES_ROOT"),
+    (0x8000_0001, "HKEY_CURRENT_USER"),
+    (0x8000_0002, "HKEY_LOCAL_MACHINE"),
+    (0x8000_0003, "HKEY_USERS"),
+    (0x8000_0004, "HKEY_PERFORMANCE_DATA"),
+    (0x8000_0005, "HKEY_CURRENT_CONFIG"),
+];
+
+/// Record an indicator, unless an earlier call already named it
+fn indicate(uc: &mut Emulator, kind: IndicatorKind, value: String) {
+    let state = uc.get_data_mut();
+    if value.is_empty() || state.indicators.iter().any(|indicator| indicator.kind == kind && indicator.value == value) {
+        return;
+    }
+    let api = state.calls.last().map(|call| call.name.clone()).unwrap_or_default();
+    state.indicators.push(Indicator { kind, value, api });
+}
+
+/// Record the paths at `paths`, returning 0
+fn file(uc: &mut Emulator, paths: &[u64], wide: bool) -> u64 {
+    for path in paths {
+        let path = read_text(uc, *path, wide);
+        indicate(uc, IndicatorKind::File, path);
+    }
+    0
+}
+
+/// `INVALID_HANDLE_VALUE`, at the width of a pointer
+fn invalid_handle(uc: &Emulator) -> u64 {
+    match uc.get_data().arch {
+        Arch::X86 => u32::MAX as u64,
+        _ => u64::MAX,
+    }
+}
+
+/// Path of the key `handle` refers to, with the subkey at `subkey` appended
+fn key_path(uc: &Emulator, handle: u64, subkey: u64, wide: bool) -> String {
+    let index = handle.wrapping_sub(KEY_BASE) / 4;
+    let root = match ROOT_KEYS.iter().find(|(root, _)| *root == handle & 0xffff_ffff) {
+        Some((_, name)) => name.to_string(),
+        None => match uc.get_data().keys.get(index as usize) {
+            Some(path) => path.clone(),
+            None => format!("{:#x}", handle),
+        },
+    };
+    match read_text(uc, subkey, wide) {
+        subkey if subkey.is_empty() => root,
+        subkey => format!("{}\\{}", root, subkey.trim_start_matches('\\')),
+    }
+}
+
+/// Record the key, returning `result`
+fn key(uc: &mut Emulator, handle: u64, subkey: u64, wide: bool, result: u64) -> u64 {
+    let path = key_path(uc, handle, subkey, wide);
+    indicate(uc, IndicatorKind::Registry, path);
+    result
+}
+
+/// Record the key and give it a handle, stored at `result`
+fn open_key(uc: &mut Emulator, handle: u64, subkey: u64, result: u64, wide: bool) -> u64 {
+    let path = key_path(uc, handle, subkey, wide);
+    indicate(uc, IndicatorKind::Registry, path.clone());
+    let keys = &mut uc.get_data_mut().keys;
+    keys.push(path);
+    let handle = KEY_BASE + (keys.len() as u64 - 1) * 4;
+    if result != 0 {
+        write_pointer(uc, result, handle);
+    }
+    ERROR_SUCCESS
+}
+
+/// Record the value `name` of the key, returning `result`
+fn value(uc: &mut Emulator, handle: u64, subkey: u64, name: u64, wide: bool, result: u64) -> u64 {
+    let name = match read_text(uc, name, wide) {
+        name if name.is_empty() => "(Default)".to_string(),
+        name => name,
+    };
+    let path = format!("{}\\{}", key_path(uc, handle, subkey, wide), name);
+    indicate(uc, IndicatorKind::Registry, path);
+    result
+}
+
+/// Handle for a module loaded by name, the same one for every load
+fn module(uc: &mut Emulator, name: String) -> u64 {
+    if name.is_empty() {
+        return IMAGE_BASE;
+    }
+    indicate(uc, IndicatorKind::Module, name.clone());
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_suffix(".DLL").unwrap_or(&name).to_string();
+    let modules = &mut uc.get_data_mut().modules;
+    let index = match modules.iter().position(|module| *module == name) {
+        Some(index) => index,
+        None => {
+            modules.push(name);
+            modules.len() - 1
+        }
+    };
+    MODULE_BASE + index as u64 * MODULE_SIZE
+}
+
+/// A new stub for the function, named `MODULE$Function` like a dynamic
+/// function resolution import
+fn get_proc_address(uc: &mut Emulator, args: &[u64]) -> u64 {
+    let index = args[0].wrapping_sub(MODULE_BASE) / MODULE_SIZE;
+    let module = uc.get_data().modules.get(index as usize).cloned().unwrap_or_else(|| "?".to_string());
+    // small values are ordinals rather than names
+    let function = match args[1] {
+        ordinal if ordinal < 0x10000 => format!("#{}", ordinal),
+        name => read_string(uc, name),
+    };
+    let stubs = &mut uc.get_data_mut().stubs;
+    if stubs.len() as u64 + 1 >= STUB_COUNT {
+        return 0;
+    }
+    stubs.push(format!("{}${}", module, function));
+    STUB_BASE + stubs.len() as u64 * STUB_SIZE
+}
+
+fn beacon_data_parse(uc: &mut Emulator, args: &[u64]) -> u64 {
+    let (parser, buffer, size) = (args[0], args[1], args[2] as i32);
+    // the first four bytes hold the total length of the packed arguments
+    let state = match buffer == 0 || size < 4 {
+        true => (buffer, buffer, 0, 0),
+        false => (buffer, buffer + 4, size as u32 - 4, size as u32 - 4),
+    };
+    write_buffer(uc, parser, state);
+    0
+}
+
+fn beacon_data_extract(uc: &mut Emulator, args: &[u64]) -> u64 {
+    let length = take(uc, args[0], 4).map_or(0, |data| read_int(&data));
+    // an empty item is the current position, not NULL
+    let data = match u32::try_from(length) {
+        Ok(length) => take_pointer(uc, args[0], length as u64),
+        Err(_) => None,
+    };
+    if args[1] != 0 {
+        let size = if data.is_some() { length } else { 0 };
+        let _ = uc.mem_write(args[1], &size.to_le_bytes());
+    }
+    data.unwrap_or(0)
+}
+
+fn beacon_format_alloc(uc: &mut Emulator, args: &[u64]) -> u64 {
+    let size = (args[1] as i32).max(0) as u64;
+    let buffer = allocate(uc, size + 1);
+    let size = if buffer == 0 { 0 } else { size as u32 };
+    write_buffer(uc, args[0], (buffer, buffer, 0, size));
+    0
+}
+
+/// Append to a format buffer, dropping data that doesn't fit
+fn append(uc: &mut Emulator, format: u64, data: &[u8]) -> u64 {
+    let (original, buffer, length, size) = read_buffer(uc, format);
+    // the guest can leave any length in the buffer
+    if buffer == 0 || size.checked_sub(length).is_none_or(|free| (free as usize) < data.len()) {
+        return 0;
+    }
+    let _ = uc.mem_write(buffer, data);
+    write_buffer(uc, format, (original, buffer.wrapping_add(data.len() as u64), length + data.len() as u32, size));
+    0
+}
+
+/// Advance a data parser by `count` bytes, returning them
+fn take(uc: &mut Emulator, parser: u64, count: u64) -> Option<Vec<u8>> {
+    let data = take_pointer(uc, parser, count)?;
+    uc.mem_read_as_vec(data, count as usize).ok()
+}
+
+fn take_pointer(uc: &mut Emulator, parser: u64, count: u64) -> Option<u64> {
+    let (original, buffer, length, size) = read_buffer(uc, parser);
+    if buffer == 0 || (length as u64) < count || (length as i32) < 0 {
+        return None;
+    }
+    write_buffer(uc, parser, (original, buffer + count, length - count as u32, size));
+    Some(buffer)
+}
+
+/// `datap` and `formatp` from beacon.h: original, buffer, length, size
+fn read_buffer(uc: &Emulator, address: u64) -> (u64, u64, u32, u32) {
+    let pointer = pointer_size(uc.get_data().arch);
+    (
+        read_pointer(uc, address),
+        read_pointer(uc, address + pointer),
+        read_i32(uc, address + 2 * pointer) as u32,
+        read_i32(uc, address + 2 * pointer + 4) as u32,
+    )
+}
+
+fn write_buffer(uc: &mut Emulator, address: u64, (original, buffer, length, size): (u64, u64, u32, u32)) {
+    let pointer = pointer_size(uc.get_data().arch);
+    write_pointer(uc, address, original);
+    write_pointer(uc, address + pointer, buffer);
+    let _ = uc.mem_write(address + 2 * pointer, &length.to_le_bytes());
+    let _ = uc.mem_write(address + 2 * pointer + 4, &size.to_le_bytes());
+}
+
+fn format(uc: &Emulator, fmt: u64, args: &[u64]) -> Vec<u8> {
+    let fmt = Emulated(uc).string(fmt);
+    beacon::format(&fmt, args, &Emulated(uc))
+}
+
+/// Emulated memory, for format strings
+struct Emulated<'a, 'b>(&'a Emulator<'b>);
+
+impl Memory for Emulated<'_, '_> {
+    fn string(&self, address: u64) -> Vec<u8> {
+        let mut text = Vec::new();
+        let mut byte = [0];
+        while text.len() < MAX_STRING && self.0.mem_read(address + text.len() as u64, &mut byte).is_ok() && byte[0] != 0 {
+            text.push(byte[0]);
+        }
+        text
+    }
+
+    fn wide_string(&self, address: u64) -> Vec<u16> {
+        let mut text = Vec::new();
+        let mut unit = [0; 2];
+        while text.len() < MAX_STRING
+            && self.0.mem_read(address + 2 * text.len() as u64, &mut unit).is_ok()
+            && unit != [0, 0]
+        {
+            text.push(u16::from_le_bytes(unit));
+        }
+        text
+    }
+}
+
+fn read_string(uc: &Emulator, address: u64) -> String {
+    match address {
+        0 => String::new(),
+        address => String::from_utf8_lossy(&Emulated(uc).string(address)).to_string(),
+    }
+}
+
+fn read_wide_string(uc: &Emulator, address: u64) -> String {
+    match address {
+        0 => String::new(),
+        address => String::from_utf16_lossy(&Emulated(uc).wide_string(address)),
+    }
+}
+
+fn read_text(uc: &Emulator, address: u64, wide: bool) -> String {
+    match wide {
+        true => read_wide_string(uc, address),
+        false => read_string(uc, address),
+    }
+}
+
+fn read_pointer(uc: &Emulator, address: u64) -> u64 {
+    let mut bytes = [0; 8];
+    let size = pointer_size(uc.get_data().arch) as usize;
+    let _ = uc.mem_read(address, &mut bytes[..size]);
+    u64::from_le_bytes(bytes)
+}
+
+fn write_pointer(uc: &mut Emulator, address: u64, value: u64) {
+    let size = pointer_size(uc.get_data().arch) as usize;
+    let _ = uc.mem_write(address, &value.to_le_bytes()[..size]);
+}
+
+fn read_i32(uc: &Emulator, address: u64) -> i32 {
+    let mut bytes = [0; 4];
+    let _ = uc.mem_read(address, &mut bytes);
+    i32::from_le_bytes(bytes)
+}
+
+/// A little-endian 2 or 4 byte integer, sign-extended to 32 bits
+fn read_int(data: &[u8]) -> i32 {
+    match data.len() {
+        2 => i16::from_le_bytes([data[0], data[1]]) as i32,
+        _ => i32::from_le_bytes([data[0], data[1], data[2], data[3]]),
+    }
+}
+
+fn pointer_size(arch: Arch) -> u64 {
+    match arch {
+        Arch::X86 => 4,
+        _ => 8,
+    }
+}
+
+fn align(value: u64, alignment: u64) -> u64 {
+    value.div_ceil(alignment) * alignment
+}
@@ -0,0 +1,121 @@
+//! Entrypoint shape
+//!
+//! Loaders look the entrypoint up by name among the external symbols and
+//! jump to it. A symbol with the right name can still be a variable, only
+//! declared, or `static`, and then the load either fails or jumps into data.
+//! On x86 the C function `go` is the symbol `_go`; an undecorated `go` comes
+//! from `-fno-leading-underscore` or hand-written assembly, and a `WINAPI`
+//! entrypoint is decorated `_go@8`, which loaders don't look for and which
+//! pops arguments its caller also pops.
+
+use std::fmt;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use goblin::pe::symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_FILE, IMAGE_SYM_UNDEFINED};
+
+use crate::disasm::is_section_definition;
+use crate::{Arch, Bof, Severity};
+
+/// Why a symbol named like the entrypoint isn't one
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EntrypointProblem {
+    /// Defined in a section that isn't code
+    Data { section: String },
+    /// Declared but not defined in the object
+    Undefined,
+    /// Defined `static`, so it isn't exported
+    Static,
+    /// Declared `__stdcall` on x86
+    Stdcall,
+    /// An x86 entrypoint without the leading underscore, which loaders
+    /// looking for `expected` won't find
+    Undecorated { expected: String },
+}
+
+/// A symbol that looks like the entrypoint but can't be called as one
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EntrypointIssue {
+    /// Symbol name as it appears in the symbol table
+    pub symbol: String,
+    pub problem: EntrypointProblem,
+}
+
+impl EntrypointIssue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            EntrypointProblem::Undecorated { .. } => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for EntrypointIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            EntrypointProblem::Data { section } => {
+                write!(f, "entrypoint is data in {}, not a function: {}", section, self.symbol)
+            }
+            EntrypointProblem::Undefined => write!(f, "entrypoint is declared but never defined: {}", self.symbol),
+            EntrypointProblem::Static => write!(f, "entrypoint is static, so the loader can't find it: {}", self.symbol),
+            EntrypointProblem::Stdcall => {
+                write!(f, "entrypoint is declared __stdcall, loaders call it as cdecl: {}", self.symbol)
+            }
+            EntrypointProblem::Undecorated { expected } => {
+                write!(f, "x86 entrypoint without the leading underscore: {} (loaders look for {})", self.symbol, expected)
+            }
+        }
+    }
+}
+
+/// Whether `name` is `entrypoint` with x86 stdcall decoration
+fn is_stdcall(name: &str, entrypoint: &str) -> bool {
+    let name = name.strip_prefix('_').unwrap_or(name);
+    name.strip_prefix(entrypoint)
+        .and_then(|rest| rest.strip_prefix('@'))
+        .is_some_and(|size| !size.is_empty() && size.bytes().all(|byte| byte.is_ascii_digit()))
+}
+
+/// Find symbols named `entrypoint` that aren't external functions, and on
+/// x86 stdcall-decorated or undecorated definitions of it
+pub fn lint(bof: &Bof, entrypoint: &str) -> Vec<EntrypointIssue> {
+    let x86 = bof.arch() == Some(Arch::X86);
+    let decorated = Arch::X86.decorate(entrypoint);
+    let sections = bof.sections();
+    let mut issues = Vec::new();
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.storage_class == IMAGE_SYM_CLASS_FILE || is_section_definition(bof, &symbol) {
+            continue;
+        }
+        let name = bof.symbol_name(&symbol);
+        let problem = if name == entrypoint || (x86 && name == decorated) {
+            match (symbol.section_number, symbol.value) {
+                // a common symbol, which the loader allocates like .bss
+                (IMAGE_SYM_UNDEFINED, 1..) => EntrypointProblem::Data { section: ".bss".to_string() },
+                (IMAGE_SYM_UNDEFINED, _) => EntrypointProblem::Undefined,
+                (number, _) if number < 0 => continue,
+                (number, _) => match sections.get(number as usize - 1) {
+                    Some(section) if section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) == 0 => {
+                        EntrypointProblem::Data { section: section.name.clone() }
+                    }
+                    Some(_) if symbol.storage_class != IMAGE_SYM_CLASS_EXTERNAL => EntrypointProblem::Static,
+                    _ => continue,
+                },
+            }
+        } else if x86 && symbol.section_number > 0 && is_stdcall(&name, entrypoint) {
+            EntrypointProblem::Stdcall
+        } else {
+            continue;
+        };
+        issues.push(EntrypointIssue { symbol: name, problem });
+    }
+    let entrypoints = bof.entrypoints();
+    if x86 && !entrypoints.contains(&decorated) && entrypoints.iter().any(|name| name == entrypoint) {
+        issues.push(EntrypointIssue {
+            symbol: entrypoint.to_string(),
+            problem: EntrypointProblem::Undecorated { expected: decorated },
+        });
+    }
+    issues
+}
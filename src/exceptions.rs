@@ -0,0 +1,127 @@
+//! SEH and C++ exception machinery
+//!
+//! Windows finds a function's exception handlers through the unwind tables
+//! in `.pdata`/`.xdata`, which only work once the image is registered with
+//! the exception dispatcher. BOF loaders copy sections into memory and never
+//! register them, so a `__try` or `throw` in a BOF crashes Beacon instead of
+//! reaching its handler, and the handler runtimes the compiler references
+//! aren't resolvable anyway.
+
+use std::fmt;
+
+use crate::{External, ExternalKind, Relocation, Section, Severity};
+
+/// Exception runtimes, by the handlers and helpers their code references
+static HANDLERS: &[(&str, Runtime)] = &[
+    ("__C_specific_handler", Runtime::Seh),
+    ("_C_specific_handler", Runtime::Seh),
+    ("_except_handler3", Runtime::Seh),
+    ("_except_handler4", Runtime::Seh),
+    ("__except_handler3", Runtime::Seh),
+    ("__except_handler4", Runtime::Seh),
+    ("__CxxFrameHandler", Runtime::MsvcCxx),
+    ("__CxxFrameHandler3", Runtime::MsvcCxx),
+    ("__CxxFrameHandler4", Runtime::MsvcCxx),
+    ("__GSHandlerCheck_EH", Runtime::MsvcCxx),
+    ("__GSHandlerCheck_EH4", Runtime::MsvcCxx),
+    ("_CxxThrowException", Runtime::MsvcCxx),
+    ("__gxx_personality_v0", Runtime::GccCxx),
+    ("__gxx_personality_seh0", Runtime::GccCxx),
+    ("__gxx_personality_sj0", Runtime::GccCxx),
+    ("__cxa_throw", Runtime::GccCxx),
+    ("__cxa_begin_catch", Runtime::GccCxx),
+    ("__cxa_end_catch", Runtime::GccCxx),
+    ("__cxa_allocate_exception", Runtime::GccCxx),
+    ("_Unwind_Resume", Runtime::GccCxx),
+    ("_Unwind_SjLj_Resume", Runtime::GccCxx),
+];
+
+/// Which exception mechanism a handler belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Runtime {
+    /// Structured exception handling, `__try`/`__except`
+    Seh,
+    /// C++ exceptions as MSVC implements them
+    MsvcCxx,
+    /// C++ exceptions as GCC and clang implement them for MinGW
+    GccCxx,
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seh => f.write_str("SEH"),
+            Self::MsvcCxx => f.write_str("MSVC C++ exception"),
+            Self::GccCxx => f.write_str("GCC C++ exception"),
+        }
+    }
+}
+
+/// The runtime `function` (without x86 decoration) is an exception handler
+/// or helper of
+pub(crate) fn handler_runtime(name: &str, function: &str) -> Option<Runtime> {
+    HANDLERS.iter().find(|(handler, _)| *handler == name || *handler == function).map(|(_, runtime)| *runtime)
+}
+
+/// Exception machinery the loader won't support
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExceptionIssue {
+    /// Unwind tables, which the loader never registers; they can be dropped
+    /// if no other section refers to them
+    UnwindInfo { sections: Vec<String>, size: usize, removable: bool },
+    /// A reference to an exception handler or helper of `runtime`
+    Handler { symbol: String, runtime: Runtime },
+}
+
+impl ExceptionIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnwindInfo { .. } => Severity::Warning,
+            Self::Handler { .. } => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for ExceptionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnwindInfo { sections, size, .. } => write!(
+                f,
+                "unwind information the loader never registers, so exceptions won't work: {} ({} bytes)",
+                sections.join(", "),
+                size
+            ),
+            Self::Handler { symbol, runtime } => {
+                write!(f, "{} handler referenced, but BOF loaders don't support exceptions: {}", runtime, symbol)
+            }
+        }
+    }
+}
+
+fn is_unwind(section: &str) -> bool {
+    section.starts_with(".pdata") || section.starts_with(".xdata")
+}
+
+/// Find unwind sections and references to exception handlers
+pub fn lint(sections: &[Section], relocations: &[Relocation], externals: &[External]) -> Vec<ExceptionIssue> {
+    let mut issues = Vec::new();
+    let unwind: Vec<&Section> = sections.iter().filter(|section| is_unwind(&section.name)).collect();
+    if !unwind.is_empty() {
+        let removable = !relocations.iter().any(|relocation| {
+            !is_unwind(&relocation.section) && relocation.target_section.as_deref().is_some_and(is_unwind)
+        });
+        issues.push(ExceptionIssue::UnwindInfo {
+            sections: unwind.iter().map(|section| section.name.clone()).collect(),
+            size: unwind.iter().map(|section| section.size).sum(),
+            removable,
+        });
+    }
+    for external in externals.iter().filter(|external| external.kind == ExternalKind::ExceptionHandler) {
+        if let Some(runtime) = handler_runtime(&external.name, &external.function) {
+            issues.push(ExceptionIssue::Handler { symbol: external.name.clone(), runtime });
+        }
+    }
+    issues
+}
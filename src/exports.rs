@@ -0,0 +1,101 @@
+//! Export name database for verifying dynamic function resolution imports
+//!
+//! The bundled lists are generated from the Win32 metadata, so they cover
+//! documented exports only. More can be loaded from a directory of
+//! `MODULE.txt` files with one export name per line.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+static BUNDLED: &[(&str, &str)] = &[
+    ("ADVAPI32", include_str!("../data/exports/ADVAPI32.txt")),
+    ("COMCTL32", include_str!("../data/exports/COMCTL32.txt")),
+    ("COMDLG32", include_str!("../data/exports/COMDLG32.txt")),
+    ("GDI32", include_str!("../data/exports/GDI32.txt")),
+    ("KERNEL32", include_str!("../data/exports/KERNEL32.txt")),
+    ("NETAPI32", include_str!("../data/exports/NETAPI32.txt")),
+    ("NTDLL", include_str!("../data/exports/NTDLL.txt")),
+    ("OLE32", include_str!("../data/exports/OLE32.txt")),
+    ("USER32", include_str!("../data/exports/USER32.txt")),
+    ("WININET", include_str!("../data/exports/WININET.txt")),
+    ("WS2_32", include_str!("../data/exports/WS2_32.txt")),
+];
+
+/// Export names of Win32 libraries, keyed by upper case module name
+#[derive(Debug, Clone, Default)]
+pub struct ExportDatabase {
+    modules: HashMap<String, HashSet<String>>,
+}
+
+impl ExportDatabase {
+    /// A database with no modules, which verifies nothing
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// The export lists shipped with bof-kit
+    pub fn bundled() -> Self {
+        let mut database = Self::empty();
+        for (module, list) in BUNDLED {
+            database.add(module, list);
+        }
+        database
+    }
+
+    /// Add the export names in `list` (one per line, `#` starts a comment)
+    /// to `module`
+    pub fn add(&mut self, module: &str, list: &str) {
+        let names = self.modules.entry(module.to_ascii_uppercase()).or_default();
+        names.extend(
+            list.lines()
+                .map(|line| line.split('#').next().unwrap_or("").trim())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string),
+        );
+    }
+
+    /// Add every `MODULE.txt` file in `dir`
+    pub fn load_dir(&mut self, dir: &Path) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|extension| extension.to_str()) != Some("txt") {
+                continue;
+            }
+            if let Some(module) = path.file_stem().and_then(|stem| stem.to_str()) {
+                let list = fs::read_to_string(&path)?;
+                self.add(module, &list);
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `module` exports `function`, or `None` if the module isn't in
+    /// the database
+    pub fn contains(&self, module: &str, function: &str) -> Option<bool> {
+        self.modules.get(&module.to_ascii_uppercase()).map(|names| names.contains(function))
+    }
+
+    /// The export of `module` nearest to `function`, if any is within a
+    /// couple of edits
+    pub fn closest(&self, module: &str, function: &str) -> Option<&str> {
+        self.modules
+            .get(&module.to_ascii_uppercase())?
+            .iter()
+            .map(|name| (crate::dfr::edit_distance(function, name), name))
+            .filter(|(distance, _)| *distance <= 2)
+            .min()
+            .map(|(_, name)| name.as_str())
+    }
+
+    /// The module exporting `function`, the first by name if there are
+    /// several
+    pub fn module_of(&self, function: &str) -> Option<&str> {
+        self.modules
+            .iter()
+            .filter(|(_, names)| names.contains(function))
+            .map(|(module, _)| module.as_str())
+            .min()
+    }
+}
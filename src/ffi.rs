@@ -0,0 +1,128 @@
+//! C interface, for C2 frameworks that want to check a BOF before accepting
+//! an upload
+//!
+//! Built with `--features ffi`, the `cdylib` exports the functions declared
+//! in `include/bof_kit.h`. Reports are returned as JSON: the
+//! [`AnalysisReport`](crate::AnalysisReport) with a `findings` array of
+//! `{ "rule", "severity", "message" }` added, plus the ATT&CK `"technique"`
+//! of OPSEC findings. Strings returned by the library are freed with
+//! [`bof_free_string`].
+//!
+//! ```c
+//! char *json = NULL;
+//! int result = bof_analyze(buf, len, &json);
+//! if (result == BOF_KIT_ERRORS) {
+//!     reject_upload(json);
+//! }
+//! bof_free_string(json);
+//! ```
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use crate::report::ResolvedReport;
+use crate::{profile, AnalysisOptions, Bof, Severity};
+
+/// Results, matching the exit codes of `bof-check --strict`
+pub const BOF_KIT_OK: c_int = 0;
+pub const BOF_KIT_ERRORS: c_int = 1;
+pub const BOF_KIT_WARNINGS: c_int = 2;
+pub const BOF_KIT_PARSE_FAILURE: c_int = 3;
+/// A null pointer, an unknown target or a bug in the library
+pub const BOF_KIT_INVALID_ARGUMENT: c_int = -1;
+
+/// Analyze the `len` bytes at `buf` against Cobalt Strike's loader
+///
+/// Returns [`BOF_KIT_ERRORS`] or [`BOF_KIT_WARNINGS`] if something was found,
+/// and stores the report in `*out_json`. If the buffer isn't a COFF file,
+/// returns [`BOF_KIT_PARSE_FAILURE`] and stores `{ "error": "..." }`
+/// instead. `*out_json` is only left null for [`BOF_KIT_INVALID_ARGUMENT`].
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes, and `out_json` to writable
+/// storage for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bof_analyze(buf: *const u8, len: usize, out_json: *mut *mut c_char) -> c_int {
+    bof_analyze_for(buf, len, ptr::null(), out_json)
+}
+
+/// Like [`bof_analyze`], against the loader `target` names (`"cs"`,
+/// `"cs-4.9"`, `"sliver"`, ...); a null `target` means Cobalt Strike
+///
+/// # Safety
+///
+/// As for [`bof_analyze`], and `target` must be null or a NUL-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn bof_analyze_for(
+    buf: *const u8,
+    len: usize,
+    target: *const c_char,
+    out_json: *mut *mut c_char,
+) -> c_int {
+    if buf.is_null() || out_json.is_null() {
+        return BOF_KIT_INVALID_ARGUMENT;
+    }
+    *out_json = ptr::null_mut();
+    let mut options = AnalysisOptions::default();
+    if !target.is_null() {
+        options.profile = match CStr::from_ptr(target).to_str().map(profile::by_name) {
+            Ok(Ok(profile)) => profile,
+            _ => return BOF_KIT_INVALID_ARGUMENT,
+        };
+    }
+    let bytes = slice::from_raw_parts(buf, len);
+    // unwinding into C is undefined behavior
+    let result = panic::catch_unwind(AssertUnwindSafe(|| analyze(bytes, &options)));
+    let (code, json) = match result {
+        Ok(result) => result,
+        Err(_) => return BOF_KIT_INVALID_ARGUMENT,
+    };
+    match CString::new(json) {
+        Ok(json) => {
+            *out_json = json.into_raw();
+            code
+        }
+        Err(_) => BOF_KIT_INVALID_ARGUMENT,
+    }
+}
+
+/// Free a string returned by the library; does nothing for null
+///
+/// # Safety
+///
+/// `string` must be null or a string from this library that hasn't been
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn bof_free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Version of the library, as a static NUL-terminated string
+#[no_mangle]
+pub extern "C" fn bof_kit_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+fn analyze(bytes: &[u8], options: &AnalysisOptions) -> (c_int, String) {
+    let bof = match Bof::parse(bytes) {
+        Ok(bof) => bof,
+        Err(e) => {
+            let error = serde_json::json!({ "error": format!("failed to parse input as COFF file: {}", e) });
+            return (BOF_KIT_PARSE_FAILURE, error.to_string());
+        }
+    };
+    let report = ResolvedReport::new(bof.analyze_with(options));
+    let code = match report.max_severity() {
+        Some(Severity::Error) => BOF_KIT_ERRORS,
+        Some(Severity::Warning) => BOF_KIT_WARNINGS,
+        None => BOF_KIT_OK,
+    };
+    (code, serde_json::to_string(&report).expect("reports serialize to JSON"))
+}
@@ -0,0 +1,268 @@
+//! Identifying widely used public BOFs
+//!
+//! The bundled database (`data/fingerprints.toml`) describes each BOF by the
+//! imports, strings and symbol names that survive rebuilding it, so a
+//! captured object can be attributed even when its hash is new. Hashes of
+//! known builds, and more entries, can be loaded from TOML files in the same
+//! format.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::hash::normalize_import;
+use crate::{Bof, Hashes, Import};
+
+static BUNDLED: &str = include_str!("../data/fingerprints.toml");
+
+/// Shortest string matched against the string indicators
+const MIN_STRING_LEN: usize = 4;
+
+/// A public BOF and what gives it away
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fingerprint {
+    pub name: String,
+    /// Where it's published, such as a GitHub `owner/repo`
+    #[serde(default)]
+    pub project: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub description: String,
+    /// SHA-256 of known builds, as lowercase hex
+    #[serde(default)]
+    pub sha256: Vec<String>,
+    /// `MODULE$Function` imports
+    #[serde(default)]
+    pub imports: Vec<String>,
+    /// Substrings of strings in loaded sections
+    #[serde(default)]
+    pub strings: Vec<String>,
+    /// Names of defined symbols
+    #[serde(default)]
+    pub symbols: Vec<String>,
+}
+
+impl Fingerprint {
+    fn indicators(&self) -> usize {
+        self.imports.len() + self.strings.len() + self.symbols.len()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FingerprintFile {
+    #[serde(default)]
+    bof: Vec<Fingerprint>,
+}
+
+/// How sure a match is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Confidence {
+    /// Half of the indicators are present, at least one of them a string
+    /// or symbol
+    Possible,
+    /// Three quarters of the indicators are present
+    Probable,
+    /// The file is a known build
+    Exact,
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Confidence::Possible => "possibly",
+            Confidence::Probable => "probably",
+            Confidence::Exact => "exactly",
+        })
+    }
+}
+
+/// A fingerprint an object matches
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Identification {
+    pub name: String,
+    pub project: String,
+    pub url: String,
+    pub confidence: Confidence,
+    /// Indicators found, such as `import KERNEL32$HeapAlloc`
+    pub matched: Vec<String>,
+    /// Indicators the fingerprint has
+    pub indicators: usize,
+}
+
+impl fmt::Display for Identification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.confidence, self.name)?;
+        if !self.project.is_empty() {
+            write!(f, " ({})", self.project)?;
+        }
+        match self.confidence {
+            Confidence::Exact => f.write_str(": known build"),
+            _ => write!(f, ": {} of {} indicators", self.matched.len(), self.indicators),
+        }
+    }
+}
+
+/// Fingerprints to identify objects with
+#[derive(Debug, Clone, Default)]
+pub struct FingerprintDatabase {
+    fingerprints: Vec<Fingerprint>,
+}
+
+impl FingerprintDatabase {
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    /// The fingerprints shipped with bof-kit
+    pub fn bundled() -> Self {
+        let mut database = Self::empty();
+        database.add_toml(BUNDLED).expect("bundled fingerprints are valid");
+        database
+    }
+
+    /// Add the `[[bof]]` entries of a TOML document
+    pub fn add_toml(&mut self, text: &str) -> Result<(), toml::de::Error> {
+        let file: FingerprintFile = toml::from_str(text)?;
+        self.fingerprints.extend(file.bof);
+        Ok(())
+    }
+
+    /// Add the entries of a TOML file
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        self.add_toml(&text).map_err(|e| format!("invalid fingerprints in {}: {}", path.display(), e))
+    }
+
+    pub fn fingerprints(&self) -> &[Fingerprint] {
+        &self.fingerprints
+    }
+
+    /// Fingerprints `bof` matches, most certain first
+    pub fn identify(&self, bof: &Bof, hashes: &Hashes, imports: &[Import]) -> Vec<Identification> {
+        let imports: HashSet<String> = imports.iter().map(|import| normalize_import(&import.name)).collect();
+        let strings = bof.strings(MIN_STRING_LEN);
+        let symbols: HashSet<String> = bof.coff.symbols.iter()
+            .filter(|(_, _, symbol)| symbol.section_number > 0)
+            .map(|(_, _, symbol)| bof.symbol_name(&symbol))
+            .collect();
+        let defines = |name: &str| symbols.contains(name) || symbols.contains(&format!("_{}", name));
+
+        let mut found = Vec::new();
+        for fingerprint in &self.fingerprints {
+            let identification = |confidence, matched| Identification {
+                name: fingerprint.name.clone(),
+                project: fingerprint.project.clone(),
+                url: fingerprint.url.clone(),
+                confidence,
+                matched,
+                indicators: fingerprint.indicators(),
+            };
+            if fingerprint.sha256.iter().any(|sha256| sha256.eq_ignore_ascii_case(&hashes.sha256)) {
+                found.push(identification(Confidence::Exact, Vec::new()));
+                continue;
+            }
+            let mut matched = Vec::new();
+            let mut distinctive = false;
+            for import in &fingerprint.imports {
+                if imports.contains(&normalize_import(import)) {
+                    matched.push(format!("import {}", import));
+                }
+            }
+            for string in &fingerprint.strings {
+                if strings.iter().any(|found| found.value.contains(string.as_str())) {
+                    matched.push(format!("string {:?}", string));
+                    distinctive = true;
+                }
+            }
+            for symbol in &fingerprint.symbols {
+                if defines(symbol) {
+                    matched.push(format!("symbol {}", symbol));
+                    distinctive = true;
+                }
+            }
+            // a single indicator, or imports alone, are too common to
+            // attribute anything by
+            let confidence = match matched.len() * 4 / fingerprint.indicators().max(1) {
+                _ if matched.len() < 2 || !distinctive => continue,
+                3.. => Confidence::Probable,
+                2 => Confidence::Possible,
+                _ => continue,
+            };
+            found.push(identification(confidence, matched));
+        }
+        found.sort_by(|a, b| {
+            b.confidence.cmp(&a.confidence).then((b.matched.len() * a.indicators).cmp(&(a.matched.len() * b.indicators)))
+        });
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use goblin::pe::header::COFF_MACHINE_X86_64;
+    use goblin::pe::relocation::IMAGE_REL_AMD64_REL32;
+    use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ};
+    use goblin::pe::symbol::IMAGE_SYM_CLASS_EXTERNAL;
+
+    use super::*;
+    use crate::writer::{Object, Relocation, Section, Symbol};
+    use crate::AnalysisOptions;
+
+    /// An object calling the TrustedSec kit's imports, which any BOF that
+    /// buffers its output on the heap does, and defining `symbols`
+    fn object(symbols: &[&str]) -> Vec<u8> {
+        let mut object = Object::new(COFF_MACHINE_X86_64);
+        let code = [0xff, 0x15, 0, 0, 0, 0].repeat(3);
+        let text = object.add_section(Section::new(".text", IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ, code));
+        object.add_symbol(Symbol::new("go", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        for symbol in symbols {
+            object.add_symbol(Symbol::new(symbol, 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        }
+        for (index, import) in ["MSVCRT$vsnprintf", "KERNEL32$HeapAlloc", "KERNEL32$GetProcessHeap"].into_iter().enumerate() {
+            let symbol = object.add_symbol(Symbol::new(&format!("__imp_{}", import), 0, 0, IMAGE_SYM_CLASS_EXTERNAL));
+            object.sections[0].relocations.push(Relocation { offset: index as u32 * 6 + 2, symbol, typ: IMAGE_REL_AMD64_REL32 });
+        }
+        object.to_bytes().unwrap()
+    }
+
+    fn identify(buffer: &[u8]) -> Vec<Identification> {
+        let options = AnalysisOptions { fingerprints: Some(Arc::new(FingerprintDatabase::bundled())), ..Default::default() };
+        Bof::parse(buffer).unwrap().analyze_with(&options).identifications
+    }
+
+    #[test]
+    fn imports_alone_identify_nothing() {
+        assert!(identify(&object(&[])).is_empty());
+    }
+
+    #[test]
+    fn symbols_and_imports_identify_the_kit() {
+        let identifications = identify(&object(&["internal_printf", "printoutput"]));
+        assert_eq!(identifications.len(), 1);
+        assert_eq!(identifications[0].name, "TrustedSec BOF kit");
+        assert_eq!(identifications[0].confidence, Confidence::Probable);
+        assert_eq!(identifications[0].matched.len(), 5);
+    }
+
+    #[test]
+    fn known_builds_match_exactly() {
+        let buffer = object(&[]);
+        let sha256 = Bof::parse(&buffer).unwrap().hashes().sha256;
+        let mut database = FingerprintDatabase::empty();
+        database.add_toml(&format!("[[bof]]\nname = \"whoami\"\nsha256 = [\"{}\"]\n", sha256.to_uppercase())).unwrap();
+        let bof = Bof::parse(&buffer).unwrap();
+        let identifications = database.identify(&bof, &bof.hashes(), &[]);
+        assert_eq!(identifications.len(), 1);
+        assert_eq!(identifications[0].confidence, Confidence::Exact);
+    }
+}
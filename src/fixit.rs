@@ -0,0 +1,166 @@
+//! Machine-readable fixes for findings
+//!
+//! A [`FixIt`] is one edit to the source or the build that makes a finding
+//! go away, described precisely enough for an editor integration to offer
+//! it as a one-click fix. The prose hints bof-check prints stay with the
+//! checks themselves.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::compile::{Compiler, Flag};
+use crate::{
+    AnalysisReport, Arch, ClassifiedSymbol, DfrProblem, EntrypointIssue, EntrypointProblem, ExceptionIssue, ExportDatabase, ExternalKind, Finding, FormatProblem,
+    SectionProblem,
+};
+
+/// An edit that fixes a finding
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "kebab-case"))]
+pub enum FixIt {
+    /// Rename a symbol, e.g. a misspelled import or entrypoint
+    RenameSymbol { from: String, to: String },
+    /// Declare a function as the dynamic function resolution import
+    /// `module$function`
+    PrefixImport { function: String, module: String },
+    /// Declare an x86 import `WINAPI` so it gets stdcall decoration
+    DeclareStdcall { function: String },
+    /// Remove the declaration of an import nothing uses
+    RemoveImport { name: String },
+    /// Call `function` in `caller`, before the call to `before` or, without
+    /// one, before `caller` returns
+    AddCall { function: String, caller: String, before: Option<String> },
+    /// Compile with another flag, spelled for GCC and clang, and for MSVC if
+    /// it has one
+    AddFlag { gcc: String, msvc: Option<String> },
+    /// Add a definition to the source
+    DefineSymbol { definition: String },
+    /// Run a bof-kit tool on the object
+    RunCommand { command: String },
+}
+
+impl fmt::Display for FixIt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RenameSymbol { from, to } => write!(f, "rename symbol {} -> {}", from, to),
+            Self::PrefixImport { function, module } => write!(f, "prefix import with {}$: {}", module, function),
+            Self::DeclareStdcall { function } => write!(f, "declare {} WINAPI", function),
+            Self::RemoveImport { name } => write!(f, "remove the declaration of {}", name),
+            Self::AddCall { function, caller, before: Some(before) } => {
+                write!(f, "call {} in {} before {}", function, caller, before)
+            }
+            Self::AddCall { function, caller, before: None } => {
+                write!(f, "call {} before {} returns", function, caller)
+            }
+            Self::AddFlag { gcc, msvc: Some(msvc) } => write!(f, "add {} ({} for MSVC)", gcc, msvc),
+            Self::AddFlag { gcc, msvc: None } => write!(f, "add {}", gcc),
+            Self::DefineSymbol { definition } => write!(f, "define {}", definition),
+            Self::RunCommand { command } => write!(f, "run {}", command),
+        }
+    }
+}
+
+/// Export lists to look up the module of an undecorated import in
+fn bundled_exports() -> &'static ExportDatabase {
+    static EXPORTS: OnceLock<ExportDatabase> = OnceLock::new();
+    EXPORTS.get_or_init(ExportDatabase::bundled)
+}
+
+/// The [`crate::compile`] flags that keep `rule` quiet by getting rid of
+/// `gcc_symbol` and `msvc_symbol`
+fn flag_for(rule: &str, gcc_symbol: &str, msvc_symbol: &str) -> Option<FixIt> {
+    let find = |compiler: Compiler, symbol: &str| {
+        compiler
+            .flags(Arch::X64)
+            .into_iter()
+            .find(|flag: &Flag| flag.rule == Some(rule) && flag.reason.contains(symbol))
+            .map(|flag| flag.flag.to_string())
+    };
+    Some(FixIt::AddFlag { gcc: find(Compiler::Mingw, gcc_symbol)?, msvc: find(Compiler::Msvc, msvc_symbol) })
+}
+
+/// A defined symbol that's the entrypoint with the wrong decoration or case
+fn entrypoint_candidate(report: &AnalysisReport, expected: &str) -> Option<String> {
+    let normalize = |name: &str| name.trim_start_matches('_').to_ascii_lowercase();
+    report.symbols.iter().find_map(|symbol| match symbol {
+        ClassifiedSymbol::Internal(name) if name != expected && normalize(name) == normalize(expected) => {
+            Some(name.clone())
+        }
+        _ => None,
+    })
+}
+
+impl AnalysisReport {
+    /// An edit that fixes `finding`, if one can be worked out
+    pub fn fix(&self, finding: &Finding) -> Option<FixIt> {
+        match finding {
+            Finding::MissingEntrypoint(expected) => {
+                Some(FixIt::RenameSymbol { from: entrypoint_candidate(self, expected)?, to: expected.clone() })
+            }
+            Finding::Entrypoint(EntrypointIssue { symbol, problem: EntrypointProblem::Undecorated { expected } }) => {
+                Some(FixIt::RenameSymbol { from: symbol.clone(), to: expected.clone() })
+            }
+            Finding::UnknownImport(import) => {
+                // x86 stdcall imports carry @N, which the declaration doesn't
+                let function = import.name.split('@').next().unwrap_or(&import.name);
+                let module = match crate::crt::is_c_library(function) {
+                    true => "MSVCRT",
+                    false => bundled_exports().module_of(function)?,
+                };
+                Some(FixIt::PrefixImport { function: function.to_string(), module: module.to_string() })
+            }
+            Finding::UnreferencedImport(import) => Some(FixIt::RemoveImport { name: import.name.clone() }),
+            Finding::Dfr(issue) => match (&issue.problem, &issue.suggestion) {
+                (DfrProblem::MissingDecoration, _) => Some(FixIt::DeclareStdcall { function: issue.import.clone() }),
+                (_, Some(suggestion)) => {
+                    Some(FixIt::RenameSymbol { from: issue.import.clone(), to: suggestion.clone() })
+                }
+                (_, None) => None,
+            },
+            Finding::Decoration(issue) => {
+                Some(FixIt::RenameSymbol { from: issue.symbol.clone(), to: issue.suggestion.clone() })
+            }
+            Finding::BuildPath(_) => Some(FixIt::RunCommand { command: "bof-strip --scrub-paths".to_string() }),
+            Finding::DeadCode(_) => Some(FixIt::RunCommand { command: "bof-strip --dead-code".to_string() }),
+            Finding::Format(issue) => {
+                let (function, before) = match &issue.problem {
+                    FormatProblem::NeverAllocated { call } | FormatProblem::UsedBeforeAlloc { call } => {
+                        ("BeaconFormatAlloc", Some(call.clone()))
+                    }
+                    FormatProblem::NotFreed => ("BeaconFormatFree", None),
+                    FormatProblem::UsedAfterFree { .. } => return None,
+                };
+                Some(FixIt::AddCall { function: function.to_string(), caller: issue.function.clone(), before })
+            }
+            Finding::Injection(issue) => Some(FixIt::AddCall {
+                function: "BeaconCleanupProcess".to_string(),
+                caller: issue.function.clone(),
+                before: None,
+            }),
+            Finding::UnresolvedExternal(external) => match external.kind {
+                ExternalKind::StackProtector => flag_for("unresolved-external", "__stack_chk_fail", "__security_check_cookie"),
+                ExternalKind::StackProbe => flag_for("unresolved-external", "chkstk", "chkstk"),
+                ExternalKind::FloatSupport => Some(FixIt::DefineSymbol { definition: "int _fltused = 1;".to_string() }),
+                ExternalKind::MemoryIntrinsic | ExternalKind::CLibrary => Some(FixIt::PrefixImport {
+                    function: external.function.clone(),
+                    module: "MSVCRT".to_string(),
+                }),
+                ExternalKind::ArithmeticHelper | ExternalKind::ExceptionHandler
+                | ExternalKind::ThreadLocal
+                | ExternalKind::ExitHandler
+                | ExternalKind::Other => None,
+            },
+            Finding::Exceptions(ExceptionIssue::UnwindInfo { removable: true, .. }) => {
+                Some(FixIt::RunCommand { command: "bof-strip --unwind".to_string() })
+            }
+            Finding::Exceptions(ExceptionIssue::UnwindInfo { removable: false, .. }) => {
+                flag_for("exceptions", "unwind", "unwind")
+            }
+            Finding::Section(issue) if issue.problem == SectionProblem::Debug => {
+                Some(FixIt::RunCommand { command: "bof-strip".to_string() })
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+//! Misuse of the Beacon Format API
+//!
+//! A `formatp` buffer has to be set up with `BeaconFormatAlloc` before any
+//! other Format function touches it, and handed back with
+//! `BeaconFormatFree`; Beacon crashes on the first and leaks task memory on
+//! the second. Buffers can't be told apart without a full dataflow analysis,
+//! so each function's calls are checked in the order its code makes them,
+//! on the assumption that a function works on one buffer at a time.
+
+use std::fmt;
+
+use crate::callgraph::CallGraph;
+use crate::Severity;
+
+const ALLOC: &str = "BeaconFormatAlloc";
+const FREE: &str = "BeaconFormatFree";
+
+/// Format functions that take an allocated buffer
+const USES: &[&str] = &[
+    "BeaconFormatReset",
+    "BeaconFormatAppend",
+    "BeaconFormatPrintf",
+    "BeaconFormatToString",
+    "BeaconFormatInt",
+];
+
+/// What's wrong with a function's Format calls
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormatProblem {
+    /// A buffer is used but nothing in the object allocates one
+    NeverAllocated { call: String },
+    /// A buffer is used before the function allocates it
+    UsedBeforeAlloc { call: String },
+    /// A buffer is used after the function's last `BeaconFormatFree`
+    UsedAfterFree { call: String },
+    /// A buffer is allocated and nothing related frees it
+    NotFreed,
+}
+
+/// A Format API call sequence that will crash or leak
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FormatIssue {
+    pub function: String,
+    pub problem: FormatProblem,
+}
+
+impl FormatIssue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            FormatProblem::NeverAllocated { .. } | FormatProblem::UsedAfterFree { .. } => Severity::Error,
+            FormatProblem::UsedBeforeAlloc { .. } | FormatProblem::NotFreed => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for FormatIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.problem {
+            FormatProblem::NeverAllocated { call } => {
+                write!(f, "{} calls {} but nothing calls {}", self.function, call, ALLOC)
+            }
+            FormatProblem::UsedBeforeAlloc { call } => {
+                write!(f, "{} calls {} before {}", self.function, call, ALLOC)
+            }
+            FormatProblem::UsedAfterFree { call } => write!(f, "{} calls {} after {}", self.function, call, FREE),
+            FormatProblem::NotFreed => write!(f, "{} calls {} and never frees the buffer", self.function, ALLOC),
+        }
+    }
+}
+
+/// Check the Format calls of every function in `graph`
+pub fn lint(graph: &CallGraph) -> Vec<FormatIssue> {
+    if !graph.imports.contains(ALLOC) && !USES.iter().any(|name| graph.imports.contains(*name)) {
+        return Vec::new();
+    }
+    let mut issues = Vec::new();
+    let mut issue = |function: &str, problem| issues.push(FormatIssue { function: function.to_string(), problem });
+    for (function, calls) in &graph.sequences {
+        let is_use = |call: &&String| USES.contains(&call.as_str());
+        let first_alloc = calls.iter().position(|call| call == ALLOC);
+        let last_free = calls.iter().rposition(|call| call == FREE);
+        if !graph.imports.contains(ALLOC) {
+            if let Some(call) = calls.iter().find(is_use) {
+                issue(function, FormatProblem::NeverAllocated { call: call.clone() });
+            }
+            continue;
+        }
+        // a function that allocates nothing may be a helper handed a buffer
+        if let Some(first_alloc) = first_alloc {
+            if let Some(call) = calls[..first_alloc].iter().find(is_use) {
+                issue(function, FormatProblem::UsedBeforeAlloc { call: call.clone() });
+            }
+            if !frees_after(graph, &calls[first_alloc..]) && !freed_by_caller(graph, function, &mut Vec::new()) {
+                issue(function, FormatProblem::NotFreed);
+            }
+        }
+        // an early return can free the buffer too, so only the last free counts
+        if let Some(last_free) = last_free {
+            let after = &calls[last_free + 1..];
+            if !after.iter().any(|call| call == ALLOC) {
+                if let Some(call) = after.iter().find(is_use) {
+                    issue(function, FormatProblem::UsedAfterFree { call: call.clone() });
+                }
+            }
+        }
+    }
+    issues
+}
+
+/// Whether `calls` free a buffer, directly or through a function that
+/// doesn't allocate one of its own
+fn frees_after(graph: &CallGraph, calls: &[String]) -> bool {
+    calls.iter().any(|call| {
+        let reachable = graph.reachable(call);
+        call == FREE || (reachable.contains(FREE) && !reachable.contains(ALLOC))
+    })
+}
+
+/// Whether a function calling `name` frees a buffer after the call, the way
+/// a `go` calling `setup()` and `cleanup()` would
+fn freed_by_caller(graph: &CallGraph, name: &str, seen: &mut Vec<String>) -> bool {
+    for caller in graph.callers(name) {
+        if seen.iter().any(|known| known == caller) {
+            continue;
+        }
+        seen.push(caller.to_string());
+        let calls = graph.sequences.get(caller).map(Vec::as_slice).unwrap_or_default();
+        let after = calls.iter().position(|call| call == name).map_or(calls, |call| &calls[call + 1..]);
+        if frees_after(graph, after) || freed_by_caller(graph, caller, seen) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,262 @@
+//! Unit tests for BOFs, run natively against the mock Beacon API
+//!
+//! A [`Harness`] holds the arguments to pass and a return value for each
+//! Win32 import the BOF calls; the Beacon API is the [mock](crate::beacon_mock)
+//! unless it's stubbed too. [`Harness::run`] loads the BOF, calls its
+//! entrypoint and returns what it sent through `BeaconOutput` and
+//! `BeaconPrintf`, with how often each import was called:
+//!
+//! ```no_run
+//! use bof_kit::args::Arg;
+//! use bof_kit::harness::Harness;
+//! use bof_kit::Bof;
+//!
+//! let buffer = std::fs::read("whoami.x64.o").unwrap();
+//! let bof = Bof::parse(&buffer).unwrap();
+//! let mut harness = Harness::with_args(&[Arg::Int(1)]);
+//! harness.stub("KERNEL32$GetCurrentProcessId", 1234);
+//! let run = unsafe { harness.run(&bof) }.unwrap();
+//! assert_eq!(run.text(), "pid: 1234\n");
+//! assert_eq!(run.calls("KERNEL32$GetCurrentProcessId"), 1);
+//! ```
+//!
+//! Imports that are neither stubbed nor part of the mock fail the load with
+//! [`LoadError::UnresolvedSymbol`], so a new dependency of the BOF shows up
+//! as a test failure rather than a crash.
+
+use std::collections::BTreeMap;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::args::{self, Arg};
+use crate::beacon_mock::{self, Callback, CALLBACK_ERROR};
+use crate::loader::{self, LoadError, LoadedBof};
+use crate::{Bof, BEACON_ENTRYPOINT};
+
+/// Bytes of machine code for each import
+const STUB_SIZE: usize = 32;
+
+/// How to call a BOF under test
+#[derive(Debug, Clone)]
+pub struct Harness {
+    /// Function to call
+    pub entrypoint: String,
+    /// Packed arguments to pass to it
+    pub args: Vec<u8>,
+    /// Value each stubbed import returns, by import name without the
+    /// prefix, e.g. `KERNEL32$GetCurrentProcessId`
+    pub stubs: BTreeMap<String, u64>,
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self { entrypoint: BEACON_ENTRYPOINT.to_string(), args: Vec::new(), stubs: BTreeMap::new() }
+    }
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A harness passing `args`, packed like `bof_pack()` would
+    pub fn with_args(args: &[Arg]) -> Self {
+        Self { args: args::pack(args), ..Self::default() }
+    }
+
+    /// Make the import `name` return `value` without doing anything else
+    pub fn stub(&mut self, name: &str, value: u64) -> &mut Self {
+        self.stubs.insert(name.to_string(), value);
+        self
+    }
+
+    /// Load `bof` and call its entrypoint, collecting its output
+    ///
+    /// # Safety
+    ///
+    /// This runs arbitrary native code from the object file, see
+    /// [`LoadedBof::call`].
+    pub unsafe fn run(&self, bof: &Bof) -> Result<TestRun, LoadError> {
+        let stubs = Stubs::new(bof.imports().count() + 1)?;
+        let mut bound = Vec::new();
+        let loaded = LoadedBof::load(bof, |name| {
+            let address = match self.stubs.get(name) {
+                Some(value) => stubs.bind(bound.len(), Target::Return(*value)),
+                None => stubs.bind(bound.len(), Target::Jump(beacon_mock::resolve(name)?)),
+            };
+            bound.push(name.to_string());
+            Some(address)
+        })?;
+        stubs.seal()?;
+        let (result, output) = beacon_mock::capture(|| loaded.call(&self.entrypoint, &self.args));
+        result?;
+        let mut calls = BTreeMap::new();
+        for (index, name) in bound.into_iter().enumerate() {
+            *calls.entry(name).or_default() += stubs.count(index);
+        }
+        Ok(TestRun { output, calls })
+    }
+}
+
+/// Output of a BOF under test, and the imports it called
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestRun {
+    /// Every callback, in the order it was sent
+    pub output: Vec<Callback>,
+    /// Number of calls to each import, by import name without the prefix
+    pub calls: BTreeMap<String, u64>,
+}
+
+impl TestRun {
+    /// Data of every callback, concatenated
+    pub fn bytes(&self) -> Vec<u8> {
+        self.output.iter().flat_map(|callback| callback.data.iter().copied()).collect()
+    }
+
+    /// Every callback as text, with invalid UTF-8 replaced
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.bytes()).to_string()
+    }
+
+    /// Callbacks sent as `CALLBACK_ERROR`, as text
+    pub fn errors(&self) -> Vec<String> {
+        self.output
+            .iter()
+            .filter(|callback| callback.typ == CALLBACK_ERROR)
+            .map(|callback| String::from_utf8_lossy(&callback.data).to_string())
+            .collect()
+    }
+
+    /// Number of calls to the import `name`; 0 if it wasn't imported
+    pub fn calls(&self, name: &str) -> u64 {
+        self.calls.get(name).copied().unwrap_or(0)
+    }
+}
+
+/// What a stub does after counting the call
+enum Target {
+    Return(u64),
+    Jump(usize),
+}
+
+/// Executable stubs that count their calls, one per import
+struct Stubs {
+    code: *mut u8,
+    size: usize,
+    /// Incremented by the stubs themselves
+    counters: Box<[AtomicU64]>,
+}
+
+impl Stubs {
+    fn new(count: usize) -> Result<Self, LoadError> {
+        let size = loader::align(count * STUB_SIZE, loader::page_size());
+        Ok(Self { code: loader::map(size)?, size, counters: (0..count).map(|_| AtomicU64::new(0)).collect() })
+    }
+
+    /// Write stub `index`, returning its address
+    fn bind(&self, index: usize, target: Target) -> usize {
+        let counter = self.counters[index].as_ptr() as u64;
+        let mut code = Vec::with_capacity(STUB_SIZE);
+        // mov rax, counter; lock inc qword ptr [rax]
+        code.extend([0x48, 0xb8]);
+        code.extend(counter.to_le_bytes());
+        code.extend([0xf0, 0x48, 0xff, 0x00]);
+        match target {
+            // mov rax, value; ret
+            Target::Return(value) => {
+                code.extend([0x48, 0xb8]);
+                code.extend(value.to_le_bytes());
+                code.push(0xc3);
+            }
+            // mov rax, function; jmp rax
+            Target::Jump(function) => {
+                code.extend([0x48, 0xb8]);
+                code.extend((function as u64).to_le_bytes());
+                code.extend([0xff, 0xe0]);
+            }
+        }
+        let stub = unsafe { self.code.add(index * STUB_SIZE) };
+        unsafe { ptr::copy_nonoverlapping(code.as_ptr(), stub, code.len()) };
+        stub as usize
+    }
+
+    fn seal(&self) -> Result<(), LoadError> {
+        loader::protect(self.code, self.size, libc::PROT_READ | libc::PROT_EXEC)
+    }
+
+    fn count(&self, index: usize) -> u64 {
+        self.counters[index].load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for Stubs {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.code as *mut libc::c_void, self.size) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::header::COFF_MACHINE_X86_64;
+    use goblin::pe::relocation::IMAGE_REL_AMD64_REL32;
+    use goblin::pe::section_table::{
+        IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ,
+    };
+    use goblin::pe::symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_STATIC};
+
+    use super::*;
+    use crate::writer::{Object, Relocation, Section, Symbol};
+
+    /// `go` printing the process ID it gets from an import:
+    ///
+    /// ```text
+    /// sub rsp, 40
+    /// call [__imp_KERNEL32$GetCurrentProcessId]
+    /// mov r8d, eax
+    /// xor ecx, ecx
+    /// lea rdx, [format]
+    /// call [__imp_BeaconPrintf]
+    /// add rsp, 40
+    /// ret
+    /// ```
+    fn object() -> Vec<u8> {
+        let mut object = Object::new(COFF_MACHINE_X86_64);
+        let code = vec![
+            0x48, 0x83, 0xec, 0x28, 0xff, 0x15, 0, 0, 0, 0, 0x41, 0x89, 0xc0, 0x31, 0xc9, 0x48, 0x8d, 0x15, 0, 0, 0, 0, 0xff,
+            0x15, 0, 0, 0, 0, 0x48, 0x83, 0xc4, 0x28, 0xc3,
+        ];
+        let text = object.add_section(Section::new(".text", IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ, code));
+        let rdata = object.add_section(Section::new(".rdata", IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ, b"pid: %d\n\0".to_vec()));
+        object.add_symbol(Symbol::new("go", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        let format = object.add_symbol(Symbol::new("format", 0, rdata, IMAGE_SYM_CLASS_STATIC));
+        let pid = object.add_symbol(Symbol::new("__imp_KERNEL32$GetCurrentProcessId", 0, 0, IMAGE_SYM_CLASS_EXTERNAL));
+        let printf = object.add_symbol(Symbol::new("__imp_BeaconPrintf", 0, 0, IMAGE_SYM_CLASS_EXTERNAL));
+        object.sections[0].relocations = [(6, pid), (18, format), (24, printf)]
+            .into_iter()
+            .map(|(offset, symbol)| Relocation { offset, symbol, typ: IMAGE_REL_AMD64_REL32 })
+            .collect();
+        object.to_bytes().unwrap()
+    }
+
+    #[test]
+    fn run_counts_stubbed_imports() {
+        let buffer = object();
+        let bof = Bof::parse(&buffer).unwrap();
+        let mut harness = Harness::new();
+        harness.stub("KERNEL32$GetCurrentProcessId", 1234);
+        let run = unsafe { harness.run(&bof) }.unwrap();
+        assert_eq!(run.text(), "pid: 1234\n");
+        assert!(run.errors().is_empty());
+        assert_eq!(run.calls("KERNEL32$GetCurrentProcessId"), 1);
+        assert_eq!(run.calls("BeaconPrintf"), 1);
+        assert_eq!(run.calls("KERNEL32$Sleep"), 0);
+    }
+
+    #[test]
+    fn unstubbed_imports_fail_the_load() {
+        let buffer = object();
+        let bof = Bof::parse(&buffer).unwrap();
+        let error = unsafe { Harness::new().run(&bof) }.unwrap_err();
+        assert!(matches!(error, LoadError::UnresolvedSymbol(name) if name.contains("GetCurrentProcessId")));
+    }
+}
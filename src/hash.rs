@@ -0,0 +1,90 @@
+//! Hashes identifying a BOF, for deduplicating and tracking builds across an
+//! arsenal
+//!
+//! Besides the usual digests of the whole file, [`imphash`] hashes the import
+//! list the way pefile does for PEs, so rebuilds that only change code or
+//! data keep the same value. With the `fuzzy-hash` feature a TLSH of the file
+//! is included too, which stays close between similar builds.
+
+use std::fmt::{self, Write};
+
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Hashes of an object file, as lowercase hex
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hashes {
+    pub sha256: String,
+    pub sha1: String,
+    pub md5: String,
+    /// MD5 of the import list, see [`imphash`]
+    pub imphash: String,
+    /// TLSH of the file, if built with the `fuzzy-hash` feature and the file
+    /// is big and varied enough to have one
+    pub tlsh: Option<String>,
+}
+
+impl Hashes {
+    /// Hash `bytes`, with `imports` as in the symbol table without the import
+    /// prefix
+    pub fn new<'a>(bytes: &[u8], imports: impl IntoIterator<Item = &'a str>) -> Self {
+        Self {
+            sha256: hex(&Sha256::digest(bytes)),
+            sha1: hex(&Sha1::digest(bytes)),
+            md5: hex(&Md5::digest(bytes)),
+            imphash: imphash(imports),
+            tlsh: tlsh(bytes),
+        }
+    }
+}
+
+impl fmt::Display for Hashes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sha256 {}\nsha1 {}\nmd5 {}\nimphash {}", self.sha256, self.sha1, self.md5, self.imphash)?;
+        if let Some(tlsh) = &self.tlsh {
+            write!(f, "\ntlsh {}", tlsh)?;
+        }
+        Ok(())
+    }
+}
+
+/// MD5 of the imports in order, lowercased and joined with commas
+///
+/// `MODULE$Function` imports are spelled `module.function`, like pefile's
+/// imphash, and x86 `@N` decoration is dropped so both architectures' builds
+/// of a BOF hash the same.
+pub fn imphash<'a>(imports: impl IntoIterator<Item = &'a str>) -> String {
+    let names: Vec<String> = imports.into_iter().map(normalize_import).collect();
+    hex(&Md5::digest(names.join(",").as_bytes()))
+}
+
+/// An import as it goes into the [`imphash`]
+pub(crate) fn normalize_import(name: &str) -> String {
+    let name = name.split_once('@').map_or(name, |(name, _)| name);
+    match name.split_once('$') {
+        Some((module, function)) => format!("{}.{}", module, function),
+        None => name.to_string(),
+    }
+    .to_lowercase()
+}
+
+#[cfg(feature = "fuzzy-hash")]
+fn tlsh(bytes: &[u8]) -> Option<String> {
+    let tlsh = tlsh2::TlshDefaultBuilder::build_from(bytes)?;
+    Some(String::from_utf8_lossy(&tlsh.hash()).to_string())
+}
+
+#[cfg(not(feature = "fuzzy-hash"))]
+fn tlsh(_: &[u8]) -> Option<String> {
+    None
+}
+
+pub(crate) fn hex(digest: &[u8]) -> String {
+    let mut hex = String::with_capacity(digest.len() * 2);
+    for byte in digest {
+        let _ = write!(hex, "{:02x}", byte);
+    }
+    hex
+}
@@ -0,0 +1,154 @@
+//! Static constructors, destructors and exit handlers
+//!
+//! A linked image's CRT startup code calls every function pointer in the
+//! `.CRT$XC*`/`.CRT$XI*` (MSVC) or `.ctors` (GCC) tables before `main`, and
+//! the `.CRT$XP*`/`.CRT$XT*` or `.dtors` ones on the way out, along with
+//! whatever `atexit` registered. BOF loaders call the entrypoint and nothing
+//! else, so C++ globals are never constructed. `atexit` imported from MSVCRT
+//! does register its function, but with the process: it runs at exit, long
+//! after Beacon freed the BOF, and crashes it.
+
+use std::fmt;
+
+use crate::disasm::{function_at, is_section_definition};
+use crate::{relocations, section_name, Arch, Bof, Severity};
+
+/// Functions registering a function to run at exit
+static EXIT_HANDLERS: &[&str] = &["atexit", "_onexit", "__cxa_atexit", "at_quick_exit"];
+
+/// Whether `name`, or `function` without x86 decoration, registers a
+/// function to run at exit
+pub(crate) fn is_exit_handler(name: &str, function: &str) -> bool {
+    EXIT_HANDLERS.contains(&name) || EXIT_HANDLERS.contains(&function)
+}
+
+/// Which end of the program a table of functions runs at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TableKind {
+    Constructors,
+    Destructors,
+}
+
+/// What a section of function pointers holds, by its name
+fn table_kind(section: &str) -> Option<TableKind> {
+    if [".ctors", ".CRT$XC", ".CRT$XI"].iter().any(|prefix| section.starts_with(prefix)) {
+        Some(TableKind::Constructors)
+    } else if [".dtors", ".CRT$XP", ".CRT$XT"].iter().any(|prefix| section.starts_with(prefix)) {
+        Some(TableKind::Destructors)
+    } else {
+        None
+    }
+}
+
+/// Startup or shutdown code the loader never runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InitializerIssue {
+    /// A table of functions the CRT would call, with the functions in it
+    Table { section: String, kind: TableKind, functions: Vec<String> },
+    /// A reference to `atexit` or one of its relatives, with the functions
+    /// calling it
+    AtExit { symbol: String, functions: Vec<String> },
+}
+
+impl InitializerIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::Table { .. } => Severity::Warning,
+            Self::AtExit { .. } => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for InitializerIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let functions = match self {
+            Self::Table { section, kind: TableKind::Constructors, functions } => {
+                write!(f, "static constructors will never run: {}", section)?;
+                functions
+            }
+            Self::Table { section, kind: TableKind::Destructors, functions } => {
+                write!(f, "static destructors will never run: {}", section)?;
+                functions
+            }
+            Self::AtExit { symbol, functions } => {
+                write!(f, "exit handlers would run after the BOF is freed: {}", symbol)?;
+                functions
+            }
+        };
+        match (self, functions.is_empty()) {
+            (_, true) => Ok(()),
+            (Self::Table { .. }, false) => write!(f, " (skips {})", functions.join(", ")),
+            (Self::AtExit { .. }, false) => write!(f, " (called by {})", functions.join(", ")),
+        }
+    }
+}
+
+/// Find constructor and destructor tables, with the functions in them, and
+/// references to `atexit`
+pub fn lint(bof: &Bof) -> Vec<InitializerIssue> {
+    let (arch, prefix) = match (bof.arch(), bof.import_prefix()) {
+        (Some(arch), Some(prefix)) => (arch, prefix),
+        _ => return Vec::new(),
+    };
+    let mut issues = Vec::new();
+    for (index, section) in bof.coff.sections.iter().enumerate() {
+        let number = index as i16 + 1;
+        let name = section_name(section);
+        let data = bof.section_contents(section);
+        let kind = table_kind(&name);
+        let mut functions = Vec::new();
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            let target = match bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                Some((_, target)) => target,
+                None => continue,
+            };
+            let symbol = bof.symbol_name(&target);
+            let function = match kind {
+                // a pointer into a section points at a static function
+                Some(_) if is_section_definition(bof, &target) => {
+                    let addend = relocations::addend(arch, entry.typ, data, entry.virtual_address as usize).unwrap_or(0);
+                    function_at(bof, target.section_number, addend.max(0) as u32).unwrap_or(symbol)
+                }
+                Some(_) => symbol,
+                None => {
+                    let called = match symbol.strip_prefix(prefix) {
+                        Some(import) => import.rsplit('$').next().unwrap_or(import),
+                        None if arch == Arch::X86 => symbol.strip_prefix('_').unwrap_or(&symbol),
+                        None => &symbol,
+                    };
+                    if !is_exit_handler(&symbol, called) {
+                        continue;
+                    }
+                    let caller = function_at(bof, number, entry.virtual_address);
+                    add_caller(&mut issues, symbol, caller);
+                    continue;
+                }
+            };
+            if !functions.contains(&function) {
+                functions.push(function);
+            }
+        }
+        if let Some(kind) = kind {
+            issues.push(InitializerIssue::Table { section: name, kind, functions });
+        }
+    }
+    issues
+}
+
+/// Record that `caller` calls the exit handler `symbol`
+fn add_caller(issues: &mut Vec<InitializerIssue>, symbol: String, caller: Option<String>) {
+    let known = issues
+        .iter()
+        .position(|issue| matches!(issue, InitializerIssue::AtExit { symbol: known, .. } if *known == symbol));
+    let index = known.unwrap_or_else(|| {
+        issues.push(InitializerIssue::AtExit { symbol, functions: Vec::new() });
+        issues.len() - 1
+    });
+    if let InitializerIssue::AtExit { functions, .. } = &mut issues[index] {
+        if let Some(caller) = caller.filter(|caller| !functions.contains(caller)) {
+            functions.push(caller);
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Process handles leaked by the Beacon injection API
+//!
+//! `BeaconInjectProcess` and `BeaconInjectTemporaryProcess` work on a
+//! process the BOF opened or spawned, and `BeaconCleanupProcess` closes the
+//! handles in its `PROCESS_INFORMATION` afterwards. A BOF that never calls it
+//! leaks two handles into Beacon on every run.
+
+use std::fmt;
+
+use crate::callgraph::CallGraph;
+use crate::Severity;
+
+const INJECT: &[&str] = &["BeaconInjectProcess", "BeaconInjectTemporaryProcess"];
+const CLEANUP: &str = "BeaconCleanupProcess";
+
+/// A function that injects without cleaning up the process
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InjectionIssue {
+    pub function: String,
+    /// The injection API it calls
+    pub call: String,
+}
+
+impl InjectionIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for InjectionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} calls {} but never reaches {}", self.function, self.call, CLEANUP)
+    }
+}
+
+/// Find functions calling an injection API with no `BeaconCleanupProcess`
+/// call in them, in a function they call, or in a caller after the call
+pub fn lint(graph: &CallGraph) -> Vec<InjectionIssue> {
+    let mut issues = Vec::new();
+    for function in &graph.functions {
+        let call = match graph.callees(function).find(|callee| INJECT.contains(callee)) {
+            Some(call) => call,
+            None => continue,
+        };
+        if !graph.reachable(function).contains(CLEANUP) && !cleaned_up_by_caller(graph, function, &mut Vec::new()) {
+            issues.push(InjectionIssue { function: function.clone(), call: call.to_string() });
+        }
+    }
+    issues
+}
+
+/// Whether a function calling `name` cleans up after the call
+fn cleaned_up_by_caller(graph: &CallGraph, name: &str, seen: &mut Vec<String>) -> bool {
+    for caller in graph.callers(name) {
+        if seen.iter().any(|known| known == caller) {
+            continue;
+        }
+        seen.push(caller.to_string());
+        let calls = graph.sequences.get(caller).map(Vec::as_slice).unwrap_or_default();
+        let after = calls.iter().position(|call| call == name).map_or(calls, |call| &calls[call + 1..]);
+        let cleans_up = after.iter().any(|call| call == CLEANUP || graph.reachable(call).contains(CLEANUP));
+        if cleans_up || cleaned_up_by_caller(graph, caller, seen) {
+            return true;
+        }
+    }
+    false
+}
@@ -0,0 +1,139 @@
+//! Build paths and user names left in a BOF
+//!
+//! `__FILE__` strings, `.file` symbols and debug information record where
+//! the BOF was built, which often includes the operator's user name or
+//! project layout. None of it is needed at runtime; `bof-strip
+//! --scrub-paths` cuts the paths down to file names or blanks them.
+
+use std::fmt;
+use std::ops::Range;
+
+use goblin::pe::symbol::{COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_FILE};
+
+use crate::{section_name, Bof};
+
+/// An absolute path found in the object
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathLeak {
+    /// Section name, `string table` or `.file symbol`
+    pub location: String,
+    /// Offset of the path within `location`
+    pub offset: usize,
+    pub path: String,
+    /// User name from a home directory in the path
+    pub user: Option<String>,
+}
+
+impl fmt::Display for PathLeak {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "build path in {}+0x{:x}: {}", self.location, self.offset, self.path)?;
+        if let Some(user) = &self.user {
+            write!(f, " (leaks user name {})", user)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every absolute path in the sections, the string table and `.file` symbols
+pub fn lint(bof: &Bof) -> Vec<PathLeak> {
+    let mut leaks = Vec::new();
+    let mut found = |location: &str, data: &[u8]| {
+        for span in find_paths(data) {
+            let path = String::from_utf8_lossy(&data[span.path.clone()]).to_string();
+            let user = user_name(&path);
+            leaks.push(PathLeak { location: location.to_string(), offset: span.path.start, path, user });
+        }
+    };
+    for section in &bof.coff.sections {
+        found(&section_name(section), bof.section_contents(section));
+    }
+    found("string table", bof.string_table());
+    for (index, _, symbol) in bof.coff.symbols.iter().filter(|(_, _, symbol)| symbol.storage_class == IMAGE_SYM_CLASS_FILE) {
+        let start = bof.coff.header.pointer_to_symbol_table as usize + (index + 1) * COFF_SYMBOL_SIZE;
+        let end = start + symbol.number_of_aux_symbols as usize * COFF_SYMBOL_SIZE;
+        found(".file symbol", bof.buffer.get(start..end).unwrap_or_default());
+    }
+    leaks
+}
+
+/// Where a path is in some data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PathSpan {
+    pub path: Range<usize>,
+    /// Start of the file name within `path`
+    pub name_start: usize,
+    /// End of the string the path is part of
+    pub string_end: usize,
+}
+
+/// Absolute paths in the printable ASCII strings of `data`
+pub(crate) fn find_paths(data: &[u8]) -> Vec<PathSpan> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let end = start + data[start..].iter().take_while(|byte| is_text(**byte)).count();
+        let mut offset = start;
+        while let Some(span) = find_path(&data[..end], offset) {
+            offset = span.path.end;
+            spans.push(span);
+        }
+        start = end + 1;
+    }
+    spans
+}
+
+/// The first absolute path at or after `from` in `text`
+fn find_path(text: &[u8], from: usize) -> Option<PathSpan> {
+    let starts_path = |index: usize| {
+        let after_word = index > 0 && (text[index - 1].is_ascii_alphanumeric() || b"/\\:.".contains(&text[index - 1]));
+        !after_word && is_absolute_path(&text[index..])
+    };
+    let start = (from..text.len()).find(|index| starts_path(*index))?;
+    // a path runs up to a quote, a tab, the next path or a space after a
+    // file name with an extension; directories can have spaces in them
+    let mut component_start = start;
+    let end = (start + 1..text.len())
+        .find(|index| {
+            let byte = text[*index];
+            if byte == b'\\' || byte == b'/' {
+                component_start = *index;
+            }
+            b"\"'\t".contains(&byte)
+                || starts_path(*index)
+                || (byte == b' ' && text[component_start..*index].contains(&b'.'))
+        })
+        .unwrap_or(text.len());
+    let name_start = start + text[start..end].iter().rposition(|byte| *byte == b'\\' || *byte == b'/')? + 1;
+    Some(PathSpan { path: start..end, name_start, string_end: text.len() })
+}
+
+/// Drive-letter, UNC and home directory paths that say something about the
+/// build machine
+fn is_absolute_path(text: &[u8]) -> bool {
+    let unix = [&b"/home/"[..], b"/Users/", b"/root/", b"/mnt/", b"/tmp/"];
+    match text {
+        [drive, b':', b'\\' | b'/', rest @ ..] => {
+            drive.is_ascii_alphabetic() && rest.iter().any(|byte| *byte == b'\\' || *byte == b'/')
+        }
+        [b'\\', b'\\', rest @ ..] => rest.first().is_some_and(u8::is_ascii_alphanumeric) && rest.contains(&b'\\'),
+        _ => unix.iter().any(|prefix| text.starts_with(prefix)),
+    }
+}
+
+/// The user whose home directory `path` is in
+fn user_name(path: &str) -> Option<String> {
+    let components: Vec<&str> = path.split(['\\', '/']).filter(|component| !component.is_empty()).collect();
+    let home = components.iter().position(|component| {
+        ["users", "home", "documents and settings"].contains(&component.to_ascii_lowercase().as_str())
+    });
+    match home {
+        // the last component is the file name, not a user
+        Some(home) if home + 2 < components.len() => Some(components[home + 1].to_string()),
+        _ => (components.first() == Some(&"root")).then(|| "root".to_string()),
+    }
+}
+
+fn is_text(byte: u8) -> bool {
+    (0x20..0x7f).contains(&byte) || byte == b'\t'
+}
@@ -1,7 +1,129 @@
-use colored::Colorize;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
 use itertools::Itertools;
-use goblin::error::Result;
-use goblin::pe::{Coff, symbol::Symbol};
+use goblin::error::{Error, Result};
+use goblin::pe::{Coff, relocation::COFF_RELOCATION_SIZE};
+use goblin::pe::symbol::{Symbol, COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_UNDEFINED};
+use goblin::pe::section_table::{
+    SectionTable, IMAGE_SCN_CNT_CODE, IMAGE_SCN_LNK_INFO, IMAGE_SCN_LNK_REMOVE, IMAGE_SCN_MEM_DISCARDABLE,
+    IMAGE_SCN_MEM_EXECUTE,
+};
+
+pub mod apihash;
+pub mod archive;
+pub mod args;
+pub mod auxiliary;
+pub mod batch;
+pub mod beacon;
+pub mod bigobj;
+pub mod bloat;
+pub mod bundle;
+pub mod checksum;
+pub mod callgraph;
+pub mod capabilities;
+pub mod cluster;
+pub mod cna;
+pub mod comdat;
+pub mod compile;
+pub mod compress;
+pub mod crt;
+pub mod deadcode;
+pub mod descriptor;
+pub mod detection;
+pub mod decoration;
+pub mod diagnostic;
+pub mod dfr;
+pub mod diff;
+pub mod disasm;
+pub mod dump;
+pub mod duplicates;
+pub mod entrypoint;
+pub mod exceptions;
+pub mod exports;
+pub mod fingerprint;
+pub mod fixit;
+pub mod format_api;
+pub mod hash;
+pub mod initializers;
+pub mod injection;
+pub mod leaks;
+pub mod merge;
+pub mod meterpreter;
+pub mod names;
+pub mod opsec;
+pub mod package;
+pub mod pair;
+pub mod pe;
+pub mod profile;
+pub mod provenance;
+pub mod relocations;
+mod report;
+pub mod rule_file;
+pub mod rules;
+pub mod scaffold;
+pub mod sections;
+pub mod sliver;
+pub mod sniff;
+pub mod spec;
+pub mod stack;
+pub mod strings;
+pub mod strip;
+pub mod tls;
+pub mod symbols;
+pub mod watch;
+pub mod weak;
+pub mod writer;
+pub mod yara;
+#[cfg(all(unix, target_arch = "x86_64"))]
+pub mod beacon_mock;
+#[cfg(all(unix, target_arch = "x86_64"))]
+pub mod harness;
+#[cfg(all(unix, target_arch = "x86_64"))]
+pub mod loader;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "emulate")]
+pub mod emulate;
+
+pub use auxiliary::{AuxRecord, AuxSymbol, TranslationUnit};
+pub use bloat::SizeEntry;
+pub use capabilities::{Capability, CapabilityUse, TechniqueUse};
+pub use checksum::ChecksumIssue;
+pub use comdat::{Comdat, ComdatIssue, ComdatProblem};
+pub use compress::CompressedSize;
+pub use crt::{External, ExternalKind};
+pub use deadcode::{DeadCode, DeadFunction};
+pub use decoration::DecorationIssue;
+pub use dfr::{DfrIssue, DfrProblem};
+pub use duplicates::DuplicateSymbol;
+pub use entrypoint::{EntrypointIssue, EntrypointProblem};
+pub use exceptions::ExceptionIssue;
+pub use exports::ExportDatabase;
+pub use fingerprint::{Confidence, FingerprintDatabase, Identification};
+pub use fixit::FixIt;
+pub use format_api::{FormatIssue, FormatProblem};
+pub use hash::Hashes;
+pub use initializers::{InitializerIssue, TableKind};
+pub use injection::InjectionIssue;
+pub use leaks::PathLeak;
+pub use names::NameIssue;
+pub use opsec::OpsecIssue;
+pub use profile::ApiProfile;
+pub use provenance::Provenance;
+pub use relocations::Relocation;
+pub use rules::{Rule, RuleConfig, RuleRegistry};
+pub use sections::{BofSection, Section, SectionIssue, SectionProblem};
+pub use spec::{ArgSpec, SpecIssue};
+pub use strings::{Encoding, FoundString};
+pub use symbols::ClassifiedSymbol;
+pub use tls::{TlsIssue, TlsSymbol};
+pub use weak::{WeakExternal, WeakIssue, WeakProblem};
+pub use report::{AnalysisReport, Arch, Finding, Import, ImportKind, Severity};
+
+use writer::{Object, WriteError};
 
 /// Image file machine constants (winnt.h)
 /// https://docs.microsoft.com/en-us/windows/win32/sysinfo/image-file-machine-constants
@@ -11,7 +133,7 @@ const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
 
 /// Exported entrypoint for CS Beacon BOFs
 /// https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics/beacon-object-files_main.htm
-const BEACON_ENTRYPOINT: &'static str = "go";
+pub const BEACON_ENTRYPOINT: &str = "go";
 
 /// Exported functions supplied by Beacon (Cobalt Strike 4.1)
 /// https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/beacon.h
@@ -46,6 +168,49 @@ static BEACON_EXPORTS: &[&str] = &[
     "toWideChar",
 ];
 
+/// Functions added to Beacon's API in Cobalt Strike 4.7
+static BEACON_EXPORTS_4_7: &[&str] = &[
+    "BeaconSpawnTemporaryProcess",
+];
+
+/// Functions added to Beacon's API in Cobalt Strike 4.9
+static BEACON_EXPORTS_4_9: &[&str] = &[
+    "BeaconDataPtr",
+    "BeaconInformation",
+    // key/value store
+    "BeaconAddValue",
+    "BeaconGetValue",
+    "BeaconRemoveValue",
+];
+
+/// Functions added to Beacon's API in Cobalt Strike 4.10
+static BEACON_EXPORTS_4_10: &[&str] = &[
+    // data store
+    "BeaconDataStoreGetItem",
+    "BeaconDataStoreProtectItem",
+    "BeaconDataStoreUnprotectItem",
+    "BeaconDataStoreMaxEntries",
+    "BeaconGetCustomUserData",
+    // system call API
+    "BeaconGetSyscallInformation",
+    "BeaconVirtualAlloc",
+    "BeaconVirtualAllocEx",
+    "BeaconVirtualProtect",
+    "BeaconVirtualProtectEx",
+    "BeaconVirtualFree",
+    "BeaconVirtualQuery",
+    "BeaconGetThreadContext",
+    "BeaconSetThreadContext",
+    "BeaconResumeThread",
+    "BeaconOpenProcess",
+    "BeaconOpenThread",
+    "BeaconCloseHandle",
+    "BeaconUnmapViewOfFile",
+    "BeaconDuplicateHandle",
+    "BeaconReadProcessMemory",
+    "BeaconWriteProcessMemory",
+];
+
 /// Win32 functions built into Beacon
 /// https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics/beacon-object-files_main.htm
 static WIN32_BUILTIN: &[&str] = &[
@@ -75,131 +240,640 @@ static WIN32_MODULES: &[&str] = &[
     "WININET",
 ];
 
-pub struct Bof<'a>(Coff<'a>);
+/// Default maximum size of a task sent to Beacon (`tasks_max_size` in the
+/// Malleable C2 profile)
+/// https://hstechdocs.helpsystems.com/manuals/cobaltstrike/current/userguide/content/topics/malleable-c2_other-beacon-options.htm
+pub const DEFAULT_TASKS_MAX_SIZE: usize = 1024 * 1024;
+
+/// Settings for the checks run by [`Bof::analyze_with`]
+#[derive(Debug, Clone)]
+pub struct AnalysisOptions {
+    /// Loader the BOF is validated against
+    pub profile: Arc<dyn ApiProfile>,
+    /// Largest estimated loaded size that fits in a single task
+    pub max_size: usize,
+    /// Entrypoint to check instead of the profile's, for objects exporting
+    /// several commands
+    pub entrypoint: Option<String>,
+    /// Architecture the BOF is meant for, if objects for another one should
+    /// be flagged
+    pub arch: Option<Arch>,
+    /// Longest import name, in bytes, that isn't flagged
+    pub max_name_length: usize,
+    /// Largest worst-case stack usage, in bytes, that isn't flagged
+    pub max_stack: usize,
+    /// Exports that dynamic function resolution imports are checked against
+    pub exports: Arc<ExportDatabase>,
+    /// Also flag imports of heavily monitored functions and embedded build
+    /// paths
+    pub opsec: bool,
+    /// Also collect strings of at least this many characters
+    pub strings: Option<usize>,
+    /// Public BOFs to identify the object as
+    pub fingerprints: Option<Arc<FingerprintDatabase>>,
+    /// Arguments the BOF is expected to read
+    pub spec: Option<Arc<ArgSpec>>,
+    /// Also disassemble the entrypoint
+    pub disassemble: bool,
+    /// Also build the call graph
+    pub call_graph: bool,
+    /// Rule levels to report findings with
+    pub rules: Arc<RuleConfig>,
+    /// Checks to run after the built-in ones
+    pub extra_rules: Arc<RuleRegistry>,
+}
+
+impl Default for AnalysisOptions {
+    fn default() -> Self {
+        Self {
+            profile: Arc::new(profile::CobaltStrike::default()),
+            max_size: DEFAULT_TASKS_MAX_SIZE,
+            entrypoint: None,
+            arch: None,
+            max_name_length: names::DEFAULT_MAX_NAME_LENGTH,
+            max_stack: stack::DEFAULT_MAX_STACK,
+            exports: Arc::new(ExportDatabase::bundled()),
+            opsec: false,
+            strings: None,
+            fingerprints: None,
+            spec: None,
+            disassemble: false,
+            call_graph: false,
+            rules: Arc::new(RuleConfig::default()),
+            extra_rules: Arc::new(RuleRegistry::new()),
+        }
+    }
+}
+
+impl AnalysisOptions {
+    /// Name of the entrypoint to check
+    pub fn entrypoint(&self) -> &str {
+        self.entrypoint.as_deref().unwrap_or_else(|| self.profile.entrypoint())
+    }
+}
+
+pub struct Bof<'a> {
+    coff: Coff<'a>,
+    buffer: &'a [u8],
+}
 
 impl<'a> Bof<'a> {
+    /// Parse an object file; bigobj files and the inputs [`sniff`]
+    /// recognizes fail with an error naming what they are
     pub fn parse(buffer: &'a [u8]) -> Result<Self> {
-        Coff::parse(buffer).map(|coff| Self(coff))
+        if bigobj::is_bigobj(buffer) {
+            return Err(bigobj::error());
+        }
+        if let Some(foreign) = sniff::sniff(buffer) {
+            return Err(Error::Malformed(foreign.to_string()));
+        }
+        let coff = Coff::parse(buffer)?;
+        if let Some(foreign) = sniff::sniff_sections(&coff.sections) {
+            return Err(Error::Malformed(foreign.to_string()));
+        }
+        // offsets count the string table's size field, which goblin subtracts
+        if let Some((index, _, _)) = coff.symbols.iter().find(|(_, _, symbol)| {
+            symbol.name[..4] == [0; 4] && u32::from_le_bytes([symbol.name[4], symbol.name[5], symbol.name[6], symbol.name[7]]) < 4
+        }) {
+            return Err(Error::Malformed(format!("symbol {} has a name inside the string table size", index)));
+        }
+        Ok(Self { coff, buffer })
+    }
+
+    /// Run every check with the default options and collect the results
+    pub fn analyze(&self) -> AnalysisReport {
+        self.analyze_with(&AnalysisOptions::default())
+    }
+
+    /// Run every check and collect the results
+    pub fn analyze_with(&self, options: &AnalysisOptions) -> AnalysisReport {
+        let referenced: BTreeSet<String> = self.referenced_symbols()
+            .into_iter()
+            .filter_map(|index| self.coff.symbols.get(index).map(|(_, symbol)| self.symbol_name(&symbol)))
+            .collect();
+        let imports: Vec<Import> = self.imports()
+            .map(|symbol| {
+                let name = self.symbol_name(&symbol);
+                let mut import = classify_import(
+                    self.import_prefix().and_then(|prefix| name.strip_prefix(prefix)).unwrap_or(&name),
+                    options.profile.as_ref(),
+                );
+                import.referenced = referenced.contains(&name);
+                import
+            })
+            .collect();
+        // imports decorated for the other architecture are reported on their own
+        let decoration_issues = decoration::lint(self, options.profile.as_ref());
+        let misdecorated = |import: &Import| {
+            let prefix = self.import_prefix().unwrap_or_default();
+            decoration_issues.iter().any(|issue| issue.symbol.strip_prefix(prefix) == Some(import.name.as_str()))
+        };
+        let dfr_issues = match self.arch() {
+            Some(arch) => imports.iter()
+                .filter(|import| !misdecorated(import))
+                .flat_map(|import| dfr::lint(&import.name, arch, options.profile.as_ref(), &options.exports))
+                .collect(),
+            None => Vec::new(),
+        };
+        let sections: Vec<Section> = self.sections().iter().map(Section::new).collect();
+        let opsec_issues = match options.opsec {
+            true => opsec::lint(&imports),
+            false => Vec::new(),
+        };
+        let path_leaks = match options.opsec {
+            true => leaks::lint(self),
+            false => Vec::new(),
+        };
+        let capabilities = capabilities::summarize(&imports);
+        let techniques = capabilities::techniques(&imports);
+        let inferred_format = spec::infer_format(self);
+        let spec_issues = options.spec.as_ref()
+            .map(|spec| spec::lint(spec, &imports, inferred_format.as_deref()))
+            .unwrap_or_default();
+        let call_graph = self.call_graph();
+        let externals = self.unresolved_externals();
+        let relocations = self.relocations();
+        let comdats = self.comdats();
+        let weak_externals = self.weak_externals();
+        let entrypoints = self.entrypoints();
+        // x86 objects should have the decorated name, but might have the C one
+        let decorated = self.arch().map_or_else(|| options.entrypoint().to_string(), |arch| arch.decorate(options.entrypoint()));
+        let entrypoint = [decorated.as_str(), options.entrypoint()]
+            .into_iter()
+            .find(|name| entrypoints.iter().any(|defined| defined == name))
+            .map(str::to_string);
+        let hashes = self.hashes();
+        let identifications = options.fingerprints.as_ref()
+            .map(|database| database.identify(self, &hashes, &imports))
+            .unwrap_or_default();
+        let mut report = AnalysisReport {
+            target: options.profile.name().to_string(),
+            machine: self.coff.header.machine,
+            arch: self.arch(),
+            expected_arch: options.arch,
+            arch_supported: self.arch().is_none_or(|arch| options.profile.supports_arch(arch)),
+            hashes,
+            identifications,
+            provenance: provenance::detect(self),
+            expected_entrypoint: options.entrypoint().to_string(),
+            entrypoint: entrypoint.clone(),
+            entrypoint_issues: entrypoint::lint(self, options.entrypoint()),
+            entrypoints,
+            imports,
+            symbols: self.symbols_with(options.profile.as_ref()).collect(),
+            aux_records: self.aux_records(),
+            translation_units: self.translation_units(),
+            dfr_issues,
+            decoration_issues,
+            name_issues: names::lint(self, options.max_name_length),
+            opsec_issues,
+            path_leaks,
+            capabilities,
+            techniques,
+            inferred_format,
+            spec_issues,
+            format_issues: format_api::lint(&call_graph),
+            injection_issues: injection::lint(&call_graph),
+            exception_issues: exceptions::lint(&sections, &relocations, &externals),
+            tls_issues: tls::lint(self),
+            initializer_issues: initializers::lint(self),
+            externals,
+            section_issues: sections::lint(&sections),
+            duplicate_symbols: duplicates::lint(self),
+            comdat_issues: comdat::lint(self, &comdats, entrypoint.as_deref()),
+            checksum_issues: checksum::lint(self),
+            comdats,
+            weak_issues: weak::lint(&weak_externals),
+            weak_externals,
+            sections,
+            relocations,
+            strings: options.strings.map(|min_len| self.strings(min_len)).unwrap_or_default(),
+            disassembly: match (options.disassemble, &entrypoint) {
+                (true, Some(entrypoint)) => self.disassemble(entrypoint).unwrap_or_default(),
+                _ => Vec::new(),
+            },
+            stack: entrypoint.as_deref().and_then(|entrypoint| stack::estimate(self, &call_graph, entrypoint)),
+            max_stack: options.max_stack,
+            dead_code: deadcode::find(self, &call_graph),
+            call_graph: options.call_graph.then_some(call_graph),
+            estimated_size: self.estimated_loaded_size(),
+            compressed_size: self.compressed_size(),
+            max_size: options.max_size,
+            custom_findings: Vec::new(),
+            rules: options.rules.as_ref().clone(),
+        };
+        options.extra_rules.run(self, &mut report);
+        report
+    }
+
+    /// Digests of the file and its import list, without running any checks
+    pub fn hashes(&self) -> Hashes {
+        Hashes::new(self.buffer, self.import_names().iter().map(String::as_str))
+    }
+
+    /// Approximate number of bytes Beacon needs to receive and map the BOF
+    ///
+    /// This counts every loaded section, each relocation entry, and the name
+    /// and function pointer slot of every import.
+    pub fn estimated_loaded_size(&self) -> usize {
+        let sections: usize = self.coff.sections.iter()
+            .filter(|section| is_loaded(section))
+            .map(|section| section.size_of_raw_data.max(section.virtual_size) as usize)
+            .sum();
+        let relocations: usize = self.coff.sections.iter()
+            .filter(|section| is_loaded(section))
+            .map(|section| section.number_of_relocations as usize * COFF_RELOCATION_SIZE)
+            .sum();
+        let pointer_size = match self.arch() {
+            Some(Arch::X86) => 4,
+            _ => 8,
+        };
+        let imports: usize = self.imports()
+            .map(|symbol| self.symbol_name(&symbol).len() + 1 + pointer_size)
+            .sum();
+        sections + relocations + imports
+    }
+
+    /// Size of the object file, and of it compressed with deflate and LZNT1
+    /// as C2 channels send it
+    pub fn compressed_size(&self) -> CompressedSize {
+        CompressedSize::new(self.buffer)
+    }
+
+    pub fn arch(&self) -> Option<Arch> {
+        Arch::from_machine(self.coff.header.machine)
+    }
+
+    /// Every section in section table order
+    pub fn sections(&self) -> Vec<BofSection<'a>> {
+        self.coff.sections.iter()
+            .map(|section| BofSection::new(section, self.section_contents(section)))
+            .collect()
+    }
+
+    /// Raw contents of the first section called `name`, empty for
+    /// uninitialized data
+    ///
+    /// Long names are looked up in the string table, so `.rdata$zzzdbg`
+    /// finds the section whose header says `/4`; the `/4` spelling works
+    /// too.
+    pub fn section_data(&self, name: &str) -> Option<&'a [u8]> {
+        self.section_table(name).map(|section| self.section_contents(section))
+    }
+
+    /// Bytes of the symbol `name`, from its value to the next symbol in its
+    /// section or the section's end, with the section's name
+    ///
+    /// COFF symbols have no size, so this assumes nothing unnamed follows
+    /// the symbol, which holds for most globals and string literals.
+    pub fn symbol_data(&self, name: &str) -> Option<(String, &'a [u8])> {
+        let (_, _, symbol) = self.coff.symbols.iter().find(|(_, _, symbol)| {
+            symbol.section_number > 0 && !disasm::is_section_definition(self, symbol) && self.symbol_name(symbol) == name
+        })?;
+        let section = self.coff.sections.get(symbol.section_number as usize - 1)?;
+        let data = self.section_contents(section);
+        let (start, end) = disasm::extent(self, &symbol, data.len() as u32);
+        Some((section_name(section), &data[start as usize..end as usize]))
     }
 
+    /// Symbols defined in the first section called `name`, with their bytes
+    /// as [`Bof::symbol_data`] finds them, in offset order
+    pub fn section_symbols(&self, name: &str) -> Vec<(String, &'a [u8])> {
+        let number = match self.coff.sections.iter().position(|section| Some(section) == self.section_table(name)) {
+            Some(index) => index as i16 + 1,
+            None => return Vec::new(),
+        };
+        let data = self.section_contents(&self.coff.sections[number as usize - 1]);
+        let mut symbols: Vec<(u32, u32, String)> = self.coff.symbols.iter()
+            .map(|(_, _, symbol)| symbol)
+            .filter(|symbol| symbol.section_number == number && !disasm::is_section_definition(self, symbol))
+            .map(|symbol| {
+                let (start, end) = disasm::extent(self, &symbol, data.len() as u32);
+                (start, end, self.symbol_name(&symbol))
+            })
+            .collect();
+        symbols.sort();
+        symbols.into_iter().map(|(start, end, name)| (name, &data[start as usize..end as usize])).collect()
+    }
+
+    /// ASCII and UTF-16LE strings of at least `min_len` characters in the
+    /// sections the loader maps, in section order
+    pub fn strings(&self, min_len: usize) -> Vec<FoundString> {
+        self.coff.sections.iter()
+            .filter(|section| is_loaded(section))
+            .flat_map(|section| {
+                strings::extract(self.section_contents(section), min_len)
+                    .into_iter()
+                    .map(move |(offset, encoding, value)| FoundString {
+                        section: section_name(section),
+                        offset,
+                        encoding,
+                        value,
+                    })
+            })
+            .collect()
+    }
+
+    /// Copy of the object with the symbol `old` renamed to `new`, e.g. to
+    /// retarget the entrypoint for a loader that expects another name
+    pub fn rename_symbol(&self, old: &str, new: &str) -> std::result::Result<Vec<u8>, WriteError> {
+        let mut object = Object::from_bof(self)?;
+        object.rename_symbol(old, new)?;
+        object.to_bytes()
+    }
+
+    /// Instructions of the function `name`, with relocated operands shown as
+    /// the symbols they point at
+    pub fn disassemble(&self, name: &str) -> std::result::Result<Vec<disasm::Instruction>, disasm::DisasmError> {
+        disasm::disassemble(self, name)
+    }
+
+    /// Functions a loader can call by name: external symbols defined in
+    /// code sections, in symbol table order
+    ///
+    /// BOF packs export a command per entrypoint (`go`, `list`, `dump`).
+    pub fn entrypoints(&self) -> Vec<String> {
+        self.coff.symbols.iter()
+            .map(|(_, _, symbol)| symbol)
+            .filter(|symbol| symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL && symbol.section_number > 0)
+            .filter(|symbol| {
+                self.coff.sections.get(symbol.section_number as usize - 1).is_some_and(|section| {
+                    section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0
+                })
+            })
+            .map(|symbol| self.symbol_name(&symbol))
+            .collect()
+    }
+
+    /// Every COMDAT section, with its selection rule and COMDAT symbol
+    pub fn comdats(&self) -> Vec<Comdat> {
+        comdat::sections(self)
+    }
+
+    /// Every weak external symbol, with its default and what it resolves to
+    pub fn weak_externals(&self) -> Vec<WeakExternal> {
+        weak::externals(self)
+    }
+
+    /// Bytes of each loaded section attributed to the functions and
+    /// variables in it, largest first
+    pub fn bloat(&self) -> Vec<SizeEntry> {
+        bloat::attribute(self)
+    }
+
+    /// Static functions no entrypoint reaches, directly or through the
+    /// call graph, and no data points at
+    pub fn dead_code(&self) -> DeadCode {
+        deadcode::find(self, &self.call_graph())
+    }
+
+    /// Which functions call which functions and imports
+    pub fn call_graph(&self) -> callgraph::CallGraph {
+        callgraph::build(self)
+    }
+
+    /// Whether the symbol table has a symbol called `name`
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.coff.symbols.iter().any(|tuple| self.symbol_name(&tuple.2) == name)
+    }
+
+    /// Every symbol table entry other than file records, classified against
+    /// Cobalt Strike's API
+    pub fn symbols(&self) -> impl Iterator<Item=ClassifiedSymbol> + '_ {
+        let profile = profile::CobaltStrike::default();
+        self.coff.symbols.iter()
+            .filter_map(move |(_, _, symbol)| symbols::classify(self, &symbol, &profile))
+    }
+
+    /// Every symbol table entry other than file records, classified against
+    /// `profile`
+    pub fn symbols_with<'b>(&'b self, profile: &'b dyn ApiProfile) -> impl Iterator<Item=ClassifiedSymbol> + 'b {
+        self.coff.symbols.iter()
+            .filter_map(move |(_, _, symbol)| symbols::classify(self, &symbol, profile))
+    }
+
+    /// Auxiliary records of every symbol table entry that has them
+    pub fn aux_records(&self) -> Vec<AuxSymbol> {
+        auxiliary::records(self)
+    }
+
+    /// Source files the object was compiled from, with the functions from
+    /// each
+    pub fn translation_units(&self) -> Vec<TranslationUnit> {
+        auxiliary::translation_units(self)
+    }
+
+    /// Imported symbols, or nothing if the machine type is unsupported
     pub fn imports(&self) -> impl Iterator<Item=Symbol> + '_ {
-        self.0.symbols.iter()
+        let prefix = self.import_prefix();
+        self.coff.symbols.iter()
             .map(|tuple| { tuple.2 })
-            .filter(move |s| {
-                s.name(&self.0.strings).unwrap().starts_with(self.import_prefix())
+            .filter(move |s| match prefix {
+                Some(prefix) => self.symbol_name(s).starts_with(prefix),
+                None => false,
+            })
+    }
+
+    /// Undefined symbols, other than imports, that loaded sections relocate
+    /// against
+    ///
+    /// The loader can't resolve these, so the BOF will fail to load.
+    pub fn unresolved_externals(&self) -> Vec<External> {
+        let (arch, prefix) = match (self.arch(), self.import_prefix()) {
+            (Some(arch), Some(prefix)) => (arch, prefix),
+            _ => return Vec::new(),
+        };
+        self.referenced_symbols()
+            .into_iter()
+            .filter_map(|index| self.coff.symbols.get(index).map(|(_, symbol)| symbol))
+            // undefined symbols with a value are common symbols, which the loader allocates
+            .filter(|symbol| {
+                symbol.section_number == IMAGE_SYM_UNDEFINED
+                    && symbol.value == 0
+                    && symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL
             })
+            .map(|symbol| self.symbol_name(&symbol))
+            .filter(|name| !name.starts_with(prefix))
+            .unique()
+            .map(|name| External::new(&name, arch))
+            .collect()
     }
 
-    fn import_prefix(&self) -> &str {
-        match self.0.header.machine {
-            IMAGE_FILE_MACHINE_I386 => "__imp__",
-            IMAGE_FILE_MACHINE_AMD64 => "__imp_",
-            _ => panic!("Unsupported machine type")
+    /// Imports that no relocation in a loaded section points at, without the
+    /// import prefix
+    ///
+    /// These are left over from declarations the code never calls, and can
+    /// be removed with [`strip::strip`].
+    pub fn unreferenced_imports(&self) -> Vec<String> {
+        let prefix = match self.import_prefix() {
+            Some(prefix) => prefix,
+            None => return Vec::new(),
+        };
+        let referenced = self.referenced_symbols();
+        self.coff.symbols.iter()
+            .filter(|(index, _, _)| !referenced.contains(index))
+            .filter_map(|(_, _, symbol)| self.symbol_name(&symbol).strip_prefix(prefix).map(str::to_string))
+            .collect()
+    }
+
+    /// Indexes of the symbols relocations in loaded sections point at
+    pub(crate) fn referenced_symbols(&self) -> BTreeSet<usize> {
+        let mut referenced = BTreeSet::new();
+        for section in self.coff.sections.iter().filter(|section| is_loaded(section)) {
+            if let Ok(entries) = section.relocations(self.buffer) {
+                referenced.extend(entries.map(|entry| entry.symbol_table_index as usize));
+            }
         }
+        referenced
     }
-}
 
-pub fn parse(buffer: &[u8]) {
-    match Coff::parse(buffer) {
-        Ok(coff) => check_all(&coff),
-        Err(e) => {
-            println!("[!] Failed to parse input as COFF file");
-            println!(" -> Error: {:?}", e);
+    /// Relocations of every section the loader maps into memory
+    pub fn relocations(&self) -> Vec<Relocation> {
+        let arch = match self.arch() {
+            Some(arch) => arch,
+            None => return Vec::new(),
+        };
+        let mut relocations = Vec::new();
+        for section in self.coff.sections.iter().filter(|section| is_loaded(section)) {
+            let entries = match section.relocations(self.buffer) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries {
+                let symbol = self.coff.symbols.get(entry.symbol_table_index as usize).map(|(_, symbol)| symbol);
+                let target_section = symbol
+                    .filter(|symbol| symbol.section_number > 0)
+                    .and_then(|symbol| self.coff.sections.get(symbol.section_number as usize - 1))
+                    .map(section_name);
+                relocations.push(Relocation {
+                    section: section_name(section),
+                    offset: entry.virtual_address,
+                    typ: entry.typ,
+                    type_name: relocations::type_name(arch, entry.typ),
+                    symbol: symbol.map(|symbol| self.symbol_name(&symbol)).unwrap_or_default(),
+                    target_section,
+                    supported: relocations::is_supported(arch, entry.typ),
+                });
+            }
         }
-    };
-}
+        relocations
+    }
 
-fn print_coff(coff: &Coff) {
-    println!("COFF header machine type: 0x{:04x}", &coff.header.machine);
-    println!("COFF header number of sections: {}", &coff.header.number_of_sections);
-    // println!("COFF symbols:");
-    // for (_, _, symbol) in coff.symbols.iter() {
-    //     let name = symbol.name(&coff.strings).unwrap();
-    //     println!(" -> {}", &name);
-    // }
-    println!("COFF sections:");
-    for section in coff.sections.iter() {
-        println!(" -> {}", &section.name().unwrap_or("UNKNOWN"));
-    }
-    println!("COFF imports:");
-    for import in get_imports(coff) {
-        let name: &str = import.name(&coff.strings).unwrap();
-        println!(" -> {}", name);
+    fn import_prefix(&self) -> Option<&'static str> {
+        self.arch().map(|arch| arch.import_prefix())
     }
-}
 
-fn get_imports<'a>(coff: &'a Coff) -> impl Iterator<Item=Symbol> + 'a {
-    let prefix: &str = match coff.header.machine {
-        IMAGE_FILE_MACHINE_I386 => "__imp__",
-        IMAGE_FILE_MACHINE_AMD64 => "__imp_",
-        _ => panic!("Unsupported machine type")
-    };
-    coff.symbols.iter()
-        .map(|tuple| { tuple.2 })
-        .filter(move |s| {
-            s.name(&coff.strings).unwrap().starts_with(prefix)
+    /// Names of the imported symbols, without the import prefix
+    pub(crate) fn import_names(&self) -> Vec<String> {
+        let prefix = self.import_prefix().unwrap_or_default();
+        self.imports()
+            .map(|symbol| {
+                let name = self.symbol_name(&symbol);
+                match name.strip_prefix(prefix) {
+                    Some(stripped) => stripped.to_string(),
+                    None => name,
+                }
+            })
+            .collect()
+    }
+
+    /// Name of a symbol, with invalid UTF-8 replaced and a placeholder for
+    /// a string table offset that's out of bounds
+    fn symbol_name(&self, symbol: &Symbol) -> String {
+        let bytes = match symbol.name {
+            // goblin computes the offset without checking it's past the size
+            [0, 0, 0, 0, a, b, c, d] => {
+                let offset = u32::from_le_bytes([a, b, c, d]);
+                match self.string_table().get(offset as usize..) {
+                    Some(rest) if offset >= 4 => rest,
+                    _ => return format!("<name at {:#x}>", offset),
+                }
+            }
+            _ => &symbol.name[..],
+        };
+        String::from_utf8_lossy(bytes.split(|byte| *byte == 0).next().unwrap_or_default()).into_owned()
+    }
+
+    /// Raw string table, which follows the symbol table with its size first
+    fn string_table(&self) -> &'a [u8] {
+        let header = &self.coff.header;
+        let start = header.pointer_to_symbol_table as usize + header.number_of_symbol_table as usize * COFF_SYMBOL_SIZE;
+        let size = match self.buffer.get(start..start + 4) {
+            Some(size) => u32::from_le_bytes([size[0], size[1], size[2], size[3]]) as usize,
+            None => return &[],
+        };
+        self.buffer.get(start..start + size).unwrap_or_default()
+    }
+
+    /// Header of the first section called `name`, by its full name or the
+    /// name field of the header
+    fn section_table(&self, name: &str) -> Option<&SectionTable> {
+        self.coff.sections.iter().find(|section| {
+            section_name(section) == name || section.name.split(|byte| *byte == 0).next() == Some(name.as_bytes())
         })
-        //.collect()
+    }
+
+    /// Raw contents of a section, or nothing for uninitialized data
+    fn section_contents(&self, section: &SectionTable) -> &'a [u8] {
+        let start = section.pointer_to_raw_data as usize;
+        let end = start + section.size_of_raw_data as usize;
+        match section.pointer_to_raw_data {
+            0 => &[],
+            _ => self.buffer.get(start..end).unwrap_or_default(),
+        }
+    }
 }
 
-fn check_all(coff: &Coff) {
-    check_arch(coff);
-    check_entrypoint(coff);
-    check_imports(coff);
+/// Section flags of debug info and linker directives, which the loader skips
+const NOT_LOADED: u32 = IMAGE_SCN_LNK_REMOVE | IMAGE_SCN_LNK_INFO | IMAGE_SCN_MEM_DISCARDABLE;
+
+/// Sections that take up memory at runtime
+fn is_loaded(section: &SectionTable) -> bool {
+    section.characteristics & NOT_LOADED == 0
 }
 
-fn check_arch(coff: &Coff) {
-    let arch: &str = match coff.header.machine {
-        IMAGE_FILE_MACHINE_I386 => "x86",
-        IMAGE_FILE_MACHINE_AMD64 => "x64",
-        IMAGE_FILE_MACHINE_ARM64 => "aarch64",
-        _ => panic!("Unsupported machine type")
-    };
-    println!("[+] machine arch: {}", &arch);
+fn section_name(section: &SectionTable) -> String {
+    section.name().unwrap_or("UNKNOWN").to_string()
 }
 
-fn check_entrypoint(coff: &Coff) -> () {
-    match coff.symbols.iter()
-        .map(|tuple| { tuple.2.name(&coff.strings)
-            .expect("Unable to read symbol name")
-            .to_string()
-        })
-        .any(|s| s.eq(BEACON_ENTRYPOINT)) {
-            true => println!("[+] entrypoint: {}()", BEACON_ENTRYPOINT),
-            false => println!("{} {}", "[!] entrypoint not found:".bold().red(), BEACON_ENTRYPOINT.bold().red()),
-        }
+/// A string borrowed from one of the crate's tables; spelled as an alias so
+/// the serde derive doesn't try to borrow it from the input instead
+pub(crate) type TableStr = &'static str;
+
+/// Deserialize a string as the one in `known` it's equal to, for report
+/// fields that borrow from the crate's tables
+#[cfg(feature = "serde")]
+fn deserialize_known<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+    known: impl IntoIterator<Item = &'static str>,
+) -> std::result::Result<&'static str, D::Error> {
+    let value = <String as serde::Deserialize>::deserialize(deserializer)?;
+    known
+        .into_iter()
+        .find(|known| *known == value)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown value: {}", value)))
 }
 
-fn check_imports(coff: &Coff) {
-    let prefix: &str = match coff.header.machine {
-        IMAGE_FILE_MACHINE_I386 => "__imp__",
-        IMAGE_FILE_MACHINE_AMD64 => "__imp_",
-        _ => panic!("Unsupported machine type")
+/// Categorize an import by how the target's loader will resolve it
+fn classify_import(name: &str, profile: &dyn ApiProfile) -> Import {
+    let kind = if profile.is_export(name) {
+        ImportKind::BeaconApi
+    } else if profile.is_builtin(name) {
+        ImportKind::Win32Builtin
+    } else if profile.is_foreign_api(name) {
+        ImportKind::UnsupportedApi
+    } else if let Some((module, function)) = name.split('$').next_tuple() {
+        // remove suffix from symbol name
+        let function = function.split('@').next().unwrap().to_string();
+        let module = module.to_string();
+        // LoadLibraryA doesn't care about case, so a lower case module still resolves
+        if profile.is_known_module(&module) || profile.is_known_module(&module.to_ascii_uppercase()) {
+            ImportKind::Dynamic { module, function }
+        } else {
+            ImportKind::UnrecognizedModule { module, function }
+        }
+    } else {
+        ImportKind::Unknown
     };
-    coff.symbols.iter()
-        .map(|tuple| { tuple.2.name(&coff.strings)
-            .expect("Unable to read symbol name")
-            .to_string()
-        })
-        .filter_map(|s| match s.starts_with(prefix) {
-            true => Some(s.strip_prefix(prefix).unwrap().to_string()),
-            false => None,
-        })
-        .for_each(|name| {
-            if BEACON_EXPORTS.contains(&&name[..]) {
-                println!("[+] beacon export: {}", &name);
-            } else if WIN32_BUILTIN.contains(&&name[..]) {
-                println!("[+] beacon win32 builtin: {}", &name);
-            } else if let Some((module, function)) = &name.split('$').next_tuple() {
-                // remove suffix from symbol name
-                let function = function.split('@').next().unwrap();
-                if WIN32_MODULES.contains(module) {
-                    println!("[+] dynamic function resolution: {}${}", &module, &function);
-                } else {
-                    println!("{} {}", "[!] unrecognized win32 library:".bold().red(), &name.bold().red());
-                }
-            } else {
-                println!("{} {}", "[!] unknown import:".bold().red(), &name.bold().red());
-            }
-        });
-}
\ No newline at end of file
+    Import { name: name.to_string(), kind, referenced: true }
+}
@@ -0,0 +1,350 @@
+//! In-process COFF loader for smoke-testing BOFs
+//!
+//! Only x64 objects can be loaded, and only into an x86_64 process. The BOF
+//! runs natively with the Windows x64 calling convention, so it must not rely
+//! on anything beyond the symbols handed to it by the resolver (no TEB/PEB
+//! access, no direct syscalls).
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+use goblin::pe::relocation::{
+    IMAGE_REL_AMD64_ABSOLUTE, IMAGE_REL_AMD64_ADDR32NB, IMAGE_REL_AMD64_ADDR64, IMAGE_REL_AMD64_REL32,
+    IMAGE_REL_AMD64_REL32_5,
+};
+use goblin::pe::section_table::{SectionTable, IMAGE_SCN_CNT_UNINITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE};
+use goblin::pe::symbol::{IMAGE_SYM_ABSOLUTE, IMAGE_SYM_CLASS_WEAK_EXTERNAL, IMAGE_SYM_UNDEFINED};
+
+use crate::comdat::{self, ComdatConflict};
+use crate::{is_loaded, section_name, Arch, Bof, IMAGE_FILE_MACHINE_AMD64};
+
+/// Signature of a BOF entrypoint: `void go(char *args, int len)`
+type Entrypoint = unsafe extern "win64" fn(*mut c_char, c_int);
+
+#[derive(Debug)]
+pub enum LoadError {
+    /// Only x64 objects can be executed in-process
+    UnsupportedMachine(u16),
+    /// A section or relocation table points outside of the input buffer
+    Malformed(String),
+    /// The resolver has no address for an imported or external symbol
+    UnresolvedSymbol(String),
+    /// A relocation type the loader doesn't implement
+    UnsupportedRelocation { section: String, typ: u16 },
+    /// A relocation target is too far away to be encoded
+    RelocationOverflow { section: String, symbol: String },
+    /// The requested entrypoint isn't a function defined by the object
+    MissingEntrypoint(String),
+    /// COMDAT copies that the selection rule says can't be merged
+    Comdat(ComdatConflict),
+    /// Allocating or protecting memory failed
+    Memory(std::io::Error),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMachine(machine) => {
+                write!(f, "unsupported machine type 0x{:04x} (only x64 objects can be loaded)", machine)
+            }
+            Self::Malformed(reason) => write!(f, "malformed object: {}", reason),
+            Self::UnresolvedSymbol(name) => write!(f, "unresolved symbol: {}", name),
+            Self::UnsupportedRelocation { section, typ } => {
+                write!(f, "unsupported relocation type 0x{:04x} in section {}", typ, section)
+            }
+            Self::RelocationOverflow { section, symbol } => {
+                write!(f, "relocation against {} in section {} is out of range", symbol, section)
+            }
+            Self::MissingEntrypoint(name) => write!(f, "entrypoint not found: {}", name),
+            Self::Comdat(conflict) => write!(f, "{}", conflict),
+            Self::Memory(e) => write!(f, "memory error: {}", e),
+        }
+    }
+}
+
+impl error::Error for LoadError {}
+
+/// A BOF mapped into the current process, ready to be called
+pub struct LoadedBof {
+    base: *mut u8,
+    size: usize,
+    symbols: HashMap<String, usize>,
+    entrypoints: Vec<String>,
+}
+
+impl LoadedBof {
+    /// Map `bof` into memory, resolving every `__imp_` symbol through `resolve`
+    ///
+    /// `resolve` is given the import name with the prefix removed (e.g.
+    /// `BeaconPrintf` or `KERNEL32$GetLastError`) and returns the address of
+    /// the function to bind it to.
+    pub fn load<F>(bof: &Bof, mut resolve: F) -> Result<Self, LoadError>
+    where
+        F: FnMut(&str) -> Option<usize>,
+    {
+        if bof.coff.header.machine != IMAGE_FILE_MACHINE_AMD64 {
+            return Err(LoadError::UnsupportedMachine(bof.coff.header.machine));
+        }
+        let page = page_size();
+
+        // Like a linker, keep one copy of each COMDAT
+        let discarded = comdat::discarded(bof, &bof.comdats()).map_err(LoadError::Comdat)?;
+
+        // Layout: each mapped section on its own pages, then a table of
+        // import pointers, then storage for common symbols.
+        let mut offsets = Vec::with_capacity(bof.coff.sections.len());
+        let mut size = 0;
+        for (index, section) in bof.coff.sections.iter().enumerate() {
+            if is_loaded(section) && !discarded.contains(&index) {
+                offsets.push(Some(size));
+                size += align(section.size_of_raw_data.max(section.virtual_size) as usize, page);
+            } else {
+                offsets.push(None);
+            }
+        }
+        let imports_offset = size;
+        let import_count = bof.imports().count();
+        size += import_count * 8;
+        let commons_offset = align(size, 16);
+        size = commons_offset;
+        let mut commons = HashMap::new();
+        for (_, _, symbol) in bof.coff.symbols.iter() {
+            if symbol.section_number == IMAGE_SYM_UNDEFINED && symbol.value != 0 {
+                commons.insert(bof.symbol_name(&symbol), size);
+                size = align(size + symbol.value as usize, 16);
+            }
+        }
+        let size = align(size.max(1), page);
+
+        let mut loaded = Self {
+            base: map(size)?,
+            size,
+            symbols: HashMap::new(),
+            entrypoints: bof.entrypoints(),
+        };
+
+        // Copy initialized section data
+        for (section, offset) in bof.coff.sections.iter().zip(&offsets) {
+            if let (Some(offset), false) = (offset, section.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0) {
+                let data = section_data(bof, section)?;
+                unsafe { ptr::copy_nonoverlapping(data.as_ptr(), loaded.base.add(*offset), data.len()) };
+            }
+        }
+
+        // Resolve the address of every symbol
+        let prefix = Arch::X64.import_prefix();
+        let mut addresses = HashMap::new();
+        let mut next_import = 0;
+        // symbols of discarded COMDAT copies, bound to the kept copy by name
+        let mut copies = Vec::new();
+        for (index, _, symbol) in bof.coff.symbols.iter() {
+            let name = bof.symbol_name(&symbol);
+            let address = match symbol.section_number {
+                IMAGE_SYM_UNDEFINED if symbol.value != 0 => loaded.address(commons[&name]),
+                IMAGE_SYM_UNDEFINED => match name.strip_prefix(prefix) {
+                    Some(import) => {
+                        let function = resolve(import).ok_or_else(|| LoadError::UnresolvedSymbol(name.clone()))?;
+                        let slot = loaded.address(imports_offset + next_import * 8);
+                        next_import += 1;
+                        unsafe { (slot as *mut u64).write_unaligned(function as u64) };
+                        slot
+                    }
+                    None => continue,
+                },
+                IMAGE_SYM_ABSOLUTE => symbol.value as usize,
+                number if number > 0 => match offsets.get(number as usize - 1) {
+                    Some(Some(offset)) => loaded.address(offset + symbol.value as usize),
+                    _ if discarded.contains(&(number as usize - 1)) => {
+                        copies.push((index, name));
+                        continue;
+                    }
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            addresses.insert(index, address);
+            if symbol.storage_class == goblin::pe::symbol::IMAGE_SYM_CLASS_EXTERNAL {
+                loaded.symbols.insert(name, address);
+            }
+        }
+        for (index, name) in copies {
+            if let Some(address) = loaded.symbols.get(&name) {
+                addresses.insert(index, *address);
+            }
+        }
+        // weak externals bind to a definition of their name, or else to their default
+        for (index, _, symbol) in bof.coff.symbols.iter() {
+            if symbol.storage_class != IMAGE_SYM_CLASS_WEAK_EXTERNAL || addresses.contains_key(&index) {
+                continue;
+            }
+            let name = bof.symbol_name(&symbol);
+            let default = bof.coff.symbols.aux_weak_external(index + 1).and_then(|aux| addresses.get(&(aux.tag_index as usize)));
+            if let Some(address) = loaded.symbols.get(&name).or(default).copied() {
+                addresses.insert(index, address);
+            }
+        }
+
+        // Apply relocations
+        for (section, offset) in bof.coff.sections.iter().zip(&offsets) {
+            let offset = match offset {
+                Some(offset) => *offset,
+                None => continue,
+            };
+            let section_name = section_name(section);
+            let relocations = section
+                .relocations(bof.buffer)
+                .map_err(|e| LoadError::Malformed(format!("relocations of {}: {}", section_name, e)))?;
+            for relocation in relocations {
+                if relocation.typ == IMAGE_REL_AMD64_ABSOLUTE {
+                    continue;
+                }
+                let symbol_name = || {
+                    bof.coff
+                        .symbols
+                        .get(relocation.symbol_table_index as usize)
+                        .map(|(_, symbol)| bof.symbol_name(&symbol))
+                        .unwrap_or_default()
+                };
+                let target = match addresses.get(&(relocation.symbol_table_index as usize)) {
+                    Some(target) => *target as i64,
+                    None => return Err(LoadError::UnresolvedSymbol(symbol_name())),
+                };
+                let width = if relocation.typ == IMAGE_REL_AMD64_ADDR64 { 8 } else { 4 };
+                if relocation.virtual_address as usize + width > section.size_of_raw_data as usize {
+                    return Err(LoadError::Malformed(format!("relocation outside of section {}", section_name)));
+                }
+                let place = unsafe { loaded.base.add(offset + relocation.virtual_address as usize) };
+                match relocation.typ {
+                    IMAGE_REL_AMD64_ADDR64 => unsafe {
+                        let addend = (place as *const i64).read_unaligned();
+                        (place as *mut i64).write_unaligned(target.wrapping_add(addend));
+                    },
+                    IMAGE_REL_AMD64_ADDR32NB => unsafe {
+                        let addend = (place as *const i32).read_unaligned() as i64;
+                        let value = target - loaded.base as i64 + addend;
+                        let value = u32::try_from(value).map_err(|_| LoadError::RelocationOverflow {
+                            section: section_name.clone(),
+                            symbol: symbol_name(),
+                        })?;
+                        (place as *mut u32).write_unaligned(value);
+                    },
+                    IMAGE_REL_AMD64_REL32..=IMAGE_REL_AMD64_REL32_5 => unsafe {
+                        let distance = 4 + (relocation.typ - IMAGE_REL_AMD64_REL32) as i64;
+                        let addend = (place as *const i32).read_unaligned() as i64;
+                        let value = target + addend - (place as i64 + distance);
+                        let value = i32::try_from(value).map_err(|_| LoadError::RelocationOverflow {
+                            section: section_name.clone(),
+                            symbol: symbol_name(),
+                        })?;
+                        (place as *mut i32).write_unaligned(value);
+                    },
+                    typ => {
+                        return Err(LoadError::UnsupportedRelocation {
+                            section: section_name,
+                            typ,
+                        })
+                    }
+                }
+            }
+        }
+
+        // Make code sections executable
+        for (section, offset) in bof.coff.sections.iter().zip(&offsets) {
+            if let (Some(offset), true) = (offset, section.characteristics & IMAGE_SCN_MEM_EXECUTE != 0) {
+                let length = align(section.size_of_raw_data as usize, page);
+                protect(unsafe { loaded.base.add(*offset) }, length, libc::PROT_READ | libc::PROT_EXEC)?;
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Address of an external symbol defined by the object
+    pub fn symbol(&self, name: &str) -> Option<usize> {
+        self.symbols.get(name).copied()
+    }
+
+    /// Functions [`call`](Self::call) can call, see [`Bof::entrypoints`]
+    pub fn entrypoints(&self) -> &[String] {
+        &self.entrypoints
+    }
+
+    /// Call `entrypoint`, one of the [`entrypoints`](Self::entrypoints), with
+    /// a packed argument buffer
+    ///
+    /// # Safety
+    ///
+    /// This runs arbitrary native code from the object file. The caller must
+    /// trust the BOF and every function the resolver bound it to.
+    pub unsafe fn call(&self, entrypoint: &str, args: &[u8]) -> Result<(), LoadError> {
+        let address = match self.entrypoints.iter().any(|name| name == entrypoint) {
+            true => self.symbol(entrypoint),
+            false => None,
+        }
+        .ok_or_else(|| LoadError::MissingEntrypoint(entrypoint.to_string()))?;
+        let entry: Entrypoint = std::mem::transmute(address);
+        let mut args = args.to_vec();
+        let len = args.len() as c_int;
+        let pointer = if args.is_empty() { ptr::null_mut() } else { args.as_mut_ptr() as *mut c_char };
+        entry(pointer, len);
+        Ok(())
+    }
+
+    fn address(&self, offset: usize) -> usize {
+        self.base as usize + offset
+    }
+}
+
+impl Drop for LoadedBof {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.base as *mut libc::c_void, self.size) };
+    }
+}
+
+/// Raw contents of a section; one without any in the file is zero-filled,
+/// which the fresh mapping already is
+fn section_data<'a>(bof: &Bof<'a>, section: &SectionTable) -> Result<&'a [u8], LoadError> {
+    if section.pointer_to_raw_data == 0 {
+        return Ok(&[]);
+    }
+    let start = section.pointer_to_raw_data as usize;
+    let end = start + section.size_of_raw_data as usize;
+    bof.buffer
+        .get(start..end)
+        .ok_or_else(|| LoadError::Malformed(format!("section {} data out of bounds", section_name(section))))
+}
+
+pub(crate) fn align(value: usize, alignment: usize) -> usize {
+    value.div_ceil(alignment) * alignment
+}
+
+pub(crate) fn page_size() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE) as usize }
+}
+
+pub(crate) fn map(size: usize) -> Result<*mut u8, LoadError> {
+    let base = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if base == libc::MAP_FAILED {
+        return Err(LoadError::Memory(std::io::Error::last_os_error()));
+    }
+    Ok(base as *mut u8)
+}
+
+pub(crate) fn protect(address: *mut u8, length: usize, protection: c_int) -> Result<(), LoadError> {
+    match unsafe { libc::mprotect(address as *mut libc::c_void, length, protection) } {
+        0 => Ok(()),
+        _ => Err(LoadError::Memory(std::io::Error::last_os_error())),
+    }
+}
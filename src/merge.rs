@@ -0,0 +1,355 @@
+//! Combining several objects into one BOF
+//!
+//! This is a minimal static link: sections with the same name and flags are
+//! concatenated, symbols are appended, and undefined externals defined by
+//! another object are resolved to that definition. The result is still a
+//! relocatable object, so imports stay undefined for the loader to resolve.
+
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+
+use goblin::pe::section_table::IMAGE_SCN_LNK_COMDAT;
+use goblin::pe::symbol::{COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_STATIC, IMAGE_SYM_UNDEFINED};
+
+use crate::writer::{Object, Section, Symbol, WriteError};
+
+const IMAGE_SCN_ALIGN_MASK: u32 = 0x00f0_0000;
+const IMAGE_COMDAT_SELECT_NODUPLICATES: u8 = 1;
+const IMAGE_COMDAT_SELECT_ASSOCIATIVE: u8 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    /// Nothing to merge
+    Empty,
+    /// The objects are for different machine types
+    MachineMismatch { expected: u16, found: u16 },
+    /// Two objects define the same external symbol
+    DuplicateSymbol(String),
+    Write(WriteError),
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no objects to merge"),
+            Self::MachineMismatch { expected, found } => {
+                write!(f, "machine type {:#06x} doesn't match {:#06x}", found, expected)
+            }
+            Self::DuplicateSymbol(name) => write!(f, "{} is defined more than once", name),
+            Self::Write(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for MergeError {}
+
+impl From<WriteError> for MergeError {
+    fn from(e: WriteError) -> Self {
+        Self::Write(e)
+    }
+}
+
+/// Where an input section ended up: output section index and offset
+#[derive(Clone, Copy)]
+struct Placement {
+    section: usize,
+    offset: u32,
+}
+
+/// Link `objects` into a single object, in order
+pub fn merge(objects: &[Object]) -> Result<Object, MergeError> {
+    let first = objects.first().ok_or(MergeError::Empty)?;
+    let mut out = Object::new(first.machine);
+    // (name, flags without alignment) -> output section, for mergeable sections
+    let mut merged: HashMap<(String, u32), usize> = HashMap::new();
+    // output sections that already have a section definition symbol
+    let mut defined_sections = HashSet::new();
+    // external name -> output symbol, for definitions and undefined references
+    let mut defined: HashMap<String, usize> = HashMap::new();
+    let mut undefined: HashMap<String, usize> = HashMap::new();
+
+    for object in objects {
+        if object.machine != out.machine {
+            return Err(MergeError::MachineMismatch { expected: out.machine, found: object.machine });
+        }
+
+        let dropped = duplicate_comdats(object, &defined);
+        let mut placements = Vec::with_capacity(object.sections.len());
+        for (index, section) in object.sections.iter().enumerate() {
+            if dropped.contains(&index) {
+                placements.push(None);
+                continue;
+            }
+            placements.push(Some(place(&mut out, &mut merged, section)));
+        }
+
+        // input symbol index -> output symbol index
+        let mut map: Vec<Option<usize>> = vec![None; object.symbols.len()];
+        let mut weak = Vec::new();
+        for (index, symbol) in object.symbols.iter().enumerate() {
+            let placement = match symbol.section_number {
+                number if number > 0 => match placements.get(number as usize - 1) {
+                    Some(Some(placement)) => Some(*placement),
+                    // in a dropped COMDAT section, which an earlier object defines
+                    Some(None) => {
+                        map[index] = defined.get(&symbol.name).copied();
+                        continue;
+                    }
+                    None => return Err(WriteError::Malformed(format!("{} is in a missing section", symbol.name)).into()),
+                },
+                _ => None,
+            };
+            let definition = symbol.is_section_definition(&object.sections);
+            let mut symbol = symbol.clone();
+
+            if let Some(placement) = placement {
+                symbol.section_number = placement.section as i16 + 1;
+                if definition {
+                    if placement.offset == 0 && defined_sections.insert(placement.section) {
+                        let aux = &mut symbol.aux[0];
+                        if out.sections[placement.section].characteristics & IMAGE_SCN_LNK_COMDAT == 0 {
+                            *aux = [0; COFF_SYMBOL_SIZE];
+                        } else if aux[14] == IMAGE_COMDAT_SELECT_ASSOCIATIVE {
+                            let associated = u16::from_le_bytes([aux[12], aux[13]]) as usize;
+                            let associated = match placements.get(associated.wrapping_sub(1)) {
+                                Some(Some(placement)) => placement.section as u16 + 1,
+                                _ => 0,
+                            };
+                            aux[12..14].copy_from_slice(&associated.to_le_bytes());
+                        }
+                    } else {
+                        // relocations against the section now need its offset in the merged one
+                        symbol.aux.clear();
+                        symbol.value = placement.offset;
+                    }
+                } else {
+                    symbol.value += placement.offset;
+                }
+            }
+
+            let external = symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL;
+            if external && symbol.section_number > 0 {
+                if defined.contains_key(&symbol.name) {
+                    return Err(MergeError::DuplicateSymbol(symbol.name));
+                }
+                defined.insert(symbol.name.clone(), out.symbols.len());
+            } else if external && symbol.section_number == IMAGE_SYM_UNDEFINED {
+                if let Some(existing) = defined.get(&symbol.name).or_else(|| undefined.get(&symbol.name)).copied() {
+                    // a common symbol's value is its size, the largest one wins
+                    let existing_symbol = &mut out.symbols[existing];
+                    if existing_symbol.section_number == IMAGE_SYM_UNDEFINED {
+                        existing_symbol.value = existing_symbol.value.max(symbol.value);
+                    }
+                    map[index] = Some(existing);
+                    continue;
+                }
+                undefined.insert(symbol.name.clone(), out.symbols.len());
+            }
+            if symbol.weak_default().is_some() {
+                weak.push(out.symbols.len());
+            }
+            map[index] = Some(out.add_symbol(symbol));
+        }
+        for index in weak {
+            let default = out.symbols[index].weak_default().and_then(|default| map.get(default).copied().flatten());
+            let default = default.ok_or_else(|| WriteError::Dangling(out.symbols[index].name.clone()))?;
+            out.symbols[index].set_weak_default(default);
+        }
+
+        for (section, placement) in object.sections.iter().zip(&placements) {
+            let placement = match placement {
+                Some(placement) => placement,
+                None => continue,
+            };
+            for relocation in &section.relocations {
+                let mut relocation = *relocation;
+                relocation.symbol = map
+                    .get(relocation.symbol)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| WriteError::Dangling(object.symbols[relocation.symbol].name.clone()))?;
+                relocation.offset += placement.offset;
+                out.sections[placement.section].relocations.push(relocation);
+            }
+        }
+    }
+
+    for (index, section) in out.sections.iter().enumerate() {
+        if !defined_sections.contains(&index) {
+            let mut symbol = Symbol::new(&section.name, 0, index as i16 + 1, IMAGE_SYM_CLASS_STATIC);
+            symbol.aux.push([0; COFF_SYMBOL_SIZE]);
+            out.symbols.push(symbol);
+        }
+    }
+
+    resolve(&mut out, &defined, &undefined)?;
+    Ok(out)
+}
+
+/// Sections of `object` to leave out because an earlier object already
+/// defined their COMDAT symbol, along with the sections associated with them
+fn duplicate_comdats(object: &Object, defined: &HashMap<String, usize>) -> HashSet<usize> {
+    let mut dropped = HashSet::new();
+    let mut selections = HashMap::new();
+    for symbol in object.symbols.iter().filter(|symbol| symbol.is_section_definition(&object.sections)) {
+        selections.insert(symbol.section_number, (symbol.aux[0][14], u16::from_le_bytes([symbol.aux[0][12], symbol.aux[0][13]])));
+    }
+    for (index, section) in object.sections.iter().enumerate() {
+        let number = index as i16 + 1;
+        if section.characteristics & IMAGE_SCN_LNK_COMDAT == 0 {
+            continue;
+        }
+        let selection = selections.get(&number).map_or(0, |(selection, _)| *selection);
+        if selection == IMAGE_COMDAT_SELECT_ASSOCIATIVE || selection == IMAGE_COMDAT_SELECT_NODUPLICATES {
+            continue;
+        }
+        // the COMDAT symbol is the first symbol in the section after its definition
+        let comdat = object
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.section_number == number && !symbol.is_section_definition(&object.sections))
+            .find(|symbol| symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL);
+        if comdat.is_some_and(|symbol| defined.contains_key(&symbol.name)) {
+            dropped.insert(index);
+        }
+    }
+    for (number, (selection, associated)) in &selections {
+        if *selection == IMAGE_COMDAT_SELECT_ASSOCIATIVE && dropped.contains(&(*associated as usize).wrapping_sub(1)) {
+            dropped.insert(*number as usize - 1);
+        }
+    }
+    dropped
+}
+
+/// Append `section` to the output, merging it into an earlier section with
+/// the same name and flags unless it's a COMDAT
+fn place(out: &mut Object, merged: &mut HashMap<(String, u32), usize>, section: &Section) -> Placement {
+    let flags = section.characteristics & !IMAGE_SCN_ALIGN_MASK;
+    let key = (section.name.clone(), flags);
+    let comdat = section.characteristics & IMAGE_SCN_LNK_COMDAT != 0;
+    let target = match merged.get(&key) {
+        Some(target) if !comdat => *target,
+        _ => {
+            let mut empty = section.clone();
+            empty.data.clear();
+            empty.size = 0;
+            empty.relocations.clear();
+            out.sections.push(empty);
+            if !comdat {
+                merged.insert(key, out.sections.len() - 1);
+            }
+            out.sections.len() - 1
+        }
+    };
+
+    let target_section = &mut out.sections[target];
+    if alignment(section.characteristics) > alignment(target_section.characteristics) {
+        target_section.characteristics = flags | section.characteristics & IMAGE_SCN_ALIGN_MASK;
+    }
+    let offset = target_section.size.next_multiple_of(alignment(section.characteristics));
+    if section.data.is_empty() {
+        target_section.size = offset + section.size;
+    } else {
+        target_section.data.resize(offset as usize, 0);
+        target_section.data.extend_from_slice(&section.data);
+        target_section.size = target_section.data.len() as u32;
+    }
+    Placement { section: target, offset }
+}
+
+/// Point references to undefined externals at the object that defines them,
+/// and drop the undefined symbols
+fn resolve(
+    out: &mut Object,
+    defined: &HashMap<String, usize>,
+    undefined: &HashMap<String, usize>,
+) -> Result<(), WriteError> {
+    let mut redirect: Vec<usize> = (0..out.symbols.len()).collect();
+    let mut keep = vec![true; out.symbols.len()];
+    for (name, index) in undefined {
+        if let Some(definition) = defined.get(name) {
+            redirect[*index] = *definition;
+            keep[*index] = false;
+        }
+    }
+    for relocation in out.sections.iter_mut().flat_map(|section| &mut section.relocations) {
+        relocation.symbol = redirect[relocation.symbol];
+    }
+    for symbol in &mut out.symbols {
+        if let Some(default) = symbol.weak_default() {
+            symbol.set_weak_default(redirect[default]);
+        }
+    }
+    out.retain_symbols(&keep)
+}
+
+/// Section alignment in bytes; objects default to 16
+pub(crate) fn alignment(characteristics: u32) -> u32 {
+    match (characteristics & IMAGE_SCN_ALIGN_MASK) >> 20 {
+        0 => 16,
+        bits => 1 << (bits - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::header::COFF_MACHINE_X86_64;
+    use goblin::pe::relocation::IMAGE_REL_AMD64_REL32;
+    use goblin::pe::section_table::{
+        IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ,
+    };
+
+    use super::*;
+    use crate::writer::Relocation;
+
+    const CODE: u32 = IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ;
+
+    /// `go` calling `helper`, which the second object defines after a
+    /// static it reads
+    fn objects() -> [Object; 2] {
+        let mut caller = Object::new(COFF_MACHINE_X86_64);
+        let text = caller.add_section(Section::new(".text", CODE, vec![0xe8, 0, 0, 0, 0, 0xc3]));
+        caller.add_symbol(Symbol::new("go", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        let helper = caller.add_symbol(Symbol::new("helper", 0, IMAGE_SYM_UNDEFINED, IMAGE_SYM_CLASS_EXTERNAL));
+        caller.sections[0].relocations.push(Relocation { offset: 1, symbol: helper, typ: IMAGE_REL_AMD64_REL32 });
+
+        let mut callee = Object::new(COFF_MACHINE_X86_64);
+        let text = callee.add_section(Section::new(".text", CODE, vec![0x8b, 0x05, 0, 0, 0, 0, 0xc3]));
+        let data = callee.add_section(Section::new(".data", IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ, vec![7, 0, 0, 0]));
+        callee.add_symbol(Symbol::new("helper", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        let state = callee.add_symbol(Symbol::new("state", 0, data, IMAGE_SYM_CLASS_STATIC));
+        callee.sections[0].relocations.push(Relocation { offset: 2, symbol: state, typ: IMAGE_REL_AMD64_REL32 });
+        [caller, callee]
+    }
+
+    #[test]
+    fn merge_resolves_relocations_to_definitions() {
+        let merged = merge(&objects()).unwrap();
+        let names: Vec<&str> = merged.sections.iter().map(|section| section.name.as_str()).collect();
+        assert_eq!(names, [".text", ".data"]);
+        assert!(merged.find_symbol("helper").is_some_and(|index| merged.symbols[index].section_number == 1));
+
+        // (offset, target, target's section, target's value)
+        let text = &merged.sections[0];
+        let targets: Vec<(u32, &str, i16, u32)> = text
+            .relocations
+            .iter()
+            .map(|relocation| {
+                let symbol = &merged.symbols[relocation.symbol];
+                (relocation.offset, symbol.name.as_str(), symbol.section_number, symbol.value)
+            })
+            .collect();
+        assert_eq!(targets, [(1, "helper", 1, 16), (18, "state", 2, 0)]);
+        assert_eq!(text.data[16], 0x8b);
+
+        let reparsed = Object::parse(&merged.to_bytes().unwrap()).unwrap();
+        assert_eq!(reparsed.to_bytes().unwrap(), merged.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn duplicate_definitions_fail() {
+        let [caller, _] = objects();
+        assert_eq!(merge(&[caller.clone(), caller]), Err(MergeError::DuplicateSymbol("go".to_string())));
+    }
+}
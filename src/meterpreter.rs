@@ -0,0 +1,86 @@
+//! Metasploit bofloader support
+//!
+//! Meterpreter runs BOFs with the `execute_bof` command of its bofloader
+//! extension, which embeds TrustedSec's COFFLoader. It loads x64 and x86
+//! objects only, and packs arguments itself from a `bof_pack()` style format
+//! string using the same layout as Aggressor.
+//! https://docs.metasploit.com/docs/using-metasploit/advanced/meterpreter/meterpreter-executebof-command.html
+
+/// Beacon API functions provided by the bofloader extension
+pub static METERPRETER_EXPORTS: &[&str] = &[
+    // data API
+    "BeaconDataParse",
+    "BeaconDataInt",
+    "BeaconDataShort",
+    "BeaconDataLength",
+    "BeaconDataExtract",
+    // format API
+    "BeaconFormatAlloc",
+    "BeaconFormatReset",
+    "BeaconFormatFree",
+    "BeaconFormatAppend",
+    "BeaconFormatPrintf",
+    "BeaconFormatToString",
+    "BeaconFormatInt",
+    // Output Functions
+    "BeaconPrintf",
+    "BeaconOutput",
+    // Token Functions
+    "BeaconUseToken",
+    "BeaconRevertToken",
+    "BeaconIsAdmin",
+    // Spawn+Inject Functions
+    "BeaconGetSpawnTo",
+    "BeaconSpawnTemporaryProcess",
+    "BeaconInjectProcess",
+    "BeaconInjectTemporaryProcess",
+    "BeaconCleanupProcess",
+    // Utility Functions
+    "toWideChar",
+];
+
+/// Win32 functions resolved internally by COFFLoader
+pub static METERPRETER_BUILTIN: &[&str] = &[
+    "LoadLibraryA",
+    "GetModuleHandleA",
+    "FreeLibrary",
+    "GetProcAddress",
+];
+
+/// Meterpreter console command that runs `bof` with the given arguments
+///
+/// `values` are passed through as written, since `execute_bof` parses and
+/// packs them itself according to `format`.
+pub fn execute_bof_command<S: AsRef<str>>(bof: &str, entrypoint: &str, format: &str, values: &[S]) -> String {
+    let mut command = format!("execute_bof {}", quote(bof));
+    if entrypoint != crate::BEACON_ENTRYPOINT {
+        command.push_str(&format!(" --entry {}", quote(entrypoint)));
+    }
+    if !format.is_empty() {
+        command.push_str(&format!(" --format-string {}", quote(format)));
+        for value in values {
+            command.push(' ');
+            command.push_str(&quote(value.as_ref()));
+        }
+    }
+    command
+}
+
+/// Quote a word for the console's shell-style argument splitting, if needed
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:@=+,".contains(c));
+    if plain {
+        return value.to_string();
+    }
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
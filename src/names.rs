@@ -0,0 +1,69 @@
+//! Import names loaders may fail to resolve
+//!
+//! Names longer than eight bytes live in the string table, and minimal
+//! loaders copy them into fixed-size buffers that truncate them, or split
+//! `MODULE$Function` with parsing that stops at whitespace or assumes ASCII.
+//! Either way the import fails at runtime without saying why.
+
+use std::fmt;
+
+use crate::{Bof, Severity};
+
+/// Import names longer than this are flagged by default: loaders that copy
+/// names into a 128-byte buffer with room for the terminator
+pub const DEFAULT_MAX_NAME_LENGTH: usize = 127;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NameProblem {
+    /// Longer than the configured limit, in bytes
+    TooLong { length: usize, limit: usize },
+    /// Whitespace, control or non-ASCII characters
+    UnusualCharacters,
+}
+
+/// An import whose name some loaders can't handle
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NameIssue {
+    /// Symbol name as it appears in the symbol table
+    pub symbol: String,
+    pub problem: NameProblem,
+}
+
+impl NameIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for NameIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.problem {
+            NameProblem::TooLong { length, limit } => write!(
+                f,
+                "import name of {} bytes is longer than some loaders handle ({}): {}",
+                length, limit, self.symbol
+            ),
+            NameProblem::UnusualCharacters => {
+                write!(f, "import name with characters some loaders can't parse: \"{}\"", self.symbol.escape_debug())
+            }
+        }
+    }
+}
+
+/// Find imports named with more than `max_length` bytes or with characters
+/// other than printable ASCII
+pub fn lint(bof: &Bof, max_length: usize) -> Vec<NameIssue> {
+    let mut issues = Vec::new();
+    for symbol in bof.imports() {
+        let name = bof.symbol_name(&symbol);
+        if !name.chars().all(|c| c.is_ascii_graphic()) {
+            issues.push(NameIssue { symbol: name.clone(), problem: NameProblem::UnusualCharacters });
+        }
+        if name.len() > max_length {
+            issues.push(NameIssue { problem: NameProblem::TooLong { length: name.len(), limit: max_length }, symbol: name });
+        }
+    }
+    issues
+}
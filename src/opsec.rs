@@ -0,0 +1,90 @@
+//! Imports that draw attention
+//!
+//! These all work, but EDR products hook or log them heavily, so a BOF that
+//! uses them deserves a second look before it goes into an engagement kit.
+
+use std::fmt;
+
+use crate::{Import, ImportKind, Severity};
+
+/// Functions worth flagging, by name without an `A`/`W` suffix, with how
+/// loud using them is and why
+static SUSPICIOUS: &[(&str, Severity, &str)] = &[
+    ("CreateRemoteThread", Severity::Error, "remote thread creation is the classic injection signal"),
+    ("CreateRemoteThreadEx", Severity::Error, "remote thread creation is the classic injection signal"),
+    ("NtCreateThreadEx", Severity::Error, "remote thread creation is the classic injection signal"),
+    ("RtlCreateUserThread", Severity::Error, "remote thread creation is the classic injection signal"),
+    ("WinExec", Severity::Error, "spawns a process through a legacy API that is rarely used legitimately"),
+    ("URLDownloadToFile", Severity::Error, "downloads to disk and is a well-known stager API"),
+    ("MiniDumpWriteDump", Severity::Error, "process dumping, usually of lsass, is closely watched"),
+    ("AmsiScanBuffer", Severity::Error, "importing AMSI functions usually means patching them"),
+    ("AmsiInitialize", Severity::Error, "importing AMSI functions usually means patching them"),
+    ("EtwEventWrite", Severity::Error, "importing ETW functions usually means patching them"),
+    ("EtwEventWriteFull", Severity::Error, "importing ETW functions usually means patching them"),
+    ("NtTraceEvent", Severity::Error, "importing ETW functions usually means patching them"),
+    ("VirtualAllocEx", Severity::Warning, "cross-process allocation is a step of most injection techniques"),
+    ("NtAllocateVirtualMemory", Severity::Warning, "cross-process allocation is a step of most injection techniques"),
+    ("WriteProcessMemory", Severity::Warning, "cross-process writes are a step of most injection techniques"),
+    ("NtWriteVirtualMemory", Severity::Warning, "cross-process writes are a step of most injection techniques"),
+    ("VirtualProtectEx", Severity::Warning, "making another process's memory executable is an injection signal"),
+    ("NtMapViewOfSection", Severity::Warning, "section mapping into another process is an injection technique"),
+    ("SetThreadContext", Severity::Warning, "thread hijacking redirects execution in another process"),
+    ("QueueUserAPC", Severity::Warning, "APC queuing is an injection technique"),
+    ("NtQueueApcThread", Severity::Warning, "APC queuing is an injection technique"),
+    ("CreateProcess", Severity::Warning, "new processes are logged with their command line and parent"),
+    ("CreateProcessWithLogon", Severity::Warning, "new processes are logged with their command line and parent"),
+    ("ShellExecute", Severity::Warning, "new processes are logged with their command line and parent"),
+    ("system", Severity::Warning, "runs a command through cmd.exe"),
+    ("SetWindowsHookEx", Severity::Warning, "global hooks are a keylogging and injection signal"),
+    ("OpenProcessToken", Severity::Warning, "token access is watched for privilege escalation"),
+    ("AdjustTokenPrivileges", Severity::Warning, "enabling privileges such as SeDebugPrivilege is watched"),
+    ("LogonUser", Severity::Warning, "creates logon events that stand out in authentication logs"),
+];
+
+/// A noisy import and why it's noisy
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OpsecIssue {
+    /// Import name, without the import prefix
+    pub import: String,
+    pub severity: Severity,
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_rationale"))]
+    pub rationale: crate::TableStr,
+}
+
+/// Read a rationale back as the matching entry of the table
+#[cfg(feature = "serde")]
+fn deserialize_rationale<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    crate::deserialize_known(deserializer, SUSPICIOUS.iter().map(|(_, _, rationale)| *rationale))
+}
+
+impl fmt::Display for OpsecIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "heavily monitored api: {} ({})", self.import, self.rationale)
+    }
+}
+
+/// Flag imports of heavily monitored functions
+pub fn lint(imports: &[Import]) -> Vec<OpsecIssue> {
+    imports
+        .iter()
+        .filter_map(|import| {
+            let function = match &import.kind {
+                ImportKind::Dynamic { function, .. } | ImportKind::UnrecognizedModule { function, .. } => function,
+                _ => &import.name,
+            };
+            let (severity, rationale) = lookup(function)?;
+            Some(OpsecIssue { import: import.name.clone(), severity, rationale })
+        })
+        .collect()
+}
+
+/// How loud calling `function` is and why, if it's heavily monitored; the
+/// name can have an `A`/`W` suffix and `@N` decoration
+pub(crate) fn lookup(function: &str) -> Option<(Severity, &'static str)> {
+    let function = function.split_once('@').map_or(function, |(function, _)| function);
+    let (_, severity, rationale) = SUSPICIOUS.iter().find(|(name, _, _)| {
+        function == *name || function.strip_suffix(['A', 'W']) == Some(*name)
+    })?;
+    Some((*severity, rationale))
+}
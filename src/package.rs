@@ -0,0 +1,304 @@
+//! Encrypted BOFs for custom stagers
+//!
+//! Kits that fetch BOFs with their own downloader rather than through the
+//! C2 channel often keep them encrypted at rest and in transit, so that
+//! neither the file share nor a proxy sees a COFF object. This runs an
+//! object through a chain of [`Transform`]s and writes a [`Manifest`] with
+//! what the stager needs to undo them, last to first, and the digest to
+//! report once it has the object back.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::hash::hex;
+
+/// A reversible step applied to the object
+pub trait Transform {
+    /// Name the stager dispatches on
+    fn name(&self) -> &'static str;
+    fn apply(&self, data: &[u8]) -> Vec<u8>;
+    /// Keys and anything else the stager needs to undo the step, as hex
+    fn parameters(&self) -> BTreeMap<&'static str, String>;
+}
+
+/// XOR with a repeating key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xor {
+    pub key: Vec<u8>,
+}
+
+impl Xor {
+    /// XOR with `length` random bytes
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn generate(length: usize) -> Result<Self, getrandom::Error> {
+        let mut key = vec![0; length.max(1)];
+        getrandom::getrandom(&mut key)?;
+        Ok(Xor { key })
+    }
+}
+
+impl Transform for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        data.iter().zip(self.key.iter().cycle()).map(|(byte, key)| byte ^ key).collect()
+    }
+
+    fn parameters(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([("key", hex(&self.key))])
+    }
+}
+
+/// AES-256 in CBC mode with PKCS#7 padding, which `BCryptDecrypt` undoes
+/// with `BCRYPT_BLOCK_PADDING`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Aes256Cbc {
+    pub key: [u8; 32],
+    pub iv: [u8; 16],
+}
+
+impl Aes256Cbc {
+    /// AES with a random key and IV
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn generate() -> Result<Self, getrandom::Error> {
+        let mut aes = Aes256Cbc { key: [0; 32], iv: [0; 16] };
+        getrandom::getrandom(&mut aes.key)?;
+        getrandom::getrandom(&mut aes.iv)?;
+        Ok(aes)
+    }
+}
+
+impl Transform for Aes256Cbc {
+    fn name(&self) -> &'static str {
+        "aes-256-cbc"
+    }
+
+    fn apply(&self, data: &[u8]) -> Vec<u8> {
+        let schedule = aes::expand(&self.key);
+        let padding = 16 - data.len() % 16;
+        let mut padded = data.to_vec();
+        padded.resize(data.len() + padding, padding as u8);
+        let mut previous = self.iv;
+        let mut output = Vec::with_capacity(padded.len());
+        for block in padded.chunks(16) {
+            for (byte, chained) in previous.iter_mut().zip(block) {
+                *byte ^= chained;
+            }
+            aes::encrypt(&schedule, &mut previous);
+            output.extend_from_slice(&previous);
+        }
+        output
+    }
+
+    fn parameters(&self) -> BTreeMap<&'static str, String> {
+        BTreeMap::from([("key", hex(&self.key)), ("iv", hex(&self.iv))])
+    }
+}
+
+/// A transform as the manifest records it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Step {
+    pub name: String,
+    #[serde(flatten)]
+    pub parameters: BTreeMap<String, String>,
+}
+
+/// What a stager needs to turn the packaged file back into the object and
+/// report that it did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Manifest {
+    /// Object file name
+    pub file: String,
+    pub size: usize,
+    /// SHA-256 of the object, to check and report after undoing the steps
+    pub sha256: String,
+    pub packaged_size: usize,
+    pub packaged_sha256: String,
+    /// Steps in the order they were applied; undo them last to first
+    pub transforms: Vec<Step>,
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("manifests serialize to JSON") + "\n"
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let steps: Vec<&str> = self.transforms.iter().map(|step| step.name.as_str()).collect();
+        write!(
+            f,
+            "{}: {} bytes -> {} bytes ({})",
+            self.file,
+            self.size,
+            self.packaged_size,
+            steps.join(" -> ")
+        )
+    }
+}
+
+/// `object` run through `transforms` in order, with the manifest for it
+pub fn package(file: &str, object: &[u8], transforms: &[Box<dyn Transform>]) -> (Vec<u8>, Manifest) {
+    let mut data = object.to_vec();
+    let mut steps = Vec::new();
+    for transform in transforms {
+        data = transform.apply(&data);
+        steps.push(Step {
+            name: transform.name().to_string(),
+            parameters: transform.parameters().into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+        });
+    }
+    let manifest = Manifest {
+        file: file.to_string(),
+        size: object.len(),
+        sha256: hex(&Sha256::digest(object)),
+        packaged_size: data.len(),
+        packaged_sha256: hex(&Sha256::digest(&data)),
+        transforms: steps,
+    };
+    (data, manifest)
+}
+
+/// The AES block cipher, encryption only since CBC never needs the inverse
+/// here
+mod aes {
+    const ROUNDS: usize = 14;
+
+    const SBOX: [u8; 256] = [
+        0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+        0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+        0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+        0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+        0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+        0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+        0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+        0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+        0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+        0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+        0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+        0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+        0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+        0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+        0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+        0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16,
+    ];
+
+    /// Round keys, one 16 byte block per round and one before the first
+    pub(super) type Schedule = [[u8; 16]; ROUNDS + 1];
+
+    pub(super) fn expand(key: &[u8; 32]) -> Schedule {
+        let mut words = [[0u8; 4]; 4 * (ROUNDS + 1)];
+        for (index, word) in key.chunks(4).enumerate() {
+            words[index].copy_from_slice(word);
+        }
+        let mut rcon = 1u8;
+        for index in 8..words.len() {
+            let mut word = words[index - 1];
+            if index % 8 == 0 {
+                word.rotate_left(1);
+                word = word.map(|byte| SBOX[byte as usize]);
+                word[0] ^= rcon;
+                rcon = double(rcon);
+            } else if index % 8 == 4 {
+                word = word.map(|byte| SBOX[byte as usize]);
+            }
+            for (byte, earlier) in word.iter_mut().zip(words[index - 8]) {
+                *byte ^= earlier;
+            }
+            words[index] = word;
+        }
+        let mut schedule = [[0; 16]; ROUNDS + 1];
+        for (round, key) in schedule.iter_mut().enumerate() {
+            for (column, word) in words[round * 4..round * 4 + 4].iter().enumerate() {
+                key[column * 4..column * 4 + 4].copy_from_slice(word);
+            }
+        }
+        schedule
+    }
+
+    /// Multiplication by x in GF(2^8)
+    fn double(byte: u8) -> u8 {
+        (byte << 1) ^ if byte & 0x80 != 0 { 0x1b } else { 0 }
+    }
+
+    pub(super) fn encrypt(schedule: &Schedule, block: &mut [u8; 16]) {
+        add(block, &schedule[0]);
+        for (round, key) in schedule.iter().enumerate().skip(1) {
+            for byte in block.iter_mut() {
+                *byte = SBOX[*byte as usize];
+            }
+            // the block is column-major, so row r moves r columns left
+            let state = *block;
+            for row in 1..4 {
+                for column in 0..4 {
+                    block[column * 4 + row] = state[(column + row) % 4 * 4 + row];
+                }
+            }
+            if round != ROUNDS {
+                for column in block.chunks_mut(4) {
+                    let [a, b, c, d] = [column[0], column[1], column[2], column[3]];
+                    let all = a ^ b ^ c ^ d;
+                    column[0] ^= all ^ double(a ^ b);
+                    column[1] ^= all ^ double(b ^ c);
+                    column[2] ^= all ^ double(c ^ d);
+                    column[3] ^= all ^ double(d ^ a);
+                }
+            }
+            add(block, key);
+        }
+    }
+
+    fn add(block: &mut [u8; 16], key: &[u8; 16]) {
+        for (byte, key) in block.iter_mut().zip(key) {
+            *byte ^= key;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn aes_256_known_answers() {
+        // FIPS-197 appendix C.3
+        let key: [u8; 32] = bytes("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f").try_into().unwrap();
+        let mut block: [u8; 16] = bytes("00112233445566778899aabbccddeeff").try_into().unwrap();
+        aes::encrypt(&aes::expand(&key), &mut block);
+        assert_eq!(hex(&block), "8ea2b7ca516745bfeafc49904b496089");
+
+        // FIPS-197 appendix A.3, words 8 to 11
+        let key: [u8; 32] = bytes("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap();
+        assert_eq!(hex(&aes::expand(&key)[2]), "9ba354118e6925afa51a8b5f2067fcde");
+    }
+
+    #[test]
+    fn aes_256_cbc_known_answers() {
+        // SP 800-38A F.2.5, followed by a block of padding
+        let aes = Aes256Cbc {
+            key: bytes("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4").try_into().unwrap(),
+            iv: bytes("000102030405060708090a0b0c0d0e0f").try_into().unwrap(),
+        };
+        let encrypted = aes.apply(&bytes("6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51"));
+        assert_eq!(encrypted.len(), 48);
+        assert_eq!(hex(&encrypted[..32]), "f58c4c04d6e5f1ba779eabfb5f7bfbd69cfc4e967edb808d679f777bc6702c7d");
+        assert_eq!(aes.apply(&[0; 15]).len(), 16);
+    }
+
+    #[test]
+    fn xor_undoes_itself() {
+        let xor = Xor { key: vec![0x13, 0x37, 0x42] };
+        let data = b"\x64\x86\x02\x00 object".to_vec();
+        assert_eq!(xor.apply(&xor.apply(&data)), data);
+    }
+}
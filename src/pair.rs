@@ -0,0 +1,122 @@
+//! Cross-checking the x64 and x86 builds of a BOF
+//!
+//! BOF kits conventionally ship `foo.x64.o` next to `foo.x86.o`, and the
+//! two are meant to behave the same. Imports added to one build but not the
+//! other usually mean an `#ifdef` drifted or a rebuild was missed.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::{AnalysisReport, Arch, Severity};
+
+/// Separators seen between the name and the architecture tag
+static SEPARATORS: &[char] = &['.', '_', '-'];
+
+/// Path of the other architecture's build, found by swapping the `x64`/`x86`
+/// tag in the file name (`foo.x64.o` and `foo.x86.o`, `foo_x64.o` and so on)
+pub fn sibling(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_str()?;
+    let tag = arch_tag(name)?;
+    let other = match &name[tag.clone()] {
+        "x64" => "x86",
+        _ => "x64",
+    };
+    Some(path.with_file_name(format!("{}{}{}", &name[..tag.start], other, &name[tag.end..])))
+}
+
+/// Position of the `x64` or `x86` tag in a file name, after a separator and
+/// before the extension
+pub fn arch_tag(name: &str) -> Option<Range<usize>> {
+    for arch in ["x64", "x86"] {
+        for separator in SEPARATORS {
+            let tag = format!("{}{}", separator, arch);
+            let position = match name.rfind(&tag) {
+                Some(position) => position + separator.len_utf8(),
+                None => continue,
+            };
+            let rest = &name[position + arch.len()..];
+            if rest.is_empty() || rest.starts_with('.') {
+                return Some(position..position + arch.len());
+            }
+        }
+    }
+    None
+}
+
+/// A difference between two builds that should match
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PairIssue {
+    /// The two objects aren't an x64 and an x86 build
+    NotAPair(Option<Arch>, Option<Arch>),
+    /// One build has the entrypoint and the other doesn't; the entrypoint
+    /// name and the build missing it
+    EntrypointMismatch { entrypoint: String, missing_in: Arch },
+    /// An import only one of the builds has
+    ImportDrift { import: String, only_in: Arch },
+}
+
+impl PairIssue {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::NotAPair(..) | Self::EntrypointMismatch { .. } => Severity::Error,
+            Self::ImportDrift { .. } => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for PairIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arch = |arch: &Option<Arch>| arch.map_or("unknown".to_string(), |arch| arch.to_string());
+        match self {
+            Self::NotAPair(a, b) => write!(f, "builds aren't an x64/x86 pair: {} and {}", arch(a), arch(b)),
+            Self::EntrypointMismatch { entrypoint, missing_in } => {
+                write!(f, "entrypoint {} is missing from the {} build", entrypoint, missing_in)
+            }
+            Self::ImportDrift { import, only_in } => write!(f, "import only in the {} build: {}", only_in, import),
+        }
+    }
+}
+
+/// Compare the reports of two builds of the same BOF
+pub fn compare(a: &AnalysisReport, b: &AnalysisReport) -> Vec<PairIssue> {
+    let (a_arch, b_arch) = match (a.arch, b.arch) {
+        (Some(a_arch), Some(b_arch)) if a_arch != b_arch && a_arch != Arch::Arm64 && b_arch != Arch::Arm64 => {
+            (a_arch, b_arch)
+        }
+        (a_arch, b_arch) => return vec![PairIssue::NotAPair(a_arch, b_arch)],
+    };
+    let mut issues = Vec::new();
+    match (&a.entrypoint, &b.entrypoint) {
+        (Some(entrypoint), None) => {
+            issues.push(PairIssue::EntrypointMismatch { entrypoint: entrypoint.clone(), missing_in: b_arch })
+        }
+        (None, Some(entrypoint)) => {
+            issues.push(PairIssue::EntrypointMismatch { entrypoint: entrypoint.clone(), missing_in: a_arch })
+        }
+        _ => {}
+    }
+
+    let (a_imports, b_imports) = (imports(a), imports(b));
+    for import in a_imports.difference(&b_imports) {
+        issues.push(PairIssue::ImportDrift { import: import.clone(), only_in: a_arch });
+    }
+    for import in b_imports.difference(&a_imports) {
+        issues.push(PairIssue::ImportDrift { import: import.clone(), only_in: b_arch });
+    }
+    issues
+}
+
+/// Import names without the x86 `@N` stdcall decoration, so both builds'
+/// names compare equal
+fn imports(report: &AnalysisReport) -> BTreeSet<String> {
+    report
+        .imports
+        .iter()
+        .map(|import| match import.name.rsplit_once('@') {
+            Some((name, decoration)) if decoration.bytes().all(|byte| byte.is_ascii_digit()) => name.to_string(),
+            _ => import.name.clone(),
+        })
+        .collect()
+}
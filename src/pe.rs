@@ -0,0 +1,73 @@
+//! Linked PE images passed where a BOF was expected
+//!
+//! An EXE or DLL can't be loaded as a BOF, but its exports show what would
+//! have to become the BOF's entrypoint, and its imports what the port would
+//! resolve as `MODULE$Function`.
+
+use std::fmt;
+
+use goblin::error::Result;
+use goblin::pe::characteristic::IMAGE_FILE_DLL;
+use goblin::pe::PE;
+
+/// Headers, exports and imports of a PE image
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PeImage {
+    pub machine: u16,
+    pub dll: bool,
+    /// Exported function names, or the address of unnamed ones
+    pub exports: Vec<String>,
+    /// Imports spelled the way a BOF resolves them, `MODULE$Function`
+    pub imports: Vec<String>,
+}
+
+impl PeImage {
+    /// How to get from the image to a BOF
+    pub fn hint(&self) -> &'static str {
+        match self.dll {
+            true => "port the export you need to a go(char *args, int len) entrypoint compiled with -c, \
+                     or run the DLL from a reflective loader BOF that maps it in memory",
+            false => "compile the sources with -c instead of linking them, moving main() into \
+                      go(char *args, int len)",
+        }
+    }
+}
+
+impl fmt::Display for PeImage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "this is a linked PE {} (machine 0x{:04x}), not a BOF",
+            if self.dll { "DLL" } else { "executable" },
+            self.machine
+        )
+    }
+}
+
+/// Parse the headers of a PE image
+pub fn inspect(buffer: &[u8]) -> Result<PeImage> {
+    let pe = PE::parse(buffer)?;
+    let exports = pe
+        .exports
+        .iter()
+        .map(|export| match export.name {
+            Some(name) => name.to_string(),
+            None => format!("unnamed export at 0x{:x}", export.rva),
+        })
+        .collect();
+    let imports = pe
+        .imports
+        .iter()
+        .map(|import| {
+            let module = import.dll.rsplit_once('.').map_or(import.dll, |(module, _)| module).to_uppercase();
+            format!("{}${}", module, import.name)
+        })
+        .collect();
+    Ok(PeImage {
+        machine: pe.header.coff_header.machine,
+        dll: pe.header.coff_header.characteristics & IMAGE_FILE_DLL != 0,
+        exports,
+        imports,
+    })
+}
@@ -0,0 +1,464 @@
+//! API profiles describing what each C2 framework's COFF loader provides
+//!
+//! Every loader exposes its own set of API functions to BOFs, resolves a few
+//! Win32 functions internally, and calls a fixed entrypoint. The import and
+//! entrypoint checks ask an [`ApiProfile`] rather than assuming Beacon.
+//!
+//! Besides the built-in profiles, a profile can be loaded from a TOML or
+//! JSON file, optionally extending a built-in one:
+//!
+//! ```toml
+//! name = "cs-4.9"
+//! extends = "cs"
+//! exports = ["BeaconInformation", "BeaconGetValue"]
+//! builtins = []
+//! modules = ["DNSAPI"]
+//! ```
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{
+    meterpreter, sliver, Arch, BEACON_ENTRYPOINT, BEACON_EXPORTS, BEACON_EXPORTS_4_10, BEACON_EXPORTS_4_7,
+    BEACON_EXPORTS_4_9, WIN32_BUILTIN, WIN32_MODULES,
+};
+
+/// Exported entrypoint for Brute Ratel BOFs
+/// https://bruteratel.com/tabs/badger/coffs/
+pub const BADGER_ENTRYPOINT: &str = "coffee";
+
+/// Exported functions supplied by Brute Ratel's badger
+/// https://github.com/paranoidninja/BRC4-BOF-Artillery/blob/main/badger_exports.h
+pub static BADGER_EXPORTS: &[&str] = &[
+    // output
+    "BadgerDispatch",
+    "BadgerDispatchW",
+    // string and memory helpers
+    "BadgerStrlen",
+    "BadgerWcslen",
+    "BadgerMemcpy",
+    "BadgerMemset",
+    "BadgerStrcmp",
+    "BadgerWcscmp",
+    "BadgerAtoi",
+    // allocation
+    "BadgerAlloc",
+    "BadgerFree",
+    // utility
+    "BadgerSetdebug",
+    "BadgerGetBufferSize",
+];
+
+/// Exported functions supplied by Havoc's Demon
+/// https://github.com/HavocFramework/Havoc/blob/main/payloads/Demon/src/core/CoffeeLdr.c
+pub static HAVOC_EXPORTS: &[&str] = &[
+    // data API
+    "BeaconDataParse",
+    "BeaconDataInt",
+    "BeaconDataShort",
+    "BeaconDataLength",
+    "BeaconDataExtract",
+    // format API
+    "BeaconFormatAlloc",
+    "BeaconFormatReset",
+    "BeaconFormatFree",
+    "BeaconFormatAppend",
+    "BeaconFormatPrintf",
+    "BeaconFormatToString",
+    "BeaconFormatInt",
+    // Output Functions
+    "BeaconPrintf",
+    "BeaconOutput",
+    // Token Functions
+    "BeaconUseToken",
+    "BeaconRevertToken",
+    "BeaconIsAdmin",
+    // Spawn+Inject Functions
+    "BeaconGetSpawnTo",
+    "BeaconSpawnTemporaryProcess",
+    "BeaconInjectProcess",
+    "BeaconInjectTemporaryProcess",
+    "BeaconCleanupProcess",
+    // Utility Functions
+    "toWideChar",
+];
+
+/// Win32 functions resolved internally by Havoc's Demon
+pub static HAVOC_BUILTIN: &[&str] = &[
+    "LoadLibraryA",
+    "LoadLibraryW",
+    "GetModuleHandleA",
+    "GetModuleHandleW",
+    "GetProcAddress",
+    "FreeLibrary",
+];
+
+/// The symbols a C2 framework's COFF loader resolves for a BOF
+pub trait ApiProfile: fmt::Debug + Send + Sync {
+    /// Short name used to select the profile, e.g. `cs`
+    fn name(&self) -> &str;
+
+    /// Symbol the loader calls to run the BOF
+    fn entrypoint(&self) -> &str;
+
+    /// Whether the loader can run objects built for `arch`
+    fn supports_arch(&self, _arch: Arch) -> bool {
+        true
+    }
+
+    /// Whether the loader exports the API function `name` to BOFs
+    fn is_export(&self, name: &str) -> bool;
+
+    /// Whether the loader resolves the Win32 function `name` internally
+    fn is_builtin(&self, name: &str) -> bool;
+
+    /// Whether `name` is another loader's API function that this loader
+    /// doesn't provide
+    fn is_foreign_api(&self, name: &str) -> bool {
+        !self.is_export(name) && (BeaconApiVersion::LATEST.provides(name) || BADGER_EXPORTS.contains(&name))
+    }
+
+    /// Whether `module` is a library dynamic function resolution imports
+    /// are expected to name
+    fn is_known_module(&self, module: &str) -> bool {
+        WIN32_MODULES.contains(&module)
+    }
+}
+
+/// Cobalt Strike release a Beacon API list corresponds to
+///
+/// Releases between these didn't change the API available to BOFs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum BeaconApiVersion {
+    V4_1,
+    V4_7,
+    V4_8,
+    V4_9,
+    V4_10,
+}
+
+impl BeaconApiVersion {
+    pub const LATEST: Self = Self::V4_10;
+
+    /// Whether Beacon in this release exports `name`
+    pub fn provides(&self, name: &str) -> bool {
+        let added: [(Self, &[&str]); 4] = [
+            (Self::V4_1, BEACON_EXPORTS),
+            (Self::V4_7, BEACON_EXPORTS_4_7),
+            (Self::V4_9, BEACON_EXPORTS_4_9),
+            (Self::V4_10, BEACON_EXPORTS_4_10),
+        ];
+        added.iter().any(|(version, exports)| version <= self && exports.contains(&name))
+    }
+
+    /// Release number, e.g. `4.10`
+    pub fn number(&self) -> &'static str {
+        match self {
+            Self::V4_1 => "4.1",
+            Self::V4_7 => "4.7",
+            Self::V4_8 => "4.8",
+            Self::V4_9 => "4.9",
+            Self::V4_10 => "4.10",
+        }
+    }
+}
+
+impl fmt::Display for BeaconApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.number())
+    }
+}
+
+impl FromStr for BeaconApiVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4.1" => Ok(Self::V4_1),
+            "4.7" => Ok(Self::V4_7),
+            "4.8" => Ok(Self::V4_8),
+            "4.9" => Ok(Self::V4_9),
+            "4.10" => Ok(Self::V4_10),
+            _ => Err(format!("unknown Cobalt Strike version: {} (expected 4.1, 4.7, 4.8, 4.9 or 4.10)", s)),
+        }
+    }
+}
+
+/// Cobalt Strike's Beacon, as of a given release
+#[derive(Debug, Clone, Copy)]
+pub struct CobaltStrike {
+    pub version: BeaconApiVersion,
+}
+
+impl CobaltStrike {
+    pub fn new(version: BeaconApiVersion) -> Self {
+        Self { version }
+    }
+}
+
+impl Default for CobaltStrike {
+    fn default() -> Self {
+        Self::new(BeaconApiVersion::LATEST)
+    }
+}
+
+impl ApiProfile for CobaltStrike {
+    fn name(&self) -> &str {
+        match self.version {
+            BeaconApiVersion::V4_1 => "cs-4.1",
+            BeaconApiVersion::V4_7 => "cs-4.7",
+            BeaconApiVersion::V4_8 => "cs-4.8",
+            BeaconApiVersion::V4_9 => "cs-4.9",
+            BeaconApiVersion::V4_10 => "cs-4.10",
+        }
+    }
+
+    fn entrypoint(&self) -> &str {
+        BEACON_ENTRYPOINT
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        self.version.provides(name)
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        WIN32_BUILTIN.contains(&name)
+    }
+}
+
+/// Sliver's `coff-loader` extension
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Sliver;
+
+impl ApiProfile for Sliver {
+    fn name(&self) -> &str {
+        "sliver"
+    }
+
+    fn entrypoint(&self) -> &str {
+        BEACON_ENTRYPOINT
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        sliver::SLIVER_EXPORTS.contains(&name)
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        sliver::SLIVER_BUILTIN.contains(&name)
+    }
+}
+
+/// Havoc's Demon, which implements most of the Beacon API
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Havoc;
+
+impl ApiProfile for Havoc {
+    fn name(&self) -> &str {
+        "havoc"
+    }
+
+    fn entrypoint(&self) -> &str {
+        BEACON_ENTRYPOINT
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        HAVOC_EXPORTS.contains(&name)
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        HAVOC_BUILTIN.contains(&name)
+    }
+}
+
+/// Metasploit's bofloader extension (`execute_bof`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Meterpreter;
+
+impl ApiProfile for Meterpreter {
+    fn name(&self) -> &str {
+        "meterpreter"
+    }
+
+    fn entrypoint(&self) -> &str {
+        BEACON_ENTRYPOINT
+    }
+
+    fn supports_arch(&self, arch: Arch) -> bool {
+        matches!(arch, Arch::X86 | Arch::X64)
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        meterpreter::METERPRETER_EXPORTS.contains(&name)
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        meterpreter::METERPRETER_BUILTIN.contains(&name)
+    }
+}
+
+/// Brute Ratel's badger, which has its own `Badger*` API and entrypoint
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BruteRatel;
+
+impl ApiProfile for BruteRatel {
+    fn name(&self) -> &str {
+        "brc4"
+    }
+
+    fn entrypoint(&self) -> &str {
+        BADGER_ENTRYPOINT
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        BADGER_EXPORTS.contains(&name)
+    }
+
+    // the badger doesn't resolve any Win32 functions on a BOF's behalf
+    fn is_builtin(&self, _name: &str) -> bool {
+        false
+    }
+}
+
+/// A profile read from a TOML or JSON file
+///
+/// Lists add to those of the `extends` profile, if there is one.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileProfile {
+    pub name: String,
+    /// Name of a built-in profile to start from
+    pub extends: Option<String>,
+    /// Entrypoint, if different from the base profile's (or `go`)
+    pub entrypoint: Option<String>,
+    /// Architectures the loader can run (`x86`, `x64`, `aarch64`), if restricted
+    pub arches: Option<Vec<String>>,
+    pub exports: Vec<String>,
+    pub builtins: Vec<String>,
+    pub modules: Vec<String>,
+    #[serde(skip)]
+    base: Option<Arc<dyn ApiProfile>>,
+}
+
+impl FileProfile {
+    /// Parse a profile from TOML
+    pub fn from_toml(text: &str) -> Result<Self, ProfileError> {
+        toml::from_str::<Self>(text).map_err(|e| ProfileError::Parse(e.to_string()))?.resolve()
+    }
+
+    /// Parse a profile from JSON
+    pub fn from_json(text: &str) -> Result<Self, ProfileError> {
+        serde_json::from_str::<Self>(text).map_err(|e| ProfileError::Parse(e.to_string()))?.resolve()
+    }
+
+    /// Read a profile from a file, choosing the format by its extension
+    pub fn load(path: &Path) -> Result<Self, ProfileError> {
+        let text = fs::read_to_string(path).map_err(ProfileError::Io)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// Look up the base profile and check the architecture names
+    fn resolve(mut self) -> Result<Self, ProfileError> {
+        if let Some(base) = &self.extends {
+            self.base = Some(by_name(base).map_err(|_| ProfileError::UnknownBase(base.clone()))?);
+        }
+        for arch in self.arches.iter().flatten() {
+            if Arch::from_name(arch).is_none() {
+                return Err(ProfileError::Parse(format!("unknown architecture: {}", arch)));
+            }
+        }
+        if self.name.is_empty() {
+            self.name = self.extends.clone().unwrap_or_else(|| "custom".to_string());
+        }
+        Ok(self)
+    }
+}
+
+impl ApiProfile for FileProfile {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn entrypoint(&self) -> &str {
+        match (&self.entrypoint, &self.base) {
+            (Some(entrypoint), _) => entrypoint,
+            (None, Some(base)) => base.entrypoint(),
+            (None, None) => BEACON_ENTRYPOINT,
+        }
+    }
+
+    fn supports_arch(&self, arch: Arch) -> bool {
+        match (&self.arches, &self.base) {
+            (Some(arches), _) => arches.iter().any(|name| Arch::from_name(name) == Some(arch)),
+            (None, Some(base)) => base.supports_arch(arch),
+            (None, None) => true,
+        }
+    }
+
+    fn is_export(&self, name: &str) -> bool {
+        self.exports.iter().any(|export| export == name) || self.base.as_ref().is_some_and(|base| base.is_export(name))
+    }
+
+    fn is_builtin(&self, name: &str) -> bool {
+        self.builtins.iter().any(|builtin| builtin == name)
+            || self.base.as_ref().is_some_and(|base| base.is_builtin(name))
+    }
+
+    fn is_known_module(&self, module: &str) -> bool {
+        self.modules.iter().any(|known| known == module)
+            || match &self.base {
+                Some(base) => base.is_known_module(module),
+                None => WIN32_MODULES.contains(&module),
+            }
+    }
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    /// The profile file couldn't be read
+    Io(io::Error),
+    /// The profile isn't valid TOML/JSON, or has unexpected contents
+    Parse(String),
+    /// `extends` names a profile that isn't built in
+    UnknownBase(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read profile: {}", e),
+            Self::Parse(message) => write!(f, "invalid profile: {}", message),
+            Self::UnknownBase(name) => write!(f, "profile extends unknown target: {}", name),
+        }
+    }
+}
+
+impl error::Error for ProfileError {}
+
+
+/// Look up a built-in profile by name
+///
+/// Cobalt Strike profiles can name a release, e.g. `cs-4.9`.
+pub fn by_name(name: &str) -> Result<Arc<dyn ApiProfile>, String> {
+    let name = name.to_ascii_lowercase();
+    if let Some(version) = name.strip_prefix("cs-") {
+        return Ok(Arc::new(CobaltStrike::new(version.parse()?)));
+    }
+    match name.as_str() {
+        "cs" | "cobaltstrike" | "cobalt-strike" => Ok(Arc::new(CobaltStrike::default())),
+        "sliver" => Ok(Arc::new(Sliver)),
+        "havoc" => Ok(Arc::new(Havoc)),
+        "meterpreter" | "msf" | "metasploit" => Ok(Arc::new(Meterpreter)),
+        "brc4" | "bruteratel" | "brute-ratel" => Ok(Arc::new(BruteRatel)),
+        _ => Err(format!("unknown target: {}", name)),
+    }
+}
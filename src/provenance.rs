@@ -0,0 +1,215 @@
+//! Which toolchain built a BOF, and when
+//!
+//! Compilers leave different traces in an object: MSVC an `@comp.id` symbol
+//! with its build number and quoted `/DEFAULTLIB` directives, MinGW-w64 GCC
+//! a `GCC: (...)` ident string and `.refptr` stubs, clang a `clang version`
+//! string. None of these matter to the loader, but they help explain loader
+//! failures tied to one toolchain, and the header timestamp and source file
+//! name are worth scrubbing before a BOF leaves the build machine.
+
+use std::fmt;
+
+use goblin::pe::symbol::{IMAGE_SYM_ABSOLUTE, IMAGE_SYM_CLASS_FILE};
+
+use crate::{section_name, Bof};
+
+/// A compiler family
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Toolchain {
+    Msvc,
+    /// MinGW-w64 GCC, e.g. x86_64-w64-mingw32-gcc
+    Mingw,
+    /// clang or clang-cl
+    Clang,
+}
+
+impl fmt::Display for Toolchain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Msvc => "MSVC",
+            Self::Mingw => "MinGW-w64 GCC",
+            Self::Clang => "clang",
+        })
+    }
+}
+
+/// A trace of the toolchain found in the object
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Evidence {
+    /// Toolchain it points to, if it narrows it down
+    pub toolchain: Option<Toolchain>,
+    pub detail: String,
+}
+
+impl fmt::Display for Evidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.toolchain {
+            Some(toolchain) => write!(f, "{} ({})", self.detail, toolchain),
+            None => f.write_str(&self.detail),
+        }
+    }
+}
+
+/// Build provenance of an object
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Provenance {
+    /// `time_date_stamp` from the COFF header; 0 for reproducible builds
+    pub timestamp: u32,
+    /// Most likely toolchain, if any evidence was found
+    pub toolchain: Option<Toolchain>,
+    /// Compiler version, if the object records one
+    pub version: Option<String>,
+    /// Source file named by a `.file` symbol
+    pub source_file: Option<String>,
+    /// Linker directives from `.drectve`
+    pub directives: Vec<String>,
+    pub evidence: Vec<Evidence>,
+}
+
+impl Provenance {
+    /// The timestamp as a UTC date, unless it's 0
+    pub fn timestamp_utc(&self) -> Option<String> {
+        (self.timestamp != 0).then(|| format_utc(self.timestamp))
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.toolchain, &self.version) {
+            (Some(toolchain), Some(version)) => write!(f, "{} {}", toolchain, version),
+            (Some(toolchain), None) => write!(f, "{}", toolchain),
+            (None, _) => f.write_str("unknown toolchain"),
+        }
+    }
+}
+
+/// Guess the toolchain that built `bof` from the traces it left
+pub fn detect(bof: &Bof) -> Provenance {
+    let timestamp = bof.coff.header.time_date_stamp;
+    let mut evidence = Vec::new();
+    let mut note = |toolchain, detail: String| evidence.push(Evidence { toolchain, detail });
+    let mut version = None;
+    let mut directives = Vec::new();
+    for section in &bof.coff.sections {
+        let name = section_name(section);
+        let data = bof.section_contents(section);
+        if name == ".drectve" {
+            directives = String::from_utf8_lossy(data).split_whitespace().map(str::to_string).collect();
+            continue;
+        }
+        if name.starts_with(".text$mn") {
+            note(Some(Toolchain::Msvc), format!("{} section", name));
+        }
+        if let Some(ident) = text_after(data, b"GCC: (") {
+            note(Some(Toolchain::Mingw), format!("GCC: ({} in {}", ident, name));
+            // "GCC: (GNU) 12.2.0" or "GCC: (Rev1, Built by MSYS2 project) 13.1.0"
+            version = version.or_else(|| ident.rsplit_once(") ").map(|(_, version)| version.to_string()));
+        }
+        if let Some(ident) = text_after(data, b"clang version ") {
+            note(Some(Toolchain::Clang), format!("clang version {} in {}", ident, name));
+            version = version.or_else(|| ident.split_whitespace().next().map(str::to_string));
+        }
+        if text_after(data, b"Microsoft (R) Optimizing Compiler").is_some() {
+            note(Some(Toolchain::Msvc), format!("Microsoft (R) Optimizing Compiler in {}", name));
+        }
+    }
+    for directive in &directives {
+        let lower = directive.to_ascii_lowercase();
+        if lower.starts_with("/defaultlib:\"") {
+            note(Some(Toolchain::Msvc), format!("quoted linker directive {}", directive));
+        } else if lower.starts_with("/defaultlib:") && lower.ends_with(".lib") {
+            note(Some(Toolchain::Clang), format!("linker directive {}", directive));
+        } else if lower.starts_with("-aligncomm:") || lower.starts_with("-export:") {
+            note(Some(Toolchain::Mingw), format!("GNU-style linker directive {}", directive));
+        }
+    }
+    let mut source_file = None;
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        let name = bof.symbol_name(&symbol);
+        if name == "@comp.id" && symbol.section_number == IMAGE_SYM_ABSOLUTE {
+            let build = symbol.value & 0xffff;
+            note(Some(Toolchain::Msvc), format!("@comp.id build {}", build));
+            version = version.or_else(|| Some(msvc_version(build)));
+        } else if name == "@feat.00" && symbol.section_number == IMAGE_SYM_ABSOLUTE {
+            note(None, "@feat.00 symbol (MSVC or clang-cl)".to_string());
+        } else if name.starts_with(".refptr.") {
+            note(Some(Toolchain::Mingw), format!("{} stub", name));
+        } else if name.ends_with("__chkstk_ms") {
+            note(Some(Toolchain::Mingw), format!("{} call", name));
+        } else if symbol.storage_class == IMAGE_SYM_CLASS_FILE {
+            let file = bof.coff.symbols.aux_file(index + 1, symbol.number_of_aux_symbols as usize);
+            if let Some(file) = file.filter(|file| !file.is_empty()) {
+                note(None, format!(".file symbol naming {} (GCC or clang)", file));
+                source_file = source_file.or_else(|| Some(file.to_string()));
+            }
+        }
+    }
+
+    let mut toolchain = most_likely(&evidence);
+    if toolchain.is_none() && timestamp != 0 {
+        let detail = "non-zero timestamp, which cl.exe writes unless built with /Brepro".to_string();
+        evidence.push(Evidence { toolchain: Some(Toolchain::Msvc), detail });
+        toolchain = Some(Toolchain::Msvc);
+    }
+    Provenance {
+        timestamp,
+        toolchain,
+        // a version means nothing without the toolchain it's from
+        version: version.filter(|_| toolchain.is_some()),
+        source_file,
+        directives,
+        evidence,
+    }
+}
+
+/// The toolchain most evidence points to; clang mimics MSVC as clang-cl and
+/// GCC as a MinGW target, so any trace of clang itself wins
+fn most_likely(evidence: &[Evidence]) -> Option<Toolchain> {
+    let count = |toolchain| evidence.iter().filter(|evidence| evidence.toolchain == Some(toolchain)).count();
+    if count(Toolchain::Clang) > 0 {
+        return Some(Toolchain::Clang);
+    }
+    [Toolchain::Msvc, Toolchain::Mingw]
+        .into_iter()
+        .filter(|toolchain| count(*toolchain) > 0)
+        .max_by_key(|toolchain| count(*toolchain))
+}
+
+/// Visual Studio release an MSVC build number shipped with
+fn msvc_version(build: u32) -> String {
+    let release = match build {
+        0..=21004 => "before Visual Studio 2015",
+        21005..=24999 => "Visual Studio 2015",
+        25000..=27499 => "Visual Studio 2017",
+        27500..=30199 => "Visual Studio 2019",
+        _ => "Visual Studio 2022 or later",
+    };
+    format!("build {} ({})", build, release)
+}
+
+/// Printable text following `needle` in `data`, up to a NUL or line break
+fn text_after(data: &[u8], needle: &[u8]) -> Option<String> {
+    let start = data.windows(needle.len()).position(|window| window == needle)? + needle.len();
+    let text: Vec<u8> = data[start..].iter().copied().take_while(|byte| (0x20..0x7f).contains(byte)).collect();
+    Some(String::from_utf8_lossy(&text).to_string())
+}
+
+/// `seconds` since the Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`
+fn format_utc(seconds: u32) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // civil_from_days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z / 146097;
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
@@ -0,0 +1,157 @@
+//! Relocation types that BOF loaders know how to apply
+//!
+//! Beacon's loader implements only the handful of relocation types compilers
+//! emit for ordinary code and data references; anything else leaves the
+//! target unpatched and crashes the BOF at runtime.
+//! https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#type-indicators
+
+use goblin::pe::relocation::*;
+
+use crate::Arch;
+
+/// ARM64 relocation types (goblin doesn't define these)
+pub const IMAGE_REL_ARM64_ABSOLUTE: u16 = 0x0000;
+pub const IMAGE_REL_ARM64_ADDR32: u16 = 0x0001;
+pub const IMAGE_REL_ARM64_ADDR32NB: u16 = 0x0002;
+pub const IMAGE_REL_ARM64_BRANCH26: u16 = 0x0003;
+pub const IMAGE_REL_ARM64_PAGEBASE_REL21: u16 = 0x0004;
+pub const IMAGE_REL_ARM64_REL21: u16 = 0x0005;
+pub const IMAGE_REL_ARM64_PAGEOFFSET_12A: u16 = 0x0006;
+pub const IMAGE_REL_ARM64_PAGEOFFSET_12L: u16 = 0x0007;
+pub const IMAGE_REL_ARM64_SECREL: u16 = 0x0008;
+pub const IMAGE_REL_ARM64_SECREL_LOW12A: u16 = 0x0009;
+pub const IMAGE_REL_ARM64_SECREL_HIGH12A: u16 = 0x000A;
+pub const IMAGE_REL_ARM64_SECREL_LOW12L: u16 = 0x000B;
+pub const IMAGE_REL_ARM64_TOKEN: u16 = 0x000C;
+pub const IMAGE_REL_ARM64_SECTION: u16 = 0x000D;
+pub const IMAGE_REL_ARM64_ADDR64: u16 = 0x000E;
+pub const IMAGE_REL_ARM64_BRANCH19: u16 = 0x000F;
+pub const IMAGE_REL_ARM64_BRANCH14: u16 = 0x0010;
+pub const IMAGE_REL_ARM64_REL32: u16 = 0x0011;
+
+/// A relocation entry, resolved to the names of the sections involved
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Relocation {
+    /// Section the relocation patches
+    pub section: String,
+    /// Offset of the patched location within `section`
+    pub offset: u32,
+    /// Raw relocation type
+    pub typ: u16,
+    /// Name of the relocation type, e.g. `IMAGE_REL_AMD64_REL32`
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_type_name"))]
+    pub type_name: crate::TableStr,
+    /// Symbol the relocation points at
+    pub symbol: String,
+    /// Section that defines `symbol`, if it isn't external
+    pub target_section: Option<String>,
+    /// Whether the loader can apply this relocation type
+    pub supported: bool,
+}
+
+/// Whether a BOF loader can apply relocations of type `typ`
+pub fn is_supported(arch: Arch, typ: u16) -> bool {
+    match arch {
+        Arch::X86 => matches!(typ, IMAGE_REL_I386_ABSOLUTE | IMAGE_REL_I386_DIR32 | IMAGE_REL_I386_REL32),
+        Arch::X64 => matches!(
+            typ,
+            IMAGE_REL_AMD64_ABSOLUTE
+                | IMAGE_REL_AMD64_ADDR64
+                | IMAGE_REL_AMD64_ADDR32NB
+                | IMAGE_REL_AMD64_REL32..=IMAGE_REL_AMD64_REL32_5
+        ),
+        Arch::Arm64 => matches!(
+            typ,
+            IMAGE_REL_ARM64_ABSOLUTE
+                | IMAGE_REL_ARM64_ADDR32NB
+                | IMAGE_REL_ARM64_BRANCH26
+                | IMAGE_REL_ARM64_PAGEBASE_REL21
+                | IMAGE_REL_ARM64_PAGEOFFSET_12A
+                | IMAGE_REL_ARM64_PAGEOFFSET_12L
+                | IMAGE_REL_ARM64_ADDR64
+        ),
+    }
+}
+
+/// Number of bytes a relocation of type `typ` patches
+pub fn width(arch: Arch, typ: u16) -> usize {
+    match (arch, typ) {
+        (Arch::X86, IMAGE_REL_I386_ABSOLUTE) | (Arch::X64, IMAGE_REL_AMD64_ABSOLUTE) => 0,
+        (Arch::Arm64, IMAGE_REL_ARM64_ABSOLUTE) => 0,
+        (Arch::X86, IMAGE_REL_I386_DIR16 | IMAGE_REL_I386_REL16 | IMAGE_REL_I386_SECTION) => 2,
+        (Arch::X64, IMAGE_REL_AMD64_SECTION) | (Arch::Arm64, IMAGE_REL_ARM64_SECTION) => 2,
+        (Arch::X64, IMAGE_REL_AMD64_ADDR64) | (Arch::Arm64, IMAGE_REL_ARM64_ADDR64) => 8,
+        _ => 4,
+    }
+}
+
+/// Addend stored in the bytes a relocation at `offset` patches, for the
+/// 4 and 8 byte types
+pub fn addend(arch: Arch, typ: u16, data: &[u8], offset: usize) -> Option<i64> {
+    let bytes = data.get(offset..offset + width(arch, typ))?;
+    match bytes.len() {
+        4 => Some(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64),
+        8 => Some(i64::from_le_bytes(bytes.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+/// Read a type name back as the matching entry of [`type_name`]
+#[cfg(feature = "serde")]
+fn deserialize_type_name<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    let names = [Arch::X86, Arch::X64, Arch::Arm64].into_iter().flat_map(|arch| (0..=0x20).map(move |typ| type_name(arch, typ)));
+    crate::deserialize_known(deserializer, names)
+}
+
+pub fn type_name(arch: Arch, typ: u16) -> &'static str {
+    match (arch, typ) {
+        (Arch::X86, IMAGE_REL_I386_ABSOLUTE) => "IMAGE_REL_I386_ABSOLUTE",
+        (Arch::X86, IMAGE_REL_I386_DIR16) => "IMAGE_REL_I386_DIR16",
+        (Arch::X86, IMAGE_REL_I386_REL16) => "IMAGE_REL_I386_REL16",
+        (Arch::X86, IMAGE_REL_I386_DIR32) => "IMAGE_REL_I386_DIR32",
+        (Arch::X86, IMAGE_REL_I386_DIR32NB) => "IMAGE_REL_I386_DIR32NB",
+        (Arch::X86, IMAGE_REL_I386_SEG12) => "IMAGE_REL_I386_SEG12",
+        (Arch::X86, IMAGE_REL_I386_SECTION) => "IMAGE_REL_I386_SECTION",
+        (Arch::X86, IMAGE_REL_I386_SECREL) => "IMAGE_REL_I386_SECREL",
+        (Arch::X86, IMAGE_REL_I386_TOKEN) => "IMAGE_REL_I386_TOKEN",
+        (Arch::X86, IMAGE_REL_I386_SECREL7) => "IMAGE_REL_I386_SECREL7",
+        (Arch::X86, IMAGE_REL_I386_REL32) => "IMAGE_REL_I386_REL32",
+        (Arch::X64, IMAGE_REL_AMD64_ABSOLUTE) => "IMAGE_REL_AMD64_ABSOLUTE",
+        (Arch::X64, IMAGE_REL_AMD64_ADDR64) => "IMAGE_REL_AMD64_ADDR64",
+        (Arch::X64, IMAGE_REL_AMD64_ADDR32) => "IMAGE_REL_AMD64_ADDR32",
+        (Arch::X64, IMAGE_REL_AMD64_ADDR32NB) => "IMAGE_REL_AMD64_ADDR32NB",
+        (Arch::X64, IMAGE_REL_AMD64_REL32) => "IMAGE_REL_AMD64_REL32",
+        (Arch::X64, IMAGE_REL_AMD64_REL32_1) => "IMAGE_REL_AMD64_REL32_1",
+        (Arch::X64, IMAGE_REL_AMD64_REL32_2) => "IMAGE_REL_AMD64_REL32_2",
+        (Arch::X64, IMAGE_REL_AMD64_REL32_3) => "IMAGE_REL_AMD64_REL32_3",
+        (Arch::X64, IMAGE_REL_AMD64_REL32_4) => "IMAGE_REL_AMD64_REL32_4",
+        (Arch::X64, IMAGE_REL_AMD64_REL32_5) => "IMAGE_REL_AMD64_REL32_5",
+        (Arch::X64, IMAGE_REL_AMD64_SECTION) => "IMAGE_REL_AMD64_SECTION",
+        (Arch::X64, IMAGE_REL_AMD64_SECREL) => "IMAGE_REL_AMD64_SECREL",
+        (Arch::X64, IMAGE_REL_AMD64_SECREL7) => "IMAGE_REL_AMD64_SECREL7",
+        (Arch::X64, IMAGE_REL_AMD64_TOKEN) => "IMAGE_REL_AMD64_TOKEN",
+        (Arch::X64, IMAGE_REL_AMD64_SREL32) => "IMAGE_REL_AMD64_SREL32",
+        (Arch::X64, IMAGE_REL_AMD64_PAIR) => "IMAGE_REL_AMD64_PAIR",
+        (Arch::X64, IMAGE_REL_AMD64_SSPAN32) => "IMAGE_REL_AMD64_SSPAN32",
+        (Arch::Arm64, IMAGE_REL_ARM64_ABSOLUTE) => "IMAGE_REL_ARM64_ABSOLUTE",
+        (Arch::Arm64, IMAGE_REL_ARM64_ADDR32) => "IMAGE_REL_ARM64_ADDR32",
+        (Arch::Arm64, IMAGE_REL_ARM64_ADDR32NB) => "IMAGE_REL_ARM64_ADDR32NB",
+        (Arch::Arm64, IMAGE_REL_ARM64_BRANCH26) => "IMAGE_REL_ARM64_BRANCH26",
+        (Arch::Arm64, IMAGE_REL_ARM64_PAGEBASE_REL21) => "IMAGE_REL_ARM64_PAGEBASE_REL21",
+        (Arch::Arm64, IMAGE_REL_ARM64_REL21) => "IMAGE_REL_ARM64_REL21",
+        (Arch::Arm64, IMAGE_REL_ARM64_PAGEOFFSET_12A) => "IMAGE_REL_ARM64_PAGEOFFSET_12A",
+        (Arch::Arm64, IMAGE_REL_ARM64_PAGEOFFSET_12L) => "IMAGE_REL_ARM64_PAGEOFFSET_12L",
+        (Arch::Arm64, IMAGE_REL_ARM64_SECREL) => "IMAGE_REL_ARM64_SECREL",
+        (Arch::Arm64, IMAGE_REL_ARM64_SECREL_LOW12A) => "IMAGE_REL_ARM64_SECREL_LOW12A",
+        (Arch::Arm64, IMAGE_REL_ARM64_SECREL_HIGH12A) => "IMAGE_REL_ARM64_SECREL_HIGH12A",
+        (Arch::Arm64, IMAGE_REL_ARM64_SECREL_LOW12L) => "IMAGE_REL_ARM64_SECREL_LOW12L",
+        (Arch::Arm64, IMAGE_REL_ARM64_TOKEN) => "IMAGE_REL_ARM64_TOKEN",
+        (Arch::Arm64, IMAGE_REL_ARM64_SECTION) => "IMAGE_REL_ARM64_SECTION",
+        (Arch::Arm64, IMAGE_REL_ARM64_ADDR64) => "IMAGE_REL_ARM64_ADDR64",
+        (Arch::Arm64, IMAGE_REL_ARM64_BRANCH19) => "IMAGE_REL_ARM64_BRANCH19",
+        (Arch::Arm64, IMAGE_REL_ARM64_BRANCH14) => "IMAGE_REL_ARM64_BRANCH14",
+        (Arch::Arm64, IMAGE_REL_ARM64_REL32) => "IMAGE_REL_ARM64_REL32",
+        _ => "UNKNOWN",
+    }
+}
@@ -0,0 +1,688 @@
+use std::fmt;
+
+use crate::callgraph::CallGraph;
+use crate::rules::{CustomFinding, RuleConfig};
+use crate::stack::StackEstimate;
+use crate::{capabilities, disasm, AuxRecord, AuxSymbol, TranslationUnit, Hashes, Identification, Provenance, CapabilityUse, ChecksumIssue, ClassifiedSymbol, Comdat, ComdatIssue, CompressedSize, DeadCode, DecorationIssue, DfrIssue, DuplicateSymbol, EntrypointIssue, ExceptionIssue, InitializerIssue, TlsIssue, NameIssue, DfrProblem, External, ExternalKind, FormatIssue, FoundString, InjectionIssue, OpsecIssue, PathLeak, Relocation, Section, SectionIssue, SpecIssue, TechniqueUse, WeakExternal, WeakIssue, IMAGE_FILE_MACHINE_AMD64, IMAGE_FILE_MACHINE_ARM64, IMAGE_FILE_MACHINE_I386};
+
+/// Target architecture decoded from the COFF header machine type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Arch {
+    X86,
+    X64,
+    Arm64,
+}
+
+impl Arch {
+    pub fn from_machine(machine: u16) -> Option<Self> {
+        match machine {
+            IMAGE_FILE_MACHINE_I386 => Some(Self::X86),
+            IMAGE_FILE_MACHINE_AMD64 => Some(Self::X64),
+            IMAGE_FILE_MACHINE_ARM64 => Some(Self::Arm64),
+            _ => None,
+        }
+    }
+
+    /// Parse an architecture name as loaders and toolchains spell it
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "x86" | "i386" | "386" => Some(Self::X86),
+            "x64" | "amd64" | "x86_64" => Some(Self::X64),
+            "aarch64" | "arm64" => Some(Self::Arm64),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::X86 => "x86",
+            Self::X64 => "x64",
+            Self::Arm64 => "aarch64",
+        }
+    }
+
+    /// Prefix the compiler adds to `__declspec(dllimport)` symbols, which
+    /// includes the leading underscore of the x86 C decoration
+    pub fn import_prefix(&self) -> &'static str {
+        match self {
+            Self::X86 => "__imp__",
+            Self::X64 | Self::Arm64 => "__imp_",
+        }
+    }
+
+    /// How the compiler spells the C function `function` in the symbol
+    /// table, with the leading underscore on x86
+    pub fn decorate(&self, function: &str) -> String {
+        match self {
+            Self::X86 => format!("_{}", function),
+            Self::X64 | Self::Arm64 => function.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Arch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How an imported symbol is expected to be resolved by the loader
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ImportKind {
+    /// Function exported by the loader (the Beacon API, or its equivalent)
+    BeaconApi,
+    /// Win32 function built into the loader
+    Win32Builtin,
+    /// Another loader's API function that the target's loader doesn't provide
+    UnsupportedApi,
+    /// Dynamic function resolution (`MODULE$Function`) against a common Win32 library
+    Dynamic { module: String, function: String },
+    /// Dynamic function resolution against a library we don't recognize
+    UnrecognizedModule { module: String, function: String },
+    /// Anything else, which will most likely fail to resolve at load time
+    Unknown,
+}
+
+/// An imported symbol, with the import prefix (`__imp_`) removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Import {
+    pub name: String,
+    pub kind: ImportKind,
+    /// Whether any relocation points at the import; unreferenced imports are
+    /// never resolved
+    pub referenced: bool,
+}
+
+impl Import {
+    /// Whether the loader is expected to resolve this import
+    pub fn is_recognized(&self) -> bool {
+        !matches!(
+            self.kind,
+            ImportKind::UnsupportedApi | ImportKind::UnrecognizedModule { .. } | ImportKind::Unknown
+        )
+    }
+}
+
+/// Results of running every check against a BOF
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnalysisReport {
+    /// Name of the profile the BOF was validated against
+    pub target: String,
+    /// Raw machine type from the COFF header
+    pub machine: u16,
+    /// Decoded machine type, if supported
+    pub arch: Option<Arch>,
+    /// Architecture the object was expected to be built for
+    pub expected_arch: Option<Arch>,
+    /// Whether the target's loader can run objects built for `arch`
+    pub arch_supported: bool,
+    /// Digests of the file and its import list
+    pub hashes: Hashes,
+    /// Public BOFs the object matches, if it was checked against a
+    /// fingerprint database
+    pub identifications: Vec<Identification>,
+    /// Toolchain that most likely built the BOF, and when
+    pub provenance: Provenance,
+    /// Entrypoint symbol the loader calls
+    pub expected_entrypoint: String,
+    /// Name of the entrypoint symbol, if it's an external function; on x86
+    /// the decorated `_go`, or `go` if only that was found
+    pub entrypoint: Option<String>,
+    /// Symbols named like the entrypoint that can't be called as one
+    pub entrypoint_issues: Vec<EntrypointIssue>,
+    /// Every function a loader could call, see
+    /// [`Bof::entrypoints`](crate::Bof::entrypoints)
+    pub entrypoints: Vec<String>,
+    /// Every imported symbol, in symbol table order
+    pub imports: Vec<Import>,
+    /// Every symbol table entry, classified the way the loader treats it
+    pub symbols: Vec<ClassifiedSymbol>,
+    /// Auxiliary records of the symbol table entries that have them
+    pub aux_records: Vec<AuxSymbol>,
+    /// Source files named by `.file` entries, with the functions from each
+    pub translation_units: Vec<TranslationUnit>,
+    /// Problems with dynamic function resolution imports
+    pub dfr_issues: Vec<DfrIssue>,
+    /// Imports decorated for the wrong architecture
+    pub decoration_issues: Vec<DecorationIssue>,
+    /// Imports named in ways some loaders can't resolve
+    pub name_issues: Vec<NameIssue>,
+    /// Imports of heavily monitored functions, if the OPSEC lint was enabled
+    pub opsec_issues: Vec<OpsecIssue>,
+    /// Absolute build paths left in the object, if the OPSEC lint was enabled
+    pub path_leaks: Vec<PathLeak>,
+    /// What the imports let the BOF do
+    pub capabilities: Vec<CapabilityUse>,
+    /// MITRE ATT&CK techniques the imports suggest, by ID
+    pub techniques: Vec<TechniqueUse>,
+    /// Argument format guessed from the data API calls, if the BOF parses
+    /// arguments
+    pub inferred_format: Option<String>,
+    /// Disagreements with the argument spec, if one was given
+    pub spec_issues: Vec<SpecIssue>,
+    /// Beacon Format API calls that will crash or leak
+    pub format_issues: Vec<FormatIssue>,
+    /// Functions that inject into a process and never clean it up
+    pub injection_issues: Vec<InjectionIssue>,
+    /// Unwind tables and exception handlers, which the loader doesn't support
+    pub exception_issues: Vec<ExceptionIssue>,
+    /// Thread local variables and the TLS runtime, which the loader doesn't
+    /// set up
+    pub tls_issues: Vec<TlsIssue>,
+    /// Static constructors, destructors and exit handlers, which the loader
+    /// never runs
+    pub initializer_issues: Vec<InitializerIssue>,
+    /// Undefined non-import symbols that relocations point at
+    pub externals: Vec<External>,
+    /// Every section, in section table order
+    pub sections: Vec<Section>,
+    /// Problems with individual sections
+    pub section_issues: Vec<SectionIssue>,
+    /// External symbols defined more than once
+    pub duplicate_symbols: Vec<DuplicateSymbol>,
+    /// Every COMDAT section, in section table order
+    pub comdats: Vec<Comdat>,
+    /// COMDATs a linker would have dropped, and malformed COMDAT records
+    pub comdat_issues: Vec<ComdatIssue>,
+    /// COMDATs whose stored checksum doesn't match their contents
+    pub checksum_issues: Vec<ChecksumIssue>,
+    /// Every weak external symbol, in symbol table order
+    pub weak_externals: Vec<WeakExternal>,
+    /// Weak externals with a bad or undefined default, or that relocations
+    /// point at
+    pub weak_issues: Vec<WeakIssue>,
+    /// Relocations of every loaded section
+    pub relocations: Vec<Relocation>,
+    /// Readable strings in loaded sections, if they were asked for
+    pub strings: Vec<FoundString>,
+    /// Instructions of the entrypoint, if disassembly was asked for and the
+    /// entrypoint was found
+    pub disassembly: Vec<disasm::Instruction>,
+    /// Stack used by the entrypoint's deepest call chain, if it's defined
+    /// in x86 or x64 code
+    pub stack: Option<StackEstimate>,
+    /// Stack usage limit the estimate was checked against
+    pub max_stack: usize,
+    /// Static functions no entrypoint reaches
+    pub dead_code: DeadCode,
+    /// Calls between functions and to imports, if they were asked for
+    pub call_graph: Option<CallGraph>,
+    /// See [`Bof::estimated_loaded_size`](crate::Bof::estimated_loaded_size)
+    pub estimated_size: usize,
+    /// See [`Bof::compressed_size`](crate::Bof::compressed_size)
+    pub compressed_size: CompressedSize,
+    /// Size limit the estimate was checked against
+    pub max_size: usize,
+    /// Findings of extra rules, see [`Rule`](crate::rules::Rule)
+    pub custom_findings: Vec<CustomFinding>,
+    /// Levels findings are reported with
+    pub rules: RuleConfig,
+}
+
+impl AnalysisReport {
+    /// Source file names of `.file` entries, in symbol table order
+    pub fn source_files(&self) -> impl Iterator<Item = &str> {
+        self.aux_records.iter().filter_map(|aux| match &aux.record {
+            AuxRecord::File(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Functions with a size in their function definition record, and the
+    /// size in bytes
+    pub fn function_sizes(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.aux_records.iter().filter_map(|aux| match aux.record {
+            AuxRecord::FunctionDefinition { size, .. } => Some((aux.symbol.as_str(), size)),
+            _ => None,
+        })
+    }
+
+    /// Sections with a checksum in their section definition record, and the
+    /// checksum
+    pub fn section_checksums(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.aux_records.iter().filter_map(|aux| match aux.record {
+            AuxRecord::SectionDefinition { checksum, .. } if checksum != 0 => Some((aux.symbol.as_str(), checksum)),
+            _ => None,
+        })
+    }
+
+    /// Imports that the loader is not expected to resolve
+    pub fn unknown_imports(&self) -> impl Iterator<Item = &Import> {
+        self.imports.iter().filter(|import| !import.is_recognized())
+    }
+
+    /// Every problem found whose rule isn't turned off, in the order the
+    /// checks ran
+    pub fn findings(&self) -> Vec<Finding> {
+        let mut findings = self.all_findings();
+        findings.retain(|finding| self.severity(finding).is_some());
+        findings
+    }
+
+    /// Severity `finding` is reported with under the configured rules, or
+    /// `None` if its rule is off
+    pub fn severity(&self, finding: &Finding) -> Option<Severity> {
+        self.rules.severity(finding)
+    }
+
+    /// The expected entrypoint as the compiler spells it for the object's
+    /// architecture
+    pub fn entrypoint_symbol(&self) -> String {
+        self.arch.map_or_else(|| self.expected_entrypoint.clone(), |arch| arch.decorate(&self.expected_entrypoint))
+    }
+
+    fn all_findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        if self.arch.is_none() {
+            findings.push(Finding::UnsupportedMachine(self.machine));
+        }
+        if let (Some(arch), Some(expected)) = (self.arch, self.expected_arch) {
+            if arch != expected {
+                findings.push(Finding::ArchMismatch(expected, arch));
+            }
+        }
+        if let (Some(arch), false) = (self.arch, self.arch_supported) {
+            findings.push(Finding::UnsupportedArch(arch, self.target.clone()));
+        }
+        if self.entrypoint.is_none() && self.entrypoint_issues.is_empty() {
+            findings.push(Finding::MissingEntrypoint(self.entrypoint_symbol()));
+        }
+        for issue in &self.entrypoint_issues {
+            findings.push(Finding::Entrypoint(issue.clone()));
+        }
+        for import in &self.imports {
+            match import.kind {
+                // an import decorated for the other architecture is reported on its own
+                _ if self.is_misdecorated(&import.name) => {}
+                ImportKind::UnsupportedApi => findings.push(Finding::UnsupportedApi(import.clone(), self.target.clone())),
+                // a misspelled module is reported with its suggestion below
+                ImportKind::UnrecognizedModule { .. } if !self.is_misspelled(import) => {
+                    findings.push(Finding::UnrecognizedModule(import.clone()))
+                }
+                ImportKind::Unknown => findings.push(Finding::UnknownImport(import.clone())),
+                _ => {}
+            }
+            if !import.referenced {
+                findings.push(Finding::UnreferencedImport(import.clone()));
+            }
+        }
+        for issue in &self.dfr_issues {
+            findings.push(Finding::Dfr(issue.clone()));
+        }
+        for issue in &self.decoration_issues {
+            findings.push(Finding::Decoration(issue.clone()));
+        }
+        for issue in &self.name_issues {
+            findings.push(Finding::SymbolName(issue.clone()));
+        }
+        for issue in &self.opsec_issues {
+            findings.push(Finding::Opsec(issue.clone()));
+        }
+        for leak in &self.path_leaks {
+            findings.push(Finding::BuildPath(leak.clone()));
+        }
+        for issue in &self.spec_issues {
+            findings.push(Finding::Spec(issue.clone()));
+        }
+        for issue in &self.format_issues {
+            findings.push(Finding::Format(issue.clone()));
+        }
+        for issue in &self.injection_issues {
+            findings.push(Finding::Injection(issue.clone()));
+        }
+        for issue in &self.exception_issues {
+            findings.push(Finding::Exceptions(issue.clone()));
+        }
+        for issue in &self.tls_issues {
+            findings.push(Finding::ThreadLocal(issue.clone()));
+        }
+        for issue in &self.initializer_issues {
+            findings.push(Finding::Initializer(issue.clone()));
+        }
+        for external in self.reported_externals() {
+            findings.push(Finding::UnresolvedExternal(external.clone()));
+        }
+        for issue in &self.section_issues {
+            findings.push(Finding::Section(issue.clone()));
+        }
+        for duplicate in &self.duplicate_symbols {
+            findings.push(Finding::DuplicateSymbol(duplicate.clone()));
+        }
+        for issue in &self.comdat_issues {
+            findings.push(Finding::Comdat(issue.clone()));
+        }
+        for issue in &self.checksum_issues {
+            findings.push(Finding::Checksum(issue.clone()));
+        }
+        for issue in &self.weak_issues {
+            findings.push(Finding::WeakExternal(issue.clone()));
+        }
+        for relocation in self.relocations.iter().filter(|relocation| !relocation.supported) {
+            findings.push(Finding::UnsupportedRelocation(relocation.clone()));
+        }
+        if self.estimated_size > self.max_size {
+            findings.push(Finding::Oversized { size: self.estimated_size, limit: self.max_size });
+        }
+        if let Some(stack) = self.stack.as_ref().filter(|stack| stack.size > self.max_stack) {
+            findings.push(Finding::StackUsage { size: stack.size, limit: self.max_stack, chain: stack.chain.clone() });
+        }
+        if !self.dead_code.functions.is_empty() {
+            findings.push(Finding::DeadCode(self.dead_code.clone()));
+        }
+        for finding in &self.custom_findings {
+            findings.push(Finding::Custom(finding.clone()));
+        }
+        findings
+    }
+
+    /// Whether the DFR linter found the import's module to be a misspelling
+    pub fn is_misspelled(&self, import: &Import) -> bool {
+        self.dfr_issues
+            .iter()
+            .any(|issue| issue.import == import.name && issue.problem == DfrProblem::MisspelledModule)
+    }
+
+    /// Whether `name`, an import name or an external symbol, was found to be
+    /// decorated for the other architecture
+    pub fn is_misdecorated(&self, name: &str) -> bool {
+        let prefix = self.arch.map_or("", |arch| arch.import_prefix());
+        self.decoration_issues
+            .iter()
+            .any(|issue| issue.symbol == name || issue.symbol.strip_prefix(prefix) == Some(name))
+    }
+
+    /// Externals reported as unresolved: the misdecorated ones are reported
+    /// as decoration issues, and exception handlers, the TLS runtime and exit
+    /// handlers by their own checks
+    pub fn reported_externals(&self) -> impl Iterator<Item = &External> {
+        self.externals.iter().filter(|external| {
+            !matches!(external.kind, ExternalKind::ExceptionHandler | ExternalKind::ThreadLocal | ExternalKind::ExitHandler)
+                && !self.is_misdecorated(&external.name)
+        })
+    }
+
+    /// Severity of the worst finding, if there are any
+    pub fn max_severity(&self) -> Option<Severity> {
+        self.findings().iter().filter_map(|finding| self.severity(finding)).max()
+    }
+}
+
+/// How serious a finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    /// Likely to work, but worth a second look
+    Warning,
+    /// Will most likely fail to load or run
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem detected while analyzing a BOF
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Finding {
+    /// The COFF header machine type isn't one Beacon can load
+    UnsupportedMachine(u16),
+    /// The target's loader can't run objects built for this architecture
+    UnsupportedArch(Arch, String),
+    /// The object was built for another architecture than the expected one,
+    /// given first
+    ArchMismatch(Arch, Arch),
+    /// The entrypoint symbol the loader calls wasn't found
+    MissingEntrypoint(String),
+    /// A symbol named like the entrypoint that isn't an external function
+    Entrypoint(EntrypointIssue),
+    /// An API function the target's loader doesn't provide
+    UnsupportedApi(Import, String),
+    /// A dynamic function resolution import names a library we don't recognize
+    UnrecognizedModule(Import),
+    /// An import that doesn't match any resolution convention
+    UnknownImport(Import),
+    /// An import nothing relocates against, which only takes up space
+    UnreferencedImport(Import),
+    /// A mistake in a dynamic function resolution import name
+    Dfr(DfrIssue),
+    /// An import decorated for the other architecture
+    Decoration(DecorationIssue),
+    /// An import name too long or unusual for some loaders
+    SymbolName(NameIssue),
+    /// An import of a heavily monitored function
+    Opsec(OpsecIssue),
+    /// An absolute path recording where the BOF was built
+    BuildPath(PathLeak),
+    /// Argument spec doesn't match the data API functions the BOF imports
+    Spec(SpecIssue),
+    /// A Beacon Format API call sequence that will crash or leak
+    Format(FormatIssue),
+    /// An injection without `BeaconCleanupProcess`, leaking process handles
+    Injection(InjectionIssue),
+    /// Exception handling the loader doesn't support
+    Exceptions(ExceptionIssue),
+    /// A symbol that depends on thread local storage
+    ThreadLocal(TlsIssue),
+    /// Startup or shutdown code that never runs
+    Initializer(InitializerIssue),
+    /// An undefined symbol that isn't an import, such as a compiler-emitted
+    /// `memset` call
+    UnresolvedExternal(External),
+    /// A section the loader won't handle the way the compiler intended
+    Section(SectionIssue),
+    /// An external symbol defined more than once
+    DuplicateSymbol(DuplicateSymbol),
+    /// A COMDAT section a linker would have dropped, or a malformed one
+    Comdat(ComdatIssue),
+    /// A COMDAT whose checksum is stale
+    Checksum(ChecksumIssue),
+    /// A weak external loaders can't resolve, or whose default is undefined
+    WeakExternal(WeakIssue),
+    /// A relocation type the loader can't apply
+    UnsupportedRelocation(Relocation),
+    /// The BOF won't fit in a single Beacon task
+    Oversized { size: usize, limit: usize },
+    /// The entrypoint's deepest call chain, `chain`, may need more stack
+    /// than the limit
+    StackUsage { size: usize, limit: usize, chain: Vec<String> },
+    /// Functions no entrypoint reaches
+    DeadCode(DeadCode),
+    /// A finding of an extra rule
+    Custom(CustomFinding),
+}
+
+impl Finding {
+    /// Name of the rule the finding belongs to, see [`rules::RULES`](crate::rules::RULES)
+    pub fn rule(&self) -> &str {
+        match self {
+            Self::UnsupportedMachine(_) => "machine",
+            Self::UnsupportedArch(..) | Self::ArchMismatch(..) => "arch",
+            Self::MissingEntrypoint(_) | Self::Entrypoint(_) => "entrypoint",
+            Self::UnsupportedApi(..) => "unsupported-api",
+            Self::UnrecognizedModule(_) => "unrecognized-module",
+            Self::UnknownImport(_) => "unknown-import",
+            Self::UnreferencedImport(_) => "unreferenced-import",
+            Self::Dfr(_) => "dfr",
+            Self::Decoration(_) => "decoration",
+            Self::SymbolName(_) => "symbol-name",
+            Self::Opsec(_) => "opsec",
+            Self::BuildPath(_) => "build-path",
+            Self::Spec(_) => "spec",
+            Self::Format(_) => "format-api",
+            Self::Injection(_) => "inject-cleanup",
+            Self::Exceptions(_) => "exceptions",
+            Self::ThreadLocal(_) => "thread-local",
+            Self::Initializer(_) => "static-initializers",
+            Self::UnresolvedExternal(_) => "unresolved-external",
+            Self::Section(_) => "sections",
+            Self::DuplicateSymbol(_) => "duplicate-symbols",
+            Self::Comdat(_) => "comdat",
+            Self::Checksum(_) => "checksums",
+            Self::WeakExternal(_) => "weak-externals",
+            Self::UnsupportedRelocation(_) => "relocations",
+            Self::Oversized { .. } => "size",
+            Self::StackUsage { .. } => "stack-usage",
+            Self::DeadCode(_) => "dead-code",
+            Self::Custom(finding) => &finding.rule,
+        }
+    }
+
+    /// ATT&CK technique ID the finding's import is most often used for
+    pub fn technique(&self) -> Option<&'static str> {
+        match self {
+            Self::Opsec(issue) => {
+                let function = issue.import.rsplit_once('$').map_or(issue.import.as_str(), |(_, function)| function);
+                capabilities::lookup(function).map(|(_, technique)| technique)
+            }
+            _ => None,
+        }
+    }
+
+    /// Default severity, before any rule configuration
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::UnsupportedMachine(_) => Severity::Error,
+            Self::UnsupportedArch(..) => Severity::Error,
+            Self::ArchMismatch(..) => Severity::Error,
+            Self::MissingEntrypoint(_) => Severity::Error,
+            Self::Entrypoint(issue) => issue.severity(),
+            Self::UnsupportedApi(..) => Severity::Error,
+            Self::UnrecognizedModule(_) => Severity::Warning,
+            Self::UnknownImport(_) => Severity::Error,
+            Self::UnreferencedImport(_) => Severity::Warning,
+            Self::Dfr(issue) => issue.severity(),
+            Self::Decoration(issue) => issue.severity(),
+            Self::SymbolName(issue) => issue.severity(),
+            Self::Opsec(issue) => issue.severity,
+            Self::BuildPath(_) => Severity::Warning,
+            Self::Spec(issue) => issue.severity(),
+            Self::Format(issue) => issue.severity(),
+            Self::Injection(issue) => issue.severity(),
+            Self::Exceptions(issue) => issue.severity(),
+            Self::ThreadLocal(issue) => issue.severity(),
+            Self::Initializer(issue) => issue.severity(),
+            Self::UnresolvedExternal(_) => Severity::Error,
+            Self::Section(issue) => issue.severity(),
+            Self::DuplicateSymbol(duplicate) => duplicate.severity(),
+            Self::Comdat(issue) => issue.severity(),
+            Self::Checksum(issue) => issue.severity(),
+            Self::WeakExternal(issue) => issue.severity(),
+            Self::UnsupportedRelocation(_) => Severity::Error,
+            Self::Oversized { .. } => Severity::Warning,
+            Self::StackUsage { .. } => Severity::Warning,
+            Self::DeadCode(dead) => dead.severity(),
+            Self::Custom(finding) => finding.severity,
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedMachine(machine) => write!(f, "unsupported machine type: 0x{:04x}", machine),
+            Self::UnsupportedArch(arch, target) => write!(f, "{} objects can't be loaded by {}", arch, target),
+            Self::ArchMismatch(expected, arch) => write!(f, "built for {}, expected {}", arch, expected),
+            Self::MissingEntrypoint(name) => write!(f, "entrypoint not found: {}", name),
+            Self::Entrypoint(issue) => write!(f, "{}", issue),
+            Self::UnsupportedApi(import, target) => write!(f, "api function not provided by {}: {}", target, import.name),
+            Self::UnrecognizedModule(import) => write!(f, "unrecognized win32 library: {}", import.name),
+            Self::UnknownImport(import) => write!(f, "unknown import: {}", import.name),
+            Self::UnreferencedImport(import) => write!(f, "import is never referenced: {}", import.name),
+            Self::Dfr(issue) => write!(f, "{}", issue),
+            Self::Decoration(issue) => write!(f, "{}", issue),
+            Self::SymbolName(issue) => write!(f, "{}", issue),
+            Self::Opsec(issue) => write!(f, "{}", issue),
+            Self::BuildPath(leak) => write!(f, "{}", leak),
+            Self::Spec(issue) => write!(f, "{}", issue),
+            Self::Format(issue) => write!(f, "{}", issue),
+            Self::Injection(issue) => write!(f, "{}", issue),
+            Self::Exceptions(issue) => write!(f, "{}", issue),
+            Self::ThreadLocal(issue) => write!(f, "{}", issue),
+            Self::Initializer(issue) => write!(f, "{}", issue),
+            Self::UnresolvedExternal(external) => write!(f, "{}", external),
+            Self::Section(issue) => write!(f, "{}", issue),
+            Self::DuplicateSymbol(duplicate) => write!(f, "{}", duplicate),
+            Self::Comdat(issue) => write!(f, "{}", issue),
+            Self::Checksum(issue) => write!(f, "{}", issue),
+            Self::WeakExternal(issue) => write!(f, "{}", issue),
+            Self::UnsupportedRelocation(relocation) => {
+                write!(f, "unsupported relocation: {} at {}+0x{:x} against {}",
+                    relocation.type_name, relocation.section, relocation.offset, relocation.symbol)?;
+                match &relocation.target_section {
+                    Some(section) if section != &relocation.symbol => write!(f, " (in {})", section),
+                    _ => Ok(()),
+                }
+            }
+            Self::Oversized { size, limit } => {
+                write!(f, "estimated loaded size of {} bytes exceeds the task size limit of {} bytes", size, limit)
+            }
+            Self::StackUsage { size, limit, chain } => write!(
+                f,
+                "estimated stack usage of {} bytes exceeds the limit of {} bytes: {}",
+                size,
+                limit,
+                chain.join(" -> ")
+            ),
+            Self::DeadCode(dead) => write!(f, "{}", dead),
+            Self::Custom(finding) => write!(f, "{}", finding),
+        }
+    }
+}
+
+/// A report with its findings already worked out, for bindings to languages
+/// that can't call [`AnalysisReport::findings`]
+#[cfg(any(feature = "wasm", feature = "ffi"))]
+#[derive(serde::Serialize)]
+pub(crate) struct ResolvedReport {
+    #[serde(flatten)]
+    report: AnalysisReport,
+    findings: Vec<ResolvedFinding>,
+}
+
+#[cfg(any(feature = "wasm", feature = "ffi"))]
+#[derive(serde::Serialize)]
+struct ResolvedFinding {
+    rule: String,
+    severity: Severity,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    technique: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fix: Option<crate::FixIt>,
+}
+
+#[cfg(any(feature = "wasm", feature = "ffi"))]
+impl ResolvedReport {
+    pub(crate) fn new(report: AnalysisReport) -> Self {
+        let findings = report
+            .findings()
+            .iter()
+            .filter_map(|finding| {
+                Some(ResolvedFinding {
+                    rule: finding.rule().to_string(),
+                    severity: report.severity(finding)?,
+                    message: finding.to_string(),
+                    technique: finding.technique(),
+                    fix: report.fix(finding),
+                })
+            })
+            .collect();
+        Self { report, findings }
+    }
+
+    /// Worst severity among the findings
+    #[cfg(feature = "ffi")]
+    pub(crate) fn max_severity(&self) -> Option<Severity> {
+        self.findings.iter().map(|finding| finding.severity).max()
+    }
+}
@@ -0,0 +1,109 @@
+//! Extra rules read from a file
+//!
+//! Covers the checks most teams add on top of the built-in ones, without
+//! writing a [`Rule`] of their own:
+//!
+//! ```toml
+//! [[rules]]
+//! name = "no-remote-threads"
+//! severity = "error"
+//! message = "use the injection kit instead"
+//! banned-imports = ["CreateRemoteThread", "NTDLL$NtCreateThreadEx"]
+//!
+//! [[rules]]
+//! name = "build-marker"
+//! required-strings = ["ACME-BOF"]
+//! required-symbols = ["acme_version"]
+//! ```
+//!
+//! A banned import matches either the whole import name or its function.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::rules::{ConfigError, CustomFinding, Level, Rule, RULES};
+use crate::{is_loaded, AnalysisReport, Bof, ImportKind, Severity};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    rules: Vec<FileRule>,
+}
+
+/// A rule from a rule file
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct FileRule {
+    pub name: String,
+    /// Level of the rule's findings, warn if not given
+    pub severity: Option<Level>,
+    /// Added to every finding, e.g. what to do instead
+    pub message: Option<String>,
+    #[serde(default)]
+    pub banned_imports: Vec<String>,
+    /// Strings that have to appear in a loaded section, as ASCII or UTF-16
+    #[serde(default)]
+    pub required_strings: Vec<String>,
+    #[serde(default)]
+    pub required_symbols: Vec<String>,
+}
+
+/// Read the rules of a TOML rule file
+pub fn load(path: &Path) -> Result<Vec<FileRule>, ConfigError> {
+    let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+    let file = toml::from_str::<RuleFile>(&text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+    if let Some(rule) = file.rules.iter().find(|rule| RULES.iter().any(|(name, _)| *name == rule.name)) {
+        return Err(ConfigError::Parse(format!("rule {} is built in", rule.name)));
+    }
+    Ok(file.rules)
+}
+
+impl FileRule {
+    fn finding(&self, severity: Severity, problem: String) -> CustomFinding {
+        let message = match &self.message {
+            Some(message) => format!("{} ({})", problem, message),
+            None => problem,
+        };
+        CustomFinding::new(self, severity, message)
+    }
+}
+
+impl Rule for FileRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, bof: &Bof, report: &mut AnalysisReport) {
+        let severity = match self.severity.unwrap_or(Level::Warn).severity() {
+            Some(severity) => severity,
+            None => return,
+        };
+        let mut findings = Vec::new();
+        for import in &report.imports {
+            let function = match &import.kind {
+                ImportKind::Dynamic { function, .. } | ImportKind::UnrecognizedModule { function, .. } => function,
+                _ => &import.name,
+            };
+            if self.banned_imports.iter().any(|banned| *banned == import.name || banned == function) {
+                findings.push(self.finding(severity, format!("banned import: {}", import.name)));
+            }
+        }
+        for string in &self.required_strings {
+            let ascii = string.as_bytes().to_vec();
+            let utf16: Vec<u8> = string.encode_utf16().flat_map(u16::to_le_bytes).collect();
+            let found = bof.coff.sections.iter().filter(|section| is_loaded(section)).any(|section| {
+                let data = bof.section_contents(section);
+                [&ascii, &utf16].iter().any(|needle| data.windows(needle.len()).any(|window| window == needle.as_slice()))
+            });
+            if !found && !string.is_empty() {
+                findings.push(self.finding(severity, format!("required string not found: {}", string)));
+            }
+        }
+        for symbol in self.required_symbols.iter().filter(|symbol| !bof.has_symbol(symbol)) {
+            findings.push(self.finding(severity, format!("required symbol not found: {}", symbol)));
+        }
+        report.custom_findings.extend(findings);
+    }
+}
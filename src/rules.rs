@@ -0,0 +1,224 @@
+//! Per-team policy for which findings matter
+//!
+//! Every kind of finding belongs to a named rule, and a `.bofkit.toml` file
+//! can turn a rule off or change how serious it is:
+//!
+//! ```toml
+//! [rules]
+//! unrecognized-module = "off"
+//! opsec = "error"
+//! size = "warn"
+//! ```
+//!
+//! Rules the file doesn't mention keep the severity of their findings.
+//!
+//! Other crates can add their own checks by implementing [`Rule`] and
+//! registering them in a [`RuleRegistry`]. Their findings go through the
+//! same configuration, by the rule's name.
+
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::{AnalysisReport, Bof, Finding, Severity};
+
+/// File name the configuration is discovered by
+pub const CONFIG_FILE: &str = ".bofkit.toml";
+
+/// Every built-in rule, with what it checks
+pub static RULES: &[(&str, &str)] = &[
+    ("machine", "the machine type is one BOF loaders handle"),
+    ("arch", "the target's loader runs objects of this architecture"),
+    ("entrypoint", "the entrypoint the loader calls is defined"),
+    ("unsupported-api", "no imports of another loader's API"),
+    ("unrecognized-module", "dynamic function resolution imports name a known library"),
+    ("unknown-import", "every import follows a convention the loader resolves"),
+    ("unreferenced-import", "every import is referenced"),
+    ("dfr", "dynamic function resolution imports are spelled and decorated correctly"),
+    ("decoration", "imports are decorated for the object's architecture"),
+    ("symbol-name", "import names are short and plain enough for every loader"),
+    ("opsec", "no imports of heavily monitored functions"),
+    ("build-path", "no absolute build paths or user names in the object"),
+    ("spec", "the argument spec matches the data API calls"),
+    ("format-api", "Beacon Format buffers are allocated before use and freed"),
+    ("inject-cleanup", "processes injected into are cleaned up with BeaconCleanupProcess"),
+    ("exceptions", "no unwind tables or exception handlers, which the loader doesn't support"),
+    ("thread-local", "no thread local variables, which the loader never allocates"),
+    ("static-initializers", "no static constructors, destructors or exit handlers, which the loader never runs"),
+    ("unresolved-external", "no undefined symbols other than imports"),
+    ("sections", "no sections the loader mishandles"),
+    ("duplicate-symbols", "every external symbol is defined once"),
+    ("comdat", "every COMDAT section is referenced and well formed"),
+    ("checksums", "every COMDAT checksum matches the section's contents"),
+    ("weak-externals", "no weak externals the loader can't resolve"),
+    ("relocations", "every relocation type is supported by the loader"),
+    ("size", "the BOF fits in a single task"),
+    ("stack-usage", "the entrypoint's deepest call chain fits on the stack"),
+    ("dead-code", "every function is reachable from an entrypoint"),
+];
+
+/// What to do with a rule's findings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Don't report them
+    Off,
+    #[serde(alias = "warning")]
+    Warn,
+    Error,
+}
+
+impl Level {
+    /// Severity findings are reported with, or `None` if they aren't
+    pub fn severity(self) -> Option<Severity> {
+        match self {
+            Self::Off => None,
+            Self::Warn => Some(Severity::Warning),
+            Self::Error => Some(Severity::Error),
+        }
+    }
+}
+
+/// Levels of the rules a configuration file overrides
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleConfig {
+    pub rules: BTreeMap<String, Level>,
+}
+
+impl RuleConfig {
+    /// Parse a configuration from TOML
+    ///
+    /// Rule names aren't checked, since extra rules may not be registered
+    /// yet; see [`RuleConfig::validate`].
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        toml::from_str::<Self>(text).map_err(|e| ConfigError::Parse(e.to_string()))
+    }
+
+    /// Check that every configured rule is built in or in `registry`
+    pub fn validate(&self, registry: &RuleRegistry) -> Result<(), ConfigError> {
+        let known = |rule: &str| RULES.iter().any(|(name, _)| *name == rule) || registry.names().any(|name| name == rule);
+        match self.rules.keys().find(|rule| !known(rule)) {
+            Some(rule) => Err(ConfigError::UnknownRule(rule.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// Read a configuration file
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::from_toml(&fs::read_to_string(path).map_err(ConfigError::Io)?)
+    }
+
+    /// The nearest `.bofkit.toml` in `dir` or one of its parents
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors().map(|dir| dir.join(CONFIG_FILE)).find(|path| path.is_file())
+    }
+
+    /// Severity `finding` is reported with, or `None` if its rule is off
+    pub fn severity(&self, finding: &Finding) -> Option<Severity> {
+        match self.rules.get(finding.rule()) {
+            Some(level) => level.severity(),
+            None => Some(finding.severity()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration file couldn't be read
+    Io(io::Error),
+    /// The configuration isn't valid TOML, or has unexpected contents
+    Parse(String),
+    /// A rule that doesn't exist
+    UnknownRule(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read {}: {}", CONFIG_FILE, e),
+            Self::Parse(message) => write!(f, "invalid {}: {}", CONFIG_FILE, message),
+            Self::UnknownRule(rule) => write!(f, "unknown rule in {}: {}", CONFIG_FILE, rule),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+/// A check beyond the built-in ones, such as an organization's banned APIs
+///
+/// Rules run after the built-in checks and add [`CustomFinding`]s to the
+/// report.
+pub trait Rule: Send + Sync {
+    /// Name the rule is configured and reported by
+    fn name(&self) -> &str;
+
+    fn check(&self, bof: &Bof, report: &mut AnalysisReport);
+}
+
+/// A finding added by a [`Rule`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomFinding {
+    /// Name of the rule that added it
+    pub rule: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl CustomFinding {
+    pub fn new(rule: &dyn Rule, severity: Severity, message: impl Into<String>) -> Self {
+        Self { rule: rule.name().to_string(), severity, message: message.into() }
+    }
+}
+
+impl fmt::Display for CustomFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.rule, self.message)
+    }
+}
+
+/// Extra rules to run with every analysis
+#[derive(Clone, Default)]
+pub struct RuleRegistry {
+    rules: Vec<Arc<dyn Rule>>,
+}
+
+impl RuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, rule: impl Rule + 'static) {
+        self.rules.push(Arc::new(rule));
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.rules.iter().map(|rule| rule.name())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Run every rule, in the order they were registered
+    pub fn run(&self, bof: &Bof, report: &mut AnalysisReport) {
+        for rule in &self.rules {
+            rule.check(bof, report);
+        }
+    }
+}
+
+impl fmt::Debug for RuleRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
@@ -0,0 +1,130 @@
+//! New BOF projects
+//!
+//! A project is the smallest set of files that builds and runs: `beacon.h`,
+//! a source file whose entrypoint parses the arguments of a spec, a
+//! Makefile for MinGW-w64 and MSVC using the flags of [`crate::compile`],
+//! the spec as `args.toml` and an Aggressor script that packs the same
+//! arguments.
+
+use std::fmt::Write;
+
+use crate::cna::Script;
+use crate::profile::CobaltStrike;
+use crate::{compile, ArgSpec, Arch, BEACON_ENTRYPOINT};
+
+const BEACON_H: &str = include_str!("../data/templates/beacon.h");
+
+/// A file of a new project
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectFile {
+    /// Path relative to the project directory
+    pub path: String,
+    pub contents: String,
+}
+
+/// The files of a BOF called `name` taking the arguments in `spec`
+pub fn project(name: &str, spec: &ArgSpec) -> Vec<ProjectFile> {
+    let file = |path: String, contents: String| ProjectFile { path, contents };
+    let entrypoint = spec.entrypoint.as_deref().unwrap_or(BEACON_ENTRYPOINT);
+    let script = Script::from_spec(name, entrypoint, &format!("{}.x64.o", name), None, spec);
+    let makefile = compile::makefile(name, &CobaltStrike::default(), &[Arch::X64, Arch::X86], Some("args.toml"))
+        .expect("Cobalt Strike runs x64 and x86 objects");
+    vec![
+        file("beacon.h".to_string(), BEACON_H.to_string()),
+        file(format!("{}.c", name), source(name, entrypoint, spec)),
+        file("Makefile".to_string(), makefile),
+        file("args.toml".to_string(), args_toml(name, spec)),
+        file(format!("{}.cna", name), script.to_cna()),
+        file(".gitignore".to_string(), "*.o\n".to_string()),
+    ]
+}
+
+/// Entrypoint that reads each argument and prints it back
+fn source(name: &str, entrypoint: &str, spec: &ArgSpec) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "/* {} BOF, generated by bof-new */", name);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "#include <windows.h>");
+    let _ = writeln!(out, "#include \"beacon.h\"");
+    let _ = writeln!(out);
+    let _ = writeln!(out, "void {}(char *args, int len) {{", entrypoint);
+    if spec.arguments.is_empty() {
+        let _ = writeln!(out, "    BeaconPrintf(CALLBACK_OUTPUT, \"{} ran\");", name);
+        let _ = writeln!(out, "}}");
+        return out;
+    }
+    let _ = writeln!(out, "    datap parser;");
+    let _ = writeln!(out, "    BeaconDataParse(&parser, args, len);");
+    let mut formats = Vec::new();
+    let mut values = Vec::new();
+    for argument in &spec.arguments {
+        let variable = identifier(&argument.name);
+        let _ = match argument.typ {
+            'i' => writeln!(out, "    int {} = BeaconDataInt(&parser);", variable),
+            's' => writeln!(out, "    short {} = BeaconDataShort(&parser);", variable),
+            'Z' => writeln!(out, "    wchar_t *{} = (wchar_t *)BeaconDataExtract(&parser, NULL);", variable),
+            'b' => {
+                let _ = writeln!(out, "    int {}_len = 0;", variable);
+                writeln!(out, "    char *{0} = BeaconDataExtract(&parser, &{0}_len);", variable)
+            }
+            _ => writeln!(out, "    char *{} = BeaconDataExtract(&parser, NULL);", variable),
+        };
+        let (format, value) = match argument.typ {
+            'i' => ("%d", variable.clone()),
+            's' => ("%hd", variable.clone()),
+            'Z' => ("%ls", variable.clone()),
+            'b' => ("%d bytes at %p", format!("{0}_len, {0}", variable)),
+            _ => ("%s", variable.clone()),
+        };
+        formats.push(format!("{}={}", argument.name.replace('%', "%%"), format));
+        values.push(value);
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    BeaconPrintf(CALLBACK_OUTPUT, \"{}\\n\", {});", formats.join(" ").replace('"', "\\\""), values.join(", "));
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// The spec as TOML, in the format [`ArgSpec::from_toml`] reads
+fn args_toml(name: &str, spec: &ArgSpec) -> String {
+    let quote = |value: &str| toml::Value::String(value.to_string()).to_string();
+    let mut out = String::new();
+    let _ = writeln!(out, "name = {}", quote(spec.name.as_deref().unwrap_or(name)));
+    let _ = writeln!(out, "description = {}", quote(&spec.description));
+    if let Some(entrypoint) = &spec.entrypoint {
+        let _ = writeln!(out, "entrypoint = {}", quote(entrypoint));
+    }
+    for argument in &spec.arguments {
+        let typ = match argument.typ {
+            'b' => "binary",
+            'i' => "int",
+            's' => "short",
+            'Z' => "wstring",
+            _ => "string",
+        };
+        let _ = writeln!(out);
+        let _ = writeln!(out, "[[arguments]]");
+        let _ = writeln!(out, "name = {}", quote(&argument.name));
+        let _ = writeln!(out, "type = {}", quote(typ));
+        if !argument.description.is_empty() {
+            let _ = writeln!(out, "description = {}", quote(&argument.description));
+        }
+        if let Some(default) = &argument.default {
+            let _ = writeln!(out, "default = {}", quote(default));
+        }
+    }
+    out
+}
+
+/// A C identifier for an argument name
+fn identifier(name: &str) -> String {
+    let mut identifier: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    if !identifier.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        identifier.insert(0, '_');
+    }
+    // the entrypoint's own names, and keywords an argument is likely to be called
+    if ["args", "len", "parser", "char", "default", "int", "short", "long", "signed", "unsigned"].contains(&identifier.as_str()) {
+        identifier.push_str("_arg");
+    }
+    identifier
+}
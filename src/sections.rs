@@ -0,0 +1,193 @@
+//! Section hygiene checks
+//!
+//! BOF loaders map sections and apply relocations, but don't run any of the
+//! CRT startup machinery a linker would wire up, so some sections are dead
+//! weight or quietly broken.
+
+use std::fmt;
+
+use goblin::pe::section_table::{
+    SectionTable, IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_UNINITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ,
+    IMAGE_SCN_MEM_WRITE,
+};
+
+use crate::{section_name, Severity, NOT_LOADED};
+
+/// Writable sections larger than this are flagged
+pub const LARGE_WRITABLE_SIZE: usize = 4096;
+
+/// Data sections with more bits of entropy per byte than this are flagged;
+/// compiled code and text sit well below it, encrypted or compressed data
+/// close to 8
+pub const HIGH_ENTROPY: f64 = 7.2;
+
+/// Sections smaller than this aren't checked for entropy, since a short
+/// run of bytes can't show much of it
+pub const MIN_ENTROPY_SIZE: usize = 256;
+
+/// A section of a parsed object, with its flags decoded and its contents
+/// borrowed from the input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BofSection<'a> {
+    /// Name, looked up in the string table for long names
+    pub name: String,
+    /// Raw `IMAGE_SCN_*` flags
+    pub characteristics: u32,
+    pub executable: bool,
+    pub writable: bool,
+    pub readable: bool,
+    /// Whether the loader maps it, rather than skipping it as debug
+    /// information or linker directives
+    pub loaded: bool,
+    /// Size in bytes, including uninitialized data
+    pub size: usize,
+    /// Raw contents, empty for uninitialized data
+    pub data: &'a [u8],
+    pub relocation_count: usize,
+}
+
+impl<'a> BofSection<'a> {
+    pub(crate) fn new(table: &SectionTable, data: &'a [u8]) -> Self {
+        let flags = table.characteristics;
+        Self {
+            name: section_name(table),
+            characteristics: flags,
+            executable: flags & IMAGE_SCN_MEM_EXECUTE != 0,
+            writable: flags & IMAGE_SCN_MEM_WRITE != 0,
+            readable: flags & IMAGE_SCN_MEM_READ != 0,
+            loaded: flags & NOT_LOADED == 0,
+            size: table.size_of_raw_data.max(table.virtual_size) as usize,
+            data,
+            relocation_count: table.number_of_relocations as usize,
+        }
+    }
+}
+
+/// A section's name, size and contents at a glance
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Section {
+    pub name: String,
+    /// Size in bytes, including uninitialized data
+    pub size: usize,
+    pub characteristics: u32,
+    entropy: f64,
+}
+
+impl Section {
+    pub(crate) fn new(section: &BofSection) -> Self {
+        Self {
+            name: section.name.clone(),
+            size: section.size,
+            characteristics: section.characteristics,
+            entropy: entropy(section.data),
+        }
+    }
+
+    /// Shannon entropy of the raw data, in bits per byte
+    pub fn entropy(&self) -> f64 {
+        self.entropy
+    }
+
+    /// Whether the section holds initialized data rather than code
+    pub fn is_data(&self) -> bool {
+        self.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_UNINITIALIZED_DATA) == 0
+    }
+
+    /// Whether this is a data section with suspiciously random contents
+    pub fn is_high_entropy(&self) -> bool {
+        self.is_data() && self.size >= MIN_ENTROPY_SIZE && self.entropy > HIGH_ENTROPY
+    }
+}
+
+/// Shannon entropy of `data` in bits per byte, from 0 to 8
+pub fn entropy(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for byte in data {
+        counts[*byte as usize] += 1;
+    }
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            p * (1.0 / p).log2()
+        })
+        .sum()
+}
+
+/// What's wrong with a section
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SectionProblem {
+    /// A large `.data`/`.bss`-style section; global state is shared with
+    /// every call and counts towards the task size
+    LargeWritable,
+    /// Thread local storage, which the loader doesn't set up
+    ThreadLocal,
+    /// Debug information, which only makes the object bigger
+    Debug,
+    /// Data that looks encrypted or compressed, such as an embedded payload
+    HighEntropy,
+}
+
+/// A problem with a section, with its size in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SectionIssue {
+    pub section: String,
+    pub size: usize,
+    pub problem: SectionProblem,
+}
+
+impl SectionIssue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            SectionProblem::ThreadLocal => Severity::Error,
+            SectionProblem::LargeWritable
+            | SectionProblem::Debug
+            | SectionProblem::HighEntropy => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for SectionIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (section, size) = (&self.section, self.size);
+        match self.problem {
+            SectionProblem::LargeWritable => write!(f, "large writable section: {} ({} bytes of global state)", section, size),
+            SectionProblem::ThreadLocal => write!(f, "thread local storage isn't supported: {} ({} bytes)", section, size),
+            SectionProblem::Debug => write!(f, "debug section adds {} bytes: {}", size, section),
+            SectionProblem::HighEntropy => {
+                write!(f, "high entropy data, possibly an encrypted payload: {} ({} bytes)", section, size)
+            }
+        }
+    }
+}
+
+/// Check every section for problems, in section table order
+pub fn lint(sections: &[Section]) -> Vec<SectionIssue> {
+    sections
+        .iter()
+        .filter_map(|section| {
+            let (name, size) = (section.name.clone(), section.size);
+            let writable = section.characteristics & (IMAGE_SCN_MEM_WRITE | IMAGE_SCN_CNT_UNINITIALIZED_DATA) != 0;
+            let problem = if name == ".tls" || name.starts_with(".tls$") {
+                SectionProblem::ThreadLocal
+            } else if name.starts_with(".debug") {
+                SectionProblem::Debug
+            } else if writable && size > LARGE_WRITABLE_SIZE {
+                SectionProblem::LargeWritable
+            } else if section.is_high_entropy() {
+                SectionProblem::HighEntropy
+            } else {
+                return None;
+            };
+            Some(SectionIssue { section: name, size, problem })
+        })
+        .collect()
+}
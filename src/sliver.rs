@@ -0,0 +1,241 @@
+//! Sliver extension support
+//!
+//! Sliver runs BOFs through its `coff-loader` extension, which provides a
+//! subset of the Beacon API, and describes each BOF with an `extension.json`
+//! manifest.
+//! https://github.com/sliverarmory/COFFLoader
+//! https://sliver.sh/docs?name=Aliases+and+Extensions
+
+use crate::{ArgSpec, Arch};
+
+/// Beacon API functions provided by Sliver's COFF loader
+pub static SLIVER_EXPORTS: &[&str] = &[
+    // data API
+    "BeaconDataParse",
+    "BeaconDataInt",
+    "BeaconDataShort",
+    "BeaconDataLength",
+    "BeaconDataExtract",
+    // format API
+    "BeaconFormatAlloc",
+    "BeaconFormatReset",
+    "BeaconFormatFree",
+    "BeaconFormatAppend",
+    "BeaconFormatPrintf",
+    "BeaconFormatToString",
+    "BeaconFormatInt",
+    // Output Functions
+    "BeaconPrintf",
+    "BeaconOutput",
+    // Token Functions
+    "BeaconUseToken",
+    "BeaconRevertToken",
+    "BeaconIsAdmin",
+    // Spawn+Inject Functions
+    "BeaconGetSpawnTo",
+    "BeaconSpawnTemporaryProcess",
+    "BeaconInjectProcess",
+    "BeaconInjectTemporaryProcess",
+    "BeaconCleanupProcess",
+    // Utility Functions
+    "toWideChar",
+];
+
+/// Win32 functions resolved internally by Sliver's COFF loader
+pub static SLIVER_BUILTIN: &[&str] = &[
+    "GetProcAddress",
+    "LoadLibraryA",
+    "GetModuleHandleA",
+    "FreeLibrary",
+    "__C_specific_handler",
+];
+
+/// An object file for one architecture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestFile {
+    pub arch: Arch,
+    /// Path relative to the manifest
+    pub path: String,
+}
+
+/// An argument the extension command accepts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestArgument {
+    pub name: String,
+    pub description: String,
+    /// `bof_pack()` format character of the argument
+    pub typ: char,
+    pub optional: bool,
+}
+
+/// Contents of an `extension.json` manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub command_name: String,
+    pub help: String,
+    /// Who packaged the extension and who wrote the BOF, which the armory
+    /// format records
+    pub author: String,
+    pub original_author: String,
+    pub repo_url: String,
+    pub entrypoint: String,
+    pub files: Vec<ManifestFile>,
+    pub arguments: Vec<ManifestArgument>,
+}
+
+impl Manifest {
+    /// Manifest for a single object, with one argument per character of a
+    /// `bof_pack()` format string
+    pub fn new(name: &str, entrypoint: &str, file: ManifestFile, format: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            version: "v0.0.1".to_string(),
+            command_name: name.to_string(),
+            help: String::new(),
+            author: String::new(),
+            original_author: String::new(),
+            repo_url: String::new(),
+            entrypoint: entrypoint.to_string(),
+            files: vec![file],
+            arguments: format
+                .chars()
+                .enumerate()
+                .map(|(index, typ)| ManifestArgument {
+                    name: format!("arg{}", index),
+                    description: String::new(),
+                    typ,
+                    optional: false,
+                })
+                .collect(),
+        }
+    }
+
+    /// Manifest for a single object with the arguments, help and entrypoint
+    /// of a spec, falling back to `name` and `entrypoint`
+    pub fn from_spec(name: &str, entrypoint: &str, file: ManifestFile, spec: &ArgSpec) -> Self {
+        let name = spec.name.as_deref().unwrap_or(name);
+        let mut manifest = Self::new(name, spec.entrypoint.as_deref().unwrap_or(entrypoint), file, "");
+        manifest.help = spec.description.clone();
+        manifest.arguments = spec
+            .arguments
+            .iter()
+            .map(|argument| ManifestArgument {
+                name: argument.name.clone(),
+                description: argument.description.clone(),
+                typ: argument.typ,
+                optional: argument.default.is_some(),
+            })
+            .collect();
+        manifest
+    }
+
+    /// The `extension.json` of Sliver before 1.6, one command per manifest
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\n  \"name\": {},\n  \"version\": {},\n{}}}\n",
+            quote(&self.name),
+            quote(&self.version),
+            self.command_json("  ")
+        )
+    }
+
+    /// The `extension.json` armory packages use since Sliver 1.6, with the
+    /// authors and the command in a `commands` list
+    pub fn to_armory_json(&self) -> String {
+        format!(
+            "{{\n  \"name\": {},\n  \"version\": {},\n  \"extension_author\": {},\n  \"original_author\": {},\n  \
+             \"repo_url\": {},\n  \"commands\": [\n    {{\n{}    }}\n  ]\n}}\n",
+            quote(&self.name),
+            quote(&self.version),
+            quote(&self.author),
+            quote(match self.original_author.is_empty() {
+                true => &self.author,
+                false => &self.original_author,
+            }),
+            quote(&self.repo_url),
+            self.command_json("      ")
+        )
+    }
+
+    /// The command's keys, each line indented by `indent`
+    fn command_json(&self, indent: &str) -> String {
+        let files = self
+            .files
+            .iter()
+            .map(|file| {
+                format!(
+                    "{}  {{ \"os\": \"windows\", \"arch\": {}, \"path\": {} }}",
+                    indent,
+                    quote(arch_name(file.arch)),
+                    quote(&file.path)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let arguments = self
+            .arguments
+            .iter()
+            .map(|argument| {
+                format!(
+                    "{}  {{ \"name\": {}, \"desc\": {}, \"type\": {}, \"optional\": {} }}",
+                    indent,
+                    quote(&argument.name),
+                    quote(&argument.description),
+                    quote(argument_type(argument.typ)),
+                    argument.optional
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        format!(
+            "{i}\"command_name\": {},\n{i}\"help\": {},\n{i}\"long_help\": \"\",\n{i}\"depends_on\": \"coff-loader\",\n\
+             {i}\"entrypoint\": {},\n{i}\"files\": [\n{}\n{i}],\n{i}\"arguments\": [\n{}\n{i}]\n",
+            quote(&self.command_name),
+            quote(&self.help),
+            quote(&self.entrypoint),
+            files,
+            arguments,
+            i = indent
+        )
+    }
+}
+
+/// Sliver's name for an architecture (Go's GOARCH)
+pub fn arch_name(arch: Arch) -> &'static str {
+    match arch {
+        Arch::X86 => "386",
+        Arch::X64 => "amd64",
+        Arch::Arm64 => "arm64",
+    }
+}
+
+/// Sliver's name for a `bof_pack()` argument type
+pub fn argument_type(typ: char) -> &'static str {
+    match typ {
+        'i' => "int",
+        's' => "short",
+        'z' => "string",
+        'Z' => "wstring",
+        _ => "file",
+    }
+}
+
+fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
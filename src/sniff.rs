@@ -0,0 +1,126 @@
+//! Inputs that aren't the COFF objects they're meant to be
+//!
+//! Pointed at the wrong file, goblin fails with an error about section
+//! counts or buffer sizes that says nothing about the actual mistake.
+//! [`sniff`] recognizes the usual wrong inputs by their magic so the error
+//! can say what the file is and how to build a BOF instead.
+
+use std::fmt;
+
+use goblin::pe::section_table::SectionTable;
+
+use crate::section_name;
+
+/// LLVM bitcode, `BC` 0xC0DE
+const BITCODE_MAGIC: &[u8] = &[0x42, 0x43, 0xc0, 0xde];
+/// LLVM bitcode wrapper header, 0x0B17C0DE little-endian
+const BITCODE_WRAPPER_MAGIC: &[u8] = &[0xde, 0xc0, 0x17, 0x0b];
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// 32 and 64-bit little-endian Mach-O, 0xFEEDFACE and 0xFEEDFACF
+const MACHO_MAGICS: &[&[u8]] = &[&[0xce, 0xfa, 0xed, 0xfe], &[0xcf, 0xfa, 0xed, 0xfe]];
+/// Universal Mach-O, 0xCAFEBABE big-endian
+const FAT_MACHO_MAGIC: &[u8] = &[0xca, 0xfe, 0xba, 0xbe];
+const DOS_MAGIC: &[u8] = b"MZ";
+
+const ET_REL: u16 = 1;
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+const EM_386: u16 = 3;
+const EM_X86_64: u16 = 0x3e;
+const EM_AARCH64: u16 = 0xb7;
+const MH_OBJECT: u32 = 1;
+
+/// What a file that can't be analyzed as a BOF is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Foreign {
+    /// LLVM bitcode from clang `-flto`
+    LlvmBitcode,
+    /// A COFF object holding only GCC's `-flto` intermediate code
+    GccLto,
+    /// An ELF file, from a compiler targeting Linux
+    Elf {
+        /// `e_type`, e.g. `ET_REL` for an object
+        typ: u16,
+        /// `e_machine`
+        machine: u16,
+    },
+    /// A Mach-O file, from a compiler targeting macOS
+    MachO { object: bool },
+    /// A PE image, linked where it should only have been compiled
+    Pe,
+}
+
+impl fmt::Display for Foreign {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::LlvmBitcode => {
+                f.write_str("this object was built with LTO and is LLVM bitcode; rebuild with -fno-lto")
+            }
+            Self::GccLto => f.write_str(
+                "this object was built with LTO and holds only GCC's intermediate code; rebuild with -fno-lto",
+            ),
+            Self::Elf { typ, machine } => {
+                let what = match *typ {
+                    ET_REL => "an ELF relocatable",
+                    ET_EXEC => "an ELF executable",
+                    ET_DYN => "an ELF shared object",
+                    _ => "an ELF file",
+                };
+                let compiler = match *machine {
+                    EM_386 => "i686-w64-mingw32-gcc",
+                    _ => "x86_64-w64-mingw32-gcc",
+                };
+                write!(f, "this is {} from a compiler targeting Linux; build with {} -c", what, compiler)?;
+                if *machine == EM_AARCH64 {
+                    f.write_str(" for x64, or clang --target=aarch64-pc-windows-msvc -c for ARM64")?;
+                } else if *machine != EM_386 && *machine != EM_X86_64 {
+                    write!(f, " (this one is for ELF machine 0x{:x})", machine)?;
+                }
+                Ok(())
+            }
+            Self::MachO { object } => write!(
+                f,
+                "this is a Mach-O {} from a compiler targeting macOS; build with x86_64-w64-mingw32-gcc -c \
+                 or clang --target=x86_64-pc-windows-msvc -c",
+                if *object { "object" } else { "binary" }
+            ),
+            Self::Pe => f.write_str(
+                "this is a linked PE executable or DLL, not an object file; compile with -c (cl /c) and skip linking",
+            ),
+        }
+    }
+}
+
+/// What `buffer` is, if its magic says it's not a COFF object
+pub fn sniff(buffer: &[u8]) -> Option<Foreign> {
+    if buffer.starts_with(BITCODE_MAGIC) || buffer.starts_with(BITCODE_WRAPPER_MAGIC) {
+        return Some(Foreign::LlvmBitcode);
+    }
+    if buffer.starts_with(ELF_MAGIC) && buffer.len() >= 20 {
+        // little-endian unless EI_DATA says big-endian
+        let half = |offset: usize| match buffer[5] {
+            2 => u16::from_be_bytes([buffer[offset], buffer[offset + 1]]),
+            _ => u16::from_le_bytes([buffer[offset], buffer[offset + 1]]),
+        };
+        return Some(Foreign::Elf { typ: half(16), machine: half(18) });
+    }
+    if MACHO_MAGICS.iter().any(|magic| buffer.starts_with(magic)) && buffer.len() >= 16 {
+        let filetype = u32::from_le_bytes([buffer[12], buffer[13], buffer[14], buffer[15]]);
+        return Some(Foreign::MachO { object: filetype == MH_OBJECT });
+    }
+    if buffer.starts_with(FAT_MACHO_MAGIC) {
+        return Some(Foreign::MachO { object: false });
+    }
+    if buffer.starts_with(DOS_MAGIC) {
+        return Some(Foreign::Pe);
+    }
+    None
+}
+
+/// [`Foreign::GccLto`] if the sections of a parsed object are GCC's LTO
+/// streams with no code besides
+pub(crate) fn sniff_sections(sections: &[SectionTable]) -> Option<Foreign> {
+    let lto = sections.iter().any(|section| section_name(section).starts_with(".gnu.lto_"));
+    let code = sections.iter().any(|section| section_name(section).starts_with(".text") && section.size_of_raw_data > 0);
+    (lto && !code).then_some(Foreign::GccLto)
+}
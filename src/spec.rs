@@ -0,0 +1,294 @@
+//! Argument specifications
+//!
+//! A spec describes the arguments a BOF expects, so that packing, the
+//! generated Aggressor script and Sliver manifest, and the analyzer all agree
+//! on them. Specs are TOML (`args.toml`) or JSON (`bof.json`):
+//!
+//! ```toml
+//! name = "whoami"
+//! description = "Print the current user"
+//!
+//! [[arguments]]
+//! name = "target"
+//! type = "z"
+//!
+//! [[arguments]]
+//! name = "pid"
+//! type = "int"
+//! default = "0"
+//! ```
+//!
+//! Types are `bof_pack()` format characters, or `binary`, `int`, `short`,
+//! `string` and `wstring`. Arguments with a default are optional and have to
+//! come after the required ones.
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use serde::Deserialize;
+
+use crate::args::{Arg, ArgsError};
+use crate::{Bof, Import, Severity};
+
+/// The arguments a BOF expects
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArgSpec {
+    /// Command name, if different from the file name
+    pub name: Option<String>,
+    pub description: String,
+    /// Entrypoint, if different from the target's
+    pub entrypoint: Option<String>,
+    pub arguments: Vec<ArgumentSpec>,
+}
+
+/// One argument, in packing order
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "RawArgument")]
+pub struct ArgumentSpec {
+    pub name: String,
+    /// `bof_pack()` format character
+    pub typ: char,
+    pub description: String,
+    /// Value used when the argument isn't given, which makes it optional
+    pub default: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawArgument {
+    name: String,
+    #[serde(rename = "type")]
+    typ: String,
+    #[serde(default)]
+    description: String,
+    default: Option<String>,
+}
+
+impl TryFrom<RawArgument> for ArgumentSpec {
+    type Error = String;
+
+    fn try_from(raw: RawArgument) -> Result<Self, String> {
+        let typ = match raw.typ.as_str() {
+            "b" | "binary" | "file" => 'b',
+            "i" | "int" => 'i',
+            "s" | "short" => 's',
+            "z" | "string" => 'z',
+            "Z" | "wstring" => 'Z',
+            other => return Err(format!("unknown type for argument {}: {}", raw.name, other)),
+        };
+        Ok(Self { name: raw.name, typ, description: raw.description, default: raw.default })
+    }
+}
+
+impl ArgSpec {
+    /// Parse a spec from TOML
+    pub fn from_toml(text: &str) -> Result<Self, SpecError> {
+        toml::from_str::<Self>(text).map_err(|e| SpecError::Parse(e.to_string()))?.validate()
+    }
+
+    /// Parse a spec from JSON
+    pub fn from_json(text: &str) -> Result<Self, SpecError> {
+        serde_json::from_str::<Self>(text).map_err(|e| SpecError::Parse(e.to_string()))?.validate()
+    }
+
+    /// Read a spec from a file, choosing the format by its extension
+    pub fn load(path: &Path) -> Result<Self, SpecError> {
+        let text = fs::read_to_string(path).map_err(SpecError::Io)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&text),
+            _ => Self::from_toml(&text),
+        }
+    }
+
+    /// The `bof_pack()` format string of every argument
+    pub fn format(&self) -> String {
+        self.arguments.iter().map(|argument| argument.typ).collect()
+    }
+
+    /// Number of arguments without a default
+    pub fn required(&self) -> usize {
+        self.arguments.iter().take_while(|argument| argument.default.is_none()).count()
+    }
+
+    /// A value for every argument, filling in defaults for trailing
+    /// arguments that weren't given
+    pub fn values<S: AsRef<str>>(&self, values: &[S]) -> Result<Vec<String>, ArgsError> {
+        if values.len() < self.required() || values.len() > self.arguments.len() {
+            return Err(ArgsError::CountMismatch { expected: self.arguments.len(), found: values.len() });
+        }
+        let defaults = self.arguments[values.len()..].iter().filter_map(|argument| argument.default.clone());
+        Ok(values.iter().map(|value| value.as_ref().to_string()).chain(defaults).collect())
+    }
+
+    /// Check the order of optional arguments and that each default packs
+    pub(crate) fn validate(self) -> Result<Self, SpecError> {
+        let required = self.required();
+        for argument in &self.arguments[required..] {
+            let default = match &argument.default {
+                Some(default) => default,
+                None => return Err(SpecError::Parse(format!("required argument {} follows an optional one", argument.name))),
+            };
+            if argument.typ == 'b' {
+                return Err(SpecError::Parse(format!("binary argument {} can't have a default", argument.name)));
+            }
+            Arg::parse(argument.typ, default).map_err(|e| SpecError::Parse(format!("default of {}: {}", argument.name, e)))?;
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug)]
+pub enum SpecError {
+    /// The spec file couldn't be read
+    Io(io::Error),
+    /// The spec isn't valid TOML/JSON, or has unexpected contents
+    Parse(String),
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "failed to read argument spec: {}", e),
+            Self::Parse(message) => write!(f, "invalid argument spec: {}", message),
+        }
+    }
+}
+
+impl error::Error for SpecError {}
+
+/// A disagreement between a spec and the Beacon data API functions the BOF
+/// imports
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SpecIssue {
+    /// The spec has arguments but the BOF never calls `BeaconDataParse`
+    NoParser,
+    /// The spec has an argument of this type, but the BOF doesn't import the
+    /// function that reads it
+    Unread {
+        argument: String,
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_reader"))]
+        function: crate::TableStr,
+    },
+    /// The BOF imports a function that reads a type the spec doesn't have
+    Unspecified {
+        #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_reader"))]
+        function: crate::TableStr,
+    },
+    /// The spec's format doesn't match the order of the BOF's data API calls
+    FormatMismatch { spec: String, inferred: String },
+}
+
+impl SpecIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Warning
+    }
+}
+
+impl fmt::Display for SpecIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoParser => write!(f, "argument spec has arguments but BeaconDataParse is never imported"),
+            Self::Unread { argument, function } => {
+                write!(f, "argument {} from the spec is never read: {} is not imported", argument, function)
+            }
+            Self::Unspecified { function } => {
+                write!(f, "{} is imported but the argument spec has no argument it would read", function)
+            }
+            Self::FormatMismatch { spec, inferred } => {
+                write!(f, "argument spec format {} doesn't match the calls to the data API, which read {}", spec, inferred)
+            }
+        }
+    }
+}
+
+/// Function that reads an argument of each type; binary and string
+/// arguments all come out of `BeaconDataExtract` (or `BeaconDataPtr`)
+fn reader(typ: char) -> &'static str {
+    match typ {
+        'i' => "BeaconDataInt",
+        's' => "BeaconDataShort",
+        _ => "BeaconDataExtract",
+    }
+}
+
+/// Read a function name back as the matching [`reader`]
+#[cfg(feature = "serde")]
+fn deserialize_reader<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<&'static str, D::Error> {
+    crate::deserialize_known(deserializer, "isz".chars().map(reader))
+}
+
+/// Compare a spec against the imported Beacon data API functions and, if
+/// there is one, the format inferred from their call sites
+pub fn lint(spec: &ArgSpec, imports: &[Import], inferred: Option<&str>) -> Vec<SpecIssue> {
+    let imported = |function: &str| imports.iter().any(|import| import.name == function);
+    let mut issues = Vec::new();
+    if !spec.arguments.is_empty() && !imported("BeaconDataParse") {
+        issues.push(SpecIssue::NoParser);
+    }
+    for argument in &spec.arguments {
+        let function = reader(argument.typ);
+        let read = imported(function) || (function == "BeaconDataExtract" && imported("BeaconDataPtr"));
+        if !read {
+            issues.push(SpecIssue::Unread { argument: argument.name.clone(), function });
+        }
+    }
+    for function in ["BeaconDataInt", "BeaconDataShort", "BeaconDataExtract"] {
+        if imported(function) && !spec.arguments.iter().any(|argument| reader(argument.typ) == function) {
+            issues.push(SpecIssue::Unspecified { function });
+        }
+    }
+    let format = spec.format();
+    if let Some(inferred) = inferred {
+        // the call site only shows that a value is extracted, not whether it's a string or binary
+        let reads = |format: &str| format.chars().map(reader).collect::<Vec<_>>();
+        if !inferred.is_empty() && reads(&format) != reads(inferred) {
+            issues.push(SpecIssue::FormatMismatch { spec: format, inferred: inferred.to_string() });
+        }
+    }
+    issues
+}
+
+/// Probable argument format, from the order of calls to `BeaconDataInt`,
+/// `BeaconDataShort` and `BeaconDataExtract` in the code sections; `None` if
+/// the BOF doesn't parse arguments
+///
+/// Every extracted value shows up as `z`, since the call doesn't say whether
+/// it's a string or binary data. Calls in loops, branches or helpers called
+/// out of order, or through a function pointer loaded once, throw it off.
+pub fn infer_format(bof: &Bof) -> Option<String> {
+    let prefix = bof.import_prefix()?;
+    let parses = bof.imports().any(|symbol| bof.symbol_name(&symbol).strip_prefix(prefix) == Some("BeaconDataParse"));
+    if !parses {
+        return None;
+    }
+    let mut format = String::new();
+    for section in &bof.coff.sections {
+        if section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) == 0 {
+            continue;
+        }
+        let mut calls: Vec<(u32, char)> = match section.relocations(bof.buffer) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    let (_, symbol) = bof.coff.symbols.get(entry.symbol_table_index as usize)?;
+                    let typ = match bof.symbol_name(&symbol).strip_prefix(prefix)? {
+                        "BeaconDataInt" => 'i',
+                        "BeaconDataShort" => 's',
+                        "BeaconDataExtract" => 'z',
+                        _ => return None,
+                    };
+                    Some((entry.virtual_address, typ))
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+        calls.sort_by_key(|(offset, _)| *offset);
+        format.extend(calls.into_iter().map(|(_, typ)| typ));
+    }
+    Some(format)
+}
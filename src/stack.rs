@@ -0,0 +1,172 @@
+//! Stack usage
+//!
+//! A BOF runs on the stack of the Beacon thread that loaded it, and large
+//! local buffers run off the end of it. Each function's frame is read from
+//! its prologue: the pushes, the `sub rsp, N`, and the size handed to the
+//! stack probe for frames larger than a page. Adding up the frames and
+//! return addresses along each call chain from the entrypoint gives the
+//! worst case, not counting what the imports themselves use.
+
+use std::collections::{BTreeMap, HashMap};
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+use iced_x86::{Decoder, DecoderOptions, Instruction, Mnemonic, OpKind, Register};
+
+use crate::callgraph::CallGraph;
+use crate::disasm::{extent, is_section_definition};
+use crate::{crt, Arch, Bof};
+
+/// Worst-case stack usage above this is flagged by default
+pub const DEFAULT_MAX_STACK: usize = 0x10000;
+
+/// Instructions at the start of a function searched for its prologue
+const PROLOGUE_LENGTH: usize = 16;
+
+/// Frame sizes and the call chain from the entrypoint that uses the most
+/// stack
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StackEstimate {
+    /// Bytes each function reserves in its prologue
+    pub frames: BTreeMap<String, usize>,
+    /// The deepest chain of calls, starting with the entrypoint
+    pub chain: Vec<String>,
+    /// Bytes the chain uses, frames and return addresses included
+    pub size: usize,
+    /// Whether functions reachable from the entrypoint call each other in a
+    /// cycle, which makes the estimate a lower bound
+    pub recursive: bool,
+}
+
+/// Estimate the stack used by `entrypoint` and everything it calls
+pub fn estimate(bof: &Bof, graph: &CallGraph, entrypoint: &str) -> Option<StackEstimate> {
+    let (bitness, word) = match bof.arch()? {
+        Arch::X86 => (32, 4),
+        Arch::X64 => (64, 8),
+        Arch::Arm64 => return None,
+    };
+    let mut frames = BTreeMap::new();
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.section_number <= 0 || is_section_definition(bof, &symbol) {
+            continue;
+        }
+        let section = match bof.coff.sections.get(symbol.section_number as usize - 1) {
+            Some(section) if section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0 => section,
+            _ => continue,
+        };
+        let data = bof.section_contents(section);
+        let probes: Vec<u32> = section
+            .relocations(bof.buffer)
+            .into_iter()
+            .flatten()
+            .filter(|entry| {
+                bof.coff
+                    .symbols
+                    .get(entry.symbol_table_index as usize)
+                    .is_some_and(|(_, target)| crt::is_stack_probe(&bof.symbol_name(&target)))
+            })
+            .map(|entry| entry.virtual_address)
+            .collect();
+        let (start, end) = extent(bof, &symbol, data.len() as u32);
+        let size = frame_size(&data[start as usize..end as usize], start, bitness, word, &probes);
+        frames.insert(bof.symbol_name(&symbol), size);
+    }
+    if !frames.contains_key(entrypoint) {
+        return None;
+    }
+
+    let mut estimate = StackEstimate::default();
+    let mut memo = HashMap::new();
+    let (size, chain) = deepest(graph, &frames, word, entrypoint, &mut Vec::new(), &mut estimate.recursive, &mut memo);
+    estimate.frames = frames;
+    estimate.chain = chain;
+    estimate.size = size;
+    Some(estimate)
+}
+
+/// Bytes the prologue at the start of `code` reserves; `probes` are the
+/// offsets of relocations against a stack probe
+fn frame_size(code: &[u8], start: u32, bitness: u32, word: usize, probes: &[u32]) -> usize {
+    let is_stack_pointer = |register: Register| matches!(register, Register::RSP | Register::ESP);
+    let calls_probe = |instruction: &Instruction| {
+        let ip = instruction.ip() as u32;
+        probes.iter().any(|offset| (ip..ip + instruction.len() as u32).contains(offset))
+    };
+    let mut size: usize = 0;
+    // the frame size large frames pass to the probe in eax
+    let mut probed = 0;
+    let mut decoder = Decoder::with_ip(bitness, code, start as u64, DecoderOptions::NONE);
+    for instruction in decoder.iter().take(PROLOGUE_LENGTH) {
+        let immediate = match instruction.op_count() {
+            2 if matches!(
+                instruction.op1_kind(),
+                OpKind::Immediate8to32 | OpKind::Immediate8to64 | OpKind::Immediate32 | OpKind::Immediate32to64
+            ) =>
+            {
+                // as signed at the operand size; adding a negative amount
+                // shrinks the frame, which isn't a prologue
+                let value = match bitness {
+                    32 => instruction.immediate(1) as i32 as i64,
+                    _ => instruction.immediate(1) as i64,
+                };
+                usize::try_from(value).ok()
+            }
+            _ => None,
+        };
+        match instruction.mnemonic() {
+            Mnemonic::Push => size = size.saturating_add(word),
+            Mnemonic::Mov if instruction.op0_register() == Register::EAX => probed = immediate.unwrap_or(0),
+            Mnemonic::Sub if is_stack_pointer(instruction.op0_register()) => {
+                // after a probe, sub rsp, rax
+                return size.saturating_add(immediate.unwrap_or(probed));
+            }
+            Mnemonic::Enter => return size.saturating_add(word + instruction.immediate16() as usize),
+            // the x86 probes move esp themselves, the x64 ones leave it to
+            // the prologue
+            Mnemonic::Call if calls_probe(&instruction) && bitness == 32 => return size.saturating_add(probed),
+            Mnemonic::Call if calls_probe(&instruction) => {}
+            Mnemonic::Call | Mnemonic::Ret | Mnemonic::Jmp => return size,
+            _ if instruction.op_count() > 0
+                && matches!(instruction.op0_kind(), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64) =>
+            {
+                return size
+            }
+            _ => {}
+        }
+    }
+    size
+}
+
+/// Stack used by `function` and its deepest chain of callees, along with
+/// the chain
+fn deepest(
+    graph: &CallGraph,
+    frames: &BTreeMap<String, usize>,
+    word: usize,
+    function: &str,
+    path: &mut Vec<String>,
+    recursive: &mut bool,
+    memo: &mut HashMap<String, (usize, Vec<String>)>,
+) -> (usize, Vec<String>) {
+    if let Some(known) = memo.get(function) {
+        return known.clone();
+    }
+    path.push(function.to_string());
+    let mut worst = (0, Vec::new());
+    for callee in graph.callees(function).filter(|callee| frames.contains_key(*callee)) {
+        if path.iter().any(|caller| caller == callee) {
+            *recursive = true;
+            continue;
+        }
+        let below = deepest(graph, frames, word, callee, path, recursive, memo);
+        if below.0 > worst.0 {
+            worst = below;
+        }
+    }
+    path.pop();
+    let mut chain = vec![function.to_string()];
+    chain.extend(worst.1);
+    let result = (frames[function].saturating_add(word).saturating_add(worst.0), chain);
+    memo.insert(function.to_string(), result.clone());
+    result
+}
@@ -0,0 +1,80 @@
+//! Readable strings and where they'll be in memory
+
+use std::fmt;
+
+/// How a string is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Encoding {
+    Ascii,
+    Utf16Le,
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ascii => write!(f, "ascii"),
+            Self::Utf16Le => write!(f, "utf-16le"),
+        }
+    }
+}
+
+/// A string found in a section
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FoundString {
+    pub section: String,
+    /// Offset of the first byte within `section`
+    pub offset: usize,
+    pub encoding: Encoding,
+    pub value: String,
+}
+
+impl fmt::Display for FoundString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}+{:#x} ({}): {}", self.section, self.offset, self.encoding, self.value)
+    }
+}
+
+fn is_printable(byte: u8) -> bool {
+    (0x20..0x7f).contains(&byte) || byte == b'\t'
+}
+
+/// Printable ASCII and UTF-16LE runs of at least `min_len` characters, by offset
+pub fn extract(data: &[u8], min_len: usize) -> Vec<(usize, Encoding, String)> {
+    let mut found = Vec::new();
+    let mut start = 0;
+    for (index, byte) in data.iter().chain(&[0]).enumerate() {
+        if !is_printable(*byte) {
+            if index - start >= min_len.max(1) {
+                found.push((start, Encoding::Ascii, String::from_utf8_lossy(&data[start..index]).into_owned()));
+            }
+            start = index + 1;
+        }
+    }
+
+    for alignment in 0..2 {
+        let units: Vec<(usize, u16)> = data
+            .get(alignment..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .enumerate()
+            .map(|(index, pair)| (alignment + index * 2, u16::from_le_bytes([pair[0], pair[1]])))
+            .collect();
+        let mut run: Vec<(usize, u16)> = Vec::new();
+        for unit in units.iter().copied().chain([(data.len(), 0)]) {
+            if unit.1 < 0x100 && is_printable(unit.1 as u8) {
+                run.push(unit);
+                continue;
+            }
+            if run.len() >= min_len.max(1) {
+                let value = run.iter().map(|(_, unit)| *unit as u8 as char).collect();
+                found.push((run[0].0, Encoding::Utf16Le, value));
+            }
+            run.clear();
+        }
+    }
+
+    found.sort();
+    found
+}
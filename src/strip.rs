@@ -0,0 +1,503 @@
+//! Removing sections and symbols a BOF doesn't need at runtime
+//!
+//! The loader never maps debug information or linker directives, but they
+//! still travel with every task. Stripping them (and optionally the unwind
+//! tables) rewrites the object without touching code, data or relocations;
+//! only cutting out unreachable functions moves code around.
+
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::error;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use goblin::pe::symbol::{COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_FILE, IMAGE_SYM_UNDEFINED};
+
+use iced_x86::{Decoder, DecoderOptions, OpKind};
+
+use crate::leaks::{self, PathSpan};
+use crate::writer::{Object, Symbol, WriteError};
+use crate::{relocations, Arch, NOT_LOADED};
+
+/// What to remove besides debug and linker-only sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StripOptions {
+    /// Remove `.pdata`/`.xdata`, which only matter for exception handling
+    pub unwind: bool,
+    /// Keep symbols nothing refers to, such as `.file` records and labels
+    pub keep_symbols: bool,
+    /// Remove imports nothing refers to even when keeping other symbols
+    pub dead_imports: bool,
+    /// Rewrite the header timestamp
+    pub scrub_timestamp: Option<TimestampScrub>,
+    /// Rewrite absolute build paths in `.file` symbols and section data
+    pub scrub_paths: Option<PathScrub>,
+}
+
+/// What to leave of a build path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathScrub {
+    FileName,
+    /// Nothing at all
+    Blank,
+}
+
+/// What to set the header timestamp to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampScrub {
+    Zero,
+    /// A random time between 2015 and now, so the object doesn't stand out
+    /// as scrubbed
+    Random,
+}
+
+/// What was removed
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StripSummary {
+    /// Name and size of each removed section
+    pub sections: Vec<(String, usize)>,
+    /// Number of symbol table entries removed, including auxiliary records
+    pub symbols: usize,
+    /// Names of the removed imports, with their import prefix
+    pub imports: Vec<String>,
+    /// Old and new header timestamp, if it was scrubbed
+    pub timestamp: Option<(u32, u32)>,
+    /// Build paths that were scrubbed
+    pub paths: Vec<String>,
+    /// Name and size of each function cut out
+    pub functions: Vec<(String, usize)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StripError {
+    /// A kept section relocates against a symbol in a removed one, or
+    /// against a removed function
+    Referenced { section: String, by: String },
+    /// Code can only be cut out of x86 and x64 objects
+    Machine(u16),
+    /// A short branch in `by` can't reach across the code left after
+    /// removing `function`
+    BranchRange { function: String, by: String },
+    Write(WriteError),
+}
+
+impl fmt::Display for StripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Referenced { section, by } => write!(f, "can't remove {}: {} refers to it", section, by),
+            Self::Machine(machine) => write!(f, "can't remove code from objects of machine type 0x{:04x}", machine),
+            Self::BranchRange { function, by } => {
+                write!(f, "can't remove {}: a short branch in {} would no longer reach past it", function, by)
+            }
+            Self::Write(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for StripError {}
+
+impl From<WriteError> for StripError {
+    fn from(e: WriteError) -> Self {
+        Self::Write(e)
+    }
+}
+
+/// Remove unneeded sections and symbols from `object`
+pub fn strip(object: &mut Object, options: &StripOptions) -> Result<StripSummary, StripError> {
+    strip_functions(object, options, &[])
+}
+
+/// Like [`strip`], also cutting the code of `functions` out of their
+/// sections, such as the ones [`Bof::dead_code`](crate::Bof::dead_code)
+/// finds
+pub fn strip_functions(object: &mut Object, options: &StripOptions, functions: &[String]) -> Result<StripSummary, StripError> {
+    let mut summary = StripSummary::default();
+    let before = object.symbol_count();
+
+    let removed: Vec<bool> = object
+        .sections
+        .iter()
+        .map(|section| {
+            section.characteristics & NOT_LOADED != 0
+                || section.name.starts_with(".debug")
+                || (options.unwind && (section.name.starts_with(".pdata") || section.name.starts_with(".xdata")))
+        })
+        .collect();
+    let in_removed = |section_number: i16| section_number > 0 && removed.get(section_number as usize - 1).copied().unwrap_or(false);
+    for (section, _) in object.sections.iter().zip(&removed).filter(|(_, removed)| !**removed) {
+        for relocation in &section.relocations {
+            let target = object.symbols[relocation.symbol].section_number;
+            if in_removed(target) {
+                return Err(StripError::Referenced {
+                    section: object.sections[target as usize - 1].name.clone(),
+                    by: section.name.clone(),
+                });
+            }
+        }
+    }
+    for section in object.remove_sections(&removed)? {
+        summary.sections.push((section.name, section.size as usize));
+    }
+    summary.functions = remove_functions(object, functions)?;
+
+    if !options.keep_symbols || options.dead_imports {
+        let referenced: HashSet<usize> = object
+            .sections
+            .iter()
+            .flat_map(|section| section.relocations.iter().map(|relocation| relocation.symbol))
+            .chain(object.symbols.iter().filter_map(Symbol::weak_default))
+            .collect();
+        let undefined = |symbol: &Symbol| {
+            symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL && symbol.section_number == IMAGE_SYM_UNDEFINED
+        };
+        let keep: Vec<bool> = object
+            .symbols
+            .iter()
+            .enumerate()
+            .map(|(index, symbol)| {
+                if referenced.contains(&index) {
+                    true
+                } else if undefined(symbol) {
+                    false
+                } else if options.keep_symbols || symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL {
+                    // definitions (the entrypoint) are kept
+                    true
+                } else if symbol.storage_class == IMAGE_SYM_CLASS_FILE {
+                    false
+                } else {
+                    symbol.is_section_definition(&object.sections) || symbol.section_number < 0 || symbol.weak_default().is_some()
+                }
+            })
+            .collect();
+        summary.imports = object
+            .symbols
+            .iter()
+            .zip(&keep)
+            .filter(|(symbol, keep)| !**keep && undefined(symbol) && symbol.name.starts_with("__imp_"))
+            .map(|(symbol, _)| symbol.name.clone())
+            .collect();
+        object.retain_symbols(&keep)?;
+    }
+
+    summary.symbols = before - object.symbol_count();
+    if let Some(scrub) = options.scrub_timestamp {
+        let timestamp = match scrub {
+            TimestampScrub::Zero => 0,
+            TimestampScrub::Random => random_timestamp(),
+        };
+        summary.timestamp = Some((object.time_date_stamp, timestamp));
+        object.time_date_stamp = timestamp;
+    }
+    if let Some(scrub) = options.scrub_paths {
+        summary.paths = scrub_paths(object, scrub);
+    }
+    Ok(summary)
+}
+
+/// Cut the code of `functions`, each running to the next symbol in its
+/// section, out of `object`
+///
+/// Whatever follows moves up: relocations, symbols, addends of relocations
+/// against the section, and the displacements of branches and RIP-relative
+/// operands the assembler resolved across the removed code. Fails without changing anything if
+/// something still refers to one of the functions.
+fn remove_functions(object: &mut Object, functions: &[String]) -> Result<Vec<(String, usize)>, StripError> {
+    if functions.is_empty() {
+        return Ok(Vec::new());
+    }
+    let (arch, bitness) = match Arch::from_machine(object.machine) {
+        Some(Arch::X86) => (Arch::X86, 32),
+        Some(Arch::X64) => (Arch::X64, 64),
+        _ => return Err(StripError::Machine(object.machine)),
+    };
+    let offsets = |number: i16| -> Vec<u32> {
+        object
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.section_number == number && !symbol.is_section_definition(&object.sections))
+            .map(|symbol| symbol.value)
+            .collect()
+    };
+
+    // section number -> (start, end, name) of the functions cut out of it
+    let mut cuts: BTreeMap<i16, Vec<(u32, u32, String)>> = BTreeMap::new();
+    for name in functions {
+        let symbol = object
+            .find_symbol(name)
+            .map(|index| &object.symbols[index])
+            .filter(|symbol| symbol.section_number > 0)
+            .ok_or_else(|| WriteError::MissingSymbol(name.clone()))?;
+        let size = object.sections[symbol.section_number as usize - 1].data.len() as u32;
+        let end = offsets(symbol.section_number).into_iter().filter(|value| *value > symbol.value).fold(size, u32::min);
+        cuts.entry(symbol.section_number).or_default().push((symbol.value.min(end), end, name.clone()));
+    }
+    for ranges in cuts.values_mut() {
+        ranges.sort();
+        ranges.dedup_by_key(|(start, _, _)| *start);
+    }
+    let cut_at = |number: i16, offset: u32| {
+        cuts.get(&number)?.iter().find(|(start, end, _)| (*start..*end).contains(&offset)).map(|(_, _, name)| name.clone())
+    };
+    let moved = |number: i16, offset: u32| {
+        let removed: u32 = cuts
+            .get(&number)
+            .map_or(0, |ranges| ranges.iter().filter(|(_, end, _)| *end <= offset).map(|(start, end, _)| end - start).sum());
+        offset - removed
+    };
+
+    let mut weak = object.symbols.iter().filter_map(Symbol::weak_default).map(|default| &object.symbols[default]);
+    if let Some(function) = weak.find_map(|default| cut_at(default.section_number, default.value)) {
+        return Err(StripError::Referenced { section: function, by: "a weak external".to_string() });
+    }
+
+    // (section index, offset, width, value) of each addend to rewrite
+    let mut patches: Vec<(usize, usize, usize, i64)> = Vec::new();
+    for (index, section) in object.sections.iter().enumerate() {
+        let number = index as i16 + 1;
+        for relocation in section.relocations.iter().filter(|relocation| cut_at(number, relocation.offset).is_none()) {
+            let target = &object.symbols[relocation.symbol];
+            if !cuts.contains_key(&target.section_number) {
+                continue;
+            }
+            let pointee = match target.is_section_definition(&object.sections) {
+                true => relocations::addend(arch, relocation.typ, &section.data, relocation.offset as usize).unwrap_or(0),
+                false => target.value as i64,
+            };
+            if let Some(function) = cut_at(target.section_number, pointee.max(0) as u32) {
+                return Err(StripError::Referenced { section: function, by: section.name.clone() });
+            }
+            let width = relocations::width(arch, relocation.typ);
+            if target.is_section_definition(&object.sections) && pointee >= 0 {
+                let value = moved(target.section_number, pointee as u32) as i64;
+                patches.push((index, relocation.offset as usize, width, value));
+            }
+        }
+    }
+
+    // branches and RIP-relative operands resolved within a section, decoded
+    // from each function left
+    for (&number, ranges) in &cuts {
+        let section = &object.sections[number as usize - 1];
+        let relocated: BTreeSet<u32> = section.relocations.iter().map(|relocation| relocation.offset).collect();
+        let mut bounds = offsets(number);
+        bounds.extend(ranges.iter().flat_map(|(start, end, _)| [*start, *end]));
+        bounds.push(0);
+        bounds.push(section.data.len() as u32);
+        bounds.sort();
+        bounds.dedup();
+        for pair in bounds.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if cut_at(number, start).is_some() {
+                continue;
+            }
+            let code = &section.data[start as usize..end as usize];
+            let mut decoder = Decoder::with_ip(bitness, code, start as u64, DecoderOptions::NONE);
+            while decoder.can_decode() {
+                let instruction = decoder.decode();
+                let ip = instruction.ip() as u32;
+                let branches = instruction.op_count() > 0
+                    && matches!(instruction.op0_kind(), OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64);
+                if !(branches || instruction.is_ip_rel_memory_operand())
+                    || (ip..ip + instruction.len() as u32).any(|offset| relocated.contains(&offset))
+                {
+                    continue;
+                }
+                let next = instruction.next_ip() as u32;
+                let target = match branches {
+                    true => instruction.near_branch_target() as u32,
+                    false => instruction.ip_rel_memory_address() as u32,
+                };
+                if target > section.data.len() as u32 {
+                    continue;
+                }
+                if let Some(function) = cut_at(number, target) {
+                    return Err(StripError::Referenced { section: function, by: section.name.clone() });
+                }
+                let displacement = moved(number, target) as i64 - moved(number, next) as i64;
+                if displacement == target as i64 - next as i64 {
+                    continue;
+                }
+                let constants = decoder.get_constant_offsets(&instruction);
+                let (position, width) = match branches {
+                    true => (ip as usize + constants.immediate_offset(), constants.immediate_size()),
+                    false => (ip as usize + constants.displacement_offset(), constants.displacement_size()),
+                };
+                let fits = match width {
+                    1 => i8::try_from(displacement).is_ok(),
+                    2 => i16::try_from(displacement).is_ok(),
+                    _ => i32::try_from(displacement).is_ok(),
+                };
+                if !fits {
+                    let function = ranges.iter().map(|(_, _, name)| name.clone()).next().unwrap_or_default();
+                    return Err(StripError::BranchRange { function, by: section.name.clone() });
+                }
+                patches.push((number as usize - 1, position, width, displacement));
+            }
+        }
+    }
+
+    for (index, offset, width, value) in patches {
+        let bytes = value.to_le_bytes();
+        if let Some(slot) = object.sections[index].data.get_mut(offset..offset + width) {
+            slot.copy_from_slice(&bytes[..width]);
+        }
+    }
+    for (&number, ranges) in &cuts {
+        let section = &mut object.sections[number as usize - 1];
+        section.relocations.retain(|relocation| cut_at(number, relocation.offset).is_none());
+        for relocation in &mut section.relocations {
+            relocation.offset = moved(number, relocation.offset);
+        }
+        for (start, end, _) in ranges.iter().rev() {
+            section.data.drain(*start as usize..*end as usize);
+        }
+        section.size = section.data.len() as u32;
+    }
+    let keep: Vec<bool> = object
+        .symbols
+        .iter()
+        .map(|symbol| symbol.is_section_definition(&object.sections) || cut_at(symbol.section_number, symbol.value).is_none())
+        .collect();
+    for symbol in object.symbols.iter_mut().filter(|symbol| cuts.contains_key(&symbol.section_number)) {
+        if !symbol.is_section_definition(&object.sections) && cut_at(symbol.section_number, symbol.value).is_none() {
+            symbol.value = moved(symbol.section_number, symbol.value);
+        }
+    }
+    object.retain_symbols(&keep)?;
+    Ok(cuts.into_values().flatten().map(|(start, end, name)| (name, (end - start) as usize)).collect())
+}
+
+/// Earliest randomized timestamp, 2015-01-01
+const RANDOM_TIMESTAMP_START: u32 = 1_420_070_400;
+
+fn random_timestamp() -> u32 {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |now| now.as_secs()) as u32;
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(now);
+    let span = now.saturating_sub(RANDOM_TIMESTAMP_START).max(1);
+    RANDOM_TIMESTAMP_START + (hasher.finish() % span as u64) as u32
+}
+
+/// Scrub absolute paths in `.file` records and section data, returning the
+/// paths
+///
+/// Text after a path in the same string is moved up and the freed bytes are
+/// zeroed, so C strings stay intact; paths in strings with a relocation in
+/// them are left alone.
+fn scrub_paths(object: &mut Object, scrub: PathScrub) -> Vec<String> {
+    let mut paths = Vec::new();
+    for symbol in object.symbols.iter_mut().filter(|symbol| symbol.storage_class == IMAGE_SYM_CLASS_FILE) {
+        let mut name: Vec<u8> = symbol.aux.iter().flatten().copied().collect();
+        let spans = leaks::find_paths(&name);
+        paths.extend(scrub_spans(&mut name, spans, scrub));
+        for (record, chunk) in symbol.aux.iter_mut().zip(name.chunks(COFF_SYMBOL_SIZE)) {
+            record.copy_from_slice(chunk);
+        }
+    }
+    for section in &mut object.sections {
+        let relocated: Vec<Range<usize>> =
+            section.relocations.iter().map(|relocation| relocation.offset as usize..relocation.offset as usize + 8).collect();
+        let spans = leaks::find_paths(&section.data)
+            .into_iter()
+            .filter(|span| !relocated.iter().any(|range| range.start < span.string_end && span.path.start < range.end))
+            .collect();
+        paths.extend(scrub_spans(&mut section.data, spans, scrub));
+    }
+    paths
+}
+
+/// Scrub the paths at `spans` of `data`, last first so the earlier spans
+/// stay put
+fn scrub_spans(data: &mut [u8], spans: Vec<PathSpan>, scrub: PathScrub) -> Vec<String> {
+    let mut paths = Vec::new();
+    for span in spans.into_iter().rev() {
+        paths.push(String::from_utf8_lossy(&data[span.path.clone()]).to_string());
+        let kept = match scrub {
+            PathScrub::FileName => span.name_start,
+            PathScrub::Blank => span.path.end,
+        };
+        let removed = kept - span.path.start;
+        data.copy_within(kept..span.string_end, span.path.start);
+        data[span.string_end - removed..span.string_end].fill(0);
+    }
+    paths.reverse();
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::header::COFF_MACHINE_X86_64;
+    use goblin::pe::relocation::IMAGE_REL_AMD64_REL32;
+    use goblin::pe::section_table::{
+        IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_MEM_DISCARDABLE, IMAGE_SCN_MEM_EXECUTE,
+        IMAGE_SCN_MEM_READ,
+    };
+    use goblin::pe::symbol::IMAGE_SYM_CLASS_STATIC;
+
+    use super::*;
+    use crate::writer::{Relocation, Section};
+
+    /// Debug info ahead of the code, so stripping renumbers every section
+    /// that's kept; `helper` is never called and `go` reads `counter`
+    fn object() -> Object {
+        let mut object = Object::new(COFF_MACHINE_X86_64);
+        object.add_section(Section::new(".debug$S", IMAGE_SCN_MEM_DISCARDABLE | IMAGE_SCN_MEM_READ, vec![4, 0, 0, 0]));
+        let code = vec![0xc3, 0x90, 0x90, 0x90, 0x8b, 0x05, 0, 0, 0, 0, 0xc3];
+        let text = object.add_section(Section::new(".text", IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ, code));
+        let data = object.add_section(Section::new(".data", IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ, vec![0; 8]));
+        object.add_symbol(Symbol::new("helper", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        object.add_symbol(Symbol::new("go", 4, text, IMAGE_SYM_CLASS_EXTERNAL));
+        let counter = object.add_symbol(Symbol::new("counter", 4, data, IMAGE_SYM_CLASS_STATIC));
+        object.add_symbol(Symbol::new("__imp_KERNEL32$Sleep", 0, 0, IMAGE_SYM_CLASS_EXTERNAL));
+        object.sections[1].relocations = vec![Relocation { offset: 6, symbol: counter, typ: IMAGE_REL_AMD64_REL32 }];
+        object
+    }
+
+    /// Name of the symbol each relocation in `section` targets, with the
+    /// name of the section it's defined in
+    fn targets(object: &Object, section: &str) -> Vec<(u32, String, String)> {
+        let section = object.sections.iter().find(|candidate| candidate.name == section).unwrap();
+        section
+            .relocations
+            .iter()
+            .map(|relocation| {
+                let symbol = &object.symbols[relocation.symbol];
+                let defined = object.sections[symbol.section_number as usize - 1].name.clone();
+                (relocation.offset, symbol.name.clone(), defined)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strip_keeps_relocation_targets() {
+        let mut object = object();
+        let summary = strip(&mut object, &StripOptions::default()).unwrap();
+        assert_eq!(summary.sections, [(".debug$S".to_string(), 4)]);
+        assert_eq!(summary.imports, ["__imp_KERNEL32$Sleep"]);
+        assert_eq!(targets(&object, ".text"), [(6, "counter".to_string(), ".data".to_string())]);
+        let reparsed = Object::parse(&object.to_bytes().unwrap()).unwrap();
+        assert_eq!(targets(&reparsed, ".text"), targets(&object, ".text"));
+    }
+
+    #[test]
+    fn removing_functions_moves_relocations() {
+        let mut object = object();
+        let summary = strip_functions(&mut object, &StripOptions::default(), &["helper".to_string()]).unwrap();
+        assert_eq!(summary.functions, [("helper".to_string(), 4)]);
+        assert_eq!(object.sections[0].data, [0x8b, 0x05, 0, 0, 0, 0, 0xc3]);
+        assert_eq!(targets(&object, ".text"), [(2, "counter".to_string(), ".data".to_string())]);
+        assert_eq!(object.symbols[object.find_symbol("go").unwrap()].value, 0);
+    }
+
+    #[test]
+    fn kept_sections_referring_to_removed_ones_fail() {
+        let mut object = object();
+        let debug = object.add_symbol(Symbol::new("debug_info", 0, 1, IMAGE_SYM_CLASS_STATIC));
+        object.sections[1].relocations.push(Relocation { offset: 0, symbol: debug, typ: IMAGE_REL_AMD64_REL32 });
+        let before = object.clone();
+        assert!(matches!(strip(&mut object, &StripOptions::default()), Err(StripError::Referenced { .. })));
+        assert_eq!(object, before);
+    }
+}
@@ -0,0 +1,98 @@
+//! Symbol table entries, sorted by what the loader does with them
+
+use std::fmt;
+
+use goblin::pe::symbol::IMAGE_SYM_CLASS_FILE;
+
+use crate::{classify_import, disasm, ApiProfile, Bof, ImportKind, Symbol};
+
+/// A symbol table entry, categorized the same way the analysis treats it
+///
+/// Import names have the import prefix (`__imp_`) removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClassifiedSymbol {
+    /// The function the loader calls
+    Entrypoint(String),
+    /// Function exported by the loader (the Beacon API, or its equivalent)
+    BeaconApi(String),
+    /// Dynamic function resolution import (`MODULE$Function`), with any
+    /// `@N` decoration removed from the function
+    DfrImport { module: String, function: String },
+    /// Win32 function built into the loader
+    Win32Builtin(String),
+    /// Any other import or undefined external, which the loader most likely
+    /// can't resolve
+    UnknownImport(String),
+    /// Symbol defined in the object, or a common symbol the loader allocates
+    Internal(String),
+    /// Section definition record
+    SectionSymbol(String),
+}
+
+impl ClassifiedSymbol {
+    /// Symbol name, without the import prefix; `MODULE$Function` for
+    /// dynamic function resolution imports
+    pub fn name(&self) -> String {
+        match self {
+            Self::DfrImport { module, function } => format!("{}${}", module, function),
+            Self::Entrypoint(name)
+            | Self::BeaconApi(name)
+            | Self::Win32Builtin(name)
+            | Self::UnknownImport(name)
+            | Self::Internal(name)
+            | Self::SectionSymbol(name) => name.clone(),
+        }
+    }
+
+    /// What the loader does with the symbol, e.g. `beacon api`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Entrypoint(_) => "entrypoint",
+            Self::BeaconApi(_) => "beacon api",
+            Self::DfrImport { .. } => "dynamic function resolution",
+            Self::Win32Builtin(_) => "win32 builtin",
+            Self::UnknownImport(_) => "unknown import",
+            Self::Internal(_) => "internal",
+            Self::SectionSymbol(_) => "section",
+        }
+    }
+}
+
+impl fmt::Display for ClassifiedSymbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind(), self.name())
+    }
+}
+
+/// Categorize one symbol table entry; `None` for file name records
+pub(crate) fn classify(bof: &Bof, symbol: &Symbol, profile: &dyn ApiProfile) -> Option<ClassifiedSymbol> {
+    if symbol.storage_class == IMAGE_SYM_CLASS_FILE {
+        return None;
+    }
+    let name = bof.symbol_name(symbol);
+    if symbol.section_number > 0 {
+        return Some(match () {
+            _ if disasm::is_section_definition(bof, symbol) => ClassifiedSymbol::SectionSymbol(name),
+            _ if name == profile.entrypoint() || bof.arch().is_some_and(|arch| arch.decorate(profile.entrypoint()) == name) => {
+                ClassifiedSymbol::Entrypoint(name)
+            }
+            _ => ClassifiedSymbol::Internal(name),
+        });
+    }
+
+    let import = match bof.import_prefix().and_then(|prefix| name.strip_prefix(prefix)) {
+        Some(import) => classify_import(import, profile),
+        // a common symbol's value is its size, and the loader allocates it
+        None if symbol.value > 0 || symbol.section_number < 0 => return Some(ClassifiedSymbol::Internal(name)),
+        None => return Some(ClassifiedSymbol::UnknownImport(name)),
+    };
+    Some(match import.kind {
+        ImportKind::BeaconApi => ClassifiedSymbol::BeaconApi(import.name),
+        ImportKind::Win32Builtin => ClassifiedSymbol::Win32Builtin(import.name),
+        ImportKind::Dynamic { module, function } | ImportKind::UnrecognizedModule { module, function } => {
+            ClassifiedSymbol::DfrImport { module, function }
+        }
+        ImportKind::UnsupportedApi | ImportKind::Unknown => ClassifiedSymbol::UnknownImport(import.name),
+    })
+}
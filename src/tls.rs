@@ -0,0 +1,183 @@
+//! Thread local storage
+//!
+//! `__declspec(thread)` and `_Thread_local` variables live in `.tls$`, and
+//! code finds the running thread's copy through the `_tls_index` slot that
+//! the Windows loader assigns when it processes an image's TLS directory.
+//! BOF loaders have no TLS directory to process, so the index is never set
+//! and every access lands in some other module's TLS block. GCC's emulated
+//! TLS calls `__emutls_get_address` from libgcc instead, which isn't there
+//! either.
+
+use std::fmt;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+
+use crate::disasm::{extent, is_section_definition};
+use crate::{relocations, section_name, Arch, Bof, Severity};
+
+/// The TLS index, as named on x64 and with x86 decoration
+static INDEX: &[&str] = &["_tls_index", "__tls_index"];
+
+/// GCC's emulated TLS runtime
+static EMULATED: &[&str] = &["__emutls_get_address"];
+
+/// Prefix of the control variable GCC emits for each emulated TLS variable,
+/// after any leading underscores
+const EMUTLS_VARIABLE: &str = "emutls_v.";
+
+/// Whether `name`, or `function` without x86 decoration, is part of the TLS
+/// runtime
+pub(crate) fn is_runtime(name: &str, function: &str) -> bool {
+    [INDEX, EMULATED].iter().any(|list| list.contains(&name) || list.contains(&function))
+}
+
+fn is_tls_section(name: &str) -> bool {
+    name == ".tls" || name.starts_with(".tls$")
+}
+
+/// What a TLS symbol is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TlsSymbol {
+    /// A thread local variable
+    Variable,
+    /// The TLS index native TLS accesses go through
+    Index,
+    /// GCC's emulated TLS runtime
+    Emulated,
+}
+
+/// A symbol that depends on TLS, with the functions referring to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TlsIssue {
+    /// Symbol name as it appears in the symbol table, or for emulated TLS
+    /// the variable's name
+    pub symbol: String,
+    pub kind: TlsSymbol,
+    /// Functions referring to it, in the order they first do
+    pub functions: Vec<String>,
+}
+
+impl TlsIssue {
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+}
+
+impl fmt::Display for TlsIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TlsSymbol::Variable => write!(f, "thread local variable the loader never allocates: {}", self.symbol)?,
+            TlsSymbol::Index => write!(f, "TLS index the loader never assigns: {}", self.symbol)?,
+            TlsSymbol::Emulated => write!(f, "emulated TLS runtime BOFs can't link: {}", self.symbol)?,
+        }
+        match self.functions.is_empty() {
+            true => Ok(()),
+            false => write!(f, " (used by {})", self.functions.join(", ")),
+        }
+    }
+}
+
+/// Find thread local variables and references to the TLS runtime, with the
+/// functions that use them
+pub fn lint(bof: &Bof) -> Vec<TlsIssue> {
+    let arch = match bof.arch() {
+        Some(arch) => arch,
+        None => return Vec::new(),
+    };
+    let in_tls = |number: i16| {
+        number > 0 && bof.coff.sections.get(number as usize - 1).is_some_and(|section| is_tls_section(&section_name(section)))
+    };
+    let is_code = |number: i16| {
+        number > 0
+            && bof.coff.sections.get(number as usize - 1).is_some_and(|section| {
+                section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0
+            })
+    };
+    let emulated = |name: &str| name.trim_start_matches('_').strip_prefix(EMUTLS_VARIABLE).map(str::to_string);
+
+    let mut issues = Vec::new();
+
+    // (section number, start, end, name) of every function
+    let mut layout = Vec::new();
+    // (section number, offset, name) of every TLS variable
+    let mut variables = Vec::new();
+    for (_, _, symbol) in bof.coff.symbols.iter() {
+        if is_section_definition(bof, &symbol) {
+            continue;
+        }
+        let name = bof.symbol_name(&symbol);
+        if in_tls(symbol.section_number) {
+            variables.push((symbol.section_number, symbol.value, name.clone()));
+            add(&mut issues, name, TlsSymbol::Variable, None);
+        } else if let Some(variable) = emulated(&name) {
+            add(&mut issues, variable, TlsSymbol::Variable, None);
+        } else if is_code(symbol.section_number) {
+            let section = &bof.coff.sections[symbol.section_number as usize - 1];
+            let (start, end) = extent(bof, &symbol, bof.section_contents(section).len() as u32);
+            layout.push((symbol.section_number, start, end, name));
+        }
+    }
+
+    for (index, section) in bof.coff.sections.iter().enumerate() {
+        let number = index as i16 + 1;
+        let data = bof.section_contents(section);
+        for entry in section.relocations(bof.buffer).into_iter().flatten() {
+            let target = match bof.coff.symbols.get(entry.symbol_table_index as usize) {
+                Some((_, target)) => target,
+                None => continue,
+            };
+            let function = layout
+                .iter()
+                .find(|(section, start, end, _)| *section == number && (*start..*end).contains(&entry.virtual_address))
+                .map(|(_, _, _, name)| name.clone());
+            let name = bof.symbol_name(&target);
+            let undecorated = match arch {
+                Arch::X86 => name.strip_prefix('_').unwrap_or(&name),
+                _ => &name,
+            };
+            let listed = |list: &[&str]| list.contains(&name.as_str()) || list.contains(&undecorated);
+            if in_tls(target.section_number) {
+                let variable = match is_section_definition(bof, &target) {
+                    // a static variable, found by its offset
+                    true => {
+                        let addend =
+                            relocations::addend(arch, entry.typ, data, entry.virtual_address as usize).unwrap_or(0);
+                        variables
+                            .iter()
+                            .filter(|(section, offset, _)| {
+                                *section == target.section_number && (*offset as i64) <= addend
+                            })
+                            .max_by_key(|(_, offset, _)| *offset)
+                            .map_or(name.clone(), |(_, _, variable)| variable.clone())
+                    }
+                    false => name.clone(),
+                };
+                add(&mut issues, variable, TlsSymbol::Variable, function);
+            } else if let Some(variable) = emulated(&name) {
+                add(&mut issues, variable, TlsSymbol::Variable, function);
+            } else if listed(INDEX) {
+                add(&mut issues, name, TlsSymbol::Index, function);
+            } else if listed(EMULATED) {
+                add(&mut issues, name, TlsSymbol::Emulated, function);
+            }
+        }
+    }
+    issues
+}
+
+/// Record that `function` refers to `symbol`
+fn add(issues: &mut Vec<TlsIssue>, symbol: String, kind: TlsSymbol, function: Option<String>) {
+    let index = match issues.iter().position(|issue| issue.symbol == symbol) {
+        Some(index) => index,
+        None => {
+            issues.push(TlsIssue { symbol, kind, functions: Vec::new() });
+            issues.len() - 1
+        }
+    };
+    let functions = &mut issues[index].functions;
+    if let Some(function) = function.filter(|function| !functions.contains(function)) {
+        functions.push(function);
+    }
+}
@@ -0,0 +1,48 @@
+//! Bindings for running the analysis in a browser
+//!
+//! Built with `--target wasm32-unknown-unknown --features wasm` and
+//! `wasm-bindgen`, this exposes the checks `bof-check` runs on a buffer the
+//! page already has, such as a dropped file. Nothing here touches the
+//! filesystem or stdout.
+//!
+//! ```js
+//! import init, { analyze_bytes } from "./bof_kit.js";
+//!
+//! await init();
+//! const report = analyze_bytes(new Uint8Array(await file.arrayBuffer()));
+//! for (const finding of report.findings) {
+//!     console.log(finding.severity, finding.message);
+//! }
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::report::ResolvedReport;
+use crate::{profile, AnalysisOptions, Bof};
+
+/// Analyze a BOF against Cobalt Strike's loader
+///
+/// Returns the [`AnalysisReport`](crate::AnalysisReport) as an object,
+/// with a `findings` array of `{ rule, severity, message }` added; throws
+/// if the bytes aren't a COFF file.
+#[wasm_bindgen]
+pub fn analyze_bytes(bytes: &[u8]) -> Result<JsValue, JsError> {
+    analyze(bytes, AnalysisOptions::default())
+}
+
+/// Analyze a BOF against the loader `target` names (`cs`, `cs-4.9`,
+/// `sliver`, ...), as with `bof-check --target`
+#[wasm_bindgen]
+pub fn analyze_bytes_for(bytes: &[u8], target: &str) -> Result<JsValue, JsError> {
+    let profile = profile::by_name(target).map_err(|e| JsError::new(&e))?;
+    analyze(bytes, AnalysisOptions { profile, ..AnalysisOptions::default() })
+}
+
+fn analyze(bytes: &[u8], options: AnalysisOptions) -> Result<JsValue, JsError> {
+    let bof = Bof::parse(bytes).map_err(|e| JsError::new(&format!("failed to parse input as COFF file: {}", e)))?;
+    let report = ResolvedReport::new(bof.analyze_with(&options));
+    // plain objects rather than Maps, so the result also survives JSON.stringify
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    report.serialize(&serializer).map_err(|e| JsError::new(&e.to_string()))
+}
@@ -0,0 +1,147 @@
+//! Waiting for BOFs to be rebuilt
+//!
+//! On Linux the directories holding the files are watched with inotify, so a
+//! compiler finishing a write or renaming a new object into place wakes the
+//! watcher right away. Elsewhere the files are polled. Either way a file
+//! only counts as rebuilt once its size or modification time changed and it
+//! has been left alone for [`SETTLE_TIME`].
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How long a file has to stay unchanged after a write before it's reported,
+/// so a compiler writing it in several steps is only noticed once
+pub const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// How often files are polled where there are no notifications
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Size and modification time, or `None` while the file doesn't exist
+type Stamp = Option<(u64, SystemTime)>;
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Watches files for changes
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    stamps: Vec<Stamp>,
+    #[cfg(target_os = "linux")]
+    inotify: inotify::Inotify,
+}
+
+impl Watcher {
+    pub fn new(paths: &[PathBuf]) -> io::Result<Self> {
+        Ok(Self {
+            paths: paths.to_vec(),
+            stamps: paths.iter().map(|path| stamp(path)).collect(),
+            #[cfg(target_os = "linux")]
+            inotify: inotify::Inotify::new(paths)?,
+        })
+    }
+
+    /// Block until at least one of the files is rewritten, returning the
+    /// rewritten ones
+    pub fn wait(&mut self) -> io::Result<Vec<PathBuf>> {
+        loop {
+            self.sleep()?;
+            let mut changed = Vec::new();
+            for (path, last) in self.paths.iter().zip(&mut self.stamps) {
+                let mut current = stamp(path);
+                // wait out a write in progress
+                while current.is_some() && current != *last {
+                    thread::sleep(SETTLE_TIME);
+                    let settled = stamp(path);
+                    if settled == current {
+                        break;
+                    }
+                    current = settled;
+                }
+                // a file that's gone is usually about to be replaced
+                if current.is_some() && current != *last {
+                    *last = current;
+                    changed.push(path.clone());
+                }
+            }
+            if !changed.is_empty() {
+                return Ok(changed);
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn sleep(&self) -> io::Result<()> {
+        self.inotify.wait()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn sleep(&self) -> io::Result<()> {
+        thread::sleep(POLL_INTERVAL);
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    /// Events for a file in a watched directory being written or replaced
+    const MASK: u32 = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE;
+
+    /// An inotify instance watching the directories of some files
+    pub struct Inotify {
+        fd: i32,
+    }
+
+    impl Inotify {
+        pub fn new(paths: &[PathBuf]) -> io::Result<Self> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let inotify = Self { fd };
+            let mut dirs: Vec<&Path> = Vec::new();
+            for path in paths {
+                let dir = match path.parent() {
+                    Some(dir) if !dir.as_os_str().is_empty() => dir,
+                    _ => Path::new("."),
+                };
+                if dirs.contains(&dir) {
+                    continue;
+                }
+                dirs.push(dir);
+                let dir = CString::new(dir.as_os_str().as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+                if unsafe { libc::inotify_add_watch(inotify.fd, dir.as_ptr(), MASK) } < 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(inotify)
+        }
+
+        /// Block until something happens in a watched directory
+        pub fn wait(&self) -> io::Result<()> {
+            // the events themselves don't matter, only that there were some
+            let mut buffer = [0u8; 4096];
+            let read = unsafe { libc::read(self.fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+            match read < 0 {
+                true => Err(io::Error::last_os_error()),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Weak external symbols
+//!
+//! A weak external is an undefined symbol with an auxiliary record naming a
+//! default: the linker binds references to a definition of the symbol if
+//! there is one, and to the default if not. MSVC emits them for
+//! `/alternatename` and clang and GCC for `__attribute__((weak))` and
+//! aliases. BOF loaders treat any undefined symbol that isn't an import as
+//! unresolved, so relocations against a weak external fail to load unless
+//! the loader follows the default itself, and a default that is undefined
+//! as well is never resolved by anything.
+
+use std::fmt;
+
+use goblin::pe::symbol::{
+    IMAGE_SYM_ABSOLUTE, IMAGE_SYM_CLASS_WEAK_EXTERNAL, IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_UNDEFINED,
+    IMAGE_WEAK_EXTERN_SEARCH_ALIAS, IMAGE_WEAK_EXTERN_SEARCH_LIBRARY, IMAGE_WEAK_EXTERN_SEARCH_NOLIBRARY,
+};
+
+use crate::{Bof, Severity};
+
+/// Where a linker looks for a definition before falling back to the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Search {
+    /// Only the objects being linked
+    NoLibrary,
+    /// Libraries too
+    Library,
+    /// The symbol is an alias of the default
+    Alias,
+    /// A value the PE specification doesn't define
+    Unknown(u32),
+}
+
+impl Search {
+    pub(crate) fn new(characteristics: u32) -> Self {
+        match characteristics {
+            IMAGE_WEAK_EXTERN_SEARCH_NOLIBRARY => Search::NoLibrary,
+            IMAGE_WEAK_EXTERN_SEARCH_LIBRARY => Search::Library,
+            IMAGE_WEAK_EXTERN_SEARCH_ALIAS => Search::Alias,
+            other => Search::Unknown(other),
+        }
+    }
+}
+
+impl fmt::Display for Search {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Search::NoLibrary => f.write_str("no library search"),
+            Search::Library => f.write_str("library search"),
+            Search::Alias => f.write_str("alias"),
+            Search::Unknown(value) => write!(f, "unknown search {}", value),
+        }
+    }
+}
+
+/// A weak external symbol and what it resolves to
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeakExternal {
+    pub symbol: String,
+    pub search: Search,
+    /// Symbol table index of the default
+    pub tag_index: u32,
+    /// The symbol references bind to when nothing defines `symbol`, if the
+    /// tag index names one
+    pub default: Option<String>,
+    /// Whether the object defines the default, rather than leaving it
+    /// undefined too
+    pub default_defined: bool,
+    /// Whether the object defines `symbol` itself, which a linker would bind
+    /// to instead of the default
+    pub overridden: bool,
+    /// Whether a relocation in a loaded section points at it
+    pub referenced: bool,
+}
+
+impl WeakExternal {
+    /// What a linker would bind references to
+    pub fn resolution(&self) -> &str {
+        match (&self.default, self.overridden) {
+            (_, true) => &self.symbol,
+            (Some(default), false) => default,
+            (None, false) => "nothing",
+        }
+    }
+}
+
+impl fmt::Display for WeakExternal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {} ({})", self.symbol, self.default.as_deref().unwrap_or("?"), self.search)?;
+        if self.overridden {
+            f.write_str(", overridden by its own definition")?;
+        } else if self.default.is_some() && !self.default_defined {
+            f.write_str(", default undefined")?;
+        }
+        Ok(())
+    }
+}
+
+/// What a loader would get wrong about a weak external
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WeakProblem {
+    /// The tag index doesn't name a symbol
+    BadDefault,
+    /// The default is undefined, so references resolve to nothing unless
+    /// something else defines the symbol
+    UndefinedDefault,
+    /// Relocations point at it, and loaders don't follow the default
+    Referenced,
+}
+
+/// A problem with a weak external
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeakIssue {
+    pub weak: WeakExternal,
+    pub problem: WeakProblem,
+}
+
+impl WeakIssue {
+    pub fn severity(&self) -> Severity {
+        match self.problem {
+            WeakProblem::BadDefault => Severity::Error,
+            WeakProblem::UndefinedDefault | WeakProblem::Referenced => Severity::Warning,
+        }
+    }
+}
+
+impl fmt::Display for WeakIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let weak = &self.weak;
+        match self.problem {
+            WeakProblem::BadDefault => {
+                write!(f, "weak external with a default index that isn't a symbol: {} (index {})", weak.symbol, weak.tag_index)
+            }
+            WeakProblem::UndefinedDefault => {
+                write!(f, "weak external whose default is undefined too: {} -> {}", weak.symbol, weak.resolution())
+            }
+            WeakProblem::Referenced => {
+                write!(f, "weak external referenced by relocations, which loaders don't resolve to its default: {} -> {}", weak.symbol, weak.resolution())
+            }
+        }
+    }
+}
+
+/// Every weak external symbol, in symbol table order
+pub fn externals(bof: &Bof) -> Vec<WeakExternal> {
+    let referenced = bof.referenced_symbols();
+    let defined = |section_number: i16, value: u32| {
+        section_number > 0 || section_number == IMAGE_SYM_ABSOLUTE || (section_number == IMAGE_SYM_UNDEFINED && value != 0)
+    };
+    let mut weak = Vec::new();
+    for (index, _, symbol) in bof.coff.symbols.iter() {
+        if symbol.storage_class != IMAGE_SYM_CLASS_WEAK_EXTERNAL || symbol.number_of_aux_symbols == 0 {
+            continue;
+        }
+        let aux = match bof.coff.symbols.aux_weak_external(index + 1) {
+            Some(aux) => aux,
+            None => continue,
+        };
+        let name = bof.symbol_name(&symbol);
+        let target = bof.coff.symbols.get(aux.tag_index as usize).map(|(_, target)| target);
+        let overridden = bof.coff.symbols.iter().any(|(_, _, other)| {
+            other.storage_class == IMAGE_SYM_CLASS_EXTERNAL
+                && defined(other.section_number, other.value)
+                && bof.symbol_name(&other) == name
+        });
+        weak.push(WeakExternal {
+            search: Search::new(aux.characteristics),
+            tag_index: aux.tag_index,
+            default: target.as_ref().map(|target| bof.symbol_name(target)),
+            default_defined: target.is_some_and(|target| defined(target.section_number, target.value)),
+            overridden,
+            referenced: referenced.contains(&index),
+            symbol: name,
+        });
+    }
+    weak
+}
+
+/// Find weak externals with a bad or undefined default, and ones relocations
+/// point at
+pub fn lint(weak: &[WeakExternal]) -> Vec<WeakIssue> {
+    weak.iter()
+        .filter_map(|weak| {
+            let problem = match weak {
+                WeakExternal { default: None, .. } => WeakProblem::BadDefault,
+                WeakExternal { default_defined: false, overridden: false, .. } => WeakProblem::UndefinedDefault,
+                WeakExternal { referenced: true, .. } => WeakProblem::Referenced,
+                _ => return None,
+            };
+            Some(WeakIssue { weak: weak.clone(), problem })
+        })
+        .collect()
+}
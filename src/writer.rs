@@ -0,0 +1,705 @@
+//! Owned, editable COFF object that can be serialized back to bytes
+//!
+//! goblin only parses, so rewriting an object means copying it into an
+//! [`Object`], changing that, and laying out a fresh file with
+//! [`Object::to_bytes`]. Symbols are referenced by their position in
+//! [`Object::symbols`], which doesn't count auxiliary records, and sections
+//! by their 1-based section number, as in the symbol table.
+
+use std::collections::HashMap;
+use std::error;
+use std::fmt;
+
+use goblin::pe::relocation::COFF_RELOCATION_SIZE;
+use goblin::pe::section_table::{IMAGE_SCN_CNT_UNINITIALIZED_DATA, IMAGE_SCN_LNK_COMDAT};
+use goblin::pe::symbol::{
+    COFF_SYMBOL_SIZE, IMAGE_SYM_CLASS_STATIC, IMAGE_SYM_CLASS_WEAK_EXTERNAL, IMAGE_SYM_TYPE_NULL,
+};
+
+use crate::{checksum, disasm, section_name, Bof};
+
+const COFF_HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// COMDAT selection that ties a section to another one
+const IMAGE_COMDAT_SELECT_ASSOCIATIVE: u8 = 5;
+
+/// A section and its relocations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub characteristics: u32,
+    /// Size in bytes; for uninitialized data, `data` is empty
+    pub size: u32,
+    pub data: Vec<u8>,
+    pub relocations: Vec<Relocation>,
+}
+
+impl Section {
+    /// A section holding `data`, without relocations
+    pub fn new(name: &str, characteristics: u32, data: Vec<u8>) -> Self {
+        Self { name: name.to_string(), characteristics, size: data.len() as u32, data, relocations: Vec::new() }
+    }
+
+    /// An uninitialized data section of `size` bytes
+    pub fn uninitialized(name: &str, characteristics: u32, size: u32) -> Self {
+        Self {
+            name: name.to_string(),
+            characteristics: characteristics | IMAGE_SCN_CNT_UNINITIALIZED_DATA,
+            size,
+            data: Vec::new(),
+            relocations: Vec::new(),
+        }
+    }
+
+    fn is_uninitialized(&self) -> bool {
+        self.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0
+    }
+
+    /// Size as written to the section header
+    fn raw_size(&self) -> u32 {
+        match self.is_uninitialized() {
+            true => self.size,
+            false => self.data.len() as u32,
+        }
+    }
+}
+
+/// A relocation, pointing at a symbol by its index in [`Object::symbols`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relocation {
+    pub offset: u32,
+    pub symbol: usize,
+    pub typ: u16,
+}
+
+/// A symbol table entry along with its auxiliary records
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub value: u32,
+    pub section_number: i16,
+    pub typ: u16,
+    pub storage_class: u8,
+    /// Raw auxiliary records that follow the symbol; a weak external's
+    /// default symbol is stored as an index in [`Object::symbols`]
+    pub aux: Vec<[u8; COFF_SYMBOL_SIZE]>,
+}
+
+impl Symbol {
+    /// A symbol without auxiliary records
+    pub fn new(name: &str, value: u32, section_number: i16, storage_class: u8) -> Self {
+        Self { name: name.to_string(), value, section_number, typ: IMAGE_SYM_TYPE_NULL, storage_class, aux: Vec::new() }
+    }
+
+    /// Whether this is the static symbol carrying the definition record
+    /// (length, relocation count, COMDAT selection) of its section in
+    /// `sections`
+    pub fn is_section_definition(&self, sections: &[Section]) -> bool {
+        self.section_number > 0
+            && disasm::defines_section(self.storage_class, self.value, self.typ, self.aux.len(), || {
+                sections.get(self.section_number as usize - 1).is_some_and(|section| section.name == self.name)
+            })
+    }
+
+    /// Index in [`Object::symbols`] of a weak external's default symbol
+    pub fn weak_default(&self) -> Option<usize> {
+        match (self.storage_class, self.aux.first()) {
+            (IMAGE_SYM_CLASS_WEAK_EXTERNAL, Some(aux)) => Some(u32::from_le_bytes([aux[0], aux[1], aux[2], aux[3]]) as usize),
+            _ => None,
+        }
+    }
+
+    /// Point a weak external at another default symbol
+    pub fn set_weak_default(&mut self, index: usize) {
+        self.aux[0][0..4].copy_from_slice(&(index as u32).to_le_bytes());
+    }
+}
+
+/// A COFF object file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Object {
+    pub machine: u16,
+    pub time_date_stamp: u32,
+    pub characteristics: u16,
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteError {
+    /// The input object couldn't be copied
+    Malformed(String),
+    /// A section has more relocations than the header can count
+    TooManyRelocations(String),
+    /// An edit would leave a relocation or weak external pointing at a
+    /// removed symbol
+    Dangling(String),
+    /// No symbol has the given name
+    MissingSymbol(String),
+    /// A symbol with the given name already exists
+    DuplicateSymbol(String),
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed(message) => write!(f, "malformed object: {}", message),
+            Self::TooManyRelocations(section) => write!(f, "too many relocations in {}", section),
+            Self::Dangling(symbol) => write!(f, "{} is still referenced", symbol),
+            Self::MissingSymbol(symbol) => write!(f, "no symbol named {}", symbol),
+            Self::DuplicateSymbol(symbol) => write!(f, "a symbol named {} already exists", symbol),
+        }
+    }
+}
+
+impl error::Error for WriteError {}
+
+impl Object {
+    /// An empty object for `machine`
+    pub fn new(machine: u16) -> Self {
+        Self { machine, time_date_stamp: 0, characteristics: 0, sections: Vec::new(), symbols: Vec::new() }
+    }
+
+    /// Copy a parsed BOF into an editable object
+    pub fn from_bof(bof: &Bof) -> Result<Self, WriteError> {
+        let coff = &bof.coff;
+        let header = &coff.header;
+        let table = header.pointer_to_symbol_table as usize;
+        let raw = |index: usize| -> Result<[u8; COFF_SYMBOL_SIZE], WriteError> {
+            let start = table + index * COFF_SYMBOL_SIZE;
+            bof.buffer
+                .get(start..start + COFF_SYMBOL_SIZE)
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or_else(|| WriteError::Malformed(format!("symbol {} is out of bounds", index)))
+        };
+
+        // raw symbol table index -> index in `symbols`
+        let mut indices = vec![None; header.number_of_symbol_table as usize];
+        let mut symbols = Vec::new();
+        for (index, _, symbol) in coff.symbols.iter() {
+            let name = symbol
+                .name(&coff.strings)
+                .map_err(|_| WriteError::Malformed(format!("symbol {} has no name", index)))?
+                .to_string();
+            let aux = (1..=symbol.number_of_aux_symbols as usize)
+                .map(|offset| raw(index + offset))
+                .collect::<Result<_, _>>()?;
+            if let Some(slot) = indices.get_mut(index) {
+                *slot = Some(symbols.len());
+            }
+            symbols.push(Symbol {
+                name,
+                value: symbol.value,
+                section_number: symbol.section_number,
+                typ: symbol.typ,
+                storage_class: symbol.storage_class,
+                aux,
+            });
+        }
+
+        for symbol in symbols.iter_mut().filter(|symbol| symbol.storage_class == IMAGE_SYM_CLASS_WEAK_EXTERNAL) {
+            if let Some(aux) = symbol.aux.first_mut() {
+                let tag = u32::from_le_bytes([aux[0], aux[1], aux[2], aux[3]]) as usize;
+                let tag = indices
+                    .get(tag)
+                    .copied()
+                    .flatten()
+                    .ok_or_else(|| WriteError::Malformed(format!("weak external {} has a bad default", symbol.name)))?;
+                aux[0..4].copy_from_slice(&(tag as u32).to_le_bytes());
+            }
+        }
+
+        let mut sections = Vec::new();
+        for section in &coff.sections {
+            let name = section_name(section);
+            let uninitialized = section.characteristics & IMAGE_SCN_CNT_UNINITIALIZED_DATA != 0;
+            let data = match uninitialized || section.pointer_to_raw_data == 0 {
+                true => Vec::new(),
+                false => {
+                    let start = section.pointer_to_raw_data as usize;
+                    bof.buffer
+                        .get(start..start + section.size_of_raw_data as usize)
+                        .ok_or_else(|| WriteError::Malformed(format!("{} data is out of bounds", name)))?
+                        .to_vec()
+                }
+            };
+            let relocations = section
+                .relocations(bof.buffer)
+                .map_err(|_| WriteError::Malformed(format!("{} relocations are out of bounds", name)))?
+                .map(|entry| {
+                    let symbol = indices
+                        .get(entry.symbol_table_index as usize)
+                        .copied()
+                        .flatten()
+                        .ok_or_else(|| WriteError::Malformed(format!("{} relocates against a bad symbol", name)))?;
+                    Ok(Relocation { offset: entry.virtual_address, symbol, typ: entry.typ })
+                })
+                .collect::<Result<_, _>>()?;
+            sections.push(Section {
+                name,
+                characteristics: section.characteristics,
+                size: section.size_of_raw_data,
+                data,
+                relocations,
+            });
+        }
+
+        let object = Self {
+            machine: header.machine,
+            time_date_stamp: header.time_date_stamp,
+            characteristics: header.characteristics,
+            sections,
+            symbols,
+        };
+        // editing and writing index sections and symbols through these
+        object.validate()?;
+        Ok(object)
+    }
+
+    /// Parse `buffer` and copy it into an editable object
+    pub fn parse(buffer: &[u8]) -> Result<Self, WriteError> {
+        let bof = Bof::parse(buffer).map_err(|e| WriteError::Malformed(e.to_string()))?;
+        Self::from_bof(&bof)
+    }
+
+    /// Index of the first symbol called `name`
+    pub fn find_symbol(&self, name: &str) -> Option<usize> {
+        self.symbols.iter().position(|symbol| symbol.name == name)
+    }
+
+    /// Number of symbol table entries, including auxiliary records
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.iter().map(|symbol| 1 + symbol.aux.len()).sum()
+    }
+
+    /// Rename every symbol called `old`; relocations follow them since they
+    /// refer to symbols by index
+    pub fn rename_symbol(&mut self, old: &str, new: &str) -> Result<(), WriteError> {
+        if self.find_symbol(new).is_some() {
+            return Err(WriteError::DuplicateSymbol(new.to_string()));
+        }
+        let mut renamed = false;
+        for symbol in self.symbols.iter_mut().filter(|symbol| symbol.name == old) {
+            symbol.name = new.to_string();
+            renamed = true;
+        }
+        match renamed {
+            true => Ok(()),
+            false => Err(WriteError::MissingSymbol(old.to_string())),
+        }
+    }
+
+    /// Append a symbol, returning its index
+    pub fn add_symbol(&mut self, symbol: Symbol) -> usize {
+        self.symbols.push(symbol);
+        self.symbols.len() - 1
+    }
+
+    /// Append a section along with its section definition symbol, returning
+    /// the new section number
+    pub fn add_section(&mut self, section: Section) -> i16 {
+        let number = self.sections.len() as i16 + 1;
+        let mut symbol = Symbol::new(&section.name, 0, number, IMAGE_SYM_CLASS_STATIC);
+        // length and relocation count are filled in by to_bytes
+        symbol.aux.push([0; COFF_SYMBOL_SIZE]);
+        self.sections.push(section);
+        self.add_symbol(symbol);
+        number
+    }
+
+    /// Recompute the checksum in the section definition record of every
+    /// COMDAT section, and of any other section that has one, after its
+    /// contents changed; returns how many changed
+    pub fn update_checksums(&mut self) -> usize {
+        let mut updated = 0;
+        for symbol in self.symbols.iter_mut().filter(|symbol| symbol.is_section_definition(&self.sections)) {
+            let section = match self.sections.get(symbol.section_number as usize - 1) {
+                Some(section) => section,
+                None => continue,
+            };
+            let aux = &mut symbol.aux[0];
+            let stored = u32::from_le_bytes([aux[8], aux[9], aux[10], aux[11]]);
+            if stored == 0 && section.characteristics & IMAGE_SCN_LNK_COMDAT == 0 {
+                continue;
+            }
+            let computed = checksum::compute(&section.data);
+            if computed != stored {
+                aux[8..12].copy_from_slice(&computed.to_le_bytes());
+                updated += 1;
+            }
+        }
+        updated
+    }
+
+    /// Keep only the symbols whose entry in `keep` is true, and update every
+    /// reference to the ones that move
+    ///
+    /// Fails without changing anything if a removed symbol is still the
+    /// target of a relocation or the default of a kept weak external.
+    pub fn retain_symbols(&mut self, keep: &[bool]) -> Result<(), WriteError> {
+        let kept = |index: usize| keep.get(index).copied().unwrap_or(true);
+        let relocations = self.sections.iter().flat_map(|section| &section.relocations);
+        if let Some(relocation) = relocations.clone().find(|relocation| !kept(relocation.symbol)) {
+            return Err(WriteError::Dangling(self.symbols[relocation.symbol].name.clone()));
+        }
+        let weak = self.symbols.iter().enumerate().filter(|(index, _)| kept(*index));
+        if let Some(default) = weak.filter_map(|(_, symbol)| symbol.weak_default()).find(|default| !kept(*default)) {
+            return Err(WriteError::Dangling(self.symbols[default].name.clone()));
+        }
+
+        let mut indices = vec![0; self.symbols.len()];
+        let mut next = 0;
+        for (index, slot) in indices.iter_mut().enumerate() {
+            *slot = next;
+            next += usize::from(kept(index));
+        }
+        let mut index = 0;
+        self.symbols.retain(|_| {
+            index += 1;
+            kept(index - 1)
+        });
+        for section in &mut self.sections {
+            for relocation in &mut section.relocations {
+                relocation.symbol = indices[relocation.symbol];
+            }
+        }
+        for symbol in &mut self.symbols {
+            if let Some(default) = symbol.weak_default() {
+                symbol.set_weak_default(indices[default]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the sections whose entry in `remove` is true, along with the
+    /// symbols defined in them, and renumber the rest
+    ///
+    /// Fails without changing anything if a kept section relocates against
+    /// a symbol in a removed one.
+    pub fn remove_sections(&mut self, remove: &[bool]) -> Result<Vec<Section>, WriteError> {
+        self.validate()?;
+        let removed = |number: i16| number > 0 && remove.get(number as usize - 1).copied().unwrap_or(false);
+        for (index, section) in self.sections.iter().enumerate() {
+            if removed(index as i16 + 1) {
+                continue;
+            }
+            if let Some(relocation) = section.relocations.iter().find(|r| removed(self.symbols[r.symbol].section_number)) {
+                return Err(WriteError::Dangling(self.symbols[relocation.symbol].name.clone()));
+            }
+        }
+        // removed sections may refer to each other, like .pdata to .xdata
+        for (index, section) in self.sections.iter_mut().enumerate() {
+            if removed(index as i16 + 1) {
+                section.relocations.clear();
+            }
+        }
+        let keep: Vec<bool> = self.symbols.iter().map(|symbol| !removed(symbol.section_number)).collect();
+        self.retain_symbols(&keep)?;
+
+        // new section number of every section, 0 for removed ones
+        let mut numbers = vec![0i16; self.sections.len() + 1];
+        let mut next = 1;
+        for (number, slot) in numbers.iter_mut().enumerate().skip(1) {
+            if !removed(number as i16) {
+                *slot = next;
+                next += 1;
+            }
+        }
+        for symbol in &mut self.symbols {
+            let definition = symbol.is_section_definition(&self.sections);
+            if symbol.section_number > 0 {
+                symbol.section_number = numbers[symbol.section_number as usize];
+            }
+            if definition && symbol.aux[0][14] == IMAGE_COMDAT_SELECT_ASSOCIATIVE {
+                let aux = &mut symbol.aux[0];
+                let associated = u16::from_le_bytes([aux[12], aux[13]]) as usize;
+                let associated = numbers.get(associated).copied().unwrap_or(0) as u16;
+                aux[12..14].copy_from_slice(&associated.to_le_bytes());
+            }
+        }
+
+        let sections = std::mem::take(&mut self.sections);
+        let (removed, kept): (Vec<_>, Vec<_>) =
+            sections.into_iter().enumerate().partition(|(index, _)| removed(*index as i16 + 1));
+        self.sections = kept.into_iter().map(|(_, section)| section).collect();
+        Ok(removed.into_iter().map(|(_, section)| section).collect())
+    }
+
+    /// Check that every reference in the object points somewhere
+    pub fn validate(&self) -> Result<(), WriteError> {
+        for symbol in &self.symbols {
+            if symbol.section_number > 0 && symbol.section_number as usize > self.sections.len() {
+                return Err(WriteError::Malformed(format!("{} is in a missing section", symbol.name)));
+            }
+            if symbol.weak_default().is_some_and(|default| default >= self.symbols.len()) {
+                return Err(WriteError::Malformed(format!("weak external {} has a bad default", symbol.name)));
+            }
+            if symbol.aux.len() > u8::MAX as usize {
+                return Err(WriteError::Malformed(format!("{} has too many auxiliary records", symbol.name)));
+            }
+        }
+        for section in &self.sections {
+            if section.relocations.len() > u16::MAX as usize {
+                return Err(WriteError::TooManyRelocations(section.name.clone()));
+            }
+            for relocation in &section.relocations {
+                if relocation.symbol >= self.symbols.len() {
+                    return Err(WriteError::Malformed(format!("{} relocates against a bad symbol", section.name)));
+                }
+                if relocation.offset >= section.raw_size() {
+                    return Err(WriteError::Malformed(format!(
+                        "{} has a relocation past its end at {:#x}",
+                        section.name, relocation.offset
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lay out the object as a COFF file
+    ///
+    /// Section definition records are updated to match their sections.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, WriteError> {
+        self.validate()?;
+        let mut strings = StringTable::default();
+
+        // section data and relocations follow the headers
+        let mut offset = COFF_HEADER_SIZE + SECTION_HEADER_SIZE * self.sections.len();
+        let mut layout = Vec::with_capacity(self.sections.len());
+        for section in &self.sections {
+            let data = match section.data.is_empty() {
+                true => 0,
+                false => offset,
+            };
+            offset += section.data.len();
+            let relocations = match section.relocations.is_empty() {
+                true => 0,
+                false => offset,
+            };
+            offset += section.relocations.len() * COFF_RELOCATION_SIZE;
+            layout.push((data, relocations));
+        }
+        let symbol_table = offset;
+
+        // raw symbol table index of each entry in `symbols`
+        let mut indices = Vec::with_capacity(self.symbols.len());
+        let mut count = 0;
+        for symbol in &self.symbols {
+            indices.push(count as u32);
+            count += 1 + symbol.aux.len();
+        }
+
+        let mut out = Vec::new();
+        out.extend_from_slice(&self.machine.to_le_bytes());
+        out.extend_from_slice(&(self.sections.len() as u16).to_le_bytes());
+        out.extend_from_slice(&self.time_date_stamp.to_le_bytes());
+        out.extend_from_slice(&(symbol_table as u32).to_le_bytes());
+        out.extend_from_slice(&(count as u32).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes());
+        out.extend_from_slice(&self.characteristics.to_le_bytes());
+
+        for (section, (data, relocations)) in self.sections.iter().zip(&layout) {
+            out.extend_from_slice(&strings.section_name(&section.name));
+            out.extend_from_slice(&0u32.to_le_bytes()); // virtual size
+            out.extend_from_slice(&0u32.to_le_bytes()); // virtual address
+            out.extend_from_slice(&section.raw_size().to_le_bytes());
+            out.extend_from_slice(&(*data as u32).to_le_bytes());
+            out.extend_from_slice(&(*relocations as u32).to_le_bytes());
+            out.extend_from_slice(&0u32.to_le_bytes()); // line numbers
+            out.extend_from_slice(&(section.relocations.len() as u16).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+            out.extend_from_slice(&section.characteristics.to_le_bytes());
+        }
+
+        for section in &self.sections {
+            out.extend_from_slice(&section.data);
+            for relocation in &section.relocations {
+                out.extend_from_slice(&relocation.offset.to_le_bytes());
+                out.extend_from_slice(&indices[relocation.symbol].to_le_bytes());
+                out.extend_from_slice(&relocation.typ.to_le_bytes());
+            }
+        }
+
+        for symbol in &self.symbols {
+            out.extend_from_slice(&strings.symbol_name(&symbol.name));
+            out.extend_from_slice(&symbol.value.to_le_bytes());
+            out.extend_from_slice(&symbol.section_number.to_le_bytes());
+            out.extend_from_slice(&symbol.typ.to_le_bytes());
+            out.push(symbol.storage_class);
+            out.push(symbol.aux.len() as u8);
+            for (position, aux) in symbol.aux.iter().enumerate() {
+                let mut aux = *aux;
+                if let (Some(default), 0) = (symbol.weak_default(), position) {
+                    aux[0..4].copy_from_slice(&indices[default].to_le_bytes());
+                }
+                if symbol.is_section_definition(&self.sections) {
+                    if let Some(section) = self.sections.get(symbol.section_number as usize - 1) {
+                        aux[0..4].copy_from_slice(&section.raw_size().to_le_bytes());
+                        aux[4..6].copy_from_slice(&(section.relocations.len() as u16).to_le_bytes());
+                        aux[6..8].copy_from_slice(&0u16.to_le_bytes());
+                    }
+                }
+                out.extend_from_slice(&aux);
+            }
+        }
+
+        out.extend_from_slice(&strings.into_bytes());
+        Ok(out)
+    }
+}
+
+/// String table under construction; offsets count the 4-byte size field
+#[derive(Default)]
+struct StringTable {
+    data: Vec<u8>,
+    offsets: HashMap<String, u32>,
+}
+
+impl StringTable {
+    /// Offset of `name`, which is only stored once
+    fn add(&mut self, name: &str) -> u32 {
+        if let Some(offset) = self.offsets.get(name) {
+            return *offset;
+        }
+        let offset = 4 + self.data.len() as u32;
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.push(0);
+        self.offsets.insert(name.to_string(), offset);
+        offset
+    }
+
+    /// Section names longer than 8 bytes are written as `/offset`, or as
+    /// `//` and six base64 digits once the offset needs more than seven
+    /// decimal ones
+    fn section_name(&mut self, name: &str) -> [u8; 8] {
+        const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut field = [0u8; 8];
+        if name.len() <= 8 {
+            field[..name.len()].copy_from_slice(name.as_bytes());
+            return field;
+        }
+        let offset = self.add(name);
+        if offset <= 9_999_999 {
+            let offset = format!("/{}", offset);
+            field[..offset.len()].copy_from_slice(offset.as_bytes());
+        } else {
+            field[..2].copy_from_slice(b"//");
+            for (position, digit) in field[2..].iter_mut().rev().enumerate() {
+                *digit = BASE64[((offset as u64 >> (6 * position)) & 63) as usize];
+            }
+        }
+        field
+    }
+
+    /// Symbol names longer than 8 bytes are written as four zero bytes
+    /// followed by the offset
+    fn symbol_name(&mut self, name: &str) -> [u8; 8] {
+        let mut field = [0u8; 8];
+        if name.len() <= 8 {
+            field[..name.len()].copy_from_slice(name.as_bytes());
+        } else {
+            field[4..].copy_from_slice(&self.add(name).to_le_bytes());
+        }
+        field
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = ((self.data.len() + 4) as u32).to_le_bytes().to_vec();
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::header::COFF_MACHINE_X86_64;
+    use goblin::pe::relocation::IMAGE_REL_AMD64_REL32;
+    use goblin::pe::section_table::{
+        SectionTable, IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ,
+        IMAGE_SCN_MEM_WRITE,
+    };
+    use goblin::pe::symbol::IMAGE_SYM_CLASS_EXTERNAL;
+
+    use super::*;
+
+    /// `go` in `.text` loading a static in `.data` and calling an import,
+    /// with a section name long enough to need the string table
+    fn object() -> Object {
+        let mut object = Object::new(COFF_MACHINE_X86_64);
+        object.time_date_stamp = 0x5f00_0000;
+        let code = vec![0x8b, 0x05, 0, 0, 0, 0, 0xff, 0x15, 0, 0, 0, 0, 0xc3];
+        let text = object.add_section(Section::new(".text", IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_MEM_READ, code));
+        let data = object.add_section(Section::new(
+            ".data$very_long_name",
+            IMAGE_SCN_CNT_INITIALIZED_DATA | IMAGE_SCN_MEM_READ | IMAGE_SCN_MEM_WRITE,
+            vec![1, 2, 3, 4],
+        ));
+        object.add_symbol(Symbol::new("go", 0, text, IMAGE_SYM_CLASS_EXTERNAL));
+        let value = object.add_symbol(Symbol::new("counter_value", 0, data, IMAGE_SYM_CLASS_STATIC));
+        let import = object.add_symbol(Symbol::new("__imp_KERNEL32$GetLastError", 0, 0, IMAGE_SYM_CLASS_EXTERNAL));
+        object.sections[0].relocations = vec![
+            Relocation { offset: 2, symbol: value, typ: IMAGE_REL_AMD64_REL32 },
+            Relocation { offset: 8, symbol: import, typ: IMAGE_REL_AMD64_REL32 },
+        ];
+        object
+    }
+
+    #[test]
+    fn parse_and_write_round_trip() {
+        let bytes = object().to_bytes().unwrap();
+        let parsed = Object::parse(&bytes).unwrap();
+        assert_eq!(parsed.to_bytes().unwrap(), bytes);
+        assert_eq!(parsed.sections[1].name, ".data$very_long_name");
+        let targets: Vec<&str> =
+            parsed.sections[0].relocations.iter().map(|relocation| parsed.symbols[relocation.symbol].name.as_str()).collect();
+        assert_eq!(targets, ["counter_value", "__imp_KERNEL32$GetLastError"]);
+    }
+
+    #[test]
+    fn symbols_in_missing_sections_are_malformed() {
+        let mut object = object();
+        object.symbols[2].section_number = 9;
+        let bytes = object.to_bytes();
+        assert!(matches!(bytes, Err(WriteError::Malformed(_))));
+        assert!(matches!(object.remove_sections(&[false, true]), Err(WriteError::Malformed(_))));
+    }
+
+    #[test]
+    fn reading_symbols_in_missing_sections_fails() {
+        let mut bytes = object().to_bytes().unwrap();
+        let symbols = u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize;
+        bytes[symbols + 12..symbols + 14].copy_from_slice(&9i16.to_le_bytes());
+        assert!(matches!(Object::parse(&bytes), Err(WriteError::Malformed(_))));
+    }
+
+    #[test]
+    fn static_functions_are_not_section_definitions() {
+        // gcc -ffunction-sections: a static function at the start of its own
+        // section, with a function definition aux record
+        let mut object = Object::new(COFF_MACHINE_X86_64);
+        let section = object.add_section(Section::new(".text$helper", IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE, vec![0xc3]));
+        let mut helper = Symbol::new("helper", 0, section, IMAGE_SYM_CLASS_STATIC);
+        helper.typ = 0x20;
+        helper.aux = vec![[7; COFF_SYMBOL_SIZE]];
+        object.add_symbol(helper);
+        assert!(object.symbols[0].is_section_definition(&object.sections));
+        assert!(!object.symbols[1].is_section_definition(&object.sections));
+
+        let parsed = Object::parse(&object.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.symbols[0].aux[0][0..4], 1u32.to_le_bytes());
+        assert_eq!(parsed.symbols[1].aux, [[7; COFF_SYMBOL_SIZE]]);
+    }
+
+    #[test]
+    fn section_names_past_ten_megabytes_are_base64() {
+        let mut strings = StringTable::default();
+        assert_eq!(&strings.section_name(".text$first"), b"/4\0\0\0\0\0\0");
+        strings.data.resize(10_000_000, 0);
+        let field = strings.section_name(".text$after_ten_megabytes");
+        let section = SectionTable { name: field, ..Default::default() };
+        assert_eq!(&field[..2], b"//");
+        assert_eq!(section.name_offset().unwrap(), Some(10_000_004));
+    }
+}
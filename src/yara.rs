@@ -0,0 +1,178 @@
+//! YARA rule skeletons for detecting a BOF
+//!
+//! The rule combines byte patterns from the code sections, with relocated
+//! bytes wildcarded so the patterns also match after loading, readable
+//! strings from the read-only data sections, and dynamic function resolution
+//! import names. It's a starting point to tune, not a finished detection.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use goblin::pe::section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE};
+
+use crate::{relocations, section_name, Bof, Encoding};
+
+/// Length of each code pattern in bytes
+pub const CODE_PATTERN_LEN: usize = 24;
+/// At most this many code patterns are emitted
+pub const MAX_CODE_PATTERNS: usize = 4;
+/// Shortest string worth matching on
+pub const MIN_STRING_LEN: usize = 6;
+/// At most this many strings are emitted, longest first
+pub const MAX_STRINGS: usize = 8;
+
+/// Code patterns need at least this many distinct byte values to be
+/// distinctive rather than padding or a run of the same instruction
+const MIN_DISTINCT_BYTES: usize = 12;
+
+/// Generate a YARA rule called `name` (sanitized into an identifier)
+pub fn rule(bof: &Bof, name: &str) -> String {
+    let code = code_patterns(bof);
+    let strings = rdata_strings(bof);
+    let imports: BTreeSet<String> = bof
+        .imports()
+        .map(|symbol| bof.symbol_name(&symbol))
+        .filter_map(|name| {
+            let name = bof.import_prefix().and_then(|prefix| name.strip_prefix(prefix))?;
+            name.contains('$').then(|| name.to_string())
+        })
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "rule {}", identifier(name));
+    let _ = writeln!(out, "{{");
+    let _ = writeln!(out, "    meta:");
+    let _ = writeln!(out, "        description = \"Beacon object file {}\"", escape(name));
+    if let Some(arch) = bof.arch() {
+        let _ = writeln!(out, "        arch = \"{}\"", arch.name());
+    }
+    let _ = writeln!(out, "        generator = \"bof-kit\"");
+
+    if !(code.is_empty() && strings.is_empty() && imports.is_empty()) {
+        let _ = writeln!(out);
+        let _ = writeln!(out, "    strings:");
+    }
+    for (index, pattern) in code.iter().enumerate() {
+        let _ = writeln!(out, "        $code{} = {{ {} }}", index, pattern);
+    }
+    for (index, string) in strings.iter().enumerate() {
+        let _ = writeln!(out, "        $str{} = \"{}\" ascii", index, escape(string));
+    }
+    for (index, import) in imports.iter().enumerate() {
+        let _ = writeln!(out, "        $imp{} = \"{}\" ascii", index, escape(import));
+    }
+
+    let mut clauses = Vec::new();
+    for (prefix, count) in [("code", code.len()), ("str", strings.len()), ("imp", imports.len())] {
+        match count {
+            0 => {}
+            1 => clauses.push(format!("${}0", prefix)),
+            _ => clauses.push(format!("2 of (${}*)", prefix)),
+        }
+    }
+    let _ = writeln!(out);
+    let _ = writeln!(out, "    condition:");
+    let machine = format!("uint16(0) == {:#06x}", bof.coff.header.machine);
+    match clauses.is_empty() {
+        true => {
+            let _ = writeln!(out, "        {}", machine);
+        }
+        false => {
+            let _ = writeln!(out, "        {} and ({})", machine, clauses.join(" or "));
+        }
+    }
+    let _ = writeln!(out, "}}");
+    out
+}
+
+/// Hex patterns from the code sections, most distinctive first
+fn code_patterns(bof: &Bof) -> Vec<String> {
+    let arch = match bof.arch() {
+        Some(arch) => arch,
+        None => return Vec::new(),
+    };
+    let mut candidates = Vec::new();
+    for section in &bof.coff.sections {
+        if section.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) == 0 {
+            continue;
+        }
+        let data = bof.section_contents(section);
+        // bytes the loader patches, which differ between the file and memory
+        let mut relocated = vec![false; data.len()];
+        if let Ok(entries) = section.relocations(bof.buffer) {
+            for entry in entries {
+                let start = entry.virtual_address as usize;
+                let end = (start + relocations::width(arch, entry.typ)).min(data.len());
+                relocated.get_mut(start..end).into_iter().flatten().for_each(|byte| *byte = true);
+            }
+        }
+
+        for start in (0..data.len().saturating_sub(CODE_PATTERN_LEN - 1)).step_by(CODE_PATTERN_LEN / 3) {
+            let end = start + CODE_PATTERN_LEN;
+            // YARA doesn't allow hex strings to start or end with a wildcard
+            if relocated[start] || relocated[end - 1] {
+                continue;
+            }
+            let distinct = (start..end)
+                .filter(|index| !relocated[*index])
+                .map(|index| data[index])
+                .collect::<BTreeSet<u8>>()
+                .len();
+            if distinct < MIN_DISTINCT_BYTES {
+                continue;
+            }
+            let pattern = (start..end)
+                .map(|index| match relocated[index] {
+                    true => "??".to_string(),
+                    false => format!("{:02X}", data[index]),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            candidates.push((distinct, section_name(section), start, pattern));
+        }
+    }
+
+    // most distinct byte values first, without overlapping windows
+    candidates.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| (&a.1, a.2).cmp(&(&b.1, b.2))));
+    let mut chosen: Vec<(String, usize, String)> = Vec::new();
+    for (_, section, start, pattern) in candidates {
+        let overlaps = chosen
+            .iter()
+            .any(|(other, offset, _)| *other == section && start.abs_diff(*offset) < CODE_PATTERN_LEN);
+        if !overlaps {
+            chosen.push((section, start, pattern));
+        }
+        if chosen.len() == MAX_CODE_PATTERNS {
+            break;
+        }
+    }
+    chosen.into_iter().map(|(_, _, pattern)| pattern).collect()
+}
+
+/// ASCII strings from the read-only data sections, longest first
+fn rdata_strings(bof: &Bof) -> Vec<String> {
+    let strings: BTreeSet<String> = bof
+        .strings(MIN_STRING_LEN)
+        .into_iter()
+        .filter(|string| string.section.starts_with(".rdata") && string.encoding == Encoding::Ascii)
+        .map(|string| string.value)
+        .collect();
+    let mut strings: Vec<String> = strings.into_iter().collect();
+    strings.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    strings.truncate(MAX_STRINGS);
+    strings
+}
+
+/// A YARA identifier: letters, digits and underscores, not starting with a digit
+fn identifier(name: &str) -> String {
+    let name: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+    match name.chars().next() {
+        Some(c) if !c.is_ascii_digit() => name,
+        _ => format!("bof_{}", name),
+    }
+}
+
+/// Escape a string for a YARA text string
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\t', "\\t")
+}